@@ -0,0 +1,64 @@
+/*
+ * The C API to the Game of Life engine, in the shared library built from
+ * the crate. A board is made with life_grid_new or life_grid_from_rle and
+ * must be given back to life_grid_free once done with. Its cells are read a
+ * row at a time from the top, one byte each, 1 for alive and 0 for dead.
+ */
+
+#ifndef CONWAYS_GAME_OF_LIFE_H
+#define CONWAYS_GAME_OF_LIFE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct LifeGrid LifeGrid;
+
+/* A dead board width by height with unjoined edges, or NULL if either is 0
+ * or it is too big to make. */
+LifeGrid *life_grid_new(size_t width, size_t height);
+
+/* The RLE pattern rle on a board its own size, run by the rule in its header,
+ * or NULL if it can't be read or is too big to make. */
+LifeGrid *life_grid_from_rle(const char *rle);
+
+/* Frees a board. NULL is ignored. */
+void life_grid_free(LifeGrid *grid);
+
+size_t life_grid_width(const LifeGrid *grid);
+size_t life_grid_height(const LifeGrid *grid);
+
+/* How many cells are alive. */
+size_t life_grid_population(const LifeGrid *grid);
+
+/* 1 if the cell is alive, 0 if it is dead, or -1 if it is off the board. */
+int life_grid_get(const LifeGrid *grid, size_t row, size_t col);
+
+/* Brings the cell to life if alive isn't 0, or kills it if it is, giving 0,
+ * or -1 if it is off the board. */
+int life_grid_set(LifeGrid *grid, size_t row, size_t col, int alive);
+
+/* Joins the board's edges as topology says, a name like "torus" or a pair
+ * of joins like "wrap,twist", giving 0, or -1 if it can't be read. */
+int life_grid_set_topology(LifeGrid *grid, const char *topology);
+
+/* Runs the board by rule in B/S notation, like "B36/S23", giving 0, or -1 if
+ * it can't be read. */
+int life_grid_set_rule(LifeGrid *grid, const char *rule);
+
+/* Runs generations generations. */
+void life_grid_step(LifeGrid *grid, uint64_t generations);
+
+/* Copies the cells into cells if it is at least width times height bytes
+ * long, giving how many bytes that is either way. cells can be NULL to only
+ * ask. */
+size_t life_grid_read_cells(const LifeGrid *grid, uint8_t *cells, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
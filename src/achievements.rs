@@ -0,0 +1,225 @@
+//! Running totals kept from one game to the next, and the achievements
+//! they unlock, shown on the stats screen `A` opens.
+
+use crate::i18n::Language;
+use conways_game_of_life::cycle::Behaviour;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Something worth having done, unlocked once the stats reach it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Achievement {
+    /// Ran a thousand generations in all.
+    Thousand,
+    /// Ran a million generations in all.
+    Million,
+    /// A board lived 5000 generations before settling down.
+    Methuselah,
+    /// Found an oscillator with a period of 8 or more.
+    LongOscillator,
+    /// Found a spaceship.
+    Spaceship,
+}
+
+const ACHIEVEMENTS: [Achievement; 5] = [
+    Achievement::Thousand,
+    Achievement::Million,
+    Achievement::Methuselah,
+    Achievement::LongOscillator,
+    Achievement::Spaceship,
+];
+
+impl Achievement {
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::Thousand => "ran 1,000 generations in all",
+            Achievement::Million => "ran 1,000,000 generations in all",
+            Achievement::Methuselah => "made a board that lived 5000 generations or more",
+            Achievement::LongOscillator => "found an oscillator of period 8 or more",
+            Achievement::Spaceship => "found a spaceship",
+        }
+    }
+
+    fn is_unlocked(self, stats: &Stats) -> bool {
+        match self {
+            Achievement::Thousand => stats.generations >= 1000,
+            Achievement::Million => stats.generations >= 1_000_000,
+            Achievement::Methuselah => stats.longest_lived >= 5000,
+            Achievement::LongOscillator => stats.longest_period >= 8,
+            Achievement::Spaceship => stats.spaceships > 0,
+        }
+    }
+}
+
+/// Totals over every game played.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Stats {
+    /// Generations run in all.
+    pub generations: u64,
+    /// The most generations a board has run before settling into a still
+    /// life, oscillator or spaceship, or dying out.
+    pub longest_lived: u64,
+    /// The longest period of any oscillator found.
+    pub longest_period: u64,
+    /// How many times a spaceship has been found.
+    pub spaceships: u64,
+}
+
+impl Stats {
+    /// Reads the stats saved at `path`, starting afresh if there are none.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Stats, String> {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Stats::default()),
+            Err(e) => return Err(format!("could not read {}: {}", path.display(), e)),
+        };
+        serde_json::from_str(&text).map_err(|e| format!("{}: invalid stats: {}", path.display(), e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = serde_json::to_string(self).expect("stats always serialize");
+        fs::write(path, text)
+    }
+
+    /// The achievements unlocked so far.
+    pub fn unlocked(&self) -> Vec<Achievement> {
+        ACHIEVEMENTS
+            .iter()
+            .copied()
+            .filter(|achievement| achievement.is_unlocked(self))
+            .collect()
+    }
+
+    /// Counts a generation run, returning any achievement that unlocks.
+    pub fn record_generation(&mut self) -> Vec<Achievement> {
+        self.record(|stats| stats.generations += 1)
+    }
+
+    /// Counts a board settling into `behaviour` after `lifespan`
+    /// generations, returning any achievements that unlocks.
+    pub fn record_settled(&mut self, lifespan: u64, behaviour: Behaviour) -> Vec<Achievement> {
+        self.record(|stats| {
+            stats.longest_lived = stats.longest_lived.max(lifespan);
+            match behaviour {
+                Behaviour::Oscillator { period } => {
+                    stats.longest_period = stats.longest_period.max(period)
+                }
+                Behaviour::Spaceship { .. } => stats.spaceships += 1,
+                Behaviour::Extinct | Behaviour::StillLife => {}
+            }
+        })
+    }
+
+    fn record(&mut self, change: impl FnOnce(&mut Stats)) -> Vec<Achievement> {
+        let before = self.unlocked();
+        change(self);
+        self.unlocked()
+            .into_iter()
+            .filter(|achievement| !before.contains(achievement))
+            .collect()
+    }
+
+    /// The text of the stats screen: the totals, then every achievement
+    /// with those unlocked ticked.
+    pub fn lines(&self, language: Language) -> Vec<String> {
+        let mut lines = vec![
+            language.tr("Stats").to_string(),
+            String::new(),
+            language.fill("Generations run: {}", &[&self.generations]),
+            language.fill(
+                "Longest-lived board: {} generations",
+                &[&self.longest_lived],
+            ),
+            language.fill("Longest oscillator: period {}", &[&self.longest_period]),
+            language.fill("Spaceships found: {}", &[&self.spaceships]),
+            String::new(),
+            language.tr("Achievements").to_string(),
+        ];
+        let unlocked = self.unlocked();
+        for achievement in &ACHIEVEMENTS {
+            let tick = if unlocked.contains(achievement) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            lines.push(format!(
+                "{} {}",
+                tick,
+                language.tr(achievement.description())
+            ));
+        }
+        lines
+    }
+}
+
+/// Where the stats are kept, in the platform's data directory, such as
+/// `~/.local/share/conways_game_of_life/stats.json` on Linux.
+pub fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("conways_game_of_life").join("stats.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlocking_test() {
+        let mut stats = Stats {
+            generations: 998,
+            ..Stats::default()
+        };
+
+        assert_eq!(stats.record_generation(), vec![]);
+        assert_eq!(stats.record_generation(), vec![Achievement::Thousand]);
+        assert_eq!(stats.record_generation(), vec![]);
+        let settled = stats.record_settled(6000, Behaviour::Oscillator { period: 15 });
+        assert_eq!(
+            settled,
+            vec![Achievement::Methuselah, Achievement::LongOscillator]
+        );
+        stats.record_settled(10, Behaviour::Oscillator { period: 2 });
+        assert_eq!(stats.longest_lived, 6000);
+        assert_eq!(stats.longest_period, 15);
+        assert_eq!(stats.unlocked().len(), 3);
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let path = std::env::temp_dir()
+            .join(format!("stats_test_{}", std::process::id()))
+            .join("stats.json");
+        assert_eq!(Stats::load(&path), Ok(Stats::default()));
+        let stats = Stats {
+            generations: 12,
+            spaceships: 1,
+            ..Stats::default()
+        };
+
+        stats.save(&path).unwrap();
+
+        assert_eq!(Stats::load(&path), Ok(stats));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn lines_test() {
+        let stats = Stats {
+            spaceships: 2,
+            ..Stats::default()
+        };
+
+        let lines = stats.lines(Language::English);
+
+        assert_eq!(lines[5], "Spaceships found: 2");
+        assert_eq!(lines.last().unwrap(), "[x] found a spaceship");
+        assert_eq!(lines[8], "[ ] ran 1,000 generations in all");
+    }
+}
@@ -0,0 +1,212 @@
+use crate::grid::Grid;
+use crate::render::{self, Palette};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::error::{EncodingError, ImageFormatHint};
+use image::{Delay, DynamicImage, Frame, ImageError, ImageFormat, ImageResult};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Milliseconds each frame of a saved animation is shown for by default.
+pub const DEFAULT_FRAME_DELAY: u32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    /// Animated PNG, which keeps colours exactly where a GIF is limited to
+    /// a palette of 256.
+    Apng,
+}
+
+impl AnimationFormat {
+    /// The usual file extension, an animated PNG being a PNG to anything
+    /// that can't animate it.
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        }
+    }
+}
+
+impl FromStr for AnimationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AnimationFormat, String> {
+        match s {
+            "gif" => Ok(AnimationFormat::Gif),
+            "apng" => Ok(AnimationFormat::Apng),
+            _ => Err(format!("unknown animation format '{}', use gif or apng", s)),
+        }
+    }
+}
+
+/// How the frames of a recording are drawn and timed when it is saved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStyle {
+    pub palette: Palette,
+    /// Pixels per cell.
+    pub scale: u32,
+    /// Milliseconds per frame.
+    pub delay: u32,
+}
+
+impl Default for FrameStyle {
+    fn default() -> FrameStyle {
+        FrameStyle {
+            palette: Palette::default(),
+            scale: render::DEFAULT_SCALE,
+            delay: DEFAULT_FRAME_DELAY,
+        }
+    }
+}
+
+/// Successive generations captured for saving as an animation. The boards
+/// themselves are kept rather than images, so a long recording stays small
+/// until it is rendered.
+pub struct Recording {
+    frames: Vec<Grid>,
+    limit: Option<usize>,
+}
+
+impl Recording {
+    /// A recording that stops capturing after `limit` frames, if given.
+    pub fn new(limit: Option<usize>) -> Recording {
+        Recording {
+            frames: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Adds `grid` as the next frame, unless the recording is full.
+    pub fn capture(&mut self, grid: &Grid) {
+        if !self.is_full() {
+            self.frames.push(grid.clone());
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.frames.len() >= limit)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: AnimationFormat,
+        style: FrameStyle,
+    ) -> ImageResult<()> {
+        match format {
+            AnimationFormat::Gif => self.save_gif(path, style),
+            AnimationFormat::Apng => self.save_apng(path, style),
+        }
+    }
+
+    /// Saves the frames as a GIF that loops forever.
+    pub fn save_gif<P: AsRef<Path>>(&self, path: P, style: FrameStyle) -> ImageResult<()> {
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(self.frames.iter().map(|grid| {
+            let image = render::render(grid, style.palette, style.scale);
+            Frame::from_parts(
+                DynamicImage::ImageRgb8(image).to_rgba8(),
+                0,
+                0,
+                Delay::from_numer_denom_ms(style.delay, 1),
+            )
+        }))
+    }
+
+    /// Saves the frames as an animated PNG that loops forever.
+    pub fn save_apng<P: AsRef<Path>>(&self, path: P, style: FrameStyle) -> ImageResult<()> {
+        let (width, height) = match self.frames.first() {
+            Some(grid) => (grid.width() as u32, grid.height() as u32),
+            None => (0, 0),
+        };
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, width * style.scale, height * style.scale);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(self.frames.len() as u32, 0)
+            .map_err(png_error)?;
+        // Delays are a fraction of a second with a 16 bit numerator.
+        encoder
+            .set_frame_delay(style.delay.min(u16::MAX as u32) as u16, 1000)
+            .map_err(png_error)?;
+
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        for grid in &self.frames {
+            let image = render::render(grid, style.palette, style.scale);
+            writer.write_image_data(image.as_raw()).map_err(png_error)?;
+        }
+        writer.finish().map_err(png_error)
+    }
+}
+
+fn png_error(error: png::EncodingError) -> ImageError {
+    ImageError::Encoding(EncodingError::new(
+        ImageFormatHint::Exact(ImageFormat::Png),
+        error,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    #[test]
+    fn gif_test() {
+        let path = std::env::temp_dir().join(format!("gif_test_{}.gif", std::process::id()));
+        let mut grid = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let mut recording = Recording::new(Some(3));
+        for _ in 0..5 {
+            recording.capture(&grid);
+            grid = grid.next_generation();
+        }
+        let style = FrameStyle {
+            scale: 2,
+            ..FrameStyle::default()
+        };
+
+        recording.save_gif(&path, style).unwrap();
+
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(recording.is_full());
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].buffer().dimensions(), (6, 6));
+    }
+
+    #[test]
+    fn apng_test() {
+        let path = std::env::temp_dir().join(format!("apng_test_{}.png", std::process::id()));
+        let mut recording = Recording::new(None);
+        recording.capture(&Grid::seed(2, vec![(0, 0)]));
+        recording.capture(&Grid::seed(2, vec![(1, 1)]));
+
+        recording
+            .save(&path, AnimationFormat::Apng, FrameStyle::default())
+            .unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!(info.animation_control().map(|a| a.num_frames), Some(2));
+        assert_eq!((info.width, info.height), (20, 20));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!("apng".parse(), Ok(AnimationFormat::Apng));
+        assert!("webm".parse::<AnimationFormat>().is_err());
+    }
+}
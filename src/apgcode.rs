@@ -0,0 +1,317 @@
+//! Apgcodes, the names Catagolue gives the objects its searches find, like
+//! `xs4_33` for the block or `xq4_153` for the glider. The prefix says what
+//! kind of object it is, `xs` a still life with that many cells, `xp` an
+//! oscillator and `xq` a spaceship of that period, and the rest is the
+//! object itself in extended Wechsler format, so it can be drawn without
+//! downloading anything.
+//!
+//! In that format the object is cut into strips five rows deep, from the
+//! top, separated by `z`. Each `0` to `9` or `a` to `v` is a column of a
+//! strip, its value in base 32 the cells alive in it with the top one as
+//! the lowest bit. `w` and `x` stand for two and three empty columns, and
+//! `y` followed by one of `0` to `9` or `a` to `z` for four to 39.
+//!
+//! The same object has many such codes, one for every phase and
+//! orientation, so the one Catagolue uses is the shortest of them, and of
+//! those the first in alphabetical order.
+
+use crate::census;
+use crate::cycle;
+use crate::plane::Plane;
+use crate::rle::Pattern;
+use crate::rule::Rule;
+
+/// The generations an object is run for at most while waiting for it to
+/// repeat, so the longest period it can be given an apgcode for.
+const MOST_GENERATIONS: u64 = 1000;
+
+/// The population past which an object is taken to be growing without end.
+const MOST_POPULATION: usize = 1000;
+
+/// The digits after `y` counting four to 39 empty columns.
+const RUNS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Where Catagolue describes an object, by its apgcode and then its rule.
+pub const CATAGOLUE: &str = "https://catagolue.hatsya.com/object/";
+
+/// The pattern `spec` names by its apgcode, alone like `xq4_153` or in the
+/// URL of its Catagolue page, which gives its rule as well. `None` if
+/// `spec` isn't one.
+pub fn from_spec(spec: &str) -> Option<Result<Pattern, String>> {
+    let (code, rule) = match spec.strip_prefix(CATAGOLUE) {
+        Some(rest) => rest.split_once('/').unwrap_or((rest, "")),
+        None => (spec, ""),
+    };
+    if !is_apgcode(code) {
+        return None;
+    }
+    Some(decode(code).and_then(|mut pattern| {
+        let rule = rule.trim_end_matches('/');
+        if !rule.is_empty() {
+            pattern.rule = rule.parse::<Rule>()?;
+        }
+        Ok(pattern)
+    }))
+}
+
+/// Whether `code` looks like an apgcode that can be drawn, an `xs`, `xp` or
+/// `xq` followed by a number and an underscore.
+pub fn is_apgcode(code: &str) -> bool {
+    let rest = match code
+        .strip_prefix("xs")
+        .or_else(|| code.strip_prefix("xp"))
+        .or_else(|| code.strip_prefix("xq"))
+    {
+        Some(rest) => rest,
+        None => return false,
+    };
+    match rest.split_once('_') {
+        Some((number, _)) => !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()),
+        None => false,
+    }
+}
+
+/// The object `code` stands for, at the top left of a pattern just big
+/// enough for it, under Conway's rule.
+pub fn decode(code: &str) -> Result<Pattern, String> {
+    let bad = |why: &str| format!("bad apgcode '{}': {}", code, why);
+    if !is_apgcode(code) {
+        return Err(bad("it should start like xs4_, xp2_ or xq4_"));
+    }
+    let (_, wechsler) = code.split_once('_').unwrap_or_default();
+    let mut living_cells = Vec::new();
+    let (mut strip, mut col) = (0, 0);
+    let mut chars = wechsler.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            'w' => col += 2,
+            'x' => col += 3,
+            'y' => {
+                let count = chars
+                    .next()
+                    .and_then(|count| count.to_digit(36))
+                    .ok_or_else(|| bad("y must be followed by 0 to 9 or a to z"))?;
+                col += 4 + count as usize;
+            }
+            'z' => {
+                strip += 1;
+                col = 0;
+            }
+            _ => {
+                let column = c
+                    .to_digit(32)
+                    .filter(|_| c.is_ascii_digit() || c.is_ascii_lowercase())
+                    .ok_or_else(|| bad(&format!("unexpected '{}'", c)))?;
+                for bit in 0..5 {
+                    if column & 1 << bit != 0 {
+                        living_cells.push((strip * 5 + bit, col));
+                    }
+                }
+                col += 1;
+            }
+        }
+    }
+    if living_cells.is_empty() {
+        return Err(bad("it has no living cells"));
+    }
+    let top = living_cells.iter().map(|cell| cell.0).min().unwrap_or(0);
+    let left = living_cells.iter().map(|cell| cell.1).min().unwrap_or(0);
+    for cell in &mut living_cells {
+        *cell = (cell.0 - top, cell.1 - left);
+    }
+    living_cells.sort_unstable();
+    Ok(Pattern {
+        width: living_cells
+            .iter()
+            .map(|cell| cell.1 + 1)
+            .max()
+            .unwrap_or(0),
+        height: living_cells
+            .iter()
+            .map(|cell| cell.0 + 1)
+            .max()
+            .unwrap_or(0),
+        living_cells,
+        rule: Rule::default(),
+    })
+}
+
+/// The apgcode of the object `cells` as it goes on by `rule`, wherever it
+/// lies, or none if it dies out, grows or doesn't come back to how it
+/// started within `MOST_GENERATIONS`.
+pub fn of_object(cells: &[(i64, i64)], rule: Rule) -> Option<String> {
+    let plane = Plane::from_cells(cells);
+    let (_, cycle) = cycle::run_to_cycle(plane.clone(), rule, MOST_GENERATIONS, MOST_POPULATION);
+    let cycle = cycle.filter(|cycle| cycle.first_seen == 0)?;
+    let mut phases = Vec::new();
+    let mut plane = plane;
+    for _ in 0..cycle.period {
+        phases.push(plane.living_cells());
+        plane = plane.next_generation_under(rule);
+    }
+    Some(encode(&phases, cycle.displacement != (0, 0)))
+}
+
+/// The apgcode of an object with `phases`, one for each generation of its
+/// period, that `moves` or doesn't.
+pub fn encode(phases: &[Vec<(i64, i64)>], moves: bool) -> String {
+    let prefix = match phases {
+        _ if moves => format!("xq{}", phases.len()),
+        [still] => format!("xs{}", still.len()),
+        _ => format!("xp{}", phases.len()),
+    };
+    let wechsler = phases
+        .iter()
+        .map(|phase| canonical_wechsler(phase))
+        .min_by(|a, b| (a.len(), a).cmp(&(b.len(), b)))
+        .unwrap_or_default();
+    format!("{}_{}", prefix, wechsler)
+}
+
+/// The shortest extended Wechsler code of `cells` in any orientation, the
+/// first alphabetically of the shortest.
+pub fn canonical_wechsler(cells: &[(i64, i64)]) -> String {
+    census::orientations(cells)
+        .iter()
+        .map(|shape| wechsler(shape))
+        .min_by(|a, b| (a.len(), a).cmp(&(b.len(), b)))
+        .unwrap_or_default()
+}
+
+/// The extended Wechsler code of `cells`, which are moved to the origin.
+fn wechsler(cells: &[(i64, i64)]) -> String {
+    let width = cells.iter().map(|cell| cell.1 + 1).max().unwrap_or(0) as usize;
+    let height = cells.iter().map(|cell| cell.0 + 1).max().unwrap_or(0) as usize;
+    let mut strips = vec![vec![0; width]; height.div_ceil(5)];
+    for &(row, col) in cells {
+        strips[row as usize / 5][col as usize] |= 1 << (row % 5);
+    }
+    let strips: Vec<String> = strips
+        .iter()
+        .map(|strip| {
+            let mut text = String::new();
+            let mut empty = 0;
+            for &column in strip {
+                if column == 0 {
+                    empty += 1;
+                    continue;
+                }
+                while empty >= 40 {
+                    text.push_str("yz");
+                    empty -= 39;
+                }
+                match empty {
+                    0 => {}
+                    1 => text.push('0'),
+                    2 => text.push('w'),
+                    3 => text.push('x'),
+                    _ => {
+                        text.push('y');
+                        text.push(RUNS[empty - 4] as char);
+                    }
+                }
+                empty = 0;
+                text.extend(std::char::from_digit(column, 32));
+            }
+            text
+        })
+        .collect();
+    strips.join("z")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle;
+
+    #[test]
+    fn decode_test() {
+        assert_eq!(decode("xs4_33"), rle::parse("2o$2o!"));
+        assert_eq!(decode("xp2_7"), rle::parse("o$o$o!"));
+        assert_eq!(decode("xq4_153"), rle::parse("3o$2bo$bo!"));
+        // The beehive, on its end.
+        assert_eq!(decode("xs6_696"), rle::parse("bo$obo$obo$bo!"));
+        // Two blocks four columns apart.
+        let gap = decode("xs8_33y033").unwrap();
+        assert_eq!(gap.width, 8);
+        assert_eq!(gap.living_cells.len(), 8);
+        // A second strip starts five rows down.
+        let tall = decode("xs2_1z1").unwrap();
+        assert_eq!(tall.living_cells, vec![(0, 0), (5, 0)]);
+        assert_eq!((tall.width, tall.height), (1, 6));
+
+        assert!(decode("xs4_").is_err());
+        assert!(decode("xs4_3!").is_err());
+        assert!(decode("xs4_3y").is_err());
+        assert!(decode("yl144_1_16_afb5f3db909e60548f086e22ee3353ac").is_err());
+    }
+
+    #[test]
+    fn of_object_test() {
+        let conway = Rule::default();
+        let code = |text: &str| {
+            let pattern = rle::parse(text).unwrap();
+            let cells: Vec<(i64, i64)> = pattern
+                .living_cells
+                .iter()
+                .map(|&(row, col)| (row as i64 + 7, col as i64 - 3))
+                .collect();
+            of_object(&cells, conway)
+        };
+        let known = [
+            ("2o$2o!", "xs4_33"),
+            ("b2o$o2bo$b2o!", "xs6_696"),
+            ("3o!", "xp2_7"),
+            ("bo$2bo$3o!", "xq4_153"),
+            ("b2o$o2bo$o2bo$b2o!", "xs8_6996"),
+            ("b3o$3o!", "xp2_7e"),
+            ("2o$2o$2b2o$2b2o!", "xp2_318c"),
+            ("b2o$o2bo$bobo$2bo!", "xs7_2596"),
+            ("2o$obo$bo!", "xs5_253"),
+            ("2o$obo$b2o!", "xs6_356"),
+            ("bo$obo$bo!", "xs4_252"),
+            ("bo2bo$o$o3bo$4o!", "xq4_6frc"),
+        ];
+        for &(text, apgcode) in &known {
+            assert_eq!(code(text).as_deref(), Some(apgcode), "{}", text);
+        }
+        // The R-pentomino doesn't come back to how it started.
+        assert_eq!(code("b2o$2o$bo!"), None);
+        assert_eq!(code("o!"), None);
+
+        // Whatever way up and whatever phase it is drawn in, an object's
+        // code draws it again.
+        for &(_, apgcode) in &known {
+            let pattern = decode(apgcode).unwrap();
+            let cells: Vec<(i64, i64)> = pattern
+                .living_cells
+                .iter()
+                .map(|&(row, col)| (-(col as i64), row as i64))
+                .collect();
+            assert_eq!(of_object(&cells, conway).as_deref(), Some(apgcode));
+        }
+        // 44 empty columns take a y for 39 of them and another for five.
+        assert_eq!(canonical_wechsler(&[(0, 0), (0, 45)]), "1yzy11");
+    }
+
+    #[test]
+    fn from_spec_test() {
+        let glider = rle::parse("3o$2bo$bo!").unwrap();
+        assert_eq!(from_spec("xq4_153"), Some(Ok(glider.clone())));
+        assert_eq!(
+            from_spec("https://catagolue.hatsya.com/object/xq4_153/b3s23"),
+            Some(Ok(glider))
+        );
+        let highlife = from_spec("https://catagolue.hatsya.com/object/xs4_33/b36s23/")
+            .unwrap()
+            .unwrap();
+        assert_eq!(highlife.rule.to_string(), "B36/S23");
+        assert!(from_spec("https://catagolue.hatsya.com/object/xs4_33/life")
+            .unwrap()
+            .is_err());
+        assert_eq!(from_spec("Gosper glider gun"), None);
+        assert_eq!(from_spec("xsmall_blocks"), None);
+        assert!(is_apgcode("xp15_4r4z4r4"));
+        assert!(!is_apgcode("glider"));
+    }
+}
@@ -0,0 +1,431 @@
+//! A small HTTP API for driving a run without a window, so scripts and home
+//! automation can pause it, step it, look at it and add to it:
+//!
+//! - `/pause` and `/resume` stop and start the board running,
+//! - `/step?n=N` runs N generations, 1 if not given and at most
+//!   `MAX_STEP`, and answers once they have run,
+//! - `/state` answers with the board as a JSON grid,
+//! - `/metrics` answers with how the run is going for Prometheus,
+//! - `/place?pattern=P&row=R&col=C` places P, the name of one of the
+//!   starters like `glider` or a pattern in RLE, with its top left corner at
+//!   R and C, or in the middle of the board if they aren't given.
+//!
+//! Requests can be GET or POST. Everything else is answered with JSON:
+//! the generation, population and whether the board is paused, or an error.
+
+use crate::metrics::Metrics;
+use crate::rle::{self, Pattern};
+use crate::socket::Socket;
+use crate::starters;
+use serde_json::json;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a client has to send its request, and to read the response.
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The longest request a client can send.
+const MAX_REQUEST: usize = 8192;
+
+/// The most generations one `/step` runs, since the board can't do anything
+/// else until they have.
+pub const MAX_STEP: u64 = 10_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    Pause,
+    Resume,
+    Step(u64),
+    State,
+    Metrics,
+    Place {
+        pattern: Pattern,
+        /// The row and column of the pattern's top left corner.
+        at: Option<(usize, usize)>,
+    },
+}
+
+impl Request {
+    /// The request for a request line like `GET /step?n=3 HTTP/1.1`, or
+    /// the response saying why there isn't one.
+    pub fn parse(line: &str) -> Result<Request, Response> {
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method, target),
+            _ => return Err(Response::error(400, "not an HTTP request")),
+        };
+        if method != "GET" && method != "POST" {
+            return Err(Response::error(405, "use GET or POST"));
+        }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params: Vec<(String, String)> = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (name, value) = param.split_once('=').unwrap_or((param, ""));
+                (decode(name), decode(value))
+            })
+            .collect();
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(param, _)| param == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let number = |name: &str| -> Result<Option<usize>, Response> {
+            param(name)
+                .map(|value| {
+                    value.parse().map_err(|_| {
+                        Response::error(400, &format!("{} must be a number, not '{}'", name, value))
+                    })
+                })
+                .transpose()
+        };
+        match path {
+            "/pause" => Ok(Request::Pause),
+            "/resume" => Ok(Request::Resume),
+            "/step" => match number("n")? {
+                Some(0) => Err(Response::error(400, "n must be at least 1")),
+                Some(n) if n as u64 > MAX_STEP => Err(Response::error(
+                    400,
+                    &format!("n must be at most {}", MAX_STEP),
+                )),
+                n => Ok(Request::Step(n.unwrap_or(1) as u64)),
+            },
+            "/state" => Ok(Request::State),
+            "/metrics" => Ok(Request::Metrics),
+            "/place" => {
+                let pattern = match param("pattern") {
+                    Some(spec) => pattern(spec).map_err(|e| Response::error(400, &e))?,
+                    None => return Err(Response::error(400, "/place needs a pattern")),
+                };
+                let at = match (number("row")?, number("col")?) {
+                    (Some(row), Some(col)) => Some((row, col)),
+                    (None, None) => None,
+                    _ => return Err(Response::error(400, "give both row and col, or neither")),
+                };
+                Ok(Request::Place { pattern, at })
+            }
+            _ => Err(Response::error(404, &format!("there is no {}", path))),
+        }
+    }
+}
+
+/// The starter called `spec`, ignoring case, or else `spec` read as RLE.
+fn pattern(spec: &str) -> Result<Pattern, String> {
+    let starter = (0..starters::len())
+        .filter_map(starters::starter)
+        .find(|(name, _)| name.eq_ignore_ascii_case(spec));
+    match starter {
+        Some((_, pattern)) => Ok(pattern),
+        None => rle::parse(spec)
+            .map_err(|e| format!("'{}' is not a starter's name or RLE: {}", spec, e)),
+    }
+}
+
+/// Undoes the `%XX` and `+` escapes of a URL's query.
+fn decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' if rest.len() >= 2 => {
+                match std::str::from_utf8(&rest[..2])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(decoded) => {
+                        bytes.push(decoded);
+                        rest = &rest[2..];
+                    }
+                    None => bytes.push(byte),
+                }
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    /// A response of `body`, a JSON document.
+    pub fn ok(body: String) -> Response {
+        Response {
+            status: 200,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    /// A response of `metrics` in the Prometheus text format.
+    pub fn metrics(metrics: &Metrics) -> Response {
+        Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            body: metrics.to_prometheus(),
+        }
+    }
+
+    /// The response for a board at `generation` with `population` living
+    /// cells, paused or not.
+    pub fn status(generation: u64, population: usize, paused: bool) -> Response {
+        Response::ok(
+            json!({"generation": generation, "population": population, "paused": paused})
+                .to_string(),
+        )
+    }
+
+    pub fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            body: json!({ "error": message }).to_string(),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Error",
+        }
+    }
+}
+
+pub struct Api {
+    listener: TcpListener,
+    /// Those who have connected but not yet sent all of their request, with
+    /// when they connected.
+    pending: Vec<(Socket, Instant)>,
+}
+
+impl Api {
+    /// Listens on `address`, a `host:port`.
+    pub fn bind(address: &str) -> io::Result<Api> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Api {
+            listener,
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// The requests that have come in since the last poll, each with where
+    /// to send its response. Those that can't be understood are answered
+    /// straight away. Nothing here waits on a client: a request that hasn't
+    /// all arrived is looked at again next time.
+    pub fn poll(&mut self) -> Vec<(Request, Reply)> {
+        while let Ok((stream, _)) = self.listener.accept() {
+            match Socket::new(stream) {
+                Ok(socket) => self.pending.push((socket, Instant::now())),
+                Err(e) => eprintln!("could not read an API request: {}", e),
+            }
+        }
+        let mut requests = Vec::new();
+        for (mut socket, since) in std::mem::take(&mut self.pending) {
+            match socket.head(MAX_REQUEST) {
+                Ok(Some(head)) => {
+                    let reply = Reply { socket };
+                    match Request::parse(head.lines().next().unwrap_or_default()) {
+                        Ok(request) => requests.push((request, reply)),
+                        Err(response) => reply.send(response),
+                    }
+                }
+                Ok(None) if since.elapsed() < TIMEOUT => self.pending.push((socket, since)),
+                Ok(None) => eprintln!("could not read an API request: it never all came"),
+                Err(e) => eprintln!("could not read an API request: {}", e),
+            }
+        }
+        requests
+    }
+}
+
+/// The connection a request came in on, to send its response down.
+pub struct Reply {
+    socket: Socket,
+}
+
+impl Reply {
+    /// Sends `response`, closing the connection. A client that has gone
+    /// away by now doesn't want it, so errors are ignored. What the client
+    /// can't take at once is finished off on a thread of its own, so the
+    /// game doesn't wait for it.
+    pub fn send(mut self, response: Response) {
+        let text = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.reason(),
+            response.content_type,
+            response.body.len(),
+            response.body
+        );
+        if self.socket.send(text.as_bytes()).is_err() || self.socket.is_flushed() {
+            return;
+        }
+        thread::spawn(move || {
+            let since = Instant::now();
+            while !self.socket.is_flushed()
+                && since.elapsed() < TIMEOUT
+                && self.socket.flush().is_ok()
+            {
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(Request::parse("GET /pause HTTP/1.1"), Ok(Request::Pause));
+        assert_eq!(Request::parse("POST /resume HTTP/1.1"), Ok(Request::Resume));
+        assert_eq!(Request::parse("GET /step HTTP/1.1"), Ok(Request::Step(1)));
+        assert_eq!(
+            Request::parse("GET /step?n=25 HTTP/1.1"),
+            Ok(Request::Step(25))
+        );
+        assert_eq!(Request::parse("GET /state HTTP/1.1"), Ok(Request::State));
+        assert_eq!(
+            Request::parse("GET /metrics HTTP/1.1"),
+            Ok(Request::Metrics)
+        );
+
+        assert_eq!(
+            Request::parse("GET /step?n=0 HTTP/1.1").unwrap_err().status,
+            400
+        );
+        assert_eq!(
+            Request::parse("GET /step?n=x HTTP/1.1").unwrap_err().status,
+            400
+        );
+        assert_eq!(
+            Request::parse("GET /step?n=10000 HTTP/1.1"),
+            Ok(Request::Step(MAX_STEP))
+        );
+        for n in &["10001", "18446744073709551615"] {
+            let line = format!("GET /step?n={} HTTP/1.1", n);
+            assert_eq!(Request::parse(&line).unwrap_err().status, 400);
+        }
+        assert_eq!(
+            Request::parse("GET /nowhere HTTP/1.1").unwrap_err().status,
+            404
+        );
+        assert_eq!(
+            Request::parse("DELETE /pause HTTP/1.1").unwrap_err().status,
+            405
+        );
+        assert_eq!(Request::parse("").unwrap_err().status, 400);
+    }
+
+    #[test]
+    fn place_test() {
+        let glider = starters::starter(0).unwrap().1;
+        assert_eq!(
+            Request::parse("GET /place?pattern=Glider&row=3&col=4 HTTP/1.1"),
+            Ok(Request::Place {
+                pattern: glider.clone(),
+                at: Some((3, 4)),
+            })
+        );
+        assert_eq!(
+            Request::parse("GET /place?pattern=bo%242bo%243o! HTTP/1.1"),
+            Ok(Request::Place {
+                pattern: glider,
+                at: None,
+            })
+        );
+        let gun = starters::starter(4).unwrap().1;
+        assert_eq!(
+            Request::parse("GET /place?pattern=gosper+glider%20gun HTTP/1.1"),
+            Ok(Request::Place {
+                pattern: gun,
+                at: None,
+            })
+        );
+        assert_eq!(
+            Request::parse("GET /place HTTP/1.1").unwrap_err().status,
+            400
+        );
+        assert_eq!(
+            Request::parse("GET /place?pattern=glider&row=3 HTTP/1.1")
+                .unwrap_err()
+                .status,
+            400
+        );
+    }
+
+    #[test]
+    fn huge_place_test() {
+        let request =
+            Request::parse("GET /place?pattern=glider&row=18446744073709551615&col=0 HTTP/1.1");
+        let (pattern, (row, col)) = match request {
+            Ok(Request::Place {
+                pattern,
+                at: Some(at),
+            }) => (pattern, at),
+            request => panic!("{:?}", request),
+        };
+        assert_eq!(row, usize::MAX);
+        let mut grid = Grid::seed(10, Vec::new());
+        assert!(pattern.place(&mut grid, row, col).is_err());
+        assert!(pattern.place(&mut grid, col, row).is_err());
+    }
+
+    #[test]
+    fn decode_test() {
+        assert_eq!(decode("a+b%21%2x%"), "a b!%2x%");
+        assert_eq!(decode("caf%C3%A9"), "café");
+    }
+
+    #[test]
+    fn poll_test() {
+        let mut api = Api::bind("127.0.0.1:0").unwrap();
+        let address = api.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(b"GET /step?n=2 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let mut requests = Vec::new();
+        while requests.is_empty() {
+            requests = api.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let (request, reply) = requests.pop().unwrap();
+        assert_eq!(request, Request::Step(2));
+        reply.send(Response::status(2, 5, true));
+
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"generation":2,"paused":true,"population":5}"#));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::history::History;
+use std::collections::BTreeSet;
+use std::mem;
+
+/// One line of play: its stored generations, the generation it was left
+/// at and its bookmarks.
+#[derive(Clone)]
+pub struct Branch {
+    pub history: History,
+    pub generation: u64,
+    pub bookmarks: BTreeSet<u64>,
+}
+
+struct Node {
+    parent: Option<usize>,
+    forked_at: u64,
+    /// The branch while another one is being shown, `None` for the one
+    /// being shown.
+    parked: Option<Branch>,
+}
+
+/// A tree of alternate histories. Editing a generation that has already
+/// been run past starts a new branch from it, so that what happened the
+/// first time can still be switched back to. The branch being shown is
+/// held by the caller, the others are parked here.
+pub struct BranchTree {
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+impl BranchTree {
+    pub fn new() -> BranchTree {
+        BranchTree {
+            nodes: vec![Node {
+                parent: None,
+                forked_at: 0,
+                parked: None,
+            }],
+            current: 0,
+        }
+    }
+
+    /// The index of the branch being shown, the first branch being 0.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The branch that `index` split off from, and the generation it split
+    /// off at.
+    pub fn parent(&self, index: usize) -> Option<(usize, u64)> {
+        let node = self.nodes.get(index)?;
+        node.parent.map(|parent| (parent, node.forked_at))
+    }
+
+    /// How many forks lie between the first branch and `index`.
+    pub fn depth(&self, index: usize) -> usize {
+        let mut depth = 0;
+        let mut index = index;
+        while let Some((parent, _)) = self.parent(index) {
+            depth += 1;
+            index = parent;
+        }
+        depth
+    }
+
+    /// Parks `branch`, the one being shown, and starts a new branch off it
+    /// at `generation`, returning the new branch's index.
+    pub fn fork(&mut self, branch: Branch, generation: u64) -> usize {
+        self.nodes[self.current].parked = Some(branch);
+        self.nodes.push(Node {
+            parent: Some(self.current),
+            forked_at: generation,
+            parked: None,
+        });
+        self.current = self.nodes.len() - 1;
+        self.current
+    }
+
+    /// Parks `branch`, the one being shown, and replaces it with the branch
+    /// at `index`. Returns false, leaving `branch` alone, if there is no
+    /// such branch or it is already being shown.
+    pub fn switch(&mut self, branch: &mut Branch, index: usize) -> bool {
+        let parked = match self
+            .nodes
+            .get_mut(index)
+            .and_then(|node| node.parked.take())
+        {
+            Some(parked) => parked,
+            None => return false,
+        };
+        self.nodes[self.current].parked = Some(mem::replace(branch, parked));
+        self.current = index;
+        true
+    }
+}
+
+impl Default for BranchTree {
+    fn default() -> BranchTree {
+        BranchTree::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    fn branch(generation: u64, population: usize) -> Branch {
+        let mut history = History::new(1024 * 1024);
+        let living_cells = (0..population).map(|col| (0, col)).collect();
+        history.push(generation, &Grid::seed(5, living_cells));
+        Branch {
+            history,
+            generation,
+            bookmarks: BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn fork_and_switch_test() {
+        let mut tree = BranchTree::new();
+        let first = tree.fork(branch(10, 1), 4);
+        let second = tree.fork(branch(6, 2), 5);
+        let mut shown = branch(7, 3);
+
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.parent(2), Some((1, 5)));
+        assert_eq!(tree.parent(0), None);
+        assert_eq!(tree.depth(2), 2);
+
+        assert!(tree.switch(&mut shown, 0));
+        assert_eq!(tree.current(), 0);
+        assert_eq!(shown.generation, 10);
+        assert!(!tree.switch(&mut shown, 0));
+        assert!(!tree.switch(&mut shown, 7));
+
+        assert!(tree.switch(&mut shown, 2));
+        assert_eq!(shown.history.population(7), Some(3));
+    }
+}
@@ -0,0 +1,397 @@
+use crate::apgcode;
+use crate::components::Components;
+use crate::grid::Grid;
+use crate::plane::Plane;
+use crate::rle;
+use crate::rule::Rule;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// The objects the census recognises: singular name, plural name and RLE.
+const OBJECTS: &[(&str, &str, &str)] = &[
+    ("block", "blocks", "2o$2o!"),
+    ("beehive", "beehives", "b2o$o2bo$b2o!"),
+    ("loaf", "loaves", "b2o$o2bo$bobo$2bo!"),
+    ("boat", "boats", "2o$obo$bo!"),
+    ("ship", "ships", "2o$obo$b2o!"),
+    ("tub", "tubs", "bo$obo$bo!"),
+    ("pond", "ponds", "b2o$o2bo$o2bo$b2o!"),
+    ("blinker", "blinkers", "3o!"),
+    ("toad", "toads", "b3o$3o!"),
+    ("beacon", "beacons", "2o$2o$2b2o$2b2o!"),
+    ("glider", "gliders", "bo$2bo$3o!"),
+    (
+        "lightweight spaceship",
+        "lightweight spaceships",
+        "bo2bo$o4b$o3bo$4o!",
+    ),
+];
+
+/// A living cell as `(row, column)`, signed so shapes can be reflected.
+type Cell = (i64, i64);
+
+/// The number of each kind of object on a board. Objects are found by
+/// grouping living cells whose neighbourhoods touch, then matched against
+/// every phase and orientation of a small dictionary of common objects;
+/// anything else is counted as `other`.
+///
+/// A census taken under a rule also knows each object's apgcode, known or
+/// not, which is slower since every object has to be run through its
+/// phases.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Census {
+    counts: BTreeMap<&'static str, usize>,
+    pub other: usize,
+    codes: BTreeMap<String, usize>,
+}
+
+impl Census {
+    pub fn take(grid: &Grid) -> Census {
+        Census::of_objects(objects(grid))
+    }
+
+    /// The census of everything alive on `plane`, however far apart.
+    pub fn of_plane(plane: &Plane) -> Census {
+        Census::of_objects(objects_among(plane.living_cells()))
+    }
+
+    /// The census of everything alive on `plane`, with the apgcode of
+    /// every object as it goes on by `rule`. Objects that don't repeat on
+    /// their own, which can happen when two are close enough to meet
+    /// later, aren't given one.
+    pub fn of_plane_under(plane: &Plane, rule: Rule) -> Census {
+        let objects = objects_among(plane.living_cells());
+        let mut codes = BTreeMap::new();
+        for object in &objects {
+            if let Some(code) = apgcode::of_object(object, rule) {
+                *codes.entry(code).or_insert(0) += 1;
+            }
+        }
+        Census {
+            codes,
+            ..Census::of_objects(objects)
+        }
+    }
+
+    fn of_objects(objects: Vec<Vec<Cell>>) -> Census {
+        let dictionary = dictionary();
+        let mut census = Census::default();
+
+        for object in objects {
+            match dictionary.get(&canonical(&object)) {
+                Some(&name) => *census.counts.entry(name).or_insert(0) += 1,
+                None => census.other += 1,
+            }
+        }
+
+        census
+    }
+
+    /// How many there are of each kind of object found, by name.
+    pub fn counts(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.counts.iter().map(|(&name, &count)| (name, count))
+    }
+
+    /// How many there are of each object, by apgcode, if the census was
+    /// taken under a rule.
+    pub fn apgcodes(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.codes
+            .iter()
+            .map(|(code, &count)| (code.as_str(), count))
+    }
+}
+
+impl fmt::Display for Census {
+    /// Lists the objects most common first, e.g. `12 blocks, 3 gliders`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut counts: Vec<(&str, usize)> = self.counts.iter().map(|(n, c)| (*n, *c)).collect();
+        counts.sort_by_key(|&(_, count)| Reverse(count));
+
+        let mut entries: Vec<String> = counts
+            .iter()
+            .map(|&(name, count)| format!("{} {}", count, display_name(name, count)))
+            .collect();
+        if self.other > 0 {
+            let noun = if self.other == 1 { "object" } else { "objects" };
+            entries.push(format!("{} other {}", self.other, noun));
+        }
+
+        if entries.is_empty() {
+            write!(f, "no objects")
+        } else {
+            write!(f, "{}", entries.join(", "))
+        }
+    }
+}
+
+fn display_name(name: &str, count: usize) -> &str {
+    OBJECTS
+        .iter()
+        .find(|object| object.0 == name)
+        .map_or(name, |object| if count == 1 { object.0 } else { object.1 })
+}
+
+/// Maps the canonical form of every phase of every known object to its
+/// name.
+fn dictionary() -> &'static HashMap<Vec<Cell>, &'static str> {
+    static DICTIONARY: OnceLock<HashMap<Vec<Cell>, &'static str>> = OnceLock::new();
+    DICTIONARY.get_or_init(|| {
+        let mut dictionary = HashMap::new();
+        for &(name, _, text) in OBJECTS {
+            let pattern = rle::parse(text).expect("census objects are valid RLE");
+            for phase in phases(&pattern) {
+                dictionary.insert(phase, name);
+            }
+        }
+        dictionary
+    })
+}
+
+/// Evolves a pattern on its own until it repeats, returning the canonical
+/// form of each phase it went through.
+fn phases(pattern: &rle::Pattern) -> Vec<Vec<Cell>> {
+    let margin = 4;
+    let living_cells = pattern
+        .living_cells
+        .iter()
+        .map(|&(row, col)| (row + margin, col + margin))
+        .collect();
+    let mut grid = Grid::seed_rectangle(
+        pattern.width + 2 * margin,
+        pattern.height + 2 * margin,
+        living_cells,
+    );
+
+    let mut phases: Vec<Vec<Cell>> = Vec::new();
+    loop {
+        let cells: Vec<Cell> = grid
+            .living_cells()
+            .into_iter()
+            .map(|(row, col)| (row as i64, col as i64))
+            .collect();
+        let phase = canonical(&cells);
+        if phases.contains(&phase) {
+            return phases;
+        }
+        phases.push(phase);
+        grid = grid.next_generation();
+    }
+}
+
+/// Splits the living cells into objects, two cells belonging to the same
+/// object when they are within two cells of each other so that their
+/// neighbourhoods overlap.
+fn objects(grid: &Grid) -> Vec<Vec<Cell>> {
+    Components::label_with_reach(grid, 2)
+        .clusters()
+        .into_iter()
+        .map(|cluster| {
+            cluster
+                .into_iter()
+                .map(|(row, col)| (row as i64, col as i64))
+                .collect()
+        })
+        .collect()
+}
+
+/// Splits `cells` into objects the same way as `objects`, for cells that
+/// can be any distance apart.
+pub fn objects_among(cells: Vec<Cell>) -> Vec<Vec<Cell>> {
+    clusters_among(cells, 2)
+}
+
+/// Splits `cells` into groups, two cells belonging to the same group when
+/// they are within `reach` cells of each other across and down. The groups
+/// come in order of their first cells, the same on every run.
+pub fn clusters_among(cells: Vec<Cell>, reach: i64) -> Vec<Vec<Cell>> {
+    let mut unvisited: BTreeSet<Cell> = cells.into_iter().collect();
+    let mut objects = Vec::new();
+    while let Some(&start) = unvisited.iter().next() {
+        unvisited.remove(&start);
+        let mut object = vec![start];
+        let mut next = 0;
+        while let Some(&(row, col)) = object.get(next) {
+            next += 1;
+            for row in row.saturating_sub(reach)..=row.saturating_add(reach) {
+                for col in col.saturating_sub(reach)..=col.saturating_add(reach) {
+                    if unvisited.remove(&(row, col)) {
+                        object.push((row, col));
+                    }
+                }
+            }
+        }
+        objects.push(object);
+    }
+    objects
+}
+
+/// The smallest of the eight rotations and reflections of a shape, moved
+/// to the origin, so that the same object compares equal however it lies.
+pub fn canonical(cells: &[Cell]) -> Vec<Cell> {
+    orientations(cells).into_iter().min().unwrap_or_default()
+}
+
+/// The eight rotations and reflections of a shape, each moved to the
+/// origin with its cells in order.
+pub fn orientations(cells: &[Cell]) -> Vec<Vec<Cell>> {
+    let transforms: [fn(Cell) -> Cell; 8] = [
+        |(r, c)| (r, c),
+        |(r, c)| (r, -c),
+        |(r, c)| (-r, c),
+        |(r, c)| (-r, -c),
+        |(r, c)| (c, r),
+        |(r, c)| (c, -r),
+        |(r, c)| (-c, r),
+        |(r, c)| (-c, -r),
+    ];
+
+    // Moved to the origin first, so that none of them are too far out to
+    // turn round.
+    let cells = to_origin(cells.iter().copied());
+    transforms
+        .iter()
+        .map(|transform| {
+            let mut shape = to_origin(cells.iter().map(|&cell| transform(cell)));
+            shape.sort_unstable();
+            shape
+        })
+        .collect()
+}
+
+/// `cells` moved so that their top and left are at 0.
+fn to_origin<I: Iterator<Item = Cell> + Clone>(cells: I) -> Vec<Cell> {
+    let top = cells.clone().map(|cell| cell.0).min().unwrap_or(0);
+    let left = cells.clone().map(|cell| cell.1).min().unwrap_or(0);
+    cells
+        .map(|(row, col)| {
+            let moved = row.checked_sub(top).zip(col.checked_sub(left));
+            moved.expect("objects are far smaller than the plane")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Places each RLE pattern with its top left corner at the given
+    /// position.
+    fn board(size: usize, placements: &[(&str, usize, usize)]) -> Grid {
+        let mut living_cells = Vec::new();
+        for &(text, top, left) in placements {
+            let pattern = rle::parse(text).unwrap();
+            living_cells.extend(
+                pattern
+                    .living_cells
+                    .iter()
+                    .map(|&(row, col)| (row + top, col + left)),
+            );
+        }
+        Grid::seed(size, living_cells)
+    }
+
+    fn count(census: &Census, name: &str) -> usize {
+        census.counts.get(name).copied().unwrap_or(0)
+    }
+
+    #[test]
+    fn census_test() {
+        let grid = board(
+            30,
+            &[
+                ("2o$2o!", 1, 1),
+                ("2o$2o!", 1, 20),
+                ("o$o$o!", 10, 10),
+                ("3o$2bo$bo!", 20, 20),
+                ("3o$3o$3o!", 20, 2),
+            ],
+        );
+
+        let census = Census::take(&grid);
+
+        assert_eq!(count(&census, "block"), 2);
+        assert_eq!(count(&census, "blinker"), 1);
+        assert_eq!(count(&census, "glider"), 1);
+        assert_eq!(census.other, 1);
+        assert_eq!(
+            census.to_string(),
+            "2 blocks, 1 blinker, 1 glider, 1 other object"
+        );
+    }
+
+    #[test]
+    fn every_phase_test() {
+        let mut grid = board(20, &[("b3o$3o!", 5, 5), ("2o$2o$2b2o$2b2o!", 5, 14)]);
+        for _ in 0..3 {
+            let census = Census::take(&grid);
+            assert_eq!(count(&census, "toad"), 1);
+            assert_eq!(count(&census, "beacon"), 1);
+            grid = grid.next_generation();
+        }
+    }
+
+    #[test]
+    fn plane_test() {
+        let grid = board(
+            30,
+            &[("2o$2o!", 1, 1), ("o$o$o!", 10, 10), ("3o$2bo$bo!", 20, 20)],
+        );
+        let mut plane = Plane::from_grid(&grid, -500, 1_000);
+        plane.set(-10_000, -10_000, true);
+        plane.set(10_000, 10_000, true);
+        let census = Census::of_plane(&plane);
+
+        assert_eq!(count(&census, "block"), 1);
+        assert_eq!(count(&census, "blinker"), 1);
+        assert_eq!(count(&census, "glider"), 1);
+        assert_eq!(census.other, 2);
+        assert_eq!(
+            census.counts().collect::<Vec<_>>(),
+            vec![("blinker", 1), ("block", 1), ("glider", 1)]
+        );
+
+        // Blocks in the corners of the plane are still blocks.
+        let mut plane = Plane::new();
+        for &(row, col) in &[(i64::MIN, i64::MIN), (i64::MAX - 1, i64::MAX - 1)] {
+            for (dy, dx) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                plane.set(row + dy, col + dx, true);
+            }
+        }
+        assert_eq!(count(&Census::of_plane(&plane), "block"), 2);
+    }
+
+    #[test]
+    fn apgcode_test() {
+        let grid = board(
+            30,
+            &[
+                ("2o$2o!", 1, 1),
+                ("2o$2o!", 1, 20),
+                ("3o!", 10, 10),
+                ("bo$2bo$3o!", 20, 20),
+                ("3o$3o$3o!", 20, 2),
+            ],
+        );
+        let plane = Plane::from_grid(&grid, 0, 0);
+        let census = Census::of_plane_under(&plane, Rule::default());
+        assert_eq!(
+            census.apgcodes().collect::<Vec<_>>(),
+            vec![("xp2_7", 1), ("xq4_153", 1), ("xs4_33", 2)]
+        );
+        // The square of nine settles into four blinkers, so it isn't an
+        // object of its own and has no apgcode.
+        assert_eq!(census.other, 1);
+        assert_eq!(census.to_string(), Census::of_plane(&plane).to_string());
+        assert_eq!(Census::of_plane(&plane).apgcodes().count(), 0);
+    }
+
+    #[test]
+    fn empty_test() {
+        assert_eq!(
+            Census::take(&Grid::seed(5, vec![])).to_string(),
+            "no objects"
+        );
+    }
+}
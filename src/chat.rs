@@ -0,0 +1,363 @@
+//! Edits to the board from the chat of an IRC channel, such as a Twitch
+//! stream's, for "chat plays Life". Messages like `!cell 20 30` bring a
+//! cell to life and `!glider 10 10` places one of the starters with its top
+//! left corner there, by its name without spaces or punctuation, such as
+//! `!rpentomino` or `!gosperglidergun`. Other messages, and commands meant
+//! for other bots, are ignored.
+//!
+//! The connection only reads the channel, logging in anonymously the way
+//! Twitch allows, so no account or token is needed.
+
+use crate::rle::Pattern;
+use crate::socket::Socket;
+use crate::starters;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Where chat is read from unless told otherwise.
+pub const TWITCH: &str = "irc.chat.twitch.tv:6667";
+
+/// How long connecting to the server can take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest line a server is expected to send, past which the
+/// connection is given up as broken.
+const MAX_LINE: usize = 8192;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// Brings the cell at a row and column to life.
+    Cell { row: usize, col: usize },
+    /// Places a starter with its top left corner at a row and column.
+    Pattern {
+        name: &'static str,
+        pattern: Pattern,
+        row: usize,
+        col: usize,
+    },
+}
+
+impl Edit {
+    /// The edit a chat message asks for, if it is a command for this game.
+    pub fn parse(text: &str) -> Option<Edit> {
+        let mut words = text.split_whitespace();
+        let command = words.next()?.strip_prefix('!')?.to_ascii_lowercase();
+        let row = words.next()?.parse().ok()?;
+        let col = words.next()?.parse().ok()?;
+        if command == "cell" {
+            return Some(Edit::Cell { row, col });
+        }
+        (0..starters::len())
+            .filter_map(starters::starter)
+            .find(|(name, _)| command_name(name) == command)
+            .map(|(name, pattern)| Edit::Pattern {
+                name,
+                pattern,
+                row,
+                col,
+            })
+    }
+
+    /// The cells the edit brings to life on a `width` by `height` board, or
+    /// `None` if any of it would be off the board.
+    pub fn cells_on(&self, width: usize, height: usize) -> Option<Vec<(usize, usize)>> {
+        let (row, col, pattern_width, pattern_height) = match self {
+            Edit::Cell { row, col } => (*row, *col, 1, 1),
+            Edit::Pattern {
+                pattern, row, col, ..
+            } => (*row, *col, pattern.width, pattern.height),
+        };
+        let fits = row
+            .checked_add(pattern_height)
+            .is_some_and(|bottom| bottom <= height)
+            && col
+                .checked_add(pattern_width)
+                .is_some_and(|right| right <= width);
+        if !fits {
+            return None;
+        }
+        Some(match self {
+            Edit::Cell { .. } => vec![(row, col)],
+            Edit::Pattern { pattern, .. } => pattern
+                .living_cells
+                .iter()
+                .map(|(cell_row, cell_col)| (row + cell_row, col + cell_col))
+                .collect(),
+        })
+    }
+}
+
+/// How many edits each person in chat may ask for in a while, so no one
+/// can take the board over.
+pub struct RateLimit {
+    most: usize,
+    per: Duration,
+    /// When each person's edits that still count were asked for.
+    asked: HashMap<String, VecDeque<Instant>>,
+}
+
+impl RateLimit {
+    /// Lets each person make `most` edits in any `per`.
+    pub fn new(most: usize, per: Duration) -> RateLimit {
+        RateLimit {
+            most,
+            per,
+            asked: HashMap::new(),
+        }
+    }
+
+    /// Whether `nick` may make an edit at `now`, counting it if so.
+    pub fn allow(&mut self, nick: &str, now: Instant) -> bool {
+        let per = self.per;
+        let recent = |asked: &Instant| now.saturating_duration_since(*asked) < per;
+        self.asked
+            .retain(|_, asked| asked.back().is_some_and(recent));
+        let asked = self.asked.entry(nick.to_string()).or_default();
+        while asked.front().is_some_and(|asked| !recent(asked)) {
+            asked.pop_front();
+        }
+        if asked.len() >= self.most {
+            return false;
+        }
+        asked.push_back(now);
+        true
+    }
+}
+
+/// What a starter is called in chat: its name without spaces or
+/// punctuation, in lower case.
+pub fn command_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The parts of an IRC line that matter here.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Line<'a> {
+    /// The server checking the connection is alive, to be answered with
+    /// the same token.
+    Ping(&'a str),
+    /// A chat message from `nick`.
+    Message {
+        nick: &'a str,
+        text: &'a str,
+    },
+    Other,
+}
+
+impl<'a> Line<'a> {
+    pub fn parse(line: &'a str) -> Line<'a> {
+        // Twitch puts tags like the sender's colour before the prefix.
+        let line = match line.strip_prefix('@') {
+            Some(tagged) => tagged.split_once(' ').map_or("", |(_, rest)| rest),
+            None => line,
+        };
+        let (prefix, rest) = match line.strip_prefix(':') {
+            Some(prefixed) => prefixed.split_once(' ').unwrap_or((prefixed, "")),
+            None => ("", line),
+        };
+        let (command, params) = rest.split_once(' ').unwrap_or((rest, ""));
+        match command {
+            "PING" => Line::Ping(params.trim_start_matches(':')),
+            "PRIVMSG" => match params.split_once(" :") {
+                Some((_, text)) => Line::Message {
+                    nick: prefix.split('!').next().unwrap_or_default(),
+                    text,
+                },
+                None => Line::Other,
+            },
+            _ => Line::Other,
+        }
+    }
+}
+
+/// A connection reading the chat of one channel.
+pub struct Chat {
+    socket: Socket,
+}
+
+impl Chat {
+    /// Connects to `server`, a `host:port`, and joins `channel`.
+    pub fn connect(server: &str, channel: &str) -> io::Result<Chat> {
+        let address = server.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{} not found", server))
+        })?;
+        let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        let channel = channel.trim_start_matches('#').to_ascii_lowercase();
+        // Twitch lets anyone read chat as justinfan followed by any number.
+        let nick = format!("justinfan{}", rand::thread_rng().gen_range(1000, 100_000));
+        write!(stream, "NICK {}\r\nJOIN #{}\r\n", nick, channel)?;
+        Ok(Chat {
+            socket: Socket::new(stream)?,
+        })
+    }
+
+    /// The edits asked for since the last poll, with who asked for each,
+    /// answering the server's pings along the way. An error means the
+    /// connection is lost.
+    pub fn poll(&mut self) -> io::Result<Vec<(String, Edit)>> {
+        self.socket.flush()?;
+        let mut edits = Vec::new();
+        for line in self.socket.lines(MAX_LINE)? {
+            match Line::parse(&line) {
+                Line::Ping(token) => self
+                    .socket
+                    .send(format!("PONG :{}\r\n", token).as_bytes())?,
+                Line::Message { nick, text } => {
+                    if let Some(edit) = Edit::parse(text) {
+                        edits.push((nick.to_string(), edit));
+                    }
+                }
+                Line::Other => (),
+            }
+        }
+        Ok(edits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn edit_test() {
+        assert_eq!(
+            Edit::parse("!cell 20 30"),
+            Some(Edit::Cell { row: 20, col: 30 })
+        );
+        match Edit::parse("!Glider 10 12 please") {
+            Some(Edit::Pattern { name, row, col, .. }) => {
+                assert_eq!((name, row, col), ("glider", 10, 12))
+            }
+            edit => panic!("{:?}", edit),
+        }
+        assert!(matches!(
+            Edit::parse("!gosperglidergun 0 0"),
+            Some(Edit::Pattern {
+                name: "Gosper glider gun",
+                ..
+            })
+        ));
+        assert_eq!(Edit::parse("!cell 20"), None);
+        assert_eq!(Edit::parse("!cell -1 3"), None);
+        assert_eq!(Edit::parse("!uptime 1 2"), None);
+        assert_eq!(Edit::parse("cell 20 30"), None);
+        assert_eq!(Edit::parse(""), None);
+    }
+
+    #[test]
+    fn cells_on_test() {
+        let cell = Edit::parse("!cell 2 3").unwrap();
+        assert_eq!(cell.cells_on(4, 3), Some(vec![(2, 3)]));
+        assert_eq!(cell.cells_on(3, 3), None);
+        let huge = Edit::parse("!cell 18446744073709551615 0").unwrap();
+        assert_eq!(huge.cells_on(10, 10), None);
+
+        // The glider's first living cell is in its second column, but it is
+        // the whole of the pattern that has to fit.
+        let glider = Edit::parse("!glider 1 2").unwrap();
+        assert_eq!(
+            glider.cells_on(5, 4),
+            Some(vec![(1, 3), (2, 4), (3, 2), (3, 3), (3, 4)])
+        );
+        assert_eq!(glider.cells_on(4, 4), None);
+        assert_eq!(glider.cells_on(5, 3), None);
+        let huge = Edit::parse("!glider 0 18446744073709551614").unwrap();
+        assert_eq!(huge.cells_on(10, 10), None);
+    }
+
+    #[test]
+    fn rate_limit_test() {
+        let mut limit = RateLimit::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+        assert!(limit.allow("ann", start));
+        assert!(limit.allow("ann", start + Duration::from_secs(1)));
+        assert!(!limit.allow("ann", start + Duration::from_secs(2)));
+        assert!(limit.allow("bob", start + Duration::from_secs(2)));
+        // Ann's first edit stops counting ten seconds after she made it.
+        assert!(limit.allow("ann", start + Duration::from_secs(10)));
+        assert!(!limit.allow("ann", start + Duration::from_secs(10)));
+        assert!(limit.allow("ann", start + Duration::from_secs(30)));
+        assert_eq!(limit.asked.len(), 1, "Bob's edits no longer count");
+    }
+
+    #[test]
+    fn line_test() {
+        assert_eq!(
+            Line::parse("PING :tmi.twitch.tv"),
+            Line::Ping("tmi.twitch.tv")
+        );
+        assert_eq!(
+            Line::parse(":ann!ann@ann.tmi.twitch.tv PRIVMSG #life :!cell 1 2"),
+            Line::Message {
+                nick: "ann",
+                text: "!cell 1 2"
+            }
+        );
+        assert_eq!(
+            Line::parse(
+                "@color=#FF0000;display-name=Bob :bob!bob@bob.tmi.twitch.tv PRIVMSG #life :hi: there"
+            ),
+            Line::Message {
+                nick: "bob",
+                text: "hi: there"
+            }
+        );
+        assert_eq!(
+            Line::parse(":tmi.twitch.tv 001 justinfan1 :Welcome"),
+            Line::Other
+        );
+    }
+
+    #[test]
+    fn poll_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let mut chat = Chat::connect(&server, "#Life").unwrap();
+        let (mut client, _) = listener.accept().unwrap();
+        client
+            .write_all(
+                b"PING :tmi.twitch.tv\r\n\
+                  :ann!ann@host PRIVMSG #life :!cell 3 4\r\n\
+                  :bob!bob@host PRIVMSG #life :nice\r\n\
+                  :cat!cat@host PRIVMSG #life :!acorn 5",
+            )
+            .unwrap();
+
+        let mut edits = Vec::new();
+        for _ in 0..100 {
+            edits.extend(chat.poll().unwrap());
+            if !edits.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            edits,
+            vec![("ann".to_string(), Edit::Cell { row: 3, col: 4 })]
+        );
+        client.write_all(b" 6\r\n").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(chat.poll().unwrap().len(), 1);
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let mut received = [0; 256];
+        let read = client.read(&mut received).unwrap();
+        let received = String::from_utf8_lossy(&received[..read]).into_owned();
+        assert!(received.starts_with("NICK justinfan"), "{}", received);
+        assert!(received.contains("JOIN #life\r\n"), "{}", received);
+
+        drop(client);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(chat.poll().is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Checksums of saved JSON documents, so that a save or checkpoint mangled
+//! on disk is found out when it is loaded rather than loaded as some other
+//! board. A document is sealed with a `"checksum"` field holding the CRC-32,
+//! in hex, of the rest of the document written compactly with its keys in
+//! order:
+//!
+//! ```json
+//! {"cells": [[0, 1]], "checksum": "7c181909", "height": 3, "rule": "B3/S23", "width": 3}
+//! ```
+//!
+//! Documents without a checksum, such as patterns written by hand, are read
+//! as they are.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The field a sealed document's checksum is kept in.
+const FIELD: &str = "checksum";
+
+/// The checksum of `fields`, leaving out any checksum already among them.
+fn checksum(fields: &Map<String, Value>) -> String {
+    let mut fields = fields.clone();
+    fields.remove(FIELD);
+    let text = serde_json::to_string(&fields).expect("JSON always serializes");
+    format!("{:08x}", crc32fast::hash(text.as_bytes()))
+}
+
+/// Adds the checksum of `fields` to them.
+pub fn seal(fields: &mut Map<String, Value>) {
+    let checksum = checksum(fields);
+    fields.insert(FIELD.to_string(), Value::String(checksum));
+}
+
+/// Takes the checksum out of `fields`, checking it against the rest of
+/// them, if they have one.
+pub fn verify(fields: &mut Map<String, Value>) -> Result<(), String> {
+    let recorded = match fields.remove(FIELD) {
+        None => return Ok(()),
+        Some(Value::String(recorded)) => recorded,
+        Some(recorded) => return Err(format!("invalid checksum {}", recorded)),
+    };
+    let actual = checksum(fields);
+    if recorded != actual {
+        return Err(format!(
+            "the file is corrupted: its checksum is {} but its contents give {}",
+            recorded, actual
+        ));
+    }
+    Ok(())
+}
+
+/// `document` as compact JSON, sealed with its checksum if it is an object.
+pub fn to_sealed_json<T: Serialize>(document: &T) -> String {
+    let mut value = serde_json::to_value(document).expect("the document always serializes");
+    if let Value::Object(fields) = &mut value {
+        seal(fields);
+    }
+    value.to_string()
+}
+
+/// Checks the checksum of the JSON document `text`, if it has one. Text
+/// that isn't a JSON object is left for whatever reads it to report.
+pub fn check(text: &str) -> Result<(), String> {
+    match serde_json::from_str(text) {
+        Ok(Value::Object(mut fields)) => verify(&mut fields),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn seal_test() {
+        let text = to_sealed_json(&json!({"width": 3, "cells": [[0, 1]], "density": 0.35}));
+        assert!(text.contains(r#""checksum":""#), "{}", text);
+        assert_eq!(check(&text), Ok(()));
+
+        // However it is laid out, the same document has the same checksum.
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            check(&serde_json::to_string_pretty(&value).unwrap()),
+            Ok(())
+        );
+
+        let mangled = text.replace("[[0,1]]", "[[0,2]]");
+        assert!(check(&mangled).unwrap_err().contains("corrupted"));
+        let retyped = text.replace("0.35", "0.36");
+        assert!(check(&retyped).is_err());
+        assert!(check(r#"{"width": 3, "checksum": 7}"#).is_err());
+
+        assert_eq!(check(r#"{"width": 3}"#), Ok(()));
+        assert_eq!(check("not json"), Ok(()));
+    }
+}
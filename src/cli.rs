@@ -0,0 +1,1619 @@
+use crate::stream::Format;
+use conways_game_of_life::animation::{self, AnimationFormat};
+use conways_game_of_life::explore::Explore;
+use conways_game_of_life::grid::check_rule;
+use conways_game_of_life::heatmap::Colormap;
+use conways_game_of_life::hunt::Hunt;
+use conways_game_of_life::lifespan::Lifespans;
+use conways_game_of_life::projection::View;
+use conways_game_of_life::rle::Transform;
+use conways_game_of_life::rule::Rule;
+use conways_game_of_life::search::Search;
+use conways_game_of_life::soup::Soup;
+use conways_game_of_life::stability::DEFAULT_LONGEST_PERIOD;
+use conways_game_of_life::theme::{self, Theme};
+use conways_game_of_life::topology::Topology;
+use conways_game_of_life::{chat, history, ipc, render, shared, thumbnail, websocket};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const USAGE: &str = "usage: conways_game_of_life [run] [OPTIONS] [PATTERN | -]
+       conways_game_of_life ctl [--control-socket PATH] COMMAND
+       conways_game_of_life search [--soups N] [--size N] [--density D]
+                                   [--seed N] [--threads N] [--rule RULE]
+                                   [--settle-period N]
+       conways_game_of_life hunt [--size N] [--samples N | --all] [--density D]
+                                 [--min-period N] [--generations N] [--seed N]
+                                 [--threads N] [--rule RULE] [--out DIR]
+       conways_game_of_life lifespan [--rule RULE] [--settle-period N] PATTERN
+       conways_game_of_life lifespan [--soups N] [--size N] [--density D]
+                                     [--seed N] [--threads N] [--rule RULE]
+                                     [--top N] [--out DIR] [--json FILE]
+                                     [--settle-period N]
+       conways_game_of_life sweep MANIFEST [--out CSV]
+       conways_game_of_life explore [--rules N] [--soups N] [--size N]
+                                    [--generations N] [--density D] [--seed N]
+                                    [--threads N] [--top N]
+       conways_game_of_life reproduce RUN_MANIFEST
+
+PATTERN is an RLE or JSON file to start from, or - to read it from stdin.
+
+ctl sends COMMAND to a window started with --control: pause, resume,
+step [N], load FILE, save FILE or rule RULE, like rule B36/S23.
+
+search runs --soups random soups (default 1000), each --size cells across
+(default 16), until they settle and prints how many of each object they left,
+using every core unless --threads says otherwise. The soups run by --rule
+(default B3/S23). A soup has settled once it is extinct or left repeating in
+cycles of up to --settle-period generations (default 60).
+
+hunt runs --samples random patterns (default 10000), or with --all every
+pattern, that fit in a square --size cells across (default 8), until they
+repeat, and writes each oscillator and spaceship of at least --min-period
+(default 2) they leave to its own RLE file in --out (default finds). --all
+tries every pattern up to 5 cells across.
+
+lifespan runs PATTERN, an RLE file or apgcode, or else random soups like
+search, until it settles as search does, and prints when it did and what it
+left. For soups it lists the --top longest lived (default 10), writing each to
+--out if given, then the distribution of how long they all lived and the cells
+they left, writing that to --json as well if given.
+
+sweep runs a search for every combination of the rules, densities and sizes
+the JSON or TOML MANIFEST lists and prints a table of the results, writing it
+to --out as CSV too if given.
+
+explore tries --rules random rules (default 200), each on --soups soups
+(default 3) --size cells across (default 32) for --generations (default 300),
+and lists the --top (default 10) that did the most interesting things.
+
+search, hunt, lifespan, sweep and explore also take --save-manifest FILE,
+writing every parameter of the run, with its seed and this version, to FILE
+as JSON. reproduce runs such a RUN_MANIFEST again, with the same results.
+
+Any of them takes -v to log what it is doing to stderr, with how long loading
+took, -vv to log how long every generation and frame took as well, or -vvv for
+everything. RUST_LOG, like debug or conways_game_of_life::stream=trace, says
+what to log in place of -v.
+
+options:
+  --size N             width and height of the grid (default 50)
+  --cell-size N        pixels across each cell in the window (default 10)
+  --fill-window        size the grid to fill the window, and again whenever
+                       the window is resized, in place of --size
+  --kiosk              run as a screensaver: fullscreen with no cursor or
+                       status bar, moving on to another soup or pattern once
+                       the board stagnates, until any key is pressed
+  --view NAME          how the window shows the grid: flat, sphere for wrapped
+                       round a spinning sphere, or isometric for cubes on a
+                       board seen from one corner (default flat)
+  --age-height         in the isometric view, stack cells higher the longer
+                       they have been alive
+  --theme dark|light   colours of the window (default the desktop's preference
+                       where it can be found, otherwise dark)
+  --chroma-key COLOUR  draw only what stands out from the board, on COLOUR,
+                       green, blue, magenta or one like #00ff00, so it can be
+                       keyed out to lay the board over other video
+  --mute               start with the sound of births and deaths muted
+  --sonify             play the population as a tone, higher the more is
+                       alive and louder the faster that is changing
+  --stream             write generations to stdout instead of opening a window
+  --terminal           play the board in the terminal instead of opening a
+                       window, as is offered when no window can be opened
+  --format raw|json    frame format used by --stream (default raw)
+  --generations N      stop after N generations
+  --stop-on-cycle      stop streaming once the board repeats an earlier one,
+                       allowing for movement, or settles into cycles of up
+                       to --settle-period generations
+  --settle-period N    the longest period of cycles a board counts as
+                       settled in, for --stop-on-cycle, --auto-restart and
+                       --kiosk (default 60)
+  --density N          chance of each cell of a random soup being alive, from
+                       0 to 1 (default 0.5)
+  --symmetry NAME      force random soups to be symmetric: none, horizontal,
+                       vertical, four-fold or diagonal (default none)
+  --noise N            make random soups by thresholding coherent noise with
+                       clumps about N cells across
+  --auto-restart N     start a new random soup once the board has been extinct
+                       or settled for N generations
+  --colormap NAME      heatmap colours: hot, grey, viridis or ice (default hot)
+  --heatmap-png FILE   save the activity heatmap to FILE when --stream ends
+  --scale N            pixels per cell in saved images (default 10)
+  --gif FILE           record generations from the start as an animated GIF
+  --apng FILE          record generations from the start as an animated PNG
+  --animation-format gif|apng
+                       what G records (default gif)
+  --video FILE         encode generations to a video such as run.mp4 with ffmpeg
+  --frames-dir DIR     save generations as numbered PNGs in DIR
+  --every N            save every Nth generation to --frames-dir (default 1)
+  --frames N           stop recording an animation or video after N frames
+  --frame-delay MS     milliseconds per frame of an animation (default 100)
+  --topology NAME      how the edges of the grid are joined: plane, torus,
+                       cylinder, mobius or klein, or how the sides then the
+                       top and bottom are joined, each bounded, wrap or twist,
+                       like wrap,twist (default plane), where wrap+N or
+                       wrap-N shifts those edges by N cells; or written as
+                       in Golly, like T100,50+10, which also sets the width
+                       and height of the grid
+  --rule RULE          run by RULE in B/S notation, like B36/S23 for
+                       HighLife, instead of the rule PATTERN gives or B3/S23
+  --auto-expand N      grow the grid by N dead cells on any side that living
+                       cells reach, so nothing is clipped by the edge
+  --fit                grow the grid if need be to hold PATTERN with a margin
+  --margin N           dead cells left round PATTERN by --fit (default 10)
+  --empty              start paused with every cell dead, ready for editing
+  --infinite           run on an unbounded plane, the grid being a view of it
+                       that can be dragged about with the right mouse button
+  --text TEXT          start from TEXT written across the middle of the grid
+  --place FILE@ROW,COL[:TRANSFORM]...
+                       put the pattern in FILE with its top left corner at ROW
+                       and COL, turned or mirrored by rot90, rot180, rot270,
+                       flipx or flipy; give it again to place more
+  --share TEXT         start from a board shared as text with X
+  --fetch NAME|URL     start from a pattern downloaded from LifeWiki by its
+                       NAME, like \"Gosper glider gun\", or from URL, which
+                       is kept so it only has to be downloaded once, or
+                       drawn from a Catagolue apgcode like xq4_153
+  --seed N             seed for random soups, so a run can be repeated exactly
+  --versus N           play the Immigration Game: two players take turns to
+                       place N cells each on their own half of an empty board
+  --versus-generations N
+                       generations a --versus game runs for once the cells
+                       are placed, after which the player with more cells
+                       alive wins (default 100)
+  --battle CELLS,GENERATIONS
+                       make --versus a battle: players take turns to place
+                       CELLS cells anywhere not touching the other's, and the
+                       board runs for GENERATIONS after each round of turns
+  --history-mb N       memory kept for stepping back, in megabytes (default 64)
+  --record FILE        save the session, edits and all, as a replay in FILE
+  --play FILE          play back a replay saved with --record
+  --api HOST:PORT      with --stream, take requests to pause, step, look at and
+                       place patterns on the board over HTTP on HOST:PORT
+  --chat CHANNEL       let the chat of the IRC CHANNEL, a Twitch channel unless
+                       --chat-server is given, edit the board with messages
+                       like !cell 20 30 or !glider 10 10
+  --chat-server HOST:PORT
+                       the IRC server to read --chat from
+                       (default irc.chat.twitch.tv:6667)
+  --control            take commands from `ctl` on a control socket
+  --control-socket PATH
+                       the control socket to listen on or send to, in place
+                       of one in the platform's runtime directory
+  --host HOST:PORT     share the board with others who --join it on HOST:PORT
+  --join HOST:PORT     edit the board shared by whoever hosts it on HOST:PORT
+  --serve ADDRESS      send each generation to WebSocket clients connecting to
+                       ADDRESS, such as ws://0.0.0.0:9000
+  --checkpoint-every N save the board every N generations while streaming
+  --checkpoint-dir DIR where --checkpoint-every saves to (default checkpoints)
+  --stats-csv FILE     append generation, population, births and deaths to FILE
+  --config FILE        read the config file, with the keys, speed, theme,
+                       language and music, from FILE rather than config.json
+                       in the platform's config directory
+  --thumbnails DIR     save a thumbnail of every pattern in DIR to DIR/thumbnails
+                       and exit
+  --thumbnail-size N   width and height of thumbnails in pixels (default 64)
+";
+
+/// How many generations `--kiosk` lets a board stagnate for before moving
+/// on, unless `--auto-restart` says otherwise.
+const KIOSK_RESTART: u64 = 50;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub size: usize,
+    pub cell_size: u32,
+    /// Size the grid to fill the window, following it as it is resized.
+    pub fill_window: bool,
+    /// Runs fullscreen as a screensaver, with `--fill-window` and
+    /// `--auto-restart` on.
+    pub kiosk: bool,
+    pub stream: bool,
+    /// Plays the board in the terminal in place of the window.
+    pub terminal: bool,
+    pub format: Format,
+    pub generations: Option<u64>,
+    pub stop_on_cycle: bool,
+    /// The longest period of cycles a board counts as settled in, for
+    /// `--stop-on-cycle`, `--auto-restart` and `--kiosk`.
+    pub settle_period: u64,
+    /// How many times `-v` was given, for how much to log.
+    pub verbosity: u8,
+    /// Path of an RLE pattern to start from, `-` meaning stdin.
+    pub pattern: Option<String>,
+    /// Grow the grid to fit the pattern, leaving `margin` cells round it.
+    pub fit: bool,
+    pub margin: usize,
+    pub topology: Topology,
+    /// The rule to run by, in place of the one the pattern gives.
+    pub rule: Option<Rule>,
+    /// The width and height of the grid from a Golly topology, instead of
+    /// `size` by `size`.
+    pub shape: Option<(usize, usize)>,
+    /// How many cells to grow the grid by on a side living cells reach.
+    pub auto_expand: Option<usize>,
+    /// Start from a dead board, paused.
+    pub empty: bool,
+    /// Run on an unbounded plane, of which the grid is a view.
+    pub infinite: bool,
+    /// Text to write across the board to start from.
+    pub text: Option<String>,
+    /// Patterns to place on the board, on top of whatever it starts from
+    /// or on an empty board.
+    pub placements: Vec<Placement>,
+    /// A board from `Grid::to_share_string` to start from.
+    pub share: Option<String>,
+    /// The LifeWiki name or URL of a pattern to download and start from.
+    pub fetch: Option<String>,
+    pub stats_csv: Option<String>,
+    pub auto_restart: Option<u64>,
+    /// How random soups are made.
+    pub soup: Soup,
+    pub colormap: Colormap,
+    /// How the window shows the grid.
+    pub view: View,
+    /// Whether the isometric view shows how long cells have lived by how
+    /// tall they are.
+    pub age_height: bool,
+    /// The colours of the window, if not the desktop's preference.
+    pub theme: Option<Theme>,
+    /// The colour drawn behind the living cells in place of the board.
+    pub chroma_key: Option<(u8, u8, u8)>,
+    /// Starts without the ticks of births and deaths.
+    pub mute: bool,
+    /// Plays the population as a tone.
+    pub sonify: bool,
+    pub heatmap_png: Option<String>,
+    /// Pixels per cell in screenshots and saved heatmaps.
+    pub scale: u32,
+    /// Where to record an animation to from the start.
+    pub animation: Option<String>,
+    pub animation_format: AnimationFormat,
+    pub video: Option<String>,
+    pub frames_dir: Option<String>,
+    pub every: u64,
+    /// The most frames an animation or video captures.
+    pub frames: Option<usize>,
+    pub frame_delay: u32,
+    pub history_mb: u64,
+    /// Seed for the random number generator, picked at random if not given.
+    pub seed: Option<u64>,
+    /// How many cells each player places in a game of Immigration.
+    pub versus: Option<usize>,
+    pub versus_generations: u64,
+    pub battle: Option<Battle>,
+    pub record: Option<String>,
+    pub play: Option<String>,
+    pub checkpoint_every: Option<u64>,
+    /// The `host:port` to take HTTP requests on while streaming.
+    pub api: Option<String>,
+    /// The IRC channel whose chat can edit the board.
+    pub chat: Option<String>,
+    pub chat_server: String,
+    /// Take commands on the control socket.
+    pub control: bool,
+    pub control_socket: Option<String>,
+    /// The soups to search with `search`, in place of running.
+    pub search: Option<Search>,
+    /// The patterns to hunt through with `hunt`, in place of running.
+    pub hunt: Option<Hunt>,
+    /// The soups to measure the lifespans of with `lifespan`, in place of
+    /// running, unless a pattern is given to measure instead.
+    pub lifespan: Option<Lifespans>,
+    /// Where `lifespan` writes the longest lived soups.
+    pub lifespan_out: Option<PathBuf>,
+    /// Where `lifespan` writes the distribution of its soups' lifespans.
+    pub lifespan_json: Option<PathBuf>,
+    /// Where a batch run writes the manifest to run it again by.
+    pub save_manifest: Option<PathBuf>,
+    /// The manifest of a batch run to run again.
+    pub reproduce: Option<PathBuf>,
+    /// The manifest of the experiment to run with `sweep`, in place of
+    /// running.
+    pub sweep: Option<PathBuf>,
+    /// Where `sweep` writes its results as CSV.
+    pub sweep_out: Option<PathBuf>,
+    /// The rules to try with `explore`, in place of running.
+    pub explore: Option<Explore>,
+    /// The command to send to a window with `ctl`, in place of running.
+    pub ctl: Option<ipc::Request>,
+    /// The `host:port` to share the board from.
+    pub host: Option<String>,
+    /// The `host:port` of a shared board to join.
+    pub join: Option<String>,
+    /// The `host:port` to send generations to WebSocket clients from.
+    pub serve: Option<String>,
+    pub checkpoint_dir: String,
+    /// The config file to read in place of the usual one.
+    pub config: Option<String>,
+    /// A directory of patterns to draw thumbnails of instead of running.
+    pub thumbnails: Option<String>,
+    pub thumbnail_size: u32,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            size: 50,
+            cell_size: 10,
+            fill_window: false,
+            kiosk: false,
+            stream: false,
+            terminal: false,
+            format: Format::Raw,
+            generations: None,
+            stop_on_cycle: false,
+            settle_period: DEFAULT_LONGEST_PERIOD,
+            verbosity: 0,
+            pattern: None,
+            fit: false,
+            margin: 10,
+            topology: Topology::default(),
+            rule: None,
+            shape: None,
+            auto_expand: None,
+            empty: false,
+            infinite: false,
+            text: None,
+            placements: Vec::new(),
+            share: None,
+            fetch: None,
+            stats_csv: None,
+            auto_restart: None,
+            soup: Soup::default(),
+            colormap: Colormap::Hot,
+            view: View::Flat,
+            age_height: false,
+            theme: None,
+            chroma_key: None,
+            mute: false,
+            sonify: false,
+            heatmap_png: None,
+            scale: render::DEFAULT_SCALE,
+            animation: None,
+            animation_format: AnimationFormat::Gif,
+            video: None,
+            frames_dir: None,
+            every: 1,
+            frames: None,
+            frame_delay: animation::DEFAULT_FRAME_DELAY,
+            history_mb: history::DEFAULT_MEMORY_MB,
+            seed: None,
+            versus: None,
+            versus_generations: 100,
+            battle: None,
+            record: None,
+            play: None,
+            checkpoint_every: None,
+            api: None,
+            chat: None,
+            host: None,
+            join: None,
+            chat_server: chat::TWITCH.to_string(),
+            control: false,
+            control_socket: None,
+            ctl: None,
+            search: None,
+            hunt: None,
+            lifespan: None,
+            lifespan_out: None,
+            lifespan_json: None,
+            save_manifest: None,
+            reproduce: None,
+            sweep: None,
+            sweep_out: None,
+            explore: None,
+            serve: None,
+            checkpoint_dir: "checkpoints".to_string(),
+            config: None,
+            thumbnails: None,
+            thumbnail_size: thumbnail::DEFAULT_SIZE,
+        }
+    }
+}
+
+impl Options {
+    /// The first option given that only the window can play, `--terminal`
+    /// playing a bounded board and nothing else.
+    pub fn needs_window(&self) -> Option<&'static str> {
+        if self.versus.is_some() {
+            Some("--versus")
+        } else if self.play.is_some() {
+            Some("--play")
+        } else if self.record.is_some() {
+            Some("--record")
+        } else if self.infinite {
+            Some("--infinite")
+        } else {
+            None
+        }
+    }
+
+    /// Whether the board starts from something given on the command line
+    /// rather than a random soup.
+    pub fn has_start(&self) -> bool {
+        self.pattern.is_some()
+            || self.empty
+            || self.text.is_some()
+            || self.share.is_some()
+            || self.fetch.is_some()
+            || self.play.is_some()
+            || self.versus.is_some()
+            || !self.placements.is_empty()
+    }
+
+    /// Where the control socket is.
+    pub fn control_path(&self) -> PathBuf {
+        self.control_socket
+            .as_ref()
+            .map_or_else(ipc::default_path, PathBuf::from)
+    }
+
+    /// The width and height of the grid.
+    pub fn dimensions(&self) -> (usize, usize) {
+        self.shape.unwrap_or((self.size, self.size))
+    }
+
+    pub fn parse<I>(args: I) -> Result<Options, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options::default();
+        // `-v` can be given anywhere, to any command.
+        let args: Vec<String> = args
+            .into_iter()
+            .filter(|arg| match verbosity(arg) {
+                Some(more) => {
+                    options.verbosity = options.verbosity.saturating_add(more);
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        let mut args = args.into_iter().peekable();
+
+        if args.peek().map(String::as_str) == Some("run") {
+            args.next();
+        }
+        if args.peek().map(String::as_str) == Some("ctl") {
+            args.next();
+            let mut words = Vec::new();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--control-socket" => {
+                        options.control_socket = Some(parse_value(&arg, args.next())?)
+                    }
+                    _ => words.push(arg),
+                }
+            }
+            options.ctl = Some(words.join(" ").parse()?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("search") {
+            args.next();
+            options.search = Some(parse_search(args, &mut options)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("hunt") {
+            args.next();
+            options.hunt = Some(parse_hunt(args, &mut options)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("lifespan") {
+            args.next();
+            options.lifespan = Some(parse_lifespan(args, &mut options)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("explore") {
+            args.next();
+            options.explore = Some(parse_explore(args, &mut options)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("sweep") {
+            args.next();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--out" => options.sweep_out = Some(parse_value(&arg, args.next())?),
+                    "--save-manifest" => {
+                        options.save_manifest = Some(parse_value(&arg, args.next())?)
+                    }
+                    _ if options.sweep.is_none() && !arg.starts_with('-') => {
+                        options.sweep = Some(PathBuf::from(arg))
+                    }
+                    _ => return Err(format!("unknown sweep argument '{}'\n{}", arg, USAGE)),
+                }
+            }
+            if options.sweep.is_none() {
+                return Err(format!("sweep needs a MANIFEST\n{}", USAGE));
+            }
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("reproduce") {
+            args.next();
+            options.reproduce = args.next().map(PathBuf::from);
+            if options.reproduce.is_none() || args.next().is_some() {
+                return Err(format!("reproduce needs one RUN_MANIFEST\n{}", USAGE));
+            }
+            return Ok(options);
+        }
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--size" => options.size = parse_value(&arg, args.next())?,
+                "--cell-size" => options.cell_size = parse_value(&arg, args.next())?,
+                "--fill-window" => options.fill_window = true,
+                "--kiosk" => options.kiosk = true,
+                "--view" => options.view = parse_value(&arg, args.next())?,
+                "--age-height" => options.age_height = true,
+                "--theme" => options.theme = Some(parse_value(&arg, args.next())?),
+                "--chroma-key" => {
+                    let spec: String = parse_value(&arg, args.next())?;
+                    options.chroma_key = Some(theme::chroma_key(&spec)?);
+                }
+                "--mute" => options.mute = true,
+                "--sonify" => options.sonify = true,
+                "--stream" => options.stream = true,
+                "--terminal" => options.terminal = true,
+                "--format" => options.format = parse_value(&arg, args.next())?,
+                "--stop-on-cycle" => options.stop_on_cycle = true,
+                "--settle-period" => {
+                    options.settle_period = parse_value(&arg, args.next())?;
+                    if options.settle_period == 0 {
+                        return Err("--settle-period must be at least 1".to_string());
+                    }
+                }
+                "--auto-restart" => options.auto_restart = Some(parse_value(&arg, args.next())?),
+                "--density" => options.soup.density = parse_value(&arg, args.next())?,
+                "--symmetry" => options.soup.symmetry = parse_value(&arg, args.next())?,
+                "--noise" => options.soup.noise = Some(parse_value(&arg, args.next())?),
+                "--colormap" => options.colormap = parse_value(&arg, args.next())?,
+                "--heatmap-png" => options.heatmap_png = Some(parse_value(&arg, args.next())?),
+                "--scale" => options.scale = parse_value(&arg, args.next())?,
+                "--gif" | "--apng" => {
+                    options.animation = Some(parse_value(&arg, args.next())?);
+                    options.animation_format = if arg == "--gif" {
+                        AnimationFormat::Gif
+                    } else {
+                        AnimationFormat::Apng
+                    };
+                }
+                "--animation-format" => options.animation_format = parse_value(&arg, args.next())?,
+                "--video" => options.video = Some(parse_value(&arg, args.next())?),
+                "--frames-dir" => options.frames_dir = Some(parse_value(&arg, args.next())?),
+                "--every" => options.every = parse_value(&arg, args.next())?,
+                "--frames" => options.frames = Some(parse_value(&arg, args.next())?),
+                "--frame-delay" => options.frame_delay = parse_value(&arg, args.next())?,
+                "--history-mb" => options.history_mb = parse_value(&arg, args.next())?,
+                "--fit" => options.fit = true,
+                "--margin" => options.margin = parse_value(&arg, args.next())?,
+                "--empty" => options.empty = true,
+                "--infinite" => options.infinite = true,
+                "--topology" => {
+                    let spec: String = parse_value(&arg, args.next())?;
+                    if spec.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        let (topology, width, height) = Topology::from_golly(&spec)?;
+                        options.topology = topology;
+                        options.shape = Some((width, height));
+                    } else {
+                        options.topology = spec.parse()?;
+                    }
+                }
+                "--rule" => {
+                    options.rule = Some(check_rule(&parse_value::<String>(&arg, args.next())?)?)
+                }
+                "--auto-expand" => options.auto_expand = Some(parse_value(&arg, args.next())?),
+                "--text" => options.text = Some(parse_value(&arg, args.next())?),
+                "--place" => options.placements.push(parse_value(&arg, args.next())?),
+                "--share" => options.share = Some(parse_value(&arg, args.next())?),
+                "--fetch" => options.fetch = Some(parse_value(&arg, args.next())?),
+                "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+                "--versus" => options.versus = Some(parse_value(&arg, args.next())?),
+                "--versus-generations" => {
+                    options.versus_generations = parse_value(&arg, args.next())?
+                }
+                "--battle" => options.battle = Some(parse_value(&arg, args.next())?),
+                "--record" => options.record = Some(parse_value(&arg, args.next())?),
+                "--play" => options.play = Some(parse_value(&arg, args.next())?),
+                "--chat" => options.chat = Some(parse_value(&arg, args.next())?),
+                "--chat-server" => options.chat_server = parse_value(&arg, args.next())?,
+                "--control" => options.control = true,
+                "--control-socket" => {
+                    options.control_socket = Some(parse_value(&arg, args.next())?)
+                }
+                "--api" => options.api = Some(parse_value(&arg, args.next())?),
+                "--host" => {
+                    let spec: String = parse_value(&arg, args.next())?;
+                    options.host = Some(shared::address(&spec)?);
+                }
+                "--join" => {
+                    let spec: String = parse_value(&arg, args.next())?;
+                    options.join = Some(shared::address(&spec)?);
+                }
+                "--serve" => {
+                    let spec: String = parse_value(&arg, args.next())?;
+                    options.serve = Some(websocket::address(&spec)?);
+                }
+                "--checkpoint-every" => {
+                    options.checkpoint_every = Some(parse_value(&arg, args.next())?)
+                }
+                "--checkpoint-dir" => options.checkpoint_dir = parse_value(&arg, args.next())?,
+                "--config" => options.config = Some(parse_value(&arg, args.next())?),
+                "--thumbnails" => options.thumbnails = Some(parse_value(&arg, args.next())?),
+                "--thumbnail-size" => options.thumbnail_size = parse_value(&arg, args.next())?,
+                "--stats-csv" => options.stats_csv = Some(parse_value(&arg, args.next())?),
+                "--generations" => options.generations = Some(parse_value(&arg, args.next())?),
+                _ if options.pattern.is_none() && (arg == "-" || !arg.starts_with('-')) => {
+                    options.pattern = Some(arg)
+                }
+                _ => return Err(format!("unknown argument '{}'\n{}", arg, USAGE)),
+            }
+        }
+
+        if options.size == 0 {
+            return Err("--size must be at least 1".to_string());
+        }
+        if options.cell_size == 0 {
+            return Err("--cell-size must be at least 1".to_string());
+        }
+        if options.kiosk {
+            if options.stream || options.terminal || options.versus.is_some() {
+                return Err(
+                    "--kiosk runs a window of its own, without --stream, --terminal or --versus"
+                        .to_string(),
+                );
+            }
+            if options.record.is_some() || options.play.is_some() {
+                return Err("--kiosk can't be used with --record or --play".to_string());
+            }
+            options.fill_window = true;
+            options.auto_restart.get_or_insert(KIOSK_RESTART);
+        }
+        if options.sonify && options.stream {
+            return Err("--sonify plays from the window, not --stream".to_string());
+        }
+        if options.fill_window && options.stream {
+            return Err("--fill-window needs the window, not --stream".to_string());
+        }
+        if options.terminal {
+            if let Some(option) = options
+                .stream
+                .then_some("--stream")
+                .or(options.needs_window())
+            {
+                return Err(format!("--terminal can't be used with {}", option));
+            }
+        }
+        options.soup.check()?;
+        if options.scale == 0 {
+            return Err("--scale must be at least 1".to_string());
+        }
+        if options.every == 0 {
+            return Err("--every must be at least 1".to_string());
+        }
+        if options.frames == Some(0) {
+            return Err("--frames must be at least 1".to_string());
+        }
+        if options.checkpoint_every == Some(0) {
+            return Err("--checkpoint-every must be at least 1".to_string());
+        }
+        let starts = [
+            options.pattern.is_some(),
+            options.empty,
+            options.text.is_some(),
+            options.share.is_some(),
+            options.fetch.is_some(),
+            options.play.is_some(),
+            options.versus.is_some(),
+        ];
+        if starts.iter().filter(|given| **given).count() > 1 {
+            return Err(
+                "give only one of a pattern, --empty, --text, --share, --fetch, --play and \
+                 --versus"
+                    .to_string(),
+            );
+        }
+        if options.versus.is_some() {
+            if options.stream || options.infinite || options.auto_expand.is_some() {
+                return Err("--versus needs a window and a board of its own size, \
+                     without --stream, --infinite or --auto-expand"
+                    .to_string());
+            }
+            if options.record.is_some() || !options.placements.is_empty() {
+                return Err("--versus can't be used with --record or --place".to_string());
+            }
+        }
+        if options.versus == Some(0) {
+            return Err("--versus must be at least 1".to_string());
+        }
+        if options.battle.is_some() && options.versus.is_none() {
+            return Err("--battle needs --versus for how many cells each player has".to_string());
+        }
+        if options.versus_generations == 0 {
+            return Err("--versus-generations must be at least 1".to_string());
+        }
+        if options.play.is_some() && !options.placements.is_empty() {
+            return Err("--play starts from the replay's own board, without --place".to_string());
+        }
+        if options.thumbnail_size == 0 {
+            return Err("--thumbnail-size must be at least 1".to_string());
+        }
+        if options.stream && (options.record.is_some() || options.play.is_some()) {
+            return Err("--record and --play need the window, not --stream".to_string());
+        }
+        if options.auto_expand == Some(0) {
+            return Err("--auto-expand must be at least 1".to_string());
+        }
+        if options.auto_expand.is_some() {
+            if options.infinite {
+                return Err("--infinite never needs to --auto-expand".to_string());
+            }
+            if !options.topology.is_bounded() {
+                return Err("--auto-expand only works with the plane --topology".to_string());
+            }
+            if options.animation.is_some()
+                || options.video.is_some()
+                || options.heatmap_png.is_some()
+            {
+                return Err(
+                    "--auto-expand can't be used with --gif, --apng, --video or --heatmap-png, \
+                     which need the board to stay one size"
+                        .to_string(),
+                );
+            }
+        }
+        if options.infinite && !options.topology.is_bounded() {
+            return Err("--infinite has no edges to join with --topology".to_string());
+        }
+        if options.chat.is_some()
+            && (options.stream || options.versus.is_some() || options.play.is_some())
+        {
+            return Err(
+                "--chat edits a board in the window, without --stream, --versus \
+                 or --play"
+                    .to_string(),
+            );
+        }
+        if (options.host.is_some() || options.join.is_some())
+            && (options.stream
+                || options.infinite
+                || options.versus.is_some()
+                || options.play.is_some())
+        {
+            return Err(
+                "a shared board is a bounded one in the window, without --stream, \
+                 --infinite, --versus or --play"
+                    .to_string(),
+            );
+        }
+        if options.join.is_some()
+            && (options.host.is_some() || options.chat.is_some() || options.control)
+        {
+            return Err(
+                "--join edits the host's board, so --host, --chat and --control \
+                 are for the host"
+                    .to_string(),
+            );
+        }
+        if options.rule.is_some() && options.versus.is_some() {
+            return Err("--versus is played by Conway's rule, without --rule".to_string());
+        }
+        if options.control && options.stream {
+            return Err("--control is for the window, use --api with --stream".to_string());
+        }
+        if options.api.is_some() && !options.stream {
+            return Err("--api drives a run without a window, with --stream".to_string());
+        }
+        if options.stream && options.infinite {
+            return Err("--infinite needs the window, not --stream".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+/// How much more to log for `arg`, if it is `-v`, `-vv` and so on or
+/// `--verbose`.
+fn verbosity(arg: &str) -> Option<u8> {
+    match arg {
+        "--verbose" => Some(1),
+        _ if arg.len() > 1 && arg.starts_with('-') && arg[1..].bytes().all(|c| c == b'v') => {
+            u8::try_from(arg.len() - 1).ok()
+        }
+        _ => None,
+    }
+}
+
+/// The options of `search`.
+fn parse_search<I: Iterator<Item = String>>(
+    mut args: I,
+    options: &mut Options,
+) -> Result<Search, String> {
+    let mut search = Search::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save-manifest" => options.save_manifest = Some(parse_value(&arg, args.next())?),
+            "--soups" => search.soups = parse_value(&arg, args.next())?,
+            "--size" => search.size = parse_value(&arg, args.next())?,
+            "--density" => search.density = parse_value(&arg, args.next())?,
+            "--seed" => search.seed = parse_value(&arg, args.next())?,
+            "--threads" => search.threads = parse_value(&arg, args.next())?,
+            "--rule" => search.rule = check_rule(&parse_value::<String>(&arg, args.next())?)?,
+            "--settle-period" => search.longest_period = parse_value(&arg, args.next())?,
+            _ => return Err(format!("unknown search argument '{}'\n{}", arg, USAGE)),
+        }
+    }
+    search.check()?;
+    Ok(search)
+}
+
+/// The options of `explore`.
+fn parse_explore<I: Iterator<Item = String>>(
+    mut args: I,
+    options: &mut Options,
+) -> Result<Explore, String> {
+    let mut explore = Explore::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save-manifest" => options.save_manifest = Some(parse_value(&arg, args.next())?),
+            "--rules" => explore.rules = parse_value(&arg, args.next())?,
+            "--soups" => explore.soups = parse_value(&arg, args.next())?,
+            "--size" => explore.size = parse_value(&arg, args.next())?,
+            "--generations" => explore.generations = parse_value(&arg, args.next())?,
+            "--density" => explore.density = parse_value(&arg, args.next())?,
+            "--seed" => explore.seed = parse_value(&arg, args.next())?,
+            "--threads" => explore.threads = parse_value(&arg, args.next())?,
+            "--top" => explore.top = parse_value(&arg, args.next())?,
+            _ => return Err(format!("unknown explore argument '{}'\n{}", arg, USAGE)),
+        }
+    }
+    explore.check()?;
+    Ok(explore)
+}
+
+/// The options of `hunt`.
+fn parse_hunt<I: Iterator<Item = String>>(
+    mut args: I,
+    options: &mut Options,
+) -> Result<Hunt, String> {
+    let mut hunt = Hunt::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save-manifest" => options.save_manifest = Some(parse_value(&arg, args.next())?),
+            "--size" => hunt.size = parse_value(&arg, args.next())?,
+            "--samples" => hunt.samples = Some(parse_value(&arg, args.next())?),
+            "--all" => hunt.samples = None,
+            "--density" => hunt.density = parse_value(&arg, args.next())?,
+            "--seed" => hunt.seed = parse_value(&arg, args.next())?,
+            "--min-period" => hunt.min_period = parse_value(&arg, args.next())?,
+            "--generations" => hunt.generations = parse_value(&arg, args.next())?,
+            "--threads" => hunt.threads = parse_value(&arg, args.next())?,
+            "--rule" => hunt.rule = check_rule(&parse_value::<String>(&arg, args.next())?)?,
+            "--out" => hunt.out = parse_value(&arg, args.next())?,
+            _ => return Err(format!("unknown hunt argument '{}'\n{}", arg, USAGE)),
+        }
+    }
+    hunt.check()?;
+    Ok(hunt)
+}
+
+/// The options of `lifespan`, setting the pattern to measure and its rule
+/// in `options`.
+fn parse_lifespan<I: Iterator<Item = String>>(
+    mut args: I,
+    options: &mut Options,
+) -> Result<Lifespans, String> {
+    let mut lifespans = Lifespans::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--soups" => lifespans.search.soups = parse_value(&arg, args.next())?,
+            "--size" => lifespans.search.size = parse_value(&arg, args.next())?,
+            "--density" => lifespans.search.density = parse_value(&arg, args.next())?,
+            "--seed" => lifespans.search.seed = parse_value(&arg, args.next())?,
+            "--threads" => lifespans.search.threads = parse_value(&arg, args.next())?,
+            "--rule" => {
+                let rule = check_rule(&parse_value::<String>(&arg, args.next())?)?;
+                lifespans.search.rule = rule;
+                options.rule = Some(rule);
+            }
+            "--top" => lifespans.top = parse_value(&arg, args.next())?,
+            "--settle-period" => lifespans.search.longest_period = parse_value(&arg, args.next())?,
+            "--out" => options.lifespan_out = Some(parse_value(&arg, args.next())?),
+            "--json" => options.lifespan_json = Some(parse_value(&arg, args.next())?),
+            "--save-manifest" => options.save_manifest = Some(parse_value(&arg, args.next())?),
+            _ if options.pattern.is_none() && !arg.starts_with('-') => options.pattern = Some(arg),
+            _ => return Err(format!("unknown lifespan argument '{}'\n{}", arg, USAGE)),
+        }
+    }
+    lifespans.check()?;
+    Ok(lifespans)
+}
+
+/// How the turns of a `--versus` battle go, given as `CELLS,GENERATIONS`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Battle {
+    /// How many cells a player places each turn.
+    pub per_turn: usize,
+    /// How many generations the board runs after each round of turns.
+    pub between: u64,
+}
+
+impl FromStr for Battle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Battle, String> {
+        let bad = || format!("'{}' is not CELLS,GENERATIONS", s);
+        let (per_turn, between) = s.split_once(',').ok_or_else(bad)?;
+        let per_turn = per_turn.trim().parse().map_err(|_| bad())?;
+        let between = between.trim().parse().map_err(|_| bad())?;
+        if per_turn == 0 {
+            return Err(format!("'{}' needs at least one cell a turn", s));
+        }
+        Ok(Battle { per_turn, between })
+    }
+}
+
+/// A pattern file to place on the board, given as `FILE@ROW,COL` with any
+/// number of `:TRANSFORM` suffixes applied in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub path: String,
+    pub row: usize,
+    pub col: usize,
+    pub transforms: Vec<Transform>,
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Placement, String> {
+        let bad = || format!("'{}' is not FILE@ROW,COL[:TRANSFORM]", s);
+        let (path, at) = s.rsplit_once('@').ok_or_else(bad)?;
+        let mut parts = at.split(':');
+        let (row, col) = parts
+            .next()
+            .and_then(|position| position.split_once(','))
+            .ok_or_else(bad)?;
+        Ok(Placement {
+            path: path.to_string(),
+            row: row.trim().parse().map_err(|_| bad())?,
+            col: col.trim().parse().map_err(|_| bad())?,
+            transforms: parts.map(str::parse).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conways_game_of_life::soup::Symmetry;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults_test() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn stream_options_test() {
+        let options = parse(&["--stream", "--generations", "10", "--size", "20"]).unwrap();
+        assert!(options.stream);
+        assert_eq!(options.format, Format::Raw);
+        assert_eq!(options.generations, Some(10));
+        assert_eq!(options.size, 20);
+        assert!(!parse(&[]).unwrap().stop_on_cycle);
+        assert!(parse(&["--stop-on-cycle"]).unwrap().stop_on_cycle);
+    }
+
+    #[test]
+    fn terminal_test() {
+        assert!(parse(&["--terminal", "glider.rle"]).unwrap().terminal);
+        assert!(!parse(&[]).unwrap().terminal);
+        assert!(parse(&["--terminal", "--stream"]).is_err());
+        assert!(parse(&["--terminal", "--kiosk"]).is_err());
+        for option in &["--infinite", "--record=run.json", "--play=run.json"] {
+            let mut args: Vec<&str> = option.split('=').collect();
+            args.insert(0, "--terminal");
+            assert_eq!(
+                parse(&args),
+                Err(format!("--terminal can't be used with {}", args[1]))
+            );
+            assert_eq!(parse(&args[1..]).unwrap().needs_window(), Some(args[1]));
+        }
+        assert_eq!(parse(&["--size", "8"]).unwrap().needs_window(), None);
+    }
+
+    #[test]
+    fn settle_period_test() {
+        assert_eq!(parse(&[]).unwrap().settle_period, DEFAULT_LONGEST_PERIOD);
+        let options = parse(&["--kiosk", "--settle-period", "30"]).unwrap();
+        assert_eq!(options.settle_period, 30);
+        assert!(parse(&["--settle-period", "0"]).is_err());
+        let options = parse(&["search", "--settle-period", "4"]).unwrap();
+        assert_eq!(options.search.map(|search| search.longest_period), Some(4));
+        let options = parse(&["lifespan", "--settle-period", "2", "diehard.rle"]).unwrap();
+        assert_eq!(
+            options
+                .lifespan
+                .map(|lifespans| lifespans.search.longest_period),
+            Some(2)
+        );
+        assert!(parse(&["search", "--settle-period", "0"]).is_err());
+    }
+
+    #[test]
+    fn verbosity_test() {
+        assert_eq!(parse(&[]).unwrap().verbosity, 0);
+        assert_eq!(parse(&["-v", "glider.rle"]).unwrap().verbosity, 1);
+        let options = parse(&["--stream", "-vv", "glider.rle", "--verbose"]).unwrap();
+        assert_eq!(options.verbosity, 3);
+        assert_eq!(options.pattern, Some("glider.rle".to_string()));
+        let options = parse(&["-v", "search", "--soups", "5"]).unwrap();
+        assert_eq!((options.verbosity, options.search.is_some()), (1, true));
+        assert_eq!(parse(&["search", "-vvv"]).unwrap().verbosity, 3);
+        assert!(parse(&["-vx"]).is_err());
+    }
+
+    #[test]
+    fn stats_csv_test() {
+        let options = parse(&["--stats-csv", "stats.csv"]).unwrap();
+        assert_eq!(options.stats_csv, Some("stats.csv".to_string()));
+    }
+
+    #[test]
+    fn auto_restart_test() {
+        assert_eq!(parse(&[]).unwrap().auto_restart, None);
+        assert_eq!(
+            parse(&["--auto-restart", "50"]).unwrap().auto_restart,
+            Some(50)
+        );
+    }
+
+    #[test]
+    fn density_test() {
+        assert_eq!(parse(&[]).unwrap().soup.density, 0.5);
+        assert_eq!(parse(&["--density", "0.12"]).unwrap().soup.density, 0.12);
+        assert!(parse(&["--density", "12"]).is_err());
+        assert_eq!(
+            parse(&["--symmetry", "diagonal"]).unwrap().soup.symmetry,
+            Symmetry::Diagonal
+        );
+        assert!(parse(&["--symmetry", "radial"]).is_err());
+        assert_eq!(parse(&["--noise", "8"]).unwrap().soup.noise, Some(8.0));
+        assert!(parse(&["--noise", "0"]).is_err());
+    }
+
+    #[test]
+    fn heatmap_test() {
+        let options = parse(&["--colormap", "viridis", "--heatmap-png", "heat.png"]).unwrap();
+        assert_eq!(options.colormap, Colormap::Viridis);
+        assert_eq!(options.heatmap_png, Some("heat.png".to_string()));
+        assert!(parse(&["--colormap", "rainbow"]).is_err());
+        assert_eq!(parse(&[]).unwrap().scale, 10);
+        assert_eq!(parse(&["--scale", "4"]).unwrap().scale, 4);
+        assert!(parse(&["--scale", "0"]).is_err());
+    }
+
+    #[test]
+    fn history_test() {
+        assert_eq!(parse(&[]).unwrap().history_mb, 64);
+        assert_eq!(parse(&["--history-mb", "256"]).unwrap().history_mb, 256);
+    }
+
+    #[test]
+    fn seed_test() {
+        assert_eq!(parse(&[]).unwrap().seed, None);
+        assert_eq!(parse(&["--seed", "12345"]).unwrap().seed, Some(12345));
+        assert!(parse(&["--seed", "-1"]).is_err());
+    }
+
+    #[test]
+    fn replay_test() {
+        let options = parse(&["--record", "session.json"]).unwrap();
+        assert_eq!(options.record, Some("session.json".to_string()));
+        assert_eq!(
+            parse(&["--play", "session.json"]).unwrap().play,
+            Some("session.json".to_string())
+        );
+        assert!(parse(&["--stream", "--play", "session.json"]).is_err());
+        assert!(parse(&["--play", "session.json", "glider.rle"]).is_err());
+    }
+
+    #[test]
+    fn chat_test() {
+        let options = parse(&["--chat", "#life"]).unwrap();
+        assert_eq!(options.chat, Some("#life".to_string()));
+        assert_eq!(options.chat_server, chat::TWITCH);
+        let options = parse(&["--chat", "life", "--chat-server", "irc.libera.chat:6667"]).unwrap();
+        assert_eq!(options.chat_server, "irc.libera.chat:6667");
+        assert!(parse(&["--chat", "life", "--stream"]).is_err());
+    }
+
+    #[test]
+    fn shared_test() {
+        let options = parse(&["--host", "0.0.0.0:7000"]).unwrap();
+        assert_eq!(options.host, Some("0.0.0.0:7000".to_string()));
+        let options = parse(&["--join", "192.168.1.5:7000"]).unwrap();
+        assert_eq!(options.join, Some("192.168.1.5:7000".to_string()));
+        assert!(parse(&["--join", "192.168.1.5"]).is_err());
+        assert!(parse(&["--host", "0.0.0.0:7000", "--infinite"]).is_err());
+        assert!(parse(&["--join", "192.168.1.5:7000", "--control"]).is_err());
+    }
+
+    #[test]
+    fn search_test() {
+        let options = parse(&[
+            "search", "--soups", "100000", "--size", "16", "--rule", "B3/S23",
+        ])
+        .unwrap();
+        assert_eq!(
+            options.search,
+            Some(Search {
+                soups: 100_000,
+                size: 16,
+                ..Search::default()
+            })
+        );
+        let options = parse(&["search", "--seed", "4", "--threads", "2"]).unwrap();
+        assert_eq!(
+            options.search.map(|search| (search.seed, search.threads)),
+            Some((4, 2))
+        );
+        assert_eq!(
+            parse(&["search", "--rule", "B36/S23"])
+                .unwrap()
+                .search
+                .map(|search| search.rule.to_string()),
+            Some("B36/S23".to_string())
+        );
+        assert!(parse(&["search", "--rule", "B0/S"]).is_err());
+        assert!(parse(&["search", "--soups", "0"]).is_err());
+        assert!(parse(&["search", "--fit"]).is_err());
+    }
+
+    #[test]
+    fn lifespan_test() {
+        let options = parse(&["lifespan", "--rule", "B36/S23", "r-pentomino.rle"]).unwrap();
+        assert_eq!(options.pattern.as_deref(), Some("r-pentomino.rle"));
+        assert_eq!(
+            options.rule.map(|rule| rule.to_string()),
+            Some("B36/S23".to_string())
+        );
+        assert!(options.lifespan.is_some());
+
+        let options = parse(&[
+            "lifespan",
+            "--soups",
+            "500",
+            "--top",
+            "3",
+            "--out",
+            "longest",
+            "--json",
+            "spans.json",
+        ])
+        .unwrap();
+        assert_eq!(options.pattern, None);
+        assert_eq!(options.lifespan_out, Some(PathBuf::from("longest")));
+        assert_eq!(options.lifespan_json, Some(PathBuf::from("spans.json")));
+        assert_eq!(
+            options.lifespan,
+            Some(Lifespans {
+                search: Search {
+                    soups: 500,
+                    ..Search::default()
+                },
+                top: 3,
+            })
+        );
+        assert!(parse(&["lifespan", "--top", "0"]).is_err());
+        assert!(parse(&["lifespan", "one.rle", "two.rle"]).is_err());
+        assert!(parse(&["lifespan", "--fit"]).is_err());
+    }
+
+    #[test]
+    fn explore_test() {
+        let options = parse(&["explore", "--rules", "50", "--top", "5", "--size", "24"]).unwrap();
+        assert_eq!(
+            options.explore,
+            Some(Explore {
+                rules: 50,
+                top: 5,
+                size: 24,
+                ..Explore::default()
+            })
+        );
+        assert!(parse(&["explore", "--rules", "0"]).is_err());
+        assert!(parse(&["explore", "--density", "2"]).is_err());
+        assert!(parse(&["explore", "--rule", "B3/S23"]).is_err());
+    }
+
+    #[test]
+    fn sweep_test() {
+        let options = parse(&["sweep", "rules.toml", "--out", "results.csv"]).unwrap();
+        assert_eq!(options.sweep, Some(PathBuf::from("rules.toml")));
+        assert_eq!(options.sweep_out, Some(PathBuf::from("results.csv")));
+        assert!(parse(&["sweep"]).is_err());
+        assert!(parse(&["sweep", "one.json", "two.json"]).is_err());
+        assert!(parse(&["sweep", "rules.json", "--soups", "4"]).is_err());
+    }
+
+    #[test]
+    fn manifest_test() {
+        for command in &["search", "hunt", "lifespan", "explore"] {
+            let options = parse(&[command, "--save-manifest", "run.json"]).unwrap();
+            assert_eq!(options.save_manifest, Some(PathBuf::from("run.json")));
+        }
+        let options = parse(&["sweep", "rules.toml", "--save-manifest", "run.json"]).unwrap();
+        assert_eq!(options.save_manifest, Some(PathBuf::from("run.json")));
+        assert_eq!(parse(&["search"]).unwrap().save_manifest, None);
+
+        let options = parse(&["reproduce", "run.json"]).unwrap();
+        assert_eq!(options.reproduce, Some(PathBuf::from("run.json")));
+        assert_eq!(options.search, None);
+        assert!(parse(&["reproduce"]).is_err());
+        assert!(parse(&["reproduce", "one.json", "two.json"]).is_err());
+    }
+
+    #[test]
+    fn hunt_test() {
+        let options = parse(&[
+            "hunt",
+            "--size",
+            "4",
+            "--all",
+            "--min-period",
+            "3",
+            "--out",
+            "oscillators",
+        ])
+        .unwrap();
+        assert_eq!(
+            options.hunt,
+            Some(Hunt {
+                size: 4,
+                samples: None,
+                min_period: 3,
+                out: PathBuf::from("oscillators"),
+                ..Hunt::default()
+            })
+        );
+        let options = parse(&["hunt", "--samples", "50", "--rule", "B36/S23"]).unwrap();
+        assert_eq!(
+            options
+                .hunt
+                .map(|hunt| (hunt.samples, hunt.rule.to_string())),
+            Some((Some(50), "B36/S23".to_string()))
+        );
+        assert!(parse(&["hunt", "--size", "6", "--all"]).is_err());
+        assert!(parse(&["hunt", "--samples", "0"]).is_err());
+        assert!(parse(&["hunt", "--min-period", "0"]).is_err());
+        assert!(parse(&["hunt", "--soups", "10"]).is_err());
+    }
+
+    #[test]
+    fn ctl_test() {
+        let options = parse(&["ctl", "step", "5"]).unwrap();
+        assert_eq!(options.ctl, Some(ipc::Request::Step(5)));
+        assert_eq!(options.control_path(), ipc::default_path());
+        let options = parse(&["ctl", "--control-socket", "/tmp/life.sock", "pause"]).unwrap();
+        assert_eq!(options.ctl, Some(ipc::Request::Pause));
+        assert_eq!(options.control_path(), PathBuf::from("/tmp/life.sock"));
+        assert!(parse(&["ctl", "jump"]).is_err());
+        assert!(parse(&["ctl"]).is_err());
+
+        assert!(parse(&["--control"]).unwrap().control);
+        assert!(parse(&["--control", "--stream"]).is_err());
+    }
+
+    #[test]
+    fn api_test() {
+        let options = parse(&["--stream", "--api", "127.0.0.1:8080"]).unwrap();
+        assert_eq!(options.api, Some("127.0.0.1:8080".to_string()));
+        assert_eq!(parse(&[]).unwrap().api, None);
+        assert!(parse(&["--api", "127.0.0.1:8080"]).is_err());
+    }
+
+    #[test]
+    fn serve_test() {
+        let options = parse(&["--serve", "ws://0.0.0.0:9000"]).unwrap();
+        assert_eq!(options.serve, Some("0.0.0.0:9000".to_string()));
+        assert_eq!(parse(&[]).unwrap().serve, None);
+        assert!(parse(&["--serve", "ws://0.0.0.0"]).is_err());
+    }
+
+    #[test]
+    fn checkpoint_test() {
+        let options = parse(&["--checkpoint-every", "10000", "--checkpoint-dir", "runs/"]).unwrap();
+        assert_eq!(options.checkpoint_every, Some(10000));
+        assert_eq!(options.checkpoint_dir, "runs/");
+        assert_eq!(parse(&[]).unwrap().checkpoint_dir, "checkpoints");
+        assert!(parse(&["--checkpoint-every", "0"]).is_err());
+    }
+
+    #[test]
+    fn animation_test() {
+        let options =
+            parse(&["--gif", "out.gif", "--frames", "300", "--frame-delay", "50"]).unwrap();
+        assert_eq!(options.animation, Some("out.gif".to_string()));
+        assert_eq!(options.animation_format, AnimationFormat::Gif);
+        assert_eq!(options.frames, Some(300));
+        assert_eq!(options.frame_delay, 50);
+        assert_eq!(parse(&[]).unwrap().frame_delay, 100);
+        assert!(parse(&["--frames", "0"]).is_err());
+        assert_eq!(
+            parse(&["--video", "run.webm"]).unwrap().video,
+            Some("run.webm".to_string())
+        );
+        let options = parse(&["--apng", "out.png"]).unwrap();
+        assert_eq!(options.animation, Some("out.png".to_string()));
+        assert_eq!(options.animation_format, AnimationFormat::Apng);
+        assert_eq!(
+            parse(&["--animation-format", "apng"])
+                .unwrap()
+                .animation_format,
+            AnimationFormat::Apng
+        );
+    }
+
+    #[test]
+    fn frames_dir_test() {
+        let options = parse(&["--frames-dir", "out/", "--every", "10"]).unwrap();
+        assert_eq!(options.frames_dir, Some("out/".to_string()));
+        assert_eq!(options.every, 10);
+        assert_eq!(parse(&[]).unwrap().every, 1);
+        assert!(parse(&["--every", "0"]).is_err());
+    }
+
+    #[test]
+    fn share_test() {
+        let options = parse(&["--share", "abc"]).unwrap();
+        assert_eq!(options.share, Some("abc".to_string()));
+        assert!(parse(&["--share", "abc", "glider.rle"]).is_err());
+    }
+
+    #[test]
+    fn fetch_test() {
+        let options = parse(&["--fetch", "Gosper glider gun"]).unwrap();
+        assert_eq!(options.fetch, Some("Gosper glider gun".to_string()));
+        assert!(options.has_start());
+        assert!(parse(&["--fetch", "acorn", "glider.rle"]).is_err());
+    }
+
+    #[test]
+    fn empty_test() {
+        let options = parse(&["--empty"]).unwrap();
+        assert!(options.empty);
+        assert!(options.has_start());
+        assert!(parse(&["--empty", "glider.rle"]).is_err());
+    }
+
+    #[test]
+    fn infinite_test() {
+        assert!(parse(&["--infinite", "--fit", "gun.rle"]).unwrap().infinite);
+        assert!(!parse(&[]).unwrap().infinite);
+        assert!(parse(&["--infinite", "--stream"]).is_err());
+    }
+
+    #[test]
+    fn auto_expand_test() {
+        assert_eq!(parse(&["--auto-expand", "5"]).unwrap().auto_expand, Some(5));
+        assert!(parse(&["--auto-expand", "0"]).is_err());
+        assert!(parse(&["--auto-expand", "5", "--infinite"]).is_err());
+        assert!(parse(&["--auto-expand", "5", "--gif", "run.gif"]).is_err());
+    }
+
+    #[test]
+    fn rule_test() {
+        assert_eq!(parse(&[]).unwrap().rule, None);
+        assert_eq!(
+            parse(&["--rule", "b36/s23"]).unwrap().rule,
+            Some("B36/S23".parse().unwrap())
+        );
+        assert!(parse(&["--rule", "B0/S8"]).is_err());
+        assert!(parse(&["--rule", "B3/S23", "--versus", "10"]).is_err());
+    }
+
+    #[test]
+    fn topology_test() {
+        assert_eq!(
+            parse(&["--topology", "klein"])
+                .unwrap()
+                .topology
+                .to_string(),
+            "klein"
+        );
+        assert!(parse(&["--topology", "wrap,twist"]).is_ok());
+        assert!(parse(&["--topology", "donut"]).is_err());
+        let options = parse(&["--topology", "T100,50+10"]).unwrap();
+        assert_eq!(options.topology.to_string(), "wrap+10,wrap");
+        assert_eq!(options.dimensions(), (100, 50));
+        assert_eq!(parse(&["--size", "20"]).unwrap().dimensions(), (20, 20));
+        assert!(parse(&["--topology", "T100,50+"]).is_err());
+        assert!(parse(&["--topology", "torus", "--infinite"]).is_err());
+        assert!(parse(&["--topology", "torus", "--auto-expand", "5"]).is_err());
+    }
+
+    #[test]
+    fn fill_window_test() {
+        let options = parse(&["--fill-window", "--cell-size", "4"]).unwrap();
+        assert!(options.fill_window);
+        assert_eq!(options.cell_size, 4);
+        assert_eq!(parse(&[]).unwrap().cell_size, 10);
+        assert!(parse(&["--cell-size", "0"]).is_err());
+        assert!(parse(&["--fill-window", "--stream"]).is_err());
+    }
+
+    #[test]
+    fn kiosk_test() {
+        let options = parse(&["--kiosk"]).unwrap();
+        assert!(options.kiosk);
+        assert!(options.fill_window);
+        assert_eq!(options.auto_restart, Some(KIOSK_RESTART));
+        let options = parse(&["--kiosk", "--auto-restart", "200"]).unwrap();
+        assert_eq!(options.auto_restart, Some(200));
+        assert!(!parse(&[]).unwrap().kiosk);
+        assert!(parse(&["--kiosk", "--stream"]).is_err());
+        assert!(parse(&["--kiosk", "--versus", "10"]).is_err());
+        assert!(parse(&["--kiosk", "--record", "run.json"]).is_err());
+    }
+
+    #[test]
+    fn config_test() {
+        let options = parse(&["--config", "keys.json"]).unwrap();
+        assert_eq!(options.config, Some("keys.json".to_string()));
+        assert!(parse(&["--config"]).is_err());
+    }
+
+    #[test]
+    fn versus_test() {
+        let options = parse(&["--versus", "20", "--versus-generations", "50"]).unwrap();
+        assert_eq!(options.versus, Some(20));
+        assert_eq!(options.versus_generations, 50);
+        assert!(options.has_start());
+        assert_eq!(parse(&[]).unwrap().versus_generations, 100);
+        assert!(parse(&["--versus", "0"]).is_err());
+        assert!(parse(&["--versus", "5", "--empty"]).is_err());
+        assert!(parse(&["--versus", "5", "--infinite"]).is_err());
+        assert!(parse(&["--versus", "5", "--stream"]).is_err());
+
+        let options = parse(&["--versus", "20", "--battle", "5,10"]).unwrap();
+        assert_eq!(
+            options.battle,
+            Some(Battle {
+                per_turn: 5,
+                between: 10
+            })
+        );
+        assert!(parse(&["--battle", "5,10"]).is_err());
+        assert!(parse(&["--versus", "20", "--battle", "0,10"]).is_err());
+        assert!(parse(&["--versus", "20", "--battle", "5"]).is_err());
+    }
+
+    #[test]
+    fn mute_test() {
+        assert!(parse(&["--mute"]).unwrap().mute);
+        assert!(!parse(&[]).unwrap().mute);
+        assert!(parse(&["--sonify", "--mute"]).unwrap().sonify);
+        assert!(!parse(&[]).unwrap().sonify);
+        assert!(parse(&["--sonify", "--stream"]).is_err());
+    }
+
+    #[test]
+    fn chroma_key_test() {
+        assert_eq!(
+            parse(&["--chroma-key", "green"]).unwrap().chroma_key,
+            Some((0, 255, 0))
+        );
+        assert_eq!(parse(&[]).unwrap().chroma_key, None);
+        assert!(parse(&["--chroma-key", "grean"]).is_err());
+    }
+
+    #[test]
+    fn theme_test() {
+        assert_eq!(
+            parse(&["--theme", "light"]).unwrap().theme,
+            Some(Theme::Light)
+        );
+        assert_eq!(parse(&[]).unwrap().theme, None);
+        assert!(parse(&["--theme", "blue"]).is_err());
+    }
+
+    #[test]
+    fn view_test() {
+        assert_eq!(parse(&["--view", "sphere"]).unwrap().view, View::Sphere);
+        assert_eq!(parse(&[]).unwrap().view, View::Flat);
+        assert!(parse(&["--view", "cube"]).is_err());
+        let options = parse(&["--view", "isometric", "--age-height"]).unwrap();
+        assert_eq!(options.view, View::Isometric);
+        assert!(options.age_height);
+    }
+
+    #[test]
+    fn text_test() {
+        let options = parse(&["--text", "HELLO"]).unwrap();
+        assert_eq!(options.text, Some("HELLO".to_string()));
+        assert!(parse(&["--text", "HELLO", "--share", "abc"]).is_err());
+    }
+
+    #[test]
+    fn place_test() {
+        let options = parse(&[
+            "--place",
+            "glider.rle@10,10",
+            "--place",
+            "gun.rle@0,30:rot90:flipx",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            options.placements,
+            vec![
+                Placement {
+                    path: "glider.rle".to_string(),
+                    row: 10,
+                    col: 10,
+                    transforms: vec![],
+                },
+                Placement {
+                    path: "gun.rle".to_string(),
+                    row: 0,
+                    col: 30,
+                    transforms: vec![Transform::Rotate90, Transform::FlipX],
+                },
+            ]
+        );
+        assert!(options.has_start());
+        assert!(!parse(&[]).unwrap().has_start());
+        assert!(parse(&["--place", "glider.rle"]).is_err());
+        assert!(parse(&["--place", "glider.rle@1,2:spin"]).is_err());
+        assert!(parse(&["--place", "glider.rle@1,2", "--play", "replay.json"]).is_err());
+    }
+
+    #[test]
+    fn thumbnails_test() {
+        let options = parse(&["--thumbnails", "patterns", "--thumbnail-size", "32"]).unwrap();
+        assert_eq!(options.thumbnails, Some("patterns".to_string()));
+        assert_eq!(options.thumbnail_size, 32);
+        assert_eq!(parse(&[]).unwrap().thumbnail_size, 64);
+        assert!(parse(&["--thumbnail-size", "0"]).is_err());
+    }
+
+    #[test]
+    fn format_test() {
+        assert_eq!(parse(&["--format", "json"]).unwrap().format, Format::Json);
+    }
+
+    #[test]
+    fn pattern_argument_test() {
+        assert_eq!(parse(&["run", "-"]).unwrap().pattern, Some("-".to_string()));
+        assert_eq!(
+            parse(&["glider.rle", "--stream"]).unwrap().pattern,
+            Some("glider.rle".to_string())
+        );
+        assert!(parse(&["a.rle", "b.rle"]).is_err());
+        let options = parse(&["--fit", "--margin", "4", "gun.rle"]).unwrap();
+        assert!(options.fit);
+        assert_eq!(options.margin, 4);
+        assert_eq!(parse(&[]).unwrap().margin, 10);
+    }
+
+    #[test]
+    fn bad_arguments_test() {
+        assert!(parse(&["--size"]).is_err());
+        assert!(parse(&["--size", "big"]).is_err());
+        assert!(parse(&["--size", "0"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["--format", "xml"]).is_err());
+    }
+}
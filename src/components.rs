@@ -0,0 +1,283 @@
+use crate::grid::{Grid, State};
+use std::collections::HashMap;
+
+/// The living cells of a grid split into connected clusters, each cell
+/// labelled with the index of the cluster it belongs to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Components {
+    labels: Vec<Vec<Option<usize>>>,
+    sizes: Vec<usize>,
+}
+
+impl Components {
+    /// Labels clusters of living cells that touch, including diagonally.
+    pub fn label(grid: &Grid) -> Components {
+        Components::label_with_reach(grid, 1)
+    }
+
+    /// Labels clusters of living cells, treating two cells as connected
+    /// when they are at most `reach` rows and columns apart. A reach of 1
+    /// joins cells that touch, a reach of 2 cells whose neighbourhoods
+    /// overlap.
+    pub fn label_with_reach(grid: &Grid, reach: usize) -> Components {
+        let (height, width) = (grid.height(), grid.width());
+        let mut labels = vec![vec![None; width]; height];
+        let mut sizes = Vec::new();
+
+        for (row, col) in grid.living_cells() {
+            if labels[row][col].is_some() {
+                continue;
+            }
+
+            let label = sizes.len();
+            let mut size = 0;
+            let mut stack = vec![(row, col)];
+            labels[row][col] = Some(label);
+            while let Some((row, col)) = stack.pop() {
+                size += 1;
+                let rows = row.saturating_sub(reach)..(row + reach + 1).min(height);
+                let cols = col.saturating_sub(reach)..(col + reach + 1).min(width);
+                for (r, c) in rows.flat_map(|r| cols.clone().map(move |c| (r, c))) {
+                    if grid.cells[r][c] == State::Alive && labels[r][c].is_none() {
+                        labels[r][c] = Some(label);
+                        stack.push((r, c));
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+
+        Components { labels, sizes }
+    }
+
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// The number of cells in each cluster, indexed by label.
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    pub fn largest(&self) -> usize {
+        self.sizes.iter().copied().max().unwrap_or(0)
+    }
+
+    /// The label of the cluster the cell at `(row, column)` belongs to, or
+    /// `None` for a dead cell.
+    pub fn label_at(&self, row: usize, col: usize) -> Option<usize> {
+        self.labels[row][col]
+    }
+
+    /// The `(row, column)` positions of the cells in each cluster, indexed
+    /// by label.
+    pub fn clusters(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut clusters: Vec<Vec<(usize, usize)>> = self
+            .sizes
+            .iter()
+            .map(|&size| Vec::with_capacity(size))
+            .collect();
+        for (row_idx, row) in self.labels.iter().enumerate() {
+            for (col_idx, label) in row.iter().enumerate() {
+                if let Some(label) = label {
+                    clusters[*label].push((row_idx, col_idx));
+                }
+            }
+        }
+        clusters
+    }
+}
+
+/// Follows clusters from one generation to the next, giving each an id it
+/// keeps while it moves and changes shape. When clusters merge, the one
+/// that overlaps the merged cluster the most passes its id on, and when one
+/// splits, or throws off a glider, the biggest part keeps it and the others
+/// get new ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tracker {
+    grid: Grid,
+    components: Components,
+    /// The id of each cluster, indexed by label.
+    ids: Vec<u64>,
+    next_id: u64,
+}
+
+impl Default for Tracker {
+    fn default() -> Tracker {
+        let grid = Grid::seed(0, vec![]);
+        Tracker {
+            components: Components::label(&grid),
+            grid,
+            ids: Vec::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl Tracker {
+    pub fn new() -> Tracker {
+        Tracker::default()
+    }
+
+    /// Labels the clusters of `grid`, if it has changed since last time,
+    /// and works out which of the clusters before each one carries on.
+    pub fn update(&mut self, grid: &Grid) {
+        if *grid == self.grid {
+            return;
+        }
+        let components = Components::label(grid);
+        let same_size = (grid.width(), grid.height()) == (self.grid.width(), self.grid.height());
+        // How many cells of each new cluster were in or next to each old one.
+        let mut overlaps = Vec::new();
+        if same_size {
+            for (label, cells) in components.clusters().iter().enumerate() {
+                let mut counts: HashMap<usize, usize> = HashMap::new();
+                for &(row, col) in cells {
+                    let rows = row.saturating_sub(1)..(row + 2).min(grid.height());
+                    let cols = col.saturating_sub(1)..(col + 2).min(grid.width());
+                    for (r, c) in rows.flat_map(|r| cols.clone().map(move |c| (r, c))) {
+                        if let Some(old) = self.components.label_at(r, c) {
+                            *counts.entry(old).or_insert(0) += 1;
+                        }
+                    }
+                }
+                overlaps.extend(counts.into_iter().map(|(old, count)| (count, label, old)));
+            }
+        }
+        overlaps.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+        let mut ids = vec![None; components.count()];
+        let mut passed_on = vec![false; self.ids.len()];
+        for (_, label, old) in overlaps {
+            if ids[label].is_none() && !passed_on[old] {
+                ids[label] = Some(self.ids[old]);
+                passed_on[old] = true;
+            }
+        }
+        let next_id = &mut self.next_id;
+        self.ids = ids
+            .into_iter()
+            .map(|id| {
+                id.unwrap_or_else(|| {
+                    *next_id += 1;
+                    *next_id - 1
+                })
+            })
+            .collect();
+        self.components = components;
+        self.grid = grid.clone();
+    }
+
+    /// The id of the cluster the cell at `(row, column)` belongs to, or
+    /// `None` for a dead cell.
+    pub fn id_at(&self, row: usize, col: usize) -> Option<u64> {
+        self.components
+            .label_at(row, col)
+            .map(|label| self.ids[label])
+    }
+}
+
+/// A colour for the cluster with `id`. Hues are spread round the colour
+/// wheel by the golden ratio, so clusters with ids close together, like
+/// the parts of one that has just split, look nothing alike.
+pub fn colour(id: u64) -> (u8, u8, u8) {
+    let hue = (id as f64 * 0.618_033_988_75).fract() * 6.0;
+    let (saturation, value) = (0.65, 0.95);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_test() {
+        let grid = Grid::seed(6, vec![(0, 0), (1, 1), (0, 4), (0, 5), (4, 4)]);
+
+        let components = Components::label(&grid);
+
+        assert_eq!(components.count(), 3);
+        assert_eq!(components.sizes(), &[2, 2, 1]);
+        assert_eq!(components.largest(), 2);
+        assert_eq!(components.label_at(1, 1), Some(0));
+        assert_eq!(components.label_at(4, 4), Some(2));
+        assert_eq!(components.label_at(3, 3), None);
+        assert_eq!(components.clusters()[1], vec![(0, 4), (0, 5)]);
+    }
+
+    #[test]
+    fn reach_test() {
+        let grid = Grid::seed(5, vec![(0, 0), (0, 2), (4, 4)]);
+
+        assert_eq!(Components::label(&grid).count(), 3);
+        assert_eq!(Components::label_with_reach(&grid, 2).count(), 2);
+    }
+
+    #[test]
+    fn tracker_test() {
+        let glider = Grid::seed(12, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let block = vec![(9, 9), (9, 10), (10, 9), (10, 10)];
+        let mut grid = glider.clone();
+        for &(row, col) in &block {
+            grid.toggle(row, col);
+        }
+        let mut tracker = Tracker::new();
+
+        tracker.update(&grid);
+        let (glider_id, block_id) = (tracker.id_at(0, 1).unwrap(), tracker.id_at(9, 9).unwrap());
+        assert_ne!(glider_id, block_id);
+        assert_eq!(tracker.id_at(5, 5), None);
+        for _ in 0..8 {
+            grid = grid.next_generation();
+            tracker.update(&grid);
+        }
+
+        // The glider has moved two cells down and right and kept its id.
+        assert_eq!(tracker.id_at(4, 3), Some(glider_id));
+        assert_eq!(tracker.id_at(9, 9), Some(block_id));
+    }
+
+    #[test]
+    fn split_test() {
+        let mut tracker = Tracker::new();
+        tracker.update(&Grid::seed(8, vec![(1, 1), (1, 2), (1, 3), (1, 4), (1, 5)]));
+        let id = tracker.id_at(1, 1).unwrap();
+
+        tracker.update(&Grid::seed(8, vec![(1, 1), (1, 2), (1, 3), (1, 5)]));
+
+        assert_eq!(tracker.id_at(1, 1), Some(id));
+        assert_ne!(tracker.id_at(1, 5), Some(id));
+        assert!(tracker.id_at(1, 5).is_some());
+    }
+
+    #[test]
+    fn colour_test() {
+        assert_eq!(colour(0), (242, 85, 85));
+        let colours: Vec<_> = (0..6).map(colour).collect();
+        for (i, a) in colours.iter().enumerate() {
+            for b in &colours[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_test() {
+        let components = Components::label(&Grid::seed(3, vec![]));
+
+        assert_eq!(components.count(), 0);
+        assert_eq!(components.largest(), 0);
+    }
+}
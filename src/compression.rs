@@ -0,0 +1,105 @@
+//! Gzip compression of saved boards and sessions, which for large boards
+//! run to megabytes of JSON. Files are written compressed when their names
+//! end in `.gz`, and read back compressed or not whatever they are named,
+//! told apart by the two bytes every gzip file starts with.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// The first two bytes of every gzip file.
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether a file at `path` is written compressed.
+pub fn is_compressed_name(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+/// `text` compressed with gzip.
+pub fn compress(text: &str) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(text.as_bytes())
+        .expect("writing to memory can't fail");
+    encoder.finish().expect("writing to memory can't fail")
+}
+
+/// The text of `bytes`, decompressed first if they are gzip.
+pub fn decode(bytes: Vec<u8>) -> io::Result<String> {
+    if !bytes.starts_with(&MAGIC) {
+        return String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+    let mut text = String::new();
+    GzDecoder::new(&bytes[..]).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// `input` to be read a little at a time, decompressed as it is if it is
+/// gzip, for files too big to read whole.
+pub fn reader<R: Read + 'static>(input: R) -> io::Result<Box<dyn BufRead>> {
+    let mut input = BufReader::new(input);
+    if input.fill_buf()?.starts_with(&MAGIC) {
+        Ok(Box::new(BufReader::new(GzDecoder::new(input))))
+    } else {
+        Ok(Box::new(input))
+    }
+}
+
+/// The file at `path` to be read a little at a time, compressed or not.
+pub fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    reader(File::open(path)?)
+}
+
+/// Writes `text` to `path`, compressed if its name ends in `.gz`.
+pub fn write(path: &Path, text: &str) -> io::Result<()> {
+    if is_compressed_name(path) {
+        fs::write(path, compress(text))
+    } else {
+        fs::write(path, text)
+    }
+}
+
+/// The text of the file at `path`, compressed or not.
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    decode(fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let text = "{\"cells\": [".to_string() + &"[1, 2], ".repeat(1000) + "[3, 4]]}";
+        let compressed = compress(&text);
+        assert!(compressed.len() < text.len() / 20, "{}", compressed.len());
+        assert_eq!(decode(compressed).unwrap(), text);
+        assert_eq!(decode(b"3o!".to_vec()).unwrap(), "3o!");
+        assert!(decode(vec![0x1f, 0x8b, 0, 1]).is_err());
+        assert!(decode(vec![0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn file_test() {
+        let dir = std::env::temp_dir().join(format!("compression_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (plain, packed) = (dir.join("board.json"), dir.join("board.json.gz"));
+        write(&plain, "{}").unwrap();
+        write(&packed, "{}").unwrap();
+        assert_eq!(fs::read(&plain).unwrap(), b"{}");
+        assert!(fs::read(&packed).unwrap().starts_with(&MAGIC));
+        assert_eq!(read_to_string(&packed).unwrap(), "{}");
+        assert_eq!(read_to_string(&plain).unwrap(), "{}");
+        for path in &[&plain, &packed] {
+            let mut text = String::new();
+            open(path).unwrap().read_to_string(&mut text).unwrap();
+            assert_eq!(text, "{}");
+        }
+        assert!(is_compressed_name(&packed) && !is_compressed_name(&plain));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
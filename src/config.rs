@@ -0,0 +1,165 @@
+use crate::i18n::Language;
+use conways_game_of_life::theme::Theme;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Preferences read from the config file, a JSON object such as:
+///
+/// ```json
+/// {"language": "es", "speed": 10, "keys": {"pause": ["P"], "save_favorite": ["Ctrl+F", "F2"]}}
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The language the window's text is shown in.
+    pub language: Language,
+    /// Generations a second, if not the usual.
+    pub speed: Option<u32>,
+    /// The keys to bind to each command, by name, in place of its own.
+    pub keys: BTreeMap<String, Vec<String>>,
+    /// A sound file to play on a loop behind the game, or `ambient` for
+    /// the one built in.
+    pub music: Option<String>,
+    /// How loud the music is, from 0 to 1, if not the usual.
+    pub music_volume: Option<f32>,
+    /// The theme to switch to, `dark` or `light`, in place of the one the
+    /// game started with.
+    pub theme: Option<Theme>,
+}
+
+impl Config {
+    /// Reads the config file at `path`. A missing file is the same as an
+    /// empty one, so nobody has to write one to get the defaults.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, String> {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("could not read {}: {}", path.display(), e)),
+        };
+        let config: Config = serde_json::from_str(&text)
+            .map_err(|e| format!("{}: invalid config: {}", path.display(), e))?;
+        if config.speed == Some(0) {
+            return Err(format!("{}: the speed must be at least 1", path.display()));
+        }
+        if let Some(volume) = config
+            .music_volume
+            .filter(|volume| !(0.0..=1.0).contains(volume))
+        {
+            return Err(format!(
+                "{}: the music volume must be from 0 to 1, not {}",
+                path.display(),
+                volume
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// Keeps an eye on the config file so that changes to it can be applied
+/// while the game runs. Its modification time is looked at every so often
+/// rather than on every frame.
+pub struct Watcher {
+    path: PathBuf,
+    every: Duration,
+    checked: Instant,
+    modified: Option<SystemTime>,
+}
+
+impl Watcher {
+    /// Watches `path`, checking it at most once `every` so long, starting
+    /// from how it is now.
+    pub fn new(path: PathBuf, every: Duration) -> Watcher {
+        Watcher {
+            modified: modified(&path),
+            path,
+            every,
+            checked: Instant::now(),
+        }
+    }
+
+    /// The config as it is now, if the file has changed since it was last
+    /// read, or been made or removed.
+    pub fn changed(&mut self) -> Option<Result<Config, String>> {
+        if self.checked.elapsed() < self.every {
+            return None;
+        }
+        self.checked = Instant::now();
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Config::load(&self.path))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Where the config file is kept, in the platform's config directory, such
+/// as `~/.config/conways_game_of_life/config.json` on Linux.
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("conways_game_of_life").join("config.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_test() {
+        let dir = std::env::temp_dir().join(format!("config_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+
+        assert_eq!(Config::load(&path), Ok(Config::default()));
+        fs::write(&path, r#"{"keys": {"pause": ["P", "Return"]}}"#).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.keys["pause"], vec!["P", "Return"]);
+        assert_eq!(config.language, Language::English);
+        fs::write(&path, r#"{"language": "es"}"#).unwrap();
+        assert_eq!(Config::load(&path).unwrap().language, Language::Spanish);
+        fs::write(&path, r#"{"colours": {}}"#).unwrap();
+        assert!(Config::load(&path).is_err());
+        fs::write(&path, r#"{"speed": 0}"#).unwrap();
+        assert!(Config::load(&path).is_err());
+        fs::write(&path, r#"{"music": "ambient", "music_volume": 0.3}"#).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.music, Some("ambient".to_string()));
+        assert_eq!(config.music_volume, Some(0.3));
+        fs::write(&path, r#"{"music_volume": 1.5}"#).unwrap();
+        assert!(Config::load(&path).is_err());
+        fs::write(&path, r#"{"theme": "light"}"#).unwrap();
+        assert_eq!(Config::load(&path).unwrap().theme, Some(Theme::Light));
+        fs::write(&path, r#"{"theme": "sepia"}"#).unwrap();
+        assert!(Config::load(&path).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn watcher_test() {
+        let dir = std::env::temp_dir().join(format!("watcher_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        let mut watcher = Watcher::new(path.clone(), Duration::from_secs(0));
+
+        assert_eq!(watcher.changed(), None);
+        fs::write(&path, r#"{"speed": 5}"#).unwrap();
+        let changed = watcher.changed();
+        assert_eq!(changed.map(|config| config.unwrap().speed), Some(Some(5)));
+        assert_eq!(watcher.changed(), None);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(watcher.changed(), Some(Ok(Config::default())));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,294 @@
+use crate::determinism::StableHasher;
+use crate::grid::Grid;
+use crate::plane::Plane;
+use crate::rule::Rule;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// How many recent generations are remembered by default, which is also
+/// the longest period that can be spotted.
+pub const DEFAULT_WINDOW: usize = 1000;
+
+/// A living cell as `(row, column)`.
+type Cell = (i64, i64);
+
+/// A repeat of an earlier board, possibly moved across the grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cycle {
+    /// The earlier generation that the board has returned to.
+    pub first_seen: u64,
+    pub period: u64,
+    /// How far the living cells moved over one period, as `(rows, columns)`.
+    pub displacement: (i64, i64),
+}
+
+impl Cycle {
+    /// What the repeat says about the pattern, `population` being the
+    /// number of living cells when it was found.
+    pub fn behaviour(&self, population: usize) -> Behaviour {
+        if population == 0 {
+            Behaviour::Extinct
+        } else if self.displacement != (0, 0) {
+            Behaviour::Spaceship {
+                period: self.period,
+                displacement: self.displacement,
+            }
+        } else if self.period == 1 {
+            Behaviour::StillLife
+        } else {
+            Behaviour::Oscillator {
+                period: self.period,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Behaviour {
+    Extinct,
+    StillLife,
+    Oscillator {
+        period: u64,
+    },
+    Spaceship {
+        period: u64,
+        displacement: (i64, i64),
+    },
+}
+
+impl fmt::Display for Behaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Behaviour::Extinct => write!(f, "extinct"),
+            Behaviour::StillLife => write!(f, "still life"),
+            Behaviour::Oscillator { period } => write!(f, "period {} oscillator", period),
+            Behaviour::Spaceship {
+                period,
+                displacement,
+            } => write!(
+                f,
+                "period {} spaceship moving ({}, {})",
+                period, displacement.0, displacement.1
+            ),
+        }
+    }
+}
+
+/// Spots when the board repeats by remembering, for each of the last
+/// `window` generations, a hash of the living cells relative to their
+/// bounding box and where that box was. A match of the hash is a repeat up
+/// to translation, the move of the box giving the displacement. Only hashes
+/// are kept, so the boards themselves are never compared.
+pub struct CycleDetector {
+    seen: HashMap<u64, (u64, (i64, i64))>,
+    order: VecDeque<u64>,
+    window: usize,
+}
+
+impl CycleDetector {
+    pub fn new(window: usize) -> CycleDetector {
+        CycleDetector {
+            seen: HashMap::new(),
+            order: VecDeque::with_capacity(window),
+            window,
+        }
+    }
+
+    /// Records `grid` as `generation`, returning the cycle it closes if the
+    /// same arrangement of cells was seen within the window.
+    pub fn observe(&mut self, generation: u64, grid: &Grid) -> Option<Cycle> {
+        let (hash, offset) = shape_hash(grid);
+        if let Some(&(first_seen, first_offset)) = self.seen.get(&hash) {
+            return Some(Cycle {
+                first_seen,
+                period: generation - first_seen,
+                displacement: displacement(first_offset, offset),
+            });
+        }
+
+        if self.order.len() == self.window {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(hash);
+        self.seen.insert(hash, (generation, offset));
+        None
+    }
+}
+
+/// Runs `plane` by `rule` until its living cells repeat an earlier
+/// generation's, moved or not, for `generations` at most, giving it as it
+/// was when it came round again and the cycle it closed. Every generation
+/// is kept whole rather than hashed, so this is for small patterns: it is
+/// given up on at once, with an empty plane, if it dies out or has more
+/// than `most_population` cells.
+pub fn run_to_cycle(
+    mut plane: Plane,
+    rule: Rule,
+    generations: u64,
+    most_population: usize,
+) -> (Plane, Option<Cycle>) {
+    let mut seen: HashMap<Vec<Cell>, (u64, Cell)> = HashMap::new();
+    for generation in 0..generations {
+        let cells = plane.living_cells();
+        if cells.is_empty() || cells.len() > most_population {
+            return (Plane::new(), None);
+        }
+        let top = cells.iter().map(|cell| cell.0).min().unwrap_or(0);
+        let left = cells.iter().map(|cell| cell.1).min().unwrap_or(0);
+        // A pattern spread across more of the plane than an `i64` spans is
+        // anything but small.
+        let shape: Option<Vec<Cell>> = cells
+            .iter()
+            .map(|&(row, col)| row.checked_sub(top).zip(col.checked_sub(left)))
+            .collect();
+        let shape = match shape {
+            Some(shape) => shape,
+            None => return (Plane::new(), None),
+        };
+        if let Some(&(first_seen, first_corner)) = seen.get(&shape) {
+            let cycle = Cycle {
+                first_seen,
+                period: generation - first_seen,
+                displacement: displacement(first_corner, (top, left)),
+            };
+            return (plane, Some(cycle));
+        }
+        seen.insert(shape, (generation, (top, left)));
+        plane = plane.next_generation_under(rule);
+    }
+    (plane, None)
+}
+
+/// How far a pattern moved from `from` to `to`. Nothing moves further in a
+/// period than the period itself, far short of the most an `i64` holds.
+fn displacement(from: Cell, to: Cell) -> (i64, i64) {
+    let moved = to.0.checked_sub(from.0).zip(to.1.checked_sub(from.1));
+    moved.expect("patterns move at most a cell a generation")
+}
+
+/// Hashes the living cells relative to the top left corner of their
+/// bounding box, the same on every machine, returning the hash and that
+/// corner.
+fn shape_hash(grid: &Grid) -> (u64, (i64, i64)) {
+    let living_cells = grid.living_cells();
+    let top = living_cells.iter().map(|cell| cell.0).min().unwrap_or(0);
+    let left = living_cells.iter().map(|cell| cell.1).min().unwrap_or(0);
+
+    let mut hasher = StableHasher::default();
+    for (row, col) in living_cells {
+        (row - top, col - left).hash(&mut hasher);
+    }
+    (hasher.finish(), (top as i64, left as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_cycle(mut grid: Grid, window: usize, generations: u64) -> Option<Cycle> {
+        let mut detector = CycleDetector::new(window);
+        for generation in 0..generations {
+            if let Some(cycle) = detector.observe(generation, &grid) {
+                return Some(cycle);
+            }
+            grid = grid.next_generation();
+        }
+        None
+    }
+
+    #[test]
+    fn blinker_period_test() {
+        let blinker = Grid::seed(5, vec![(1, 2), (2, 2), (3, 2)]);
+
+        assert_eq!(
+            first_cycle(blinker.clone(), 10, 10),
+            Some(Cycle {
+                first_seen: 0,
+                period: 2,
+                displacement: (0, 0)
+            })
+        );
+        assert_eq!(
+            first_cycle(blinker, 10, 10).unwrap().behaviour(3),
+            Behaviour::Oscillator { period: 2 }
+        );
+    }
+
+    #[test]
+    fn glider_test() {
+        let glider = Grid::seed(10, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+
+        let cycle = first_cycle(glider, 10, 10).unwrap();
+
+        assert_eq!(
+            cycle.behaviour(5),
+            Behaviour::Spaceship {
+                period: 4,
+                displacement: (1, 1)
+            }
+        );
+        assert_eq!(
+            cycle.behaviour(5).to_string(),
+            "period 4 spaceship moving (1, 1)"
+        );
+    }
+
+    #[test]
+    fn extinct_test() {
+        let lonely = Grid::seed(3, vec![(1, 1)]);
+
+        let cycle = first_cycle(lonely, 10, 10).unwrap();
+
+        assert_eq!(cycle.behaviour(0), Behaviour::Extinct);
+    }
+
+    #[test]
+    fn still_life_test() {
+        let block = Grid::seed(4, vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+
+        let cycle = first_cycle(block, 10, 10).unwrap();
+
+        assert_eq!(cycle.period, 1);
+        assert_eq!(cycle.behaviour(4), Behaviour::StillLife);
+    }
+
+    #[test]
+    fn run_to_cycle_test() {
+        let rule = Rule::default();
+        let glider = Plane::from_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let (plane, cycle) = run_to_cycle(glider, rule, 10, 100);
+        assert_eq!(
+            cycle,
+            Some(Cycle {
+                first_seen: 0,
+                period: 4,
+                displacement: (1, 1)
+            })
+        );
+        assert_eq!(plane.population(), 5);
+
+        let blinker = Plane::from_cells(&[(0, 0), (0, 1), (0, 2)]);
+        assert_eq!(run_to_cycle(blinker.clone(), rule, 2, 100).1, None);
+        assert_eq!(
+            run_to_cycle(blinker.clone(), rule, 3, 100)
+                .1
+                .map(|c| c.period),
+            Some(2)
+        );
+        assert_eq!(run_to_cycle(blinker, rule, 3, 2), (Plane::new(), None));
+        assert_eq!(
+            run_to_cycle(Plane::from_cells(&[(0, 0)]), rule, 10, 100).1,
+            None
+        );
+    }
+
+    #[test]
+    fn window_test() {
+        let blinker = Grid::seed(5, vec![(1, 2), (2, 2), (3, 2)]);
+
+        assert_eq!(first_cycle(blinker, 1, 10), None);
+    }
+}
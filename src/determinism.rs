@@ -0,0 +1,145 @@
+//! The randomness and hashing runs depend on, fixed so that a seed, a
+//! shared manifest or a replay gives the same boards on every machine.
+//!
+//! - Soups are drawn from ChaCha20 seeded from a `u64`, named here rather
+//!   than through `rand`'s `StdRng`, whose algorithm may change between
+//!   releases.
+//! - Draws only ever ask for fixed width integers, `f64`s made from their
+//!   bits and picks from lists, which `rand` draws as `u32`s, never for a
+//!   `usize`, which is narrower on 32 bit machines.
+//! - Working out a board only adds, multiplies and compares `f64`s, which
+//!   IEEE 754 rounds the same everywhere. The scores `explore` gives rules
+//!   take logarithms, which platforms' maths libraries can round
+//!   differently in the last digit, so rules scoring a hair apart may swap
+//!   places there.
+//! - Hashes that decide anything, such as whether a board has been seen
+//!   before, are FNV-1a over little endian bytes rather than the standard
+//!   library's hasher, which the standard library may change and which
+//!   hashes a `usize` as four bytes or eight depending on the machine.
+//! - Every generation is worked out from the last alone, cell by cell, and
+//!   anything gathered from a hash map or set is put in order before it is
+//!   used, so no result depends on iteration order.
+//!
+//! The golden tests in `tests/determinism.rs` pin the boards and hashes a
+//! few seeds give, so a change to any of these fails them.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::hash::{Hash, Hasher};
+
+/// The generator every seeded run draws from.
+pub type SeededRng = ChaCha20Rng;
+
+/// A generator seeded with `seed`.
+pub fn rng(seed: u64) -> SeededRng {
+    SeededRng::seed_from_u64(seed)
+}
+
+/// The generator of the soup or rule numbered `index` of a batch run
+/// seeded with `seed`, so that each is drawn the same whichever thread
+/// draws it and in whatever order.
+pub fn rng_for(seed: u64, index: u64) -> SeededRng {
+    rng(seed ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// A 64 bit FNV-1a hasher giving the same hash of a value on every
+/// machine: integers are hashed as little endian bytes, and `usize` and
+/// `isize` as eight of them.
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher(FNV_OFFSET)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16);
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write_u128(n as u128);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as i64 as u64);
+    }
+}
+
+/// The hash of `value` by `StableHasher`.
+pub fn stable_hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::RngCore;
+
+    #[test]
+    fn stable_hash_test() {
+        let mut hasher = StableHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash(&7usize), stable_hash(&7u64));
+        assert_eq!(stable_hash(&-7isize), stable_hash(&-7i64));
+        assert_ne!(
+            stable_hash(&(1usize, 2usize)),
+            stable_hash(&(2usize, 1usize))
+        );
+    }
+
+    #[test]
+    fn rng_test() {
+        // The same generator `rand` uses for `StdRng` today, so seeds given
+        // before it was named here give the same soups.
+        assert_eq!(rng(42).next_u64(), StdRng::seed_from_u64(42).next_u64());
+        assert_eq!(rng_for(42, 0).next_u64(), rng(42).next_u64());
+        assert_ne!(rng_for(42, 1).next_u64(), rng_for(42, 2).next_u64());
+    }
+}
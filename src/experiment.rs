@@ -0,0 +1,179 @@
+//! Manifests of batch runs, recording everything a `search`, `hunt`,
+//! `lifespan`, `explore` or `sweep` ran with so it can be run again with
+//! the same results: which it was, its rule, seed and every other
+//! parameter, and the version of this crate that ran it. A manifest is
+//! JSON like:
+//!
+//! ```json
+//! {
+//!   "version": "0.1.0",
+//!   "command": "search",
+//!   "soups": 1000,
+//!   "size": 16,
+//!   "density": 0.5,
+//!   "seed": 42,
+//!   "threads": 0,
+//!   "rule": "B3/S23"
+//! }
+//! ```
+//!
+//! Every soup is drawn from a generator seeded from the run's seed and its
+//! own number, so the number of threads doesn't change the results, though
+//! it is recorded too.
+
+use crate::explore::Explore;
+use crate::hunt::Hunt;
+use crate::lifespan::Lifespans;
+use crate::search::Search;
+use crate::sweep;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The version of this crate, which a manifest records.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A batch run and its parameters.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Experiment {
+    Search(Search),
+    Hunt(Hunt),
+    Lifespan(Lifespans),
+    Explore(Explore),
+    Sweep(sweep::Manifest),
+}
+
+impl Experiment {
+    /// Whether the run can be made.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Experiment::Search(search) => search.check(),
+            Experiment::Hunt(hunt) => hunt.check(),
+            Experiment::Lifespan(lifespans) => lifespans.check(),
+            Experiment::Explore(explore) => explore.check(),
+            Experiment::Sweep(manifest) => manifest.check(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Manifest {
+    /// The version of the crate that made the manifest.
+    pub version: String,
+    #[serde(flatten)]
+    pub experiment: Experiment,
+}
+
+impl Manifest {
+    /// A manifest of `experiment` run by this version.
+    pub fn new(experiment: Experiment) -> Manifest {
+        Manifest {
+            version: VERSION.to_string(),
+            experiment,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(text: &str) -> Result<Manifest, String> {
+        let manifest: Manifest =
+            serde_json::from_str(text).map_err(|e| format!("invalid manifest: {}", e))?;
+        manifest.experiment.check()?;
+        Ok(manifest)
+    }
+
+    /// Reads the manifest at `path`.
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Manifest::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Writes the manifest to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, format!("{}\n", self.to_json()))
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+
+    /// Whether a manifest made by another version, whose results may
+    /// differ, is being run.
+    pub fn is_other_version(&self) -> bool {
+        self.version != VERSION
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn round_trip_test() {
+        let experiments = vec![
+            Experiment::Search(Search {
+                soups: 20,
+                seed: 42,
+                rule: "B36/S23".parse().unwrap(),
+                ..Search::default()
+            }),
+            Experiment::Hunt(Hunt {
+                samples: None,
+                size: 4,
+                out: PathBuf::from("p3"),
+                ..Hunt::default()
+            }),
+            Experiment::Lifespan(Lifespans {
+                top: 3,
+                ..Lifespans::default()
+            }),
+            Experiment::Explore(Explore::default()),
+            Experiment::Sweep(sweep::Manifest {
+                densities: vec![0.3, 0.4],
+                ..sweep::Manifest::default()
+            }),
+        ];
+        for experiment in experiments {
+            let manifest = Manifest::new(experiment);
+            assert_eq!(
+                Manifest::from_json(&manifest.to_json()),
+                Ok(manifest.clone())
+            );
+            assert!(!manifest.is_other_version());
+        }
+    }
+
+    #[test]
+    fn from_json_test() {
+        let json = r#"{"version": "0.0.9", "command": "search", "seed": 7}"#;
+        let manifest = Manifest::from_json(json).unwrap();
+        assert_eq!(
+            manifest.experiment,
+            Experiment::Search(Search {
+                seed: 7,
+                ..Search::default()
+            })
+        );
+        assert!(manifest.is_other_version());
+        let json = Manifest::new(manifest.experiment).to_json();
+        assert!(json.contains(r#""command": "search""#), "{}", json);
+        assert!(json.contains(r#""rule": "B3/S23""#), "{}", json);
+
+        // Every pattern of a hunt is recorded as none sampled.
+        let json = r#"{"version": "0.1.0", "command": "hunt", "samples": null, "size": 3}"#;
+        let manifest = Manifest::from_json(json).unwrap();
+        assert!(matches!(
+            manifest.experiment,
+            Experiment::Hunt(Hunt { samples: None, .. })
+        ));
+
+        assert!(Manifest::from_json(r#"{"version": "0.1.0", "command": "dance"}"#).is_err());
+        assert!(Manifest::from_json(r#"{"command": "search"}"#).is_err());
+        let unknown = r#"{"version": "0.1.0", "command": "search", "colour": 1}"#;
+        assert!(Manifest::from_json(unknown).is_err());
+        let bad = r#"{"version": "0.1.0", "command": "explore", "density": 2.0}"#;
+        assert!(Manifest::from_json(bad).is_err());
+    }
+}
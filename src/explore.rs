@@ -0,0 +1,310 @@
+//! An explorer of the rules beyond Conway's, for finding ones worth a
+//! look. Random B/S rules are each run on a few small soups for a while and
+//! scored on how interesting what they leave is, by three heuristics:
+//!
+//! - activity, the share of cells changing each generation towards the
+//!   end, best at around one in twenty, as in Conway's rule, and worst
+//!   when still or boiling;
+//! - structure, from the entropy of the board's 2x2 blocks, best halfway
+//!   between an empty board and noise;
+//! - growth, the population at the end over that at the start, best when
+//!   it has fallen to around a fifth, neither dying out nor filling up.
+//!
+//! The score is their product, so a rule has to do well at all three.
+
+use crate::determinism;
+use crate::grid::{check_density, Grid, State, DEFAULT_DENSITY};
+use crate::rule::Rule;
+use crate::soup::Soup;
+use crate::stats::{self, Changes};
+use crate::topology::{Join, Topology};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering as Order;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The share of cells changing each generation the activity score is best
+/// at.
+const BEST_ACTIVITY: f64 = 0.05;
+
+/// The population at the end over that at the start the growth score is
+/// best at.
+const BEST_GROWTH: f64 = 0.2;
+
+/// How often progress is reported.
+const PROGRESS_EVERY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Explore {
+    /// How many random rules to try.
+    pub rules: u64,
+    /// How many soups to run by each.
+    pub soups: u64,
+    /// The width and height of each soup, on a torus.
+    pub size: usize,
+    pub generations: u64,
+    pub density: f64,
+    pub seed: u64,
+    /// How many threads to explore with, 0 for one per core.
+    pub threads: usize,
+    /// How many of the best rules to keep.
+    pub top: usize,
+}
+
+impl Default for Explore {
+    fn default() -> Explore {
+        Explore {
+            rules: 200,
+            soups: 3,
+            size: 32,
+            generations: 300,
+            density: DEFAULT_DENSITY,
+            seed: 0,
+            threads: 0,
+            top: 10,
+        }
+    }
+}
+
+/// How interesting a rule was found to be.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scored {
+    pub rule: Rule,
+    /// From 0 to 1, the product of the three below.
+    pub score: f64,
+    pub activity: f64,
+    pub structure: f64,
+    pub growth: f64,
+    /// The soup the rule left most alive in, as it left it.
+    pub preview: Grid,
+}
+
+impl Explore {
+    /// Whether the rules can be explored.
+    pub fn check(&self) -> Result<(), String> {
+        for (flag, value) in [
+            ("--rules", self.rules),
+            ("--soups", self.soups),
+            ("--size", self.size as u64),
+            ("--generations", self.generations),
+            ("--top", self.top as u64),
+        ] {
+            if value == 0 {
+                return Err(format!("{} must be at least 1", flag));
+            }
+        }
+        check_density(self.density)
+    }
+
+    /// The rule numbered `index`.
+    pub fn rule(&self, index: u64) -> Rule {
+        Rule::random(&mut determinism::rng_for(self.seed, index))
+    }
+
+    /// Runs the soups by `rule` and scores what they did, the scores
+    /// averaged over the soups.
+    pub fn score(&self, rule: Rule) -> Scored {
+        let torus = Topology {
+            horizontal: Join::Wrap { shift: 0 },
+            vertical: Join::Wrap { shift: 0 },
+        };
+        let soup = Soup {
+            density: self.density,
+            ..Soup::default()
+        };
+        let cells = (self.size * self.size) as f64;
+        let (mut activity, mut structure, mut growth) = (0.0, 0.0, 0.0);
+        let mut preview = None;
+        for index in 0..self.soups {
+            let mut rng = determinism::rng(self.seed.wrapping_add(index));
+            let mut grid = soup.generate(self.size, self.size, &mut rng);
+            let start = grid.population();
+            let mut changes = 0;
+            for generation in 0..self.generations {
+                let next = grid.next_generation_under(torus, rule);
+                if generation >= self.generations / 2 {
+                    let Changes { births, deaths } = Changes::between(&grid, &next);
+                    changes += births + deaths;
+                }
+                grid = next;
+            }
+            let measured = (self.generations - self.generations / 2).max(1);
+            let changing = changes as f64 / measured as f64 / cells;
+            activity += changing;
+            structure += stats::block_entropy(&grid);
+            growth += grid.population() as f64 / start.max(1) as f64;
+            if preview
+                .as_ref()
+                .is_none_or(|preview: &Grid| grid.population() > preview.population())
+            {
+                preview = Some(grid);
+            }
+        }
+        let soups = self.soups.max(1) as f64;
+        let (activity, structure, growth) = (activity / soups, structure / soups, growth / soups);
+        let score = near(activity, BEST_ACTIVITY)
+            * 4.0
+            * structure
+            * (1.0 - structure)
+            * near(growth, BEST_GROWTH);
+        Scored {
+            rule,
+            score,
+            activity,
+            structure,
+            growth,
+            preview: preview.unwrap_or_else(|| Grid::seed(self.size, Vec::new())),
+        }
+    }
+
+    /// Scores every rule, telling `progress` how many are done about once
+    /// a second, and gives the `top` best, best first. A rule drawn twice
+    /// is only kept once.
+    pub fn run<F: FnMut(u64)>(&self, mut progress: F) -> Vec<Scored> {
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        };
+        let next = AtomicU64::new(0);
+        let done = AtomicU64::new(0);
+        let scored = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= self.rules {
+                            return;
+                        }
+                        let scored_rule = self.score(self.rule(index));
+                        scored
+                            .lock()
+                            .expect("explore threads don't panic")
+                            .push((index, scored_rule));
+                        done.fetch_add(1, Ordering::Relaxed);
+                    })
+                })
+                .collect();
+            let mut reported = Instant::now();
+            while !workers.iter().all(|worker| worker.is_finished()) {
+                thread::sleep(Duration::from_millis(20));
+                if reported.elapsed() >= PROGRESS_EVERY {
+                    progress(done.load(Ordering::Relaxed));
+                    reported = Instant::now();
+                }
+            }
+        });
+        let mut scored = scored.into_inner().expect("explore threads don't panic");
+        scored.sort_by(|(a_index, a), (b_index, b)| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Order::Equal)
+                .then(a_index.cmp(b_index))
+        });
+        let mut best: Vec<Scored> = Vec::new();
+        for (_, scored) in scored {
+            if best.len() == self.top {
+                break;
+            }
+            if !best.iter().any(|kept| kept.rule == scored.rule) {
+                best.push(scored);
+            }
+        }
+        best
+    }
+}
+
+/// How close `value` is to `best`, from 1 when it is to 0 when it is
+/// none or far away, measured by how many times bigger or smaller it is.
+fn near(value: f64, best: f64) -> f64 {
+    if value <= 0.0 {
+        return 0.0;
+    }
+    let times = (value / best).ln();
+    (-times * times / 4.0).exp()
+}
+
+/// A picture of `grid` for the terminal, two rows of cells to a line of
+/// half blocks.
+pub fn preview(grid: &Grid) -> String {
+    let mut text = String::new();
+    for rows in grid.cells.chunks(2) {
+        for col in 0..grid.width() {
+            let alive = |row: usize| rows.get(row).is_some_and(|row| row[col] == State::Alive);
+            text.push(match (alive(0), alive(1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explore() -> Explore {
+        Explore {
+            soups: 2,
+            size: 24,
+            generations: 100,
+            ..Explore::default()
+        }
+    }
+
+    #[test]
+    fn score_test() {
+        let explore = explore();
+        let conway = explore.score(Rule::default());
+        assert!(conway.score > 0.5, "{:?}", conway);
+        assert!(conway.activity > 0.0 && conway.growth < 0.5);
+        assert_eq!(conway.preview.width(), 24);
+
+        // Dying out, filling up and boiling are all dull.
+        for rule in &["B8/S", "B3/S012345678", "B2/S"] {
+            let dull = explore.score(rule.parse().unwrap());
+            assert!(dull.score < conway.score / 2.0, "{} {:?}", rule, dull);
+        }
+        assert_eq!(explore.score("B8/S".parse().unwrap()).growth, 0.0);
+    }
+
+    #[test]
+    fn run_test() {
+        let explore = Explore {
+            rules: 8,
+            top: 3,
+            threads: 2,
+            seed: 4,
+            ..explore()
+        };
+        let best = explore.run(|_| ());
+        assert_eq!(best.len(), 3);
+        assert!(best.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert!(best
+            .iter()
+            .all(|scored| (0..8).any(|index| explore.rule(index) == scored.rule)));
+        let alone = Explore {
+            threads: 1,
+            ..explore.clone()
+        };
+        assert_eq!(alone.run(|_| ()), best);
+    }
+
+    #[test]
+    fn preview_test() {
+        let grid = Grid::seed(3, vec![(0, 0), (1, 0), (1, 1), (2, 2)]);
+        assert_eq!(preview(&grid), "█▄ \n  ▀\n");
+        assert_eq!(near(BEST_ACTIVITY, BEST_ACTIVITY), 1.0);
+        assert_eq!(near(0.0, BEST_ACTIVITY), 0.0);
+        assert!(near(10.0 * BEST_ACTIVITY, BEST_ACTIVITY) < 0.3);
+        let above = near(10.0 * BEST_ACTIVITY, BEST_ACTIVITY);
+        assert!((above - near(BEST_ACTIVITY / 10.0, BEST_ACTIVITY)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,82 @@
+use conways_game_of_life::gallery::{Entry, Seed};
+use conways_game_of_life::grid::Grid;
+use conways_game_of_life::json;
+use conways_game_of_life::rle::Pattern;
+use conways_game_of_life::thumbnail;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether `c` may be part of a favorite's name, which is also its file
+/// name.
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || c == '-' || c == '_'
+}
+
+/// Saves `grid` in `dir` as `NAME.json`, returning where it went.
+pub fn save(dir: &Path, name: &str, grid: &Grid) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return Err(format!(
+            "'{}' can't be used as the name of a favorite",
+            name
+        ));
+    }
+    let path = dir.join(format!("{}.json", name));
+    fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&path, json::to_json(grid, None)))
+        .map_err(|e| format!("could not save {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// The favorites saved in `dir` as gallery entries, named after their files.
+/// Any that can't be read are reported and left out.
+pub fn load(dir: &Path) -> Vec<Entry> {
+    let files = match thumbnail::pattern_files(dir) {
+        Ok(files) => files,
+        Err(_) => return Vec::new(),
+    };
+    files
+        .iter()
+        .filter_map(|path| match thumbnail::load_pattern(path) {
+            Ok(grid) => Some(entry(&path.file_stem()?.to_string_lossy(), &grid)),
+            Err(message) => {
+                eprintln!("{}", message);
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn entry(name: &str, grid: &Grid) -> Entry {
+    Entry {
+        name: format!("{} (favorite)", name),
+        seed: Seed::Pattern(Pattern::from_grid(grid)),
+    }
+}
+
+/// Where favorites are kept, in the platform's data directory, such as
+/// `~/.local/share/conways_game_of_life/favorites` on Linux.
+pub fn dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("conways_game_of_life").join("favorites"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_test() {
+        let dir = std::env::temp_dir().join(format!("favorites_test_{}", std::process::id()));
+        let grid = Grid::seed(4, vec![(1, 1), (1, 2), (2, 1)]);
+
+        let path = save(&dir, "my soup", &grid).unwrap();
+        let entries = load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(path, dir.join("my soup.json"));
+        assert_eq!(entries, vec![entry("my soup", &grid)]);
+        assert_eq!(entries[0].name, "my soup (favorite)");
+        assert!(save(&dir, "../escape", &grid).is_err());
+        assert!(save(&dir, " ", &grid).is_err());
+    }
+}
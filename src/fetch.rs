@@ -0,0 +1,140 @@
+//! Patterns downloaded from the LifeWiki pattern archive, or any other URL,
+//! so one read about can be tried straight away. Downloads are kept in a
+//! cache, so each pattern is only fetched once. They are made by curl, which
+//! has to be installed.
+
+use crate::rle;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where LifeWiki keeps the RLE files of the patterns it describes.
+pub const LIFEWIKI: &str = "https://conwaylife.com/patterns/";
+
+/// The URL of `spec`: itself if it is one, otherwise the LifeWiki pattern
+/// it names, like `Gosper glider gun` or `gosperglidergun`.
+pub fn url(spec: &str) -> String {
+    if spec.starts_with("http://") || spec.starts_with("https://") {
+        return spec.to_string();
+    }
+    // LifeWiki names its files after the pattern's name without spaces or
+    // punctuation.
+    let name: String = spec
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    format!("{}{}.rle", LIFEWIKI, name)
+}
+
+/// Where the download of `url` is kept in `dir`.
+pub fn cache_path(dir: &Path, url: &str) -> PathBuf {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let name: String = without_scheme
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(name)
+}
+
+/// The text of the pattern `spec` names, from `cache` if it has been
+/// fetched before, or else downloaded and kept there.
+pub fn fetch(spec: &str, cache: Option<&Path>) -> Result<String, String> {
+    fetch_with("curl", spec, cache)
+}
+
+fn fetch_with(program: &str, spec: &str, cache: Option<&Path>) -> Result<String, String> {
+    let url = url(spec);
+    let cached = cache.map(|dir| cache_path(dir, &url));
+    if let Some(text) = cached
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+    {
+        return Ok(text);
+    }
+    let text = download(program, &url)?;
+    // An error page isn't worth keeping.
+    if !text.trim_start().starts_with('{') {
+        rle::parse(&text).map_err(|e| format!("{} is not a pattern: {}", url, e))?;
+    }
+    if let Some(path) = cached {
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, &text));
+        if let Err(e) = saved {
+            eprintln!("could not keep {} in {}: {}", url, path.display(), e);
+        }
+    }
+    Ok(text)
+}
+
+fn download(program: &str, url: &str) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--max-time", "30", url])
+        .output()
+        .map_err(|e| format!("could not start {}, is it installed? {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "could not download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} is not text", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_test() {
+        assert_eq!(
+            url("Gosper glider gun"),
+            "https://conwaylife.com/patterns/gosperglidergun.rle"
+        );
+        assert_eq!(url("R-pentomino"), format!("{}rpentomino.rle", LIFEWIKI));
+        assert_eq!(
+            url("https://example.com/my.rle"),
+            "https://example.com/my.rle"
+        );
+    }
+
+    #[test]
+    fn cache_path_test() {
+        assert_eq!(
+            cache_path(
+                Path::new("cache"),
+                "https://conwaylife.com/patterns/acorn.rle"
+            ),
+            Path::new("cache").join("conwaylife.com_patterns_acorn.rle")
+        );
+    }
+
+    #[test]
+    fn fetch_test() {
+        let dir = std::env::temp_dir().join(format!("life-fetch-test-{}", std::process::id()));
+        let cached = cache_path(&dir, &url("acorn"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&cached, "bo5b$3bo3b$2o2b3o!").unwrap();
+
+        // What has been fetched before isn't downloaded again.
+        assert_eq!(
+            fetch_with("false", "acorn", Some(&dir)),
+            Ok("bo5b$3bo3b$2o2b3o!".to_string())
+        );
+        let failed = fetch_with("false", "diehard", Some(&dir)).unwrap_err();
+        assert!(failed.starts_with("could not download"), "{}", failed);
+        assert!(fetch_with("no-such-program", "diehard", None).is_err());
+        assert!(!cache_path(&dir, &url("diehard")).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+//! A C API to the engine, so it can be embedded in C and C++ programs and
+//! other languages' runtimes. It is declared for C in
+//! `include/conways_game_of_life.h`, and built into the shared library.
+//!
+//! A board is made with `life_grid_new` or `life_grid_from_rle` and must be
+//! given back to `life_grid_free` once done with. Its cells are read a row
+//! at a time from the top, one byte each, 1 for alive and 0 for dead.
+
+use crate::grid::{Grid, State};
+use crate::rle;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::slice;
+
+/// The most memory a board made through the C API may take, so one too big
+/// gives null rather than aborting the program when it can't be had.
+const MAX_BYTES: usize = 1 << 32;
+
+/// A board, how its edges are joined and the rule it runs by, only ever
+/// handled through a pointer from C.
+pub struct LifeGrid {
+    grid: Grid,
+    topology: Topology,
+    rule: Rule,
+}
+
+/// Whether a board `width` by `height` can be made: neither is 0, and it
+/// takes no more than `MAX_BYTES`.
+fn can_make(width: usize, height: usize) -> bool {
+    width > 0
+        && height > 0
+        && width
+            .checked_mul(mem::size_of::<State>())
+            .and_then(|row| row.checked_add(mem::size_of::<Vec<State>>()))
+            .and_then(|row| row.checked_mul(height))
+            .is_some_and(|bytes| bytes <= MAX_BYTES)
+}
+
+/// A dead board `width` by `height` with unjoined edges, or null if either is
+/// 0 or it is too big to make.
+#[no_mangle]
+pub extern "C" fn life_grid_new(width: usize, height: usize) -> *mut LifeGrid {
+    if !can_make(width, height) {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(LifeGrid {
+        grid: Grid::seed_rectangle(width, height, vec![]),
+        topology: Topology::default(),
+        rule: Rule::default(),
+    }))
+}
+
+/// The RLE pattern `rle` on a board its own size, run by the rule in its
+/// header, or null if it can't be read or is too big to make.
+///
+/// # Safety
+///
+/// `rle` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_from_rle(rle: *const c_char) -> *mut LifeGrid {
+    if rle.is_null() {
+        return ptr::null_mut();
+    }
+    let pattern = match CStr::from_ptr(rle).to_str().map(rle::parse) {
+        Ok(Ok(pattern)) if can_make(pattern.width.max(1), pattern.height.max(1)) => pattern,
+        _ => return ptr::null_mut(),
+    };
+    Box::into_raw(Box::new(LifeGrid {
+        grid: pattern.fitted(1, 1, 0),
+        topology: Topology::default(),
+        rule: pattern.rule,
+    }))
+}
+
+/// Frees a board. Null is ignored.
+///
+/// # Safety
+///
+/// `grid` must have come from `life_grid_new` or `life_grid_from_rle`, and
+/// not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_free(grid: *mut LifeGrid) {
+    if !grid.is_null() {
+        drop(Box::from_raw(grid));
+    }
+}
+
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_width(grid: *const LifeGrid) -> usize {
+    (*grid).grid.width()
+}
+
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_height(grid: *const LifeGrid) -> usize {
+    (*grid).grid.height()
+}
+
+/// How many cells are alive.
+///
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_population(grid: *const LifeGrid) -> usize {
+    (*grid).grid.population()
+}
+
+/// 1 if the cell at `row` and `col` is alive, 0 if it is dead, or -1 if it
+/// is off the board.
+///
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_get(grid: *const LifeGrid, row: usize, col: usize) -> c_int {
+    let cells = &(*grid).grid.cells;
+    match cells.get(row).and_then(|cells| cells.get(col)) {
+        Some(State::Alive) => 1,
+        Some(State::Dead) => 0,
+        None => -1,
+    }
+}
+
+/// Brings the cell at `row` and `col` to life if `alive` isn't 0, or kills
+/// it if it is, giving 0, or -1 if it is off the board.
+///
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_set(
+    grid: *mut LifeGrid,
+    row: usize,
+    col: usize,
+    alive: c_int,
+) -> c_int {
+    let cells = &mut (*grid).grid.cells;
+    match cells.get_mut(row).and_then(|cells| cells.get_mut(col)) {
+        Some(cell) => {
+            *cell = if alive != 0 {
+                State::Alive
+            } else {
+                State::Dead
+            };
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Joins the board's edges as `topology` says, a name like `torus` or a
+/// pair of joins like `wrap,twist`, giving 0, or -1 if it can't be read.
+///
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed, and `topology` a nul
+/// terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_set_topology(
+    grid: *mut LifeGrid,
+    topology: *const c_char,
+) -> c_int {
+    if topology.is_null() {
+        return -1;
+    }
+    match CStr::from_ptr(topology).to_str().map(str::parse) {
+        Ok(Ok(topology)) => {
+            (*grid).topology = topology;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Runs the board by `rule` in B/S notation, like `B36/S23`, giving 0, or
+/// -1 if it can't be read.
+///
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed, and `rule` a nul
+/// terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_set_rule(grid: *mut LifeGrid, rule: *const c_char) -> c_int {
+    if rule.is_null() {
+        return -1;
+    }
+    match CStr::from_ptr(rule).to_str().map(str::parse) {
+        Ok(Ok(rule)) => {
+            (*grid).rule = rule;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Runs `generations` generations.
+///
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_step(grid: *mut LifeGrid, generations: u64) {
+    let grid = &mut *grid;
+    for _ in 0..generations {
+        grid.grid = grid.grid.next_generation_under(grid.topology, grid.rule);
+    }
+}
+
+/// Copies the cells into `cells`, a row at a time, if it is at least
+/// width times height bytes long, giving how many bytes that is either way.
+///
+/// # Safety
+///
+/// `grid` must be a board that hasn't been freed, and `cells` point to
+/// `len` writable bytes, or be null to only ask how many are needed.
+#[no_mangle]
+pub unsafe extern "C" fn life_grid_read_cells(
+    grid: *const LifeGrid,
+    cells: *mut u8,
+    len: usize,
+) -> usize {
+    let grid = &(*grid).grid;
+    let needed = grid.width() * grid.height();
+    if !cells.is_null() && len >= needed {
+        let out = slice::from_raw_parts_mut(cells, needed);
+        for (byte, cell) in out.iter_mut().zip(grid.cells.iter().flatten()) {
+            *byte = (*cell == State::Alive) as u8;
+        }
+    }
+    needed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn grid_test() {
+        let grid = life_grid_new(5, 4);
+        unsafe {
+            assert_eq!((life_grid_width(grid), life_grid_height(grid)), (5, 4));
+            for col in 1..4 {
+                assert_eq!(life_grid_set(grid, 2, col, 1), 0);
+            }
+            assert_eq!(life_grid_set(grid, 4, 0, 1), -1);
+            assert_eq!(life_grid_population(grid), 3);
+
+            life_grid_step(grid, 1);
+            assert_eq!(life_grid_get(grid, 1, 2), 1);
+            assert_eq!(life_grid_get(grid, 2, 1), 0);
+            assert_eq!(life_grid_get(grid, 0, 5), -1);
+
+            let mut cells = [9; 20];
+            assert_eq!(life_grid_read_cells(grid, ptr::null_mut(), 0), 20);
+            assert_eq!(life_grid_read_cells(grid, cells.as_mut_ptr(), 19), 20);
+            assert_eq!(cells[0], 9);
+            life_grid_read_cells(grid, cells.as_mut_ptr(), cells.len());
+            assert_eq!(&cells[5..15], &[0, 0, 1, 0, 0, 0, 0, 1, 0, 0]);
+            life_grid_free(grid);
+        }
+        assert!(life_grid_new(0, 4).is_null());
+        assert!(life_grid_new(usize::MAX, 1).is_null());
+        assert!(life_grid_new(1, usize::MAX).is_null());
+        assert!(life_grid_new(1 << 33, 1 << 33).is_null());
+    }
+
+    #[test]
+    fn rle_test() {
+        let glider = CString::new("bo$2bo$3o!").unwrap();
+        let torus = CString::new("torus").unwrap();
+        unsafe {
+            let grid = life_grid_from_rle(glider.as_ptr());
+            assert_eq!((life_grid_width(grid), life_grid_height(grid)), (3, 3));
+            assert_eq!(life_grid_population(grid), 5);
+            // One of the glider's cells is born off the bottom edge.
+            life_grid_step(grid, 1);
+            assert_eq!(life_grid_population(grid), 4);
+            assert_eq!(life_grid_set_topology(grid, torus.as_ptr()), 0);
+            assert_eq!(life_grid_set_topology(grid, glider.as_ptr()), -1);
+            life_grid_free(grid);
+
+            // Under Seeds every living cell dies, and only the cells at
+            // the corners have two living neighbours.
+            let blinker = CString::new("x = 3, y = 3\n$3o!").unwrap();
+            let seeds = CString::new("B2/S").unwrap();
+            let grid = life_grid_from_rle(blinker.as_ptr());
+            assert_eq!(life_grid_set_rule(grid, seeds.as_ptr()), 0);
+            assert_eq!(life_grid_set_rule(grid, glider.as_ptr()), -1);
+            life_grid_step(grid, 1);
+            assert_eq!(life_grid_population(grid), 4);
+            life_grid_free(grid);
+
+            let nonsense = CString::new("zz!").unwrap();
+            assert!(life_grid_from_rle(nonsense.as_ptr()).is_null());
+            assert!(life_grid_from_rle(ptr::null()).is_null());
+            let huge = CString::new("x = 18446744073709551615, y = 2\no!").unwrap();
+            assert!(life_grid_from_rle(huge.as_ptr()).is_null());
+            let huge = CString::new("x = 4000000000, y = 4000000000\no!").unwrap();
+            assert!(life_grid_from_rle(huge.as_ptr()).is_null());
+        }
+    }
+
+    #[test]
+    fn header_test() {
+        let header = include_str!("../include/conways_game_of_life.h");
+        let source = include_str!("ffi.rs");
+        let functions: Vec<&str> = source
+            .lines()
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .filter_map(|rest| rest.split('(').next())
+            .collect();
+        assert_eq!(functions.len(), 12);
+        for function in functions {
+            assert!(
+                header.contains(&format!(" {}(", function))
+                    || header.contains(&format!("*{}(", function)),
+                "{} isn't declared in the header",
+                function
+            );
+        }
+    }
+}
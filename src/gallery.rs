@@ -0,0 +1,110 @@
+use crate::grid::Grid;
+use crate::rle::Pattern;
+use crate::soup::{Soup, Symmetry};
+use crate::starters;
+use rand::Rng;
+
+/// Something to start a board from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Seed {
+    /// A random soup, different every time it is picked.
+    Soup(Soup),
+    /// A pattern placed in the middle of an otherwise empty board.
+    Pattern(Pattern),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub seed: Seed,
+}
+
+impl Entry {
+    /// A `width` by `height` board started from the entry, or `None` if its
+    /// pattern doesn't fit.
+    pub fn grid<R: Rng + ?Sized>(&self, width: usize, height: usize, rng: &mut R) -> Option<Grid> {
+        match &self.seed {
+            Seed::Soup(soup) => Some(soup.generate(width, height, rng)),
+            Seed::Pattern(pattern) => pattern.centred(width, height),
+        }
+    }
+}
+
+/// Starting points to browse through: soups of several kinds, then classic
+/// patterns.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gallery {
+    entries: Vec<Entry>,
+}
+
+impl Gallery {
+    pub fn curated() -> Gallery {
+        let soups = [
+            ("sparse soup", 0.1, Symmetry::None, None),
+            ("thin soup", 0.25, Symmetry::None, None),
+            ("even soup", 0.5, Symmetry::None, None),
+            ("mirrored soup", 0.35, Symmetry::Horizontal, None),
+            ("four-fold soup", 0.3, Symmetry::FourFold, None),
+            ("diagonal soup", 0.35, Symmetry::Diagonal, None),
+            ("clumpy soup", 0.4, Symmetry::None, Some(8.0)),
+        ];
+        let mut entries: Vec<Entry> = soups
+            .iter()
+            .map(|&(name, density, symmetry, noise)| Entry {
+                name: name.to_string(),
+                seed: Seed::Soup(Soup {
+                    density,
+                    symmetry,
+                    noise,
+                }),
+            })
+            .collect();
+        entries.extend((0..starters::len()).filter_map(starters::starter).map(
+            |(name, pattern)| Entry {
+                name: name.to_string(),
+                seed: Seed::Pattern(pattern),
+            },
+        ));
+        Gallery { entries }
+    }
+
+    pub fn add(&mut self, entry: Entry) {
+        self.entries.push(entry);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Entry> {
+        self.entries.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn curated_test() {
+        let gallery = Gallery::curated();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        assert_eq!(gallery.len(), 7 + starters::len());
+        let sparse = gallery.get(0).unwrap().grid(50, 50, &mut rng).unwrap();
+        assert!(sparse.population() < 500);
+        let gun = gallery
+            .get(7 + 4)
+            .filter(|entry| entry.name == "Gosper glider gun")
+            .unwrap();
+        assert!(gun.grid(20, 20, &mut rng).is_none());
+        assert_eq!(gun.grid(40, 20, &mut rng).unwrap().population(), 36);
+        assert!(gallery.get(gallery.len()).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+//! The neon glow `O` puts round living cells. They are drawn again onto a
+//! canvas the size of the window, which is then blurred by a shader and
+//! added over the board, so bright cells bleed light onto the dark ones
+//! round them.
+
+use ggez::conf::NumSamples;
+use ggez::graphics::{
+    self, BlendMode, Canvas, Color, DrawParam, Drawable, EmptyConst, Mesh, Shader,
+};
+use ggez::{Context, GameResult};
+
+/// The vertex shader, the same as the one ggez draws with.
+const VERTEX_SHADER: &str = "#version 150 core
+
+in vec2 a_Pos;
+in vec2 a_Uv;
+
+in vec4 a_Src;
+in vec4 a_TCol1;
+in vec4 a_TCol2;
+in vec4 a_TCol3;
+in vec4 a_TCol4;
+in vec4 a_Color;
+
+layout (std140) uniform Globals {
+    mat4 u_MVP;
+};
+
+out vec2 v_Uv;
+out vec4 v_Color;
+
+void main() {
+    v_Uv = a_Uv * a_Src.zw + a_Src.xy;
+    v_Color = a_Color;
+    mat4 instance_transform = mat4(a_TCol1, a_TCol2, a_TCol3, a_TCol4);
+    gl_Position = u_MVP * instance_transform * vec4(a_Pos, 0.0, 1.0);
+}
+";
+
+/// Blurs the canvas with a Gaussian spread over `RADIUS` taps each way,
+/// `SPREAD` pixels apart, and brightens it so the glow shows up against
+/// the dark board.
+const GLOW_SHADER: &str = "#version 150 core
+
+uniform sampler2D t_Texture;
+in vec2 v_Uv;
+in vec4 v_Color;
+out vec4 Target0;
+
+layout (std140) uniform Globals {
+    mat4 u_MVP;
+};
+
+const int RADIUS = 6;
+const float SPREAD = 1.5;
+const float SIGMA = 3.0;
+const float STRENGTH = 1.8;
+
+void main() {
+    vec2 texel = SPREAD / vec2(textureSize(t_Texture, 0));
+    vec4 sum = vec4(0.0);
+    float total = 0.0;
+    for (int x = -RADIUS; x <= RADIUS; x++) {
+        for (int y = -RADIUS; y <= RADIUS; y++) {
+            float weight = exp(-float(x * x + y * y) / (2.0 * SIGMA * SIGMA));
+            sum += texture(t_Texture, v_Uv + vec2(x, y) * texel) * weight;
+            total += weight;
+        }
+    }
+    Target0 = sum / total * STRENGTH * v_Color;
+}
+";
+
+pub struct Glow {
+    shader: Shader<EmptyConst>,
+    canvas: Canvas,
+}
+
+impl Glow {
+    pub fn new(ctx: &mut Context) -> GameResult<Glow> {
+        let shader = Shader::from_u8(
+            ctx,
+            VERTEX_SHADER.as_bytes(),
+            GLOW_SHADER.as_bytes(),
+            EmptyConst,
+            "Glow",
+            Some(&[BlendMode::Add]),
+        )?;
+        let canvas = Glow::canvas(ctx)?;
+        Ok(Glow { shader, canvas })
+    }
+
+    fn canvas(ctx: &mut Context) -> GameResult<Canvas> {
+        let (width, height) = graphics::drawable_size(ctx);
+        let mut canvas = Canvas::new(ctx, width as u16, height as u16, NumSamples::One)?;
+        canvas.set_blend_mode(Some(BlendMode::Add));
+        Ok(canvas)
+    }
+
+    /// Draws the glow of `cells`, a mesh of the living cells drawn at
+    /// `param`, over what has been drawn so far.
+    pub fn draw(&mut self, ctx: &mut Context, cells: &Mesh, param: DrawParam) -> GameResult {
+        let (width, height) = graphics::drawable_size(ctx);
+        let image = self.canvas.image();
+        if (width as u16, height as u16) != (image.width(), image.height()) {
+            self.canvas = Glow::canvas(ctx)?;
+        }
+        graphics::set_canvas(ctx, Some(&self.canvas));
+        graphics::clear(ctx, Color::new(0.0, 0.0, 0.0, 0.0));
+        let drawn = graphics::draw(ctx, cells, param);
+        graphics::set_canvas(ctx, None);
+        drawn?;
+        let _lock = graphics::use_shader(ctx, &self.shader);
+        graphics::draw(ctx, &self.canvas, DrawParam::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shader_inputs_test() {
+        // ggez binds these by name, so a typo only shows when the pipeline
+        // is built.
+        for input in &["a_Pos", "a_Uv", "a_Src", "a_TCol4", "a_Color", "u_MVP"] {
+            assert!(VERTEX_SHADER.contains(input), "{}", input);
+        }
+        for input in &["t_Texture", "v_Uv", "v_Color", "Target0", "u_MVP"] {
+            assert!(GLOW_SHADER.contains(input), "{}", input);
+        }
+        assert!(VERTEX_SHADER.contains("out vec2 v_Uv;"));
+        assert!(VERTEX_SHADER.contains("out vec4 v_Color;"));
+    }
+}
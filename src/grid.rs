@@ -0,0 +1,461 @@
+use crate::rule::Rule;
+use crate::topology::Topology;
+use rand::Rng;
+use std::{fmt, fmt::Write};
+
+/// Conway's rule, which boards run by unless told otherwise, in B/S
+/// notation.
+pub const RULE: &str = "B3/S23";
+
+/// The chance of each cell of a random soup being alive by default.
+pub const DEFAULT_DENSITY: f64 = 0.5;
+
+/// Checks a rule string read from a pattern file is one we can run.
+pub fn check_rule(rule: &str) -> Result<Rule, String> {
+    rule.parse()
+}
+
+/// Checks a soup density is a chance between 0 and 1.
+pub fn check_density(density: f64) -> Result<(), String> {
+    if (0.0..=1.0).contains(&density) {
+        Ok(())
+    } else {
+        Err(format!("density {} is not between 0 and 1", density))
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq)]
+pub enum State {
+    Alive,
+    Dead,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq)]
+pub struct Grid {
+    pub cells: Vec<Vec<State>>,
+}
+
+impl Grid {
+    pub fn seed(size: usize, living_cells: Vec<(usize, usize)>) -> Grid {
+        Grid::seed_rectangle(size, size, living_cells)
+    }
+
+    pub fn seed_rectangle(width: usize, height: usize, living_cells: Vec<(usize, usize)>) -> Grid {
+        let row = vec![State::Dead; width];
+        let mut cells = vec![row; height];
+
+        for position in living_cells {
+            cells[position.0][position.1] = State::Alive;
+        }
+
+        Grid { cells }
+    }
+
+    /// A square soup with each cell alive at random with a chance of
+    /// `density`, from 0 to 1, drawn from `rng` so that seeding it reproduces
+    /// the same board.
+    pub fn random_grid<R: Rng + ?Sized>(size: usize, density: f64, rng: &mut R) -> Grid {
+        Grid::random_rectangle(size, size, density, rng)
+    }
+
+    pub fn random_rectangle<R: Rng + ?Sized>(
+        width: usize,
+        height: usize,
+        density: f64,
+        rng: &mut R,
+    ) -> Grid {
+        let mut living_cells = Vec::new();
+
+        for row in 0..height {
+            for col in 0..width {
+                if rng.gen_bool(density) {
+                    living_cells.push((row, col))
+                }
+            }
+        }
+
+        Grid::seed_rectangle(width, height, living_cells)
+    }
+
+    /// Brings a dead cell to life or kills a living one.
+    pub fn toggle(&mut self, row: usize, col: usize) {
+        let cell = &mut self.cells[row][col];
+        *cell = match cell {
+            State::Alive => State::Dead,
+            State::Dead => State::Alive,
+        };
+    }
+
+    /// A `width` by `height` copy of the grid with the old one in the
+    /// middle, padded with dead cells where it is bigger and cropped evenly
+    /// from each side where it is smaller.
+    pub fn resized(&self, width: usize, height: usize) -> Grid {
+        let top = (height as isize - self.height() as isize) / 2;
+        let left = (width as isize - self.width() as isize) / 2;
+        let living_cells = self
+            .living_cells()
+            .into_iter()
+            .map(|(row, col)| (row as isize + top, col as isize + left))
+            .filter(|&(row, col)| {
+                (0..height as isize).contains(&row) && (0..width as isize).contains(&col)
+            })
+            .map(|(row, col)| (row as usize, col as usize))
+            .collect();
+        Grid::seed_rectangle(width, height, living_cells)
+    }
+
+    /// A copy of the grid with the given number of dead rows and columns
+    /// added on each side.
+    pub fn padded(&self, top: usize, left: usize, bottom: usize, right: usize) -> Grid {
+        let living_cells = self
+            .living_cells()
+            .into_iter()
+            .map(|(row, col)| (row + top, col + left))
+            .collect();
+        Grid::seed_rectangle(
+            left + self.width() + right,
+            top + self.height() + bottom,
+            living_cells,
+        )
+    }
+
+    /// The grid grown by `margin` dead cells on every side that a living
+    /// cell touches, or `None` if none do.
+    pub fn expanded(&self, margin: usize) -> Option<Grid> {
+        let last_row = self.height().checked_sub(1)?;
+        let last_col = self.width().checked_sub(1)?;
+        let (mut top, mut left, mut bottom, mut right) = (0, 0, 0, 0);
+        for (row, col) in self.living_cells() {
+            if row == 0 {
+                top = margin;
+            }
+            if row == last_row {
+                bottom = margin;
+            }
+            if col == 0 {
+                left = margin;
+            }
+            if col == last_col {
+                right = margin;
+            }
+        }
+        if top + left + bottom + right == 0 {
+            return None;
+        }
+        Some(self.padded(top, left, bottom, right))
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, |row| row.len())
+    }
+
+    /// Checks the grid is well formed: every row is as wide as the first,
+    /// which is the width the grid reports. Every cell's state is one of
+    /// the two the life-like rules it runs by have, which the type of its
+    /// cells already ensures. Grids built by this crate always are, but
+    /// `cells` can be set to anything.
+    pub fn validate(&self) -> Result<(), String> {
+        let width = self.width();
+        match self.cells.iter().position(|row| row.len() != width) {
+            Some(row) => Err(format!(
+                "row {} is {} cells wide but the grid is {} wide",
+                row,
+                self.cells[row].len(),
+                width
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells
+            .iter()
+            .map(|row| row.iter().filter(|state| **state == State::Alive).count())
+            .sum()
+    }
+
+    /// The `(row, column)` positions of every living cell, in row order.
+    pub fn living_cells(&self) -> Vec<(usize, usize)> {
+        let mut living_cells = Vec::new();
+        for (row_idx, row) in self.cells.iter().enumerate() {
+            for (col_idx, state) in row.iter().enumerate() {
+                if *state == State::Alive {
+                    living_cells.push((row_idx, col_idx))
+                }
+            }
+        }
+        living_cells
+    }
+
+    /// The next generation with every cell beyond the edges dead.
+    pub fn next_generation(&self) -> Grid {
+        self.next_generation_on(Topology::default())
+    }
+
+    /// The next generation with the edges joined as `topology` says.
+    pub fn next_generation_on(&self, topology: Topology) -> Grid {
+        self.next_generation_under(topology, Rule::default())
+    }
+
+    /// The next generation by `rule`, with the edges joined as `topology`
+    /// says.
+    pub fn next_generation_under(&self, topology: Topology, rule: Rule) -> Grid {
+        debug_assert_eq!(self.validate(), Ok(()));
+        let mut new_cells = Vec::new();
+
+        for (row_idx, row) in self.cells.iter().enumerate() {
+            let mut new_row = Vec::new();
+            for (col_idx, state) in row.iter().enumerate() {
+                new_row.push(rule.next_state(
+                    state,
+                    alive_neighbours((row_idx, col_idx), &self.cells, topology),
+                ))
+            }
+            new_cells.push(new_row)
+        }
+
+        Grid { cells: new_cells }
+    }
+
+    /// How many of the eight cells round the one at `row` and `col` are
+    /// alive, with the edges joined as `topology` says.
+    pub fn live_neighbours(&self, row: usize, col: usize, topology: Topology) -> usize {
+        alive_neighbours((row, col), &self.cells, topology)
+    }
+}
+
+/// What a cell in `state` becomes with `live_neighbours` of its eight
+/// alive: a living cell survives with two or three, and a dead one is born
+/// with exactly three.
+pub fn next_state(state: &State, live_neighbours: usize) -> State {
+    match (state, live_neighbours) {
+        (State::Alive, 2) | (_, 3) => State::Alive,
+        _ => State::Dead,
+    }
+}
+
+fn alive_neighbours(
+    current_position: (usize, usize),
+    cells: &[Vec<State>],
+    topology: Topology,
+) -> usize {
+    let neighbours_directions = [
+        Direction::N,
+        Direction::NE,
+        Direction::E,
+        Direction::SE,
+        Direction::S,
+        Direction::SW,
+        Direction::W,
+        Direction::NW,
+    ];
+
+    neighbours_directions
+        .iter()
+        .map(|neighbours_direction| {
+            neighbours_state(
+                current_position,
+                cells,
+                neighbours_direction.translation(),
+                topology,
+            )
+        })
+        .filter(|state| *state == State::Alive)
+        .count()
+}
+
+fn neighbours_state(
+    current_position: (usize, usize),
+    cells: &[Vec<State>],
+    translation: (isize, isize),
+    topology: Topology,
+) -> State {
+    let height = cells.len();
+    let width = cells.first().map_or(0, |row| row.len());
+    match topology.locate(
+        width,
+        height,
+        current_position.0 as isize + translation.0,
+        current_position.1 as isize + translation.1,
+    ) {
+        Some((row, col)) => cells[row][col].clone(),
+        None => State::Dead,
+    }
+}
+
+enum Direction {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Direction {
+    fn translation(&self) -> (isize, isize) {
+        match self {
+            Direction::N => (-1, 0),
+            Direction::NE => (-1, 1),
+            Direction::E => (0, 1),
+            Direction::SE => (1, 1),
+            Direction::S => (1, 0),
+            Direction::SW => (1, -1),
+            Direction::W => (0, -1),
+            Direction::NW => (-1, -1),
+        }
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::new();
+        for row in &self.cells {
+            for cell in row {
+                write!(text, "{:?} ", cell)?
+            }
+            text.push('\n')
+        }
+        write!(f, "{}", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blinker_test() {
+        let blinker_start = Grid {
+            cells: vec![
+                vec![State::Dead, State::Alive, State::Dead],
+                vec![State::Dead, State::Alive, State::Dead],
+                vec![State::Dead, State::Alive, State::Dead],
+            ],
+        };
+
+        let blinker_end = Grid {
+            cells: vec![
+                vec![State::Dead, State::Dead, State::Dead],
+                vec![State::Alive, State::Alive, State::Alive],
+                vec![State::Dead, State::Dead, State::Dead],
+            ],
+        };
+
+        assert_eq!(blinker_start.next_generation(), blinker_end);
+    }
+
+    #[test]
+    fn population_test() {
+        assert_eq!(Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]).population(), 3);
+        assert_eq!(Grid::seed(3, vec![]).population(), 0);
+    }
+
+    #[test]
+    fn validate_test() {
+        assert_eq!(Grid::seed(3, vec![(0, 1)]).validate(), Ok(()));
+        assert_eq!(Grid::seed_rectangle(4, 2, vec![]).validate(), Ok(()));
+        assert_eq!(Grid { cells: Vec::new() }.validate(), Ok(()));
+        let ragged = Grid {
+            cells: vec![
+                vec![State::Dead; 3],
+                vec![State::Alive; 3],
+                vec![State::Dead; 2],
+            ],
+        };
+        assert_eq!(
+            ragged.validate(),
+            Err("row 2 is 2 cells wide but the grid is 3 wide".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn ragged_step_test() {
+        let ragged = Grid {
+            cells: vec![vec![State::Dead; 2], vec![State::Alive; 3]],
+        };
+        ragged.next_generation();
+    }
+
+    #[test]
+    fn toggle_test() {
+        let mut grid = Grid::seed(2, vec![(0, 0)]);
+        grid.toggle(0, 0);
+        grid.toggle(1, 0);
+
+        assert_eq!(grid, Grid::seed(2, vec![(1, 0)]));
+    }
+
+    #[test]
+    fn resized_test() {
+        let grid = Grid::seed(4, vec![(0, 0), (1, 2), (3, 3)]);
+
+        let bigger = grid.resized(8, 6);
+        let smaller = grid.resized(2, 3);
+
+        assert_eq!(bigger.living_cells(), vec![(1, 2), (2, 4), (4, 5)]);
+        assert_eq!(bigger.resized(4, 4), grid);
+        assert_eq!((smaller.width(), smaller.height()), (2, 3));
+        assert_eq!(smaller.living_cells(), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn expanded_test() {
+        let grid = Grid::seed(4, vec![(0, 1), (2, 3)]);
+
+        let expanded = grid.expanded(2).unwrap();
+
+        assert_eq!((expanded.width(), expanded.height()), (6, 6));
+        assert_eq!(expanded.living_cells(), vec![(2, 1), (4, 3)]);
+        assert_eq!(Grid::seed(4, vec![(1, 1), (2, 2)]).expanded(2), None);
+        assert_eq!(Grid::seed(0, vec![]).expanded(2), None);
+    }
+
+    #[test]
+    fn live_neighbours_test() {
+        let grid = Grid::seed(4, vec![(0, 0), (0, 1), (1, 0), (3, 3)]);
+
+        assert_eq!(grid.live_neighbours(1, 1, Topology::default()), 3);
+        assert_eq!(grid.live_neighbours(0, 0, Topology::default()), 2);
+        let torus = "torus".parse().unwrap();
+        assert_eq!(grid.live_neighbours(0, 0, torus), 3);
+        assert_eq!(next_state(&State::Dead, 3), State::Alive);
+        assert_eq!(next_state(&State::Alive, 2), State::Alive);
+        assert_eq!(next_state(&State::Dead, 2), State::Dead);
+        assert_eq!(next_state(&State::Alive, 4), State::Dead);
+    }
+
+    #[test]
+    fn seeded_soup_test() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let soup =
+            |seed| Grid::random_rectangle(8, 5, DEFAULT_DENSITY, &mut StdRng::seed_from_u64(seed));
+
+        assert_eq!(soup(12345), soup(12345));
+        assert_ne!(soup(12345), soup(54321));
+        assert_eq!(soup(12345).height(), 5);
+    }
+
+    #[test]
+    fn density_test() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let sparse = Grid::random_grid(100, 0.1, &mut rng).population();
+
+        assert!((700..1300).contains(&sparse), "population {}", sparse);
+        assert_eq!(Grid::random_grid(10, 0.0, &mut rng).population(), 0);
+        assert_eq!(Grid::random_grid(10, 1.0, &mut rng).population(), 100);
+    }
+}
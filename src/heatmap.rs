@@ -0,0 +1,194 @@
+use crate::grid::{Grid, State};
+use image::{ImageResult, Rgb, RgbImage};
+use std::path::Path;
+use std::str::FromStr;
+
+/// How many generations each cell has been alive for over a run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heatmap {
+    counts: Vec<Vec<u64>>,
+    max: u64,
+}
+
+impl Heatmap {
+    pub fn new(width: usize, height: usize) -> Heatmap {
+        Heatmap {
+            counts: vec![vec![0; width]; height],
+            max: 0,
+        }
+    }
+
+    pub fn record(&mut self, grid: &Grid) {
+        for (counts, row) in self.counts.iter_mut().zip(&grid.cells) {
+            for (count, state) in counts.iter_mut().zip(row) {
+                if *state == State::Alive {
+                    *count += 1;
+                    self.max = self.max.max(*count);
+                }
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.counts.first().map_or(0, |row| row.len())
+    }
+
+    pub fn height(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn count(&self, row: usize, col: usize) -> u64 {
+        self.counts[row][col]
+    }
+
+    /// How active a cell has been from 0.0 (never alive) to 1.0 (the most
+    /// alive of any cell). The scale is logarithmic so that the paths of
+    /// passing gliders still show up next to long lived still lifes.
+    pub fn intensity(&self, row: usize, col: usize) -> f32 {
+        if self.max == 0 {
+            return 0.0;
+        }
+        ((1 + self.counts[row][col]) as f32).ln() / ((1 + self.max) as f32).ln()
+    }
+
+    /// Renders the heatmap as an image with each cell `scale` pixels
+    /// square.
+    pub fn to_image(&self, colormap: Colormap, scale: u32) -> RgbImage {
+        let width = self.width() as u32 * scale;
+        let height = self.height() as u32 * scale;
+        RgbImage::from_fn(width, height, |x, y| {
+            let (row, col) = ((y / scale) as usize, (x / scale) as usize);
+            let (r, g, b) = colormap.colour(self.intensity(row, col));
+            Rgb([r, g, b])
+        })
+    }
+
+    pub fn save_png<P: AsRef<Path>>(
+        &self,
+        path: P,
+        colormap: Colormap,
+        scale: u32,
+    ) -> ImageResult<()> {
+        self.to_image(colormap, scale).save(path)
+    }
+}
+
+/// How heatmap intensities from 0.0 to 1.0 are turned into colours.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colormap {
+    /// Black through red and yellow to white.
+    Hot,
+    Grey,
+    /// An approximation of matplotlib's viridis, purple through green to
+    /// yellow.
+    Viridis,
+    /// Black through blue to pale cyan.
+    Ice,
+}
+
+impl Colormap {
+    pub fn colour(self, intensity: f32) -> (u8, u8, u8) {
+        let intensity = intensity.clamp(0.0, 1.0);
+        match self {
+            Colormap::Hot => {
+                let scaled = (intensity * 3.0 * 255.0) as u32;
+                let red = scaled.min(255) as u8;
+                let green = scaled.saturating_sub(255).min(255) as u8;
+                let blue = scaled.saturating_sub(510).min(255) as u8;
+                (red, green, blue)
+            }
+            Colormap::Grey => {
+                let level = (intensity * 255.0) as u8;
+                (level, level, level)
+            }
+            Colormap::Viridis => gradient(
+                &[
+                    (68, 1, 84),
+                    (59, 82, 139),
+                    (33, 145, 140),
+                    (94, 201, 98),
+                    (253, 231, 37),
+                ],
+                intensity,
+            ),
+            Colormap::Ice => gradient(
+                &[(0, 0, 0), (20, 60, 160), (80, 170, 230), (220, 250, 255)],
+                intensity,
+            ),
+        }
+    }
+}
+
+impl FromStr for Colormap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Colormap, String> {
+        match s {
+            "hot" => Ok(Colormap::Hot),
+            "grey" | "gray" => Ok(Colormap::Grey),
+            "viridis" => Ok(Colormap::Viridis),
+            "ice" => Ok(Colormap::Ice),
+            _ => Err(format!(
+                "unknown colormap '{}', use hot, grey, viridis or ice",
+                s
+            )),
+        }
+    }
+}
+
+/// Interpolates linearly between evenly spaced colour stops.
+fn gradient(stops: &[(u8, u8, u8)], intensity: f32) -> (u8, u8, u8) {
+    let position = intensity * (stops.len() - 1) as f32;
+    let index = (position as usize).min(stops.len() - 2);
+    let fraction = position - index as f32;
+    let (from, to) = (stops[index], stops[index + 1]);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fraction).round() as u8;
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_test() {
+        let mut grid = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let mut heatmap = Heatmap::new(3, 3);
+        for _ in 0..4 {
+            heatmap.record(&grid);
+            grid = grid.next_generation();
+        }
+
+        assert_eq!(heatmap.count(1, 1), 4);
+        assert_eq!(heatmap.count(0, 1), 2);
+        assert_eq!(heatmap.count(0, 0), 0);
+        assert_eq!(heatmap.intensity(1, 1), 1.0);
+        assert_eq!(heatmap.intensity(0, 0), 0.0);
+        assert!(heatmap.intensity(0, 1) > 0.0 && heatmap.intensity(0, 1) < 1.0);
+    }
+
+    #[test]
+    fn colormap_test() {
+        assert_eq!(Colormap::Hot.colour(0.0), (0, 0, 0));
+        assert_eq!(Colormap::Hot.colour(1.0), (255, 255, 255));
+        assert_eq!(Colormap::Hot.colour(0.5), (255, 127, 0));
+        assert_eq!(Colormap::Grey.colour(1.0), (255, 255, 255));
+        assert_eq!(Colormap::Viridis.colour(0.0), (68, 1, 84));
+        assert_eq!(Colormap::Viridis.colour(1.0), (253, 231, 37));
+        assert_eq!(Colormap::Ice.colour(0.0), (0, 0, 0));
+        assert_eq!("gray".parse(), Ok(Colormap::Grey));
+        assert!("rainbow".parse::<Colormap>().is_err());
+    }
+
+    #[test]
+    fn image_test() {
+        let mut heatmap = Heatmap::new(2, 1);
+        heatmap.record(&Grid::seed_rectangle(2, 1, vec![(0, 1)]));
+
+        let image = heatmap.to_image(Colormap::Grey, 3);
+
+        assert_eq!(image.dimensions(), (6, 3));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([0, 0, 0]));
+        assert_eq!(image.get_pixel(5, 2), &Rgb([255, 255, 255]));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::grid::Grid;
+use std::collections::VecDeque;
+use std::mem;
+
+/// How much memory the history may use by default, in megabytes.
+pub const DEFAULT_MEMORY_MB: u64 = 64;
+
+/// A generation stored in whichever of two forms is smaller: one bit per
+/// cell, or the positions of just the living cells for sparse boards.
+#[derive(Clone, Debug, PartialEq)]
+enum Cells {
+    Packed(Vec<u64>),
+    Sparse(Vec<(u32, u32)>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Snapshot {
+    generation: u64,
+    population: usize,
+    width: usize,
+    height: usize,
+    cells: Cells,
+}
+
+impl Snapshot {
+    fn new(generation: u64, grid: &Grid) -> Snapshot {
+        let (width, height) = (grid.width(), grid.height());
+        let living_cells = grid.living_cells();
+        let packed_words = (width * height).div_ceil(64);
+
+        let cells = if living_cells.len() * mem::size_of::<(u32, u32)>()
+            < packed_words * mem::size_of::<u64>()
+        {
+            Cells::Sparse(
+                living_cells
+                    .into_iter()
+                    .map(|(row, col)| (row as u32, col as u32))
+                    .collect(),
+            )
+        } else {
+            let mut words = vec![0; packed_words];
+            for (row, col) in living_cells {
+                let bit = row * width + col;
+                words[bit / 64] |= 1 << (bit % 64);
+            }
+            Cells::Packed(words)
+        };
+
+        Snapshot {
+            generation,
+            population: grid.population(),
+            width,
+            height,
+            cells,
+        }
+    }
+
+    fn to_grid(&self) -> Grid {
+        let living_cells = match &self.cells {
+            Cells::Sparse(cells) => cells
+                .iter()
+                .map(|&(row, col)| (row as usize, col as usize))
+                .collect(),
+            Cells::Packed(words) => (0..self.width * self.height)
+                .filter(|bit| words[bit / 64] & (1 << (bit % 64)) != 0)
+                .map(|bit| (bit / self.width, bit % self.width))
+                .collect(),
+        };
+        Grid::seed_rectangle(self.width, self.height, living_cells)
+    }
+
+    /// Roughly how many bytes the snapshot takes up.
+    fn bytes(&self) -> usize {
+        let payload = match &self.cells {
+            Cells::Packed(words) => words.len() * mem::size_of::<u64>(),
+            Cells::Sparse(cells) => cells.len() * mem::size_of::<(u32, u32)>(),
+        };
+        mem::size_of::<Snapshot>() + payload
+    }
+}
+
+/// Snapshots of the generations of a run, so the board can be stepped or
+/// scrubbed backwards even though Life itself can't be run in reverse.
+/// Snapshots are stored compactly, and once they take up more than the
+/// memory limit the oldest are dropped.
+#[derive(Clone)]
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    bytes: usize,
+    memory_limit: usize,
+}
+
+impl History {
+    pub fn new(memory_limit: usize) -> History {
+        History {
+            snapshots: VecDeque::new(),
+            bytes: 0,
+            memory_limit,
+        }
+    }
+
+    pub fn with_memory_mb(megabytes: u64) -> History {
+        History::new((megabytes * 1024 * 1024) as usize)
+    }
+
+    /// Stores `grid` as `generation`, first forgetting any snapshots from
+    /// that generation on, as they belong to a future that has been
+    /// replaced.
+    pub fn push(&mut self, generation: u64, grid: &Grid) {
+        self.truncate_from(generation);
+        let snapshot = Snapshot::new(generation, grid);
+        self.bytes += snapshot.bytes();
+        self.snapshots.push_back(snapshot);
+        while self.bytes > self.memory_limit {
+            match self.snapshots.pop_front() {
+                Some(oldest) => self.bytes -= oldest.bytes(),
+                None => break,
+            }
+        }
+    }
+
+    /// Forgets the snapshots of `generation` and every later generation.
+    pub fn truncate_from(&mut self, generation: u64) {
+        while self
+            .snapshots
+            .back()
+            .is_some_and(|snapshot| snapshot.generation >= generation)
+        {
+            if let Some(snapshot) = self.snapshots.pop_back() {
+                self.bytes -= snapshot.bytes();
+            }
+        }
+    }
+
+    fn snapshot(&self, generation: u64) -> Option<&Snapshot> {
+        let first = self.snapshots.front()?.generation;
+        let index = generation.checked_sub(first)? as usize;
+        self.snapshots
+            .get(index)
+            .filter(|snapshot| snapshot.generation == generation)
+    }
+
+    pub fn get(&self, generation: u64) -> Option<Grid> {
+        self.snapshot(generation).map(Snapshot::to_grid)
+    }
+
+    pub fn population(&self, generation: u64) -> Option<usize> {
+        self.snapshot(generation)
+            .map(|snapshot| snapshot.population)
+    }
+
+    pub fn first_generation(&self) -> Option<u64> {
+        self.snapshots.front().map(|snapshot| snapshot.generation)
+    }
+
+    pub fn last_generation(&self) -> Option<u64> {
+        self.snapshots.back().map(|snapshot| snapshot.generation)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Roughly how many bytes the snapshots take up.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewind_test() {
+        let mut grid = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let start = grid.clone();
+        let mut history = History::new(2 * Snapshot::new(0, &grid).bytes());
+        for generation in 0..3 {
+            history.push(generation, &grid);
+            grid = grid.next_generation();
+        }
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.first_generation(), Some(1));
+        assert_eq!(history.last_generation(), Some(2));
+        assert_eq!(history.get(2), Some(start.clone()));
+        assert_eq!(history.get(1), Some(start.next_generation()));
+        assert_eq!(history.population(1), Some(3));
+        assert_eq!(history.get(0), None);
+        assert_eq!(history.get(3), None);
+    }
+
+    #[test]
+    fn truncate_test() {
+        let grid = Grid::seed(3, vec![(1, 1)]);
+        let mut history = History::new(1024 * 1024);
+        for generation in 0..5 {
+            history.push(generation, &grid);
+        }
+
+        history.push(2, &Grid::seed(3, vec![]));
+
+        assert_eq!(history.last_generation(), Some(2));
+        assert_eq!(history.population(2), Some(0));
+        history.clear();
+        assert!(history.is_empty());
+        assert_eq!(history.bytes(), 0);
+    }
+
+    #[test]
+    fn compact_forms_test() {
+        let sparse = Grid::seed(100, vec![(3, 4), (99, 99)]);
+        let dense = Grid::seed(8, (0..8).map(|i| (i, i)).collect());
+
+        let sparse_snapshot = Snapshot::new(0, &sparse);
+        let dense_snapshot = Snapshot::new(0, &dense);
+
+        assert!(matches!(sparse_snapshot.cells, Cells::Sparse(_)));
+        assert!(matches!(dense_snapshot.cells, Cells::Packed(_)));
+        assert_eq!(sparse_snapshot.to_grid(), sparse);
+        assert_eq!(dense_snapshot.to_grid(), dense);
+    }
+
+    #[test]
+    fn no_memory_test() {
+        let mut history = History::new(0);
+        history.push(0, &Grid::seed(1, vec![]));
+
+        assert!(history.is_empty());
+    }
+}
@@ -0,0 +1,499 @@
+//! A hunt through small patterns for oscillators and spaceships. Each
+//! candidate, every pattern that fits in a small square or a random sample
+//! of them, is run on an unbounded plane until it repeats, allowing for
+//! movement. What it settles into is split into objects, and any that
+//! oscillate or fly with a long enough period are kept, once each however
+//! they are turned and whatever phase they were found in, by their
+//! apgcode.
+//!
+//! Like a soup search, each random candidate is drawn from its own
+//! generator, so a hunt finds the same things however many threads run it.
+
+use crate::apgcode;
+use crate::census;
+use crate::cycle::{self, Behaviour, Cycle};
+use crate::determinism;
+use crate::grid::{check_density, Grid, DEFAULT_DENSITY};
+use crate::plane::Plane;
+use crate::rle::Pattern;
+use crate::rule::Rule;
+use crate::soup::Soup;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The widest square whose every pattern can be tried, 2^25 of them.
+pub const MOST_ENUMERATED: usize = 5;
+
+/// The population past which a candidate is taken to be growing without
+/// end and given up on.
+const MOST_POPULATION: usize = 1000;
+
+/// How many times what a candidate became is split into parts that don't
+/// touch, at most.
+const MOST_SPLITS: usize = 4;
+
+/// How often progress is reported.
+const PROGRESS_EVERY: Duration = Duration::from_secs(1);
+
+type Cell = (i64, i64);
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hunt {
+    /// The width and height of the square each candidate starts in.
+    pub size: usize,
+    /// How many random candidates to try, or none to try every pattern
+    /// that fits in the square.
+    pub samples: Option<u64>,
+    pub density: f64,
+    pub seed: u64,
+    /// The shortest period worth keeping.
+    pub min_period: u64,
+    /// The generations a candidate, and then each object it became, is run
+    /// for at most while waiting for it to repeat.
+    pub generations: u64,
+    pub rule: Rule,
+    /// How many threads to hunt with, 0 for one per core.
+    pub threads: usize,
+    /// Where the finds are written.
+    pub out: PathBuf,
+}
+
+impl Default for Hunt {
+    fn default() -> Hunt {
+        Hunt {
+            size: 8,
+            samples: Some(10_000),
+            density: DEFAULT_DENSITY,
+            seed: 0,
+            min_period: 2,
+            generations: 1000,
+            rule: Rule::default(),
+            threads: 0,
+            out: PathBuf::from("finds"),
+        }
+    }
+}
+
+/// An oscillator or spaceship the hunt found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Find {
+    pub behaviour: Behaviour,
+    /// Its apgcode, by which it is known on Catagolue.
+    pub apgcode: String,
+    /// The phase and orientation of it its apgcode draws.
+    pub pattern: Pattern,
+    /// The candidate it was first found in.
+    pub candidate: u64,
+}
+
+impl Find {
+    pub fn period(&self) -> u64 {
+        match self.behaviour {
+            Behaviour::Oscillator { period } | Behaviour::Spaceship { period, .. } => period,
+            _ => 1,
+        }
+    }
+
+    /// A name for its file, like `p3-oscillator-1.rle` for the first
+    /// period 3 oscillator.
+    fn file_name(&self, number: usize) -> String {
+        let kind = match self.behaviour {
+            Behaviour::Spaceship { .. } => "spaceship",
+            _ => "oscillator",
+        };
+        format!("p{}-{}-{}.rle", self.period(), kind, number)
+    }
+}
+
+impl Hunt {
+    /// Whether the hunt can be run.
+    pub fn check(&self) -> Result<(), String> {
+        if self.size == 0 {
+            return Err("--size must be at least 1".to_string());
+        }
+        if self.samples == Some(0) {
+            return Err("--samples must be at least 1".to_string());
+        }
+        if self.samples.is_none() && self.size > MOST_ENUMERATED {
+            return Err(format!(
+                "--all tries every pattern up to {} cells across, not {}",
+                MOST_ENUMERATED, self.size
+            ));
+        }
+        if self.min_period == 0 {
+            return Err("--min-period must be at least 1".to_string());
+        }
+        if self.generations == 0 {
+            return Err("--generations must be at least 1".to_string());
+        }
+        check_density(self.density)
+    }
+
+    /// How many candidates there are to try.
+    pub fn candidates(&self) -> u64 {
+        self.samples.unwrap_or(1 << (self.size * self.size))
+    }
+
+    /// The candidate numbered `index`: a random soup, or the pattern whose
+    /// cells are the bits of `index` read across the square row by row.
+    pub fn candidate(&self, index: u64) -> Grid {
+        match self.samples {
+            Some(_) => {
+                let soup = Soup {
+                    density: self.density,
+                    ..Soup::default()
+                };
+                soup.generate(
+                    self.size,
+                    self.size,
+                    &mut determinism::rng_for(self.seed, index),
+                )
+            }
+            None => {
+                let living_cells = (0..self.size * self.size)
+                    .filter(|bit| index & 1 << bit != 0)
+                    .map(|bit| (bit / self.size, bit % self.size))
+                    .collect();
+                Grid::seed(self.size, living_cells)
+            }
+        }
+    }
+
+    /// Whether every pattern is tried and candidate `index` is just another
+    /// one moved across the square, one with no living cell in its top row
+    /// or left column, which needn't be run again.
+    fn is_moved(&self, index: u64) -> bool {
+        let top_row = (1 << self.size) - 1;
+        let left_column = (0..self.size).fold(0, |column, row| column | 1 << (row * self.size));
+        self.samples.is_none() && (index & top_row == 0 || index & left_column == 0)
+    }
+
+    /// Tries every candidate, telling `progress` how many are done about
+    /// once a second, and gives what was found, shortest period first.
+    pub fn run<F: FnMut(u64)>(&self, mut progress: F) -> Vec<Find> {
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        };
+        let candidates = self.candidates();
+        let next = AtomicU64::new(0);
+        let done = AtomicU64::new(0);
+        let finds = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut finds = BTreeMap::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            if index >= candidates {
+                                return finds;
+                            }
+                            if !self.is_moved(index) {
+                                for find in self.try_candidate(index) {
+                                    keep(&mut finds, find);
+                                }
+                            }
+                            done.fetch_add(1, Ordering::Relaxed);
+                        }
+                    })
+                })
+                .collect();
+            let mut reported = Instant::now();
+            while !workers.iter().all(|worker| worker.is_finished()) {
+                thread::sleep(Duration::from_millis(20));
+                if reported.elapsed() >= PROGRESS_EVERY {
+                    progress(done.load(Ordering::Relaxed));
+                    reported = Instant::now();
+                }
+            }
+            let mut finds = BTreeMap::new();
+            for worker in workers {
+                for (_, find) in worker.join().expect("hunt threads don't panic") {
+                    keep(&mut finds, find);
+                }
+            }
+            finds
+        });
+        let mut finds: Vec<Find> = finds.into_values().collect();
+        finds.sort_by_key(|find| {
+            (
+                find.period(),
+                find.pattern.living_cells.len(),
+                find.candidate,
+            )
+        });
+        finds
+    }
+
+    /// The finds in candidate `index`.
+    fn try_candidate(&self, index: u64) -> Vec<Find> {
+        let plane = Plane::from_grid(&self.candidate(index), 0, 0);
+        let (whole, _) = self.evolve(plane);
+        // What is left is tried an object at a time, so that a blinker and
+        // a glider flying away from it are found as both.
+        let mut finds = Vec::new();
+        for object in census::objects_among(whole.living_cells()) {
+            let run = self.evolve(Plane::from_cells(&object));
+            self.split(index, run, &mut finds, MOST_SPLITS);
+        }
+        finds
+    }
+
+    /// Runs `plane` until it repeats, for `generations` at most, giving it as
+    /// it was then and the cycle it closed.
+    fn evolve(&self, plane: Plane) -> (Plane, Option<Cycle>) {
+        cycle::run_to_cycle(plane, self.rule, self.generations, MOST_POPULATION)
+    }
+
+    /// Adds the find `run` ended in to `finds`, or if it is made of parts
+    /// that go on just the same apart, like two blinkers side by side, the
+    /// finds each of those ends in, splitting `splits` times at most.
+    fn split(
+        &self,
+        candidate: u64,
+        run: (Plane, Option<Cycle>),
+        finds: &mut Vec<Find>,
+        splits: usize,
+    ) {
+        let (plane, period) = match run {
+            (plane, Some(cycle)) => (plane, cycle.period),
+            _ => return,
+        };
+        let parts = census::clusters_among(plane.living_cells(), 1);
+        if splits > 0 && parts.len() > 1 && are_apart(&plane, &parts, self.rule, period) {
+            for part in parts {
+                let run = self.evolve(Plane::from_cells(&part));
+                self.split(candidate, run, finds, splits - 1);
+            }
+        } else if let Some(find) = self.find(candidate, &plane, period) {
+            finds.push(find);
+        }
+    }
+
+    /// The find `plane` is a phase of, if it repeats with `period` as an
+    /// oscillator or a spaceship and that is long enough.
+    fn find(&self, candidate: u64, plane: &Plane, period: u64) -> Option<Find> {
+        if period < self.min_period {
+            return None;
+        }
+        let apgcode = apgcode::of_object(&plane.living_cells(), self.rule)?;
+        let pattern = Pattern {
+            rule: self.rule,
+            ..apgcode::decode(&apgcode).ok()?
+        };
+        // How far it moves is measured again as it is written, which may be
+        // turned from how it was found.
+        let cells: Vec<Cell> = pattern
+            .living_cells
+            .iter()
+            .map(|&(row, col)| (row as i64, col as i64))
+            .collect();
+        let displacement = self
+            .evolve(Plane::from_cells(&cells))
+            .1
+            .map_or((0, 0), |cycle| cycle.displacement);
+        let behaviour = if displacement != (0, 0) {
+            Behaviour::Spaceship {
+                period,
+                displacement,
+            }
+        } else if period > 1 {
+            Behaviour::Oscillator { period }
+        } else {
+            return None;
+        };
+        Some(Find {
+            behaviour,
+            apgcode,
+            pattern,
+            candidate,
+        })
+    }
+}
+
+/// Keeps `find` by its apgcode, unless one found in an earlier candidate
+/// is already kept, so the finds don't depend on which thread got where
+/// first.
+fn keep(finds: &mut BTreeMap<String, Find>, find: Find) {
+    match finds.get(&find.apgcode) {
+        Some(kept) if kept.candidate <= find.candidate => {}
+        _ => {
+            finds.insert(find.apgcode.clone(), find);
+        }
+    }
+}
+
+/// Whether `plane` goes on for `generations` just as its `parts` would
+/// each on their own.
+fn are_apart(plane: &Plane, parts: &[Vec<Cell>], rule: Rule, generations: u64) -> bool {
+    let mut whole = plane.clone();
+    let mut apart: Vec<Plane> = parts.iter().map(|part| Plane::from_cells(part)).collect();
+    for _ in 0..generations {
+        whole = whole.next_generation_under(rule);
+        for part in &mut apart {
+            *part = part.next_generation_under(rule);
+        }
+        let mut cells: Vec<Cell> = apart.iter().flat_map(Plane::living_cells).collect();
+        cells.sort_unstable();
+        if cells != whole.living_cells() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Writes each find to its own RLE file in `dir`, creating it if need be,
+/// and gives the paths written in the same order.
+pub fn save(finds: &[Find], dir: &Path) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    let mut paths = Vec::new();
+    for find in finds {
+        let number = numbers.entry(find.file_name(0)).or_insert(0);
+        *number += 1;
+        let path = dir.join(find.file_name(*number));
+        let text = format!(
+            "#N {}\n#C A {}, {}, found in candidate {}.\n{}",
+            path.file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            find.behaviour,
+            find.apgcode,
+            find.candidate,
+            find.pattern.to_rle()
+        );
+        fs::write(&path, text).map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle;
+
+    #[test]
+    fn enumerate_test() {
+        let hunt = Hunt {
+            size: 3,
+            samples: None,
+            generations: 40,
+            threads: 2,
+            ..Hunt::default()
+        };
+        assert_eq!(hunt.candidates(), 512);
+        assert_eq!(
+            hunt.candidate(0b111),
+            Grid::seed(3, vec![(0, 0), (0, 1), (0, 2)])
+        );
+        assert!(hunt.is_moved(0b111 << 3));
+        assert!(!hunt.is_moved(0b111));
+
+        let finds = hunt.run(|_| ());
+        assert!(finds.iter().all(|find| find.period() >= 2));
+        // Every blinker is the same find, however many candidates make one
+        // or however it lies, and so is every glider.
+        let blinkers: Vec<&Find> = finds
+            .iter()
+            .filter(|find| find.apgcode == "xp2_7")
+            .collect();
+        assert_eq!(blinkers.len(), 1, "{:?}", finds);
+        assert_eq!(blinkers[0].behaviour, Behaviour::Oscillator { period: 2 });
+        assert_eq!(blinkers[0].pattern, apgcode::decode("xp2_7").unwrap());
+        let gliders: Vec<&Find> = finds
+            .iter()
+            .filter(|find| matches!(find.behaviour, Behaviour::Spaceship { period: 4, .. }))
+            .collect();
+        assert_eq!(gliders.len(), 1, "{:?}", finds);
+        assert_eq!(gliders[0].pattern.living_cells.len(), 5);
+
+        let longer = Hunt {
+            min_period: 3,
+            ..hunt
+        };
+        assert!(longer.run(|_| ()).iter().all(|find| find.period() >= 3));
+    }
+
+    #[test]
+    fn sample_test() {
+        let hunt = Hunt {
+            size: 4,
+            samples: Some(50),
+            seed: 3,
+            generations: 100,
+            threads: 3,
+            ..Hunt::default()
+        };
+        let finds = hunt.run(|_| ());
+        assert!(!finds.is_empty());
+        let alone = Hunt {
+            threads: 1,
+            ..hunt.clone()
+        };
+        assert_eq!(alone.run(|_| ()), finds);
+        assert_ne!(hunt.candidate(0), hunt.candidate(1));
+    }
+
+    #[test]
+    fn composite_test() {
+        // A blinker with a glider flying away from it never repeats as a
+        // whole, but both are found.
+        let hunt = Hunt {
+            size: 10,
+            ..Hunt::default()
+        };
+        let glider = rle::parse("3o5b$9b$9b$6bo2b$7bob$5b3o!").unwrap();
+        let plane = Plane::from_grid(&glider.fitted(1, 1, 0), 0, 0);
+        assert_eq!(hunt.evolve(plane).1, None);
+        let whole = Grid::seed_rectangle(10, 10, glider.living_cells);
+        let finds: Vec<(Behaviour, String)> = {
+            let (plane, _) = hunt.evolve(Plane::from_grid(&whole, 0, 0));
+            census::objects_among(plane.living_cells())
+                .iter()
+                .filter_map(|object| match hunt.evolve(Plane::from_cells(object)) {
+                    (plane, Some(cycle)) => hunt.find(0, &plane, cycle.period),
+                    _ => None,
+                })
+                .map(|find| (find.behaviour, find.apgcode))
+                .collect()
+        };
+        assert!(
+            finds.contains(&(Behaviour::Oscillator { period: 2 }, "xp2_7".to_string())),
+            "{:?}",
+            finds
+        );
+        assert!(finds.iter().any(|(behaviour, apgcode)| matches!(
+            behaviour,
+            Behaviour::Spaceship { period: 4, .. }
+        ) && apgcode == "xq4_153"));
+    }
+
+    #[test]
+    fn save_test() {
+        let dir = std::env::temp_dir().join(format!("hunt-save-{}", std::process::id()));
+        let blinker = Find {
+            behaviour: Behaviour::Oscillator { period: 2 },
+            apgcode: "xp2_7".to_string(),
+            pattern: rle::parse("3o!").unwrap(),
+            candidate: 7,
+        };
+        let paths = save(&[blinker.clone(), blinker.clone()], &dir).unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("p2-oscillator-1.rle"),
+                dir.join("p2-oscillator-2.rle")
+            ]
+        );
+        let text = fs::read_to_string(&paths[0]).unwrap();
+        assert!(text.contains("period 2 oscillator, xp2_7,"), "{}", text);
+        assert_eq!(rle::parse(&text), Ok(blinker.pattern));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
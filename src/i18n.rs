@@ -0,0 +1,389 @@
+//! The languages the window's text can be shown in. Text is written in
+//! English where it is used and looked up by that English in the
+//! translations, so anything not translated yet still shows in English.
+
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "es")]
+    Spanish,
+}
+
+impl Language {
+    /// `text` in this language.
+    pub fn tr(self, text: &'static str) -> &'static str {
+        let translations = match self {
+            Language::English => return text,
+            Language::Spanish => SPANISH,
+        };
+        translations
+            .iter()
+            .find(|(english, _)| *english == text)
+            .map_or(text, |(_, translated)| translated)
+    }
+
+    /// `template` in this language, with each `{}` in it replaced by the
+    /// next of `args`.
+    pub fn fill(self, template: &'static str, args: &[&dyn fmt::Display]) -> String {
+        let mut parts = self.tr(template).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        let mut args = args.iter();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(part);
+        }
+        text
+    }
+}
+
+const SPANISH: &[(&str, &str)] = &[
+    // The window's title.
+    ("Conway's Game of Life", "El juego de la vida de Conway"),
+    (
+        "{} - generation {}, population {}, {} gen/s",
+        "{} - generación {}, población {}, {} gen/s",
+    ),
+    // The status bar.
+    (
+        "Generation: {}   Population: {}   Speed: {}/s   Rule: {}",
+        "Generación: {}   Población: {}   Velocidad: {}/s   Regla: {}",
+    ),
+    ("   Cell: {}, {}", "   Celda: {}, {}"),
+    ("   {} view", "   vista {}"),
+    ("flat", "plana"),
+    ("sphere", "esfera"),
+    ("isometric", "isométrica"),
+    ("   View at {}, {}", "   Vista en {}, {}"),
+    (" following", " siguiendo"),
+    ("   Paused", "   En pausa"),
+    ("   Shared with {}", "   Compartido con {}"),
+    ("   Joined as {}", "   Unido como {}"),
+    ("   {} of {}: {}", "   {} de {}: {}"),
+    (
+        "   Favorite name: {}_   Return saves, Esc cancels",
+        "   Nombre del favorito: {}_   Intro lo guarda, Esc cancela",
+    ),
+    ("   Density {}%", "   Densidad {}%"),
+    (
+        "   Clusters: {} (largest {})",
+        "   Grupos: {} (el mayor {})",
+    ),
+    ("   Branch {} of {}", "   Rama {} de {}"),
+    ("   Recording", "   Grabando"),
+    ("   Muted", "   Silenciado"),
+    (
+        "   R restores the last session",
+        "   R recupera la última sesión",
+    ),
+    ("Red", "Rojo"),
+    ("Blue", "Azul"),
+    ("left", "izquierda"),
+    ("right", "derecha"),
+    (
+        "   {} places {} more on the {} half",
+        "   {} coloca {} más en la mitad {}",
+    ),
+    (
+        "   {} places {} more this turn, away from the other's cells, with {} left in all",
+        "   {} coloca {} más este turno, lejos de las celdas del otro, con {} en total",
+    ),
+    (
+        "   {} generations to the next turn",
+        "   faltan {} generaciones para el siguiente turno",
+    ),
+    ("   {} generations to go", "   faltan {} generaciones"),
+    (
+        "   {} wins, Return plays again",
+        "   gana {}, Intro juega otra vez",
+    ),
+    (
+        "   A draw, Return plays again",
+        "   Empate, Intro juega otra vez",
+    ),
+    ("extinct", "extinto"),
+    ("still life", "vida estática"),
+    ("period {} oscillator", "oscilador de periodo {}"),
+    (
+        "period {} spaceship moving ({}, {})",
+        "nave de periodo {} que se mueve ({}, {})",
+    ),
+    // The note on the cell under the mouse while paused.
+    ("Cell {}, {}", "Celda {}, {}"),
+    ("alive", "viva"),
+    ("dead", "muerta"),
+    ("{} live neighbours", "{} vecinas vivas"),
+    ("next generation: {}", "siguiente generación: {}"),
+    ("survives", "sobrevive"),
+    ("dies", "muere"),
+    ("is born", "nace"),
+    ("stays dead", "sigue muerta"),
+    // The stats screen.
+    ("Stats", "Estadísticas"),
+    ("Generations run: {}", "Generaciones ejecutadas: {}"),
+    (
+        "Longest-lived board: {} generations",
+        "Tablero más longevo: {} generaciones",
+    ),
+    (
+        "Longest oscillator: period {}",
+        "Oscilador más largo: periodo {}",
+    ),
+    ("Spaceships found: {}", "Naves encontradas: {}"),
+    ("Achievements", "Logros"),
+    (
+        "ran 1,000 generations in all",
+        "ejecutó 1.000 generaciones en total",
+    ),
+    (
+        "ran 1,000,000 generations in all",
+        "ejecutó 1.000.000 de generaciones en total",
+    ),
+    (
+        "made a board that lived 5000 generations or more",
+        "creó un tablero que vivió 5000 generaciones o más",
+    ),
+    (
+        "found an oscillator of period 8 or more",
+        "encontró un oscilador de periodo 8 o más",
+    ),
+    ("found a spaceship", "encontró una nave"),
+    ("   Achievement unlocked: {}", "   Logro desbloqueado: {}"),
+    // The tree of branches and the population graph.
+    (
+        "{} {}branch {} from generation {}",
+        "{} {}rama {} desde la generación {}",
+    ),
+    ("{} branch 1", "{} rama 1"),
+    ("max {}", "máx. {}"),
+    ("entropy {}", "entropía {}"),
+    ("change {}%", "cambio {}%"),
+    ("{} bytes deflated", "{} bytes comprimidos"),
+    // The settings menu.
+    ("Settings", "Ajustes"),
+    ("Rule", "Regla"),
+    ("Size", "Tamaño"),
+    ("Topology", "Topología"),
+    ("Theme", "Tema"),
+    ("dark", "oscuro"),
+    ("light", "claro"),
+    ("Resume", "Continuar"),
+    ("Quit", "Salir"),
+    (
+        "Return sets it, Esc cancels",
+        "Intro lo aplica, Esc cancela",
+    ),
+    (
+        "Up and Down choose, Return changes, Esc goes back to the board",
+        "Arriba y Abajo eligen, Intro cambia, Esc vuelve al tablero",
+    ),
+    (
+        "wait for the replay to finish",
+        "espera a que termine la repetición",
+    ),
+    ("the rule is {}", "la regla es {}"),
+    (
+        "the Immigration Game is played by B3/S23",
+        "el Juego de la Inmigración se juega con B3/S23",
+    ),
+    (
+        "'{}' is not a size like 50 or 80x40",
+        "'{}' no es un tamaño como 50 u 80x40",
+    ),
+    (
+        "the board needs at least one cell each way",
+        "el tablero necesita al menos una celda en cada sentido",
+    ),
+    (
+        "finish recording before resizing the board",
+        "termina de grabar antes de cambiar el tamaño del tablero",
+    ),
+    (
+        "the infinite plane has no edges to join",
+        "el plano infinito no tiene bordes que unir",
+    ),
+    (
+        "--auto-expand only grows a board without joined edges",
+        "--auto-expand solo hace crecer tableros sin bordes unidos",
+    ),
+    (
+        "could not change the setting: {}",
+        "no se pudo cambiar el ajuste: {}",
+    ),
+    ("the board is now {}x{}", "el tablero ahora mide {}x{}"),
+    ("the topology is now {}", "la topología ahora es {}"),
+    ("the theme is now {}", "el tema ahora es {}"),
+    // The help overlay.
+    ("pause or resume", "pausar o continuar"),
+    ("step forward while paused", "avanzar un paso en pausa"),
+    ("step back while paused", "retroceder un paso en pausa"),
+    ("make new soups denser", "hacer las sopas nuevas más densas"),
+    (
+        "make new soups sparser",
+        "hacer las sopas nuevas menos densas",
+    ),
+    (
+        "start a new random soup",
+        "empezar una sopa aleatoria nueva",
+    ),
+    ("grow the board", "agrandar el tablero"),
+    ("shrink the board", "encoger el tablero"),
+    (
+        "follow the action with --infinite",
+        "seguir la acción con --infinite",
+    ),
+    (
+        "zoom to fit everything alive with --infinite",
+        "encuadrar todo lo vivo con --infinite",
+    ),
+    (
+        "centre the view with --infinite",
+        "centrar la vista con --infinite",
+    ),
+    ("clear the board", "vaciar el tablero"),
+    (
+        "next starting point in the gallery",
+        "siguiente punto de partida de la galería",
+    ),
+    (
+        "previous starting point in the gallery",
+        "anterior punto de partida de la galería",
+    ),
+    ("place a classic pattern", "colocar un patrón clásico"),
+    (
+        "show or hide the population graph",
+        "mostrar u ocultar la gráfica de población",
+    ),
+    ("show or hide the census", "mostrar u ocultar el censo"),
+    (
+        "switch to or from the heatmap",
+        "cambiar al mapa de calor o volver",
+    ),
+    ("show or hide the clusters", "mostrar u ocultar los grupos"),
+    (
+        "switch between the flat, sphere and isometric views",
+        "cambiar entre las vistas plana, esfera e isométrica",
+    ),
+    (
+        "previous branch of the timeline",
+        "rama anterior de la historia",
+    ),
+    (
+        "next branch of the timeline",
+        "rama siguiente de la historia",
+    ),
+    (
+        "show or hide the tree of branches",
+        "mostrar u ocultar el árbol de ramas",
+    ),
+    (
+        "show or hide the status bar",
+        "mostrar u ocultar la barra de estado",
+    ),
+    (
+        "switch between the dark and light themes",
+        "cambiar entre el tema oscuro y el claro",
+    ),
+    (
+        "show or hide the stats and achievements",
+        "mostrar u ocultar las estadísticas y los logros",
+    ),
+    ("mute or unmute the sound", "silenciar o activar el sonido"),
+    (
+        "show or hide the effects of births and deaths",
+        "mostrar u ocultar los efectos de nacimientos y muertes",
+    ),
+    (
+        "switch the neon glow on or off",
+        "encender o apagar el brillo de neón",
+    ),
+    (
+        "colour each cluster of cells its own way",
+        "dar a cada grupo de celdas su propio color",
+    ),
+    (
+        "save the board as a favorite",
+        "guardar el tablero como favorito",
+    ),
+    ("save the board as an SVG", "guardar el tablero como SVG"),
+    (
+        "print the board as text to share",
+        "imprimir el tablero como texto para compartir",
+    ),
+    ("save a screenshot", "guardar una captura de pantalla"),
+    (
+        "start or stop recording an animation",
+        "empezar o dejar de grabar una animación",
+    ),
+    ("restore the last session", "recuperar la última sesión"),
+    (
+        "bookmark the generation or remove its bookmark",
+        "marcar la generación o quitar su marcador",
+    ),
+    (
+        "jump to the previous bookmark",
+        "saltar al marcador anterior",
+    ),
+    ("jump to the next bookmark", "saltar al marcador siguiente"),
+    (
+        "save the heatmap as a PNG",
+        "guardar el mapa de calor como PNG",
+    ),
+    ("show or hide this help", "mostrar u ocultar esta ayuda"),
+    ("open the settings menu", "abrir el menú de ajustes"),
+    ("quit", "salir"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::Command;
+
+    #[test]
+    fn fill_test() {
+        assert_eq!(
+            Language::Spanish.fill("the board is now {}x{}", &[&80, &40]),
+            "el tablero ahora mide 80x40"
+        );
+        assert_eq!(
+            Language::English.fill("the board is now {}x{}", &[&80, &40]),
+            "the board is now 80x40"
+        );
+        assert_eq!(Language::Spanish.tr("not translated"), "not translated");
+    }
+
+    #[test]
+    fn translations_test() {
+        for (english, spanish) in SPANISH {
+            assert_eq!(
+                english.matches("{}").count(),
+                spanish.matches("{}").count(),
+                "{}",
+                english
+            );
+        }
+        for command in Command::all() {
+            let description = command.description();
+            assert!(
+                SPANISH.iter().any(|(english, _)| *english == description),
+                "'{}' isn't translated",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn config_test() {
+        assert_eq!(
+            serde_json::from_str(r#""es""#).ok(),
+            Some(Language::Spanish)
+        );
+        assert!(serde_json::from_str::<Language>(r#""fr""#).is_err());
+    }
+}
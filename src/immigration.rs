@@ -0,0 +1,500 @@
+//! The Immigration Game, Life for two players. Every living cell belongs to
+//! one of them: survivors keep their owner, and a cell that is born takes
+//! the owner of most of the three cells that bred it. Each player places an
+//! equal number of cells on their own half of an empty board, taking turns,
+//! and whoever owns more of the cells left after a set number of
+//! generations wins.
+
+use crate::grid::{self, Grid, State};
+use crate::topology::Topology;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Player {
+    /// Places first, on the left half.
+    Red,
+    /// Places second, on the right half.
+    Blue,
+}
+
+impl Player {
+    pub fn other(self) -> Player {
+        match self {
+            Player::Red => Player::Blue,
+            Player::Blue => Player::Red,
+        }
+    }
+
+    /// The colour the player's cells are drawn in.
+    pub fn colour(self) -> (u8, u8, u8) {
+        match self {
+            Player::Red => (230, 70, 60),
+            Player::Blue => (60, 140, 240),
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Player::Red => 0,
+            Player::Blue => 1,
+        }
+    }
+}
+
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Player::Red => write!(f, "Red"),
+            Player::Blue => write!(f, "Blue"),
+        }
+    }
+}
+
+/// A board on which each living cell has an owner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    cells: Vec<Vec<Option<Player>>>,
+    /// How the board's edges are joined.
+    pub topology: Topology,
+}
+
+impl Board {
+    /// An empty board with the edges joined as `topology` says.
+    pub fn new(width: usize, height: usize, topology: Topology) -> Board {
+        Board {
+            cells: vec![vec![None; width]; height],
+            topology,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Who the cell at `row` and `col` belongs to, or `None` if it is dead.
+    pub fn owner(&self, row: usize, col: usize) -> Option<Player> {
+        self.cells
+            .get(row)
+            .and_then(|cells| cells.get(col))
+            .copied()?
+    }
+
+    /// Brings the cell at `row` and `col` to life for `player`.
+    pub fn place(&mut self, row: usize, col: usize, player: Player) {
+        self.cells[row][col] = Some(player);
+    }
+
+    /// The board without its owners.
+    pub fn to_grid(&self) -> Grid {
+        let cells = self
+            .cells
+            .iter()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|owner| match owner {
+                        Some(_) => State::Alive,
+                        None => State::Dead,
+                    })
+                    .collect()
+            })
+            .collect();
+        Grid { cells }
+    }
+
+    /// How many living cells Red and Blue each own.
+    pub fn score(&self) -> (usize, usize) {
+        let count = |player| {
+            self.cells
+                .iter()
+                .flatten()
+                .filter(|owner| **owner == Some(player))
+                .count()
+        };
+        (count(Player::Red), count(Player::Blue))
+    }
+
+    pub fn next_generation(&self) -> Board {
+        let grid = self.to_grid();
+        let (width, height) = (self.width(), self.height());
+        let mut next = Board::new(width, height, self.topology);
+        for row in 0..height {
+            for col in 0..width {
+                let neighbours = grid.live_neighbours(row, col, self.topology);
+                let state = &grid.cells[row][col];
+                if grid::next_state(state, neighbours) == State::Dead {
+                    continue;
+                }
+                next.cells[row][col] = match self.cells[row][col] {
+                    Some(owner) => Some(owner),
+                    None => Some(self.majority(row, col)),
+                };
+            }
+        }
+        next
+    }
+
+    /// The owners of the living cells round the one at `row` and `col`.
+    pub fn neighbour_owners(&self, row: usize, col: usize) -> Vec<Player> {
+        let mut owners = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dy, dx) == (0, 0) {
+                    continue;
+                }
+                let neighbour = self.topology.locate(
+                    self.width(),
+                    self.height(),
+                    row as isize + dy,
+                    col as isize + dx,
+                );
+                if let Some(owner) = neighbour.and_then(|(row, col)| self.cells[row][col]) {
+                    owners.push(owner);
+                }
+            }
+        }
+        owners
+    }
+
+    /// Who owns most of the living cells round the one at `row` and `col`.
+    /// A cell is only born with three, so there is always a majority.
+    fn majority(&self, row: usize, col: usize) -> Player {
+        let owners = self.neighbour_owners(row, col);
+        let red = owners.iter().filter(|owner| **owner == Player::Red).count();
+        if red * 2 > owners.len() {
+            Player::Red
+        } else {
+            Player::Blue
+        }
+    }
+}
+
+/// Where a game has got to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    /// The player whose turn it is is placing cells.
+    Placing,
+    /// The board is running between turns, with this many generations to
+    /// go before the next.
+    Advancing(u64),
+    /// Every cell has been placed and the board is running, with this many
+    /// generations to go.
+    Running(u64),
+    Over,
+}
+
+/// A game of Immigration, from placing the cells to the final score.
+///
+/// In the plain game the players take turns to place one cell at a time on
+/// their own half, and the board only runs once they have placed them all.
+/// A battle, set up with [`Game::battle`], has them place several cells a
+/// turn anywhere not touching the other player's, with the board running
+/// for a few generations after each round of turns.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Game {
+    pub board: Board,
+    /// How many cells each player places in all.
+    budget: usize,
+    /// How many cells a player places before the turn passes on.
+    per_turn: usize,
+    /// How many generations the board runs after each round of turns.
+    between: u64,
+    battle: bool,
+    /// How many generations the board runs for once the cells are placed.
+    generations: u64,
+    placed: [usize; 2],
+    /// How many cells have been placed this turn.
+    placed_this_turn: usize,
+    turn: Player,
+    generation: u64,
+    phase: Phase,
+}
+
+impl Game {
+    /// A game on an empty `width` by `height` board, in which each player
+    /// places `budget` cells and the board then runs for `generations`.
+    pub fn new(
+        width: usize,
+        height: usize,
+        topology: Topology,
+        budget: usize,
+        generations: u64,
+    ) -> Game {
+        Game {
+            board: Board::new(width, height, topology),
+            budget,
+            per_turn: 1,
+            between: 0,
+            battle: false,
+            generations,
+            placed: [0, 0],
+            placed_this_turn: 0,
+            turn: Player::Red,
+            generation: 0,
+            phase: if budget == 0 {
+                Phase::Running(generations)
+            } else {
+                Phase::Placing
+            },
+        }
+    }
+
+    /// The game as a battle, in which each player places `per_turn` cells a
+    /// turn, anywhere not next to the other's cells, and the board runs for
+    /// `between` generations after both have had a turn.
+    pub fn battle(self, per_turn: usize, between: u64) -> Game {
+        Game {
+            per_turn: per_turn.max(1),
+            between,
+            battle: true,
+            ..self
+        }
+    }
+
+    pub fn is_battle(&self) -> bool {
+        self.battle
+    }
+
+    /// The same game again from the start.
+    pub fn rematch(&self) -> Game {
+        let game = Game::new(
+            self.board.width(),
+            self.board.height(),
+            self.board.topology,
+            self.budget,
+            self.generations,
+        );
+        if self.battle {
+            game.battle(self.per_turn, self.between)
+        } else {
+            game
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Whose turn it is to place cells.
+    pub fn turn(&self) -> Player {
+        self.turn
+    }
+
+    /// How many more cells `player` has to place.
+    pub fn remaining(&self, player: Player) -> usize {
+        self.budget - self.placed[player.index()]
+    }
+
+    /// How many more cells the player whose turn it is places this turn.
+    pub fn remaining_this_turn(&self) -> usize {
+        (self.per_turn - self.placed_this_turn).min(self.remaining(self.turn))
+    }
+
+    /// How many generations the board has run for.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Whether `col` is on `player`'s half of the board. The middle column
+    /// of a board an odd number of cells wide belongs to neither.
+    pub fn is_own_half(&self, player: Player, col: usize) -> bool {
+        let width = self.board.width();
+        match player {
+            Player::Red => col < width / 2,
+            Player::Blue => col >= width.div_ceil(2) && col < width,
+        }
+    }
+
+    /// Whether the player whose turn it is may place a cell at `row` and
+    /// `col`: an empty cell on their own half, or in a battle one that
+    /// doesn't touch any of the other player's.
+    pub fn can_place(&self, row: usize, col: usize) -> bool {
+        if self.phase != Phase::Placing
+            || row >= self.board.height()
+            || col >= self.board.width()
+            || self.board.owner(row, col).is_some()
+        {
+            return false;
+        }
+        if self.is_battle() {
+            let enemy = self.turn.other();
+            !self.board.neighbour_owners(row, col).contains(&enemy)
+        } else {
+            self.is_own_half(self.turn, col)
+        }
+    }
+
+    /// Places a cell at `row` and `col` for whoever's turn it is, if they
+    /// may, passing the turn on once they have placed their cells for it.
+    /// Once both have placed all their cells the board starts running.
+    /// Returns whether the cell was placed.
+    pub fn place(&mut self, row: usize, col: usize) -> bool {
+        if !self.can_place(row, col) {
+            return false;
+        }
+        self.board.place(row, col, self.turn);
+        self.placed[self.turn.index()] += 1;
+        self.placed_this_turn += 1;
+        if self.remaining_this_turn() == 0 {
+            self.end_turn();
+        }
+        true
+    }
+
+    /// Passes the turn on, running the board between rounds of turns and
+    /// starting the final run once every cell has been placed.
+    fn end_turn(&mut self) {
+        self.placed_this_turn = 0;
+        if self.remaining(Player::Red) == 0 && self.remaining(Player::Blue) == 0 {
+            self.phase = Phase::Running(self.generations);
+            return;
+        }
+        // The round is over once Blue has had a turn, or when the other
+        // player has nothing left to place.
+        let round_over = self.turn == Player::Blue || self.remaining(self.turn.other()) == 0;
+        self.turn = if !round_over {
+            self.turn.other()
+        } else if self.remaining(Player::Red) > 0 {
+            Player::Red
+        } else {
+            Player::Blue
+        };
+        if round_over && self.between > 0 {
+            self.phase = Phase::Advancing(self.between);
+        }
+    }
+
+    /// Runs the board on a generation, if it is running.
+    pub fn step(&mut self) {
+        let left = match self.phase {
+            Phase::Advancing(left) | Phase::Running(left) => left,
+            Phase::Placing | Phase::Over => return,
+        };
+        if left > 0 {
+            self.board = self.board.next_generation();
+            self.generation += 1;
+        }
+        self.phase = match self.phase {
+            Phase::Advancing(_) if left <= 1 => Phase::Placing,
+            Phase::Advancing(_) => Phase::Advancing(left - 1),
+            _ if left <= 1 => Phase::Over,
+            _ => Phase::Running(left - 1),
+        };
+    }
+
+    /// Who owns more living cells once the game is over, or `None` while it
+    /// is still going or if it ended in a draw.
+    pub fn winner(&self) -> Option<Player> {
+        if self.phase != Phase::Over {
+            return None;
+        }
+        let (red, blue) = self.board.score();
+        if red > blue {
+            Some(Player::Red)
+        } else if blue > red {
+            Some(Player::Blue)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn birth_takes_the_majority_test() {
+        let mut board = Board::new(5, 5, Topology::default());
+        board.place(1, 1, Player::Red);
+        board.place(1, 2, Player::Red);
+        board.place(1, 3, Player::Blue);
+
+        let next = board.next_generation();
+
+        assert_eq!(next.owner(0, 2), Some(Player::Red));
+        assert_eq!(next.owner(2, 2), Some(Player::Red));
+        assert_eq!(next.owner(1, 2), Some(Player::Red));
+        assert_eq!(next.owner(1, 1), None);
+        assert_eq!(next.score(), (3, 0));
+    }
+
+    #[test]
+    fn placing_test() {
+        let mut game = Game::new(5, 4, Topology::default(), 2, 3);
+
+        assert!(!game.place(0, 2), "the middle column is no one's");
+        assert!(!game.place(0, 4), "Red can't place on Blue's half");
+        assert!(game.place(0, 0));
+        assert_eq!(game.turn(), Player::Blue);
+        assert!(game.place(0, 3));
+        assert!(!game.place(0, 0), "the cell is taken");
+        assert!(game.place(1, 0));
+        assert_eq!(game.phase(), Phase::Placing);
+        assert!(game.place(3, 4));
+        assert!(!game.place(2, 0));
+        assert_eq!(game.remaining(Player::Red), 0);
+        assert_eq!(game.phase(), Phase::Running(3));
+    }
+
+    #[test]
+    fn scoring_test() {
+        let mut game = Game::new(8, 8, Topology::default(), 3, 2);
+        // Red places a blinker, Blue three cells that die at once.
+        for (red, blue) in [((3, 0), (0, 5)), ((3, 1), (3, 7)), ((3, 2), (7, 6))] {
+            assert!(game.place(red.0, red.1));
+            assert!(game.place(blue.0, blue.1));
+        }
+
+        game.step();
+        assert_eq!(game.generation(), 1);
+        assert_eq!(game.winner(), None);
+        game.step();
+
+        assert_eq!(game.phase(), Phase::Over);
+        assert_eq!(game.board.score(), (3, 0));
+        assert_eq!(game.winner(), Some(Player::Red));
+        assert_eq!(game.rematch(), Game::new(8, 8, Topology::default(), 3, 2));
+    }
+
+    #[test]
+    fn battle_test() {
+        let plane = Topology::default();
+        let mut game = Game::new(10, 10, plane, 4, 5).battle(2, 3);
+
+        assert!(game.place(0, 0));
+        assert_eq!(game.turn(), Player::Red);
+        assert!(game.place(0, 1));
+        assert_eq!(game.turn(), Player::Blue);
+        assert!(!game.place(1, 2), "touches Red's cell");
+        assert!(game.place(0, 3));
+        assert!(game.place(9, 9));
+        assert_eq!(game.phase(), Phase::Advancing(3));
+        assert!(!game.place(5, 5));
+
+        for _ in 0..3 {
+            game.step();
+        }
+        assert_eq!(game.phase(), Phase::Placing);
+        assert_eq!(game.turn(), Player::Red);
+        assert_eq!(game.generation(), 3);
+        assert_eq!(game.board.score(), (0, 0));
+        for cell in [(5, 0), (5, 1), (5, 3), (5, 4)] {
+            assert!(game.place(cell.0, cell.1));
+        }
+        assert_eq!(game.phase(), Phase::Running(5));
+        assert_eq!(game.rematch(), Game::new(10, 10, plane, 4, 5).battle(2, 3));
+
+        // On a torus the far column touches the near one.
+        let torus = "torus".parse().unwrap();
+        let mut game = Game::new(10, 10, torus, 4, 5).battle(1, 3);
+        assert!(game.place(0, 0));
+        assert!(!game.place(0, 9), "touches Red's cell across the edge");
+        assert!(game.place(0, 8));
+        assert_eq!(game.rematch().board.topology, torus);
+    }
+}
@@ -0,0 +1,297 @@
+//! A socket for controlling a running window from the shell. Each
+//! connection sends one command as a line of text,
+//!
+//! - `pause` or `resume`,
+//! - `step N`, running N generations while paused, 1 if not given,
+//! - `load FILE`, starting again from an RLE or JSON pattern,
+//! - `save FILE`, writing the board as a JSON grid,
+//! - `rule RULE`, running the board by RULE in B/S notation from now on,
+//!
+//! and is answered with a line of `ok` or `error` followed by what
+//! happened. The socket is a Unix socket, so this only works where there
+//! are those.
+
+use crate::rule::Rule;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// How long a client has to send its command, and to read the answer.
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long `send` waits for the window to answer, which it does once a
+/// frame.
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    Pause,
+    Resume,
+    Step(u64),
+    Load(String),
+    Save(String),
+    Rule(Rule),
+}
+
+impl FromStr for Request {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Request, String> {
+        let s = s.trim();
+        let (command, rest) = s.split_once(' ').unwrap_or((s, ""));
+        let rest = rest.trim();
+        let argument = |what: &str| {
+            if rest.is_empty() {
+                Err(format!("{} needs {}", command, what))
+            } else {
+                Ok(rest.to_string())
+            }
+        };
+        match command {
+            "pause" => Ok(Request::Pause),
+            "resume" => Ok(Request::Resume),
+            "step" if rest.is_empty() => Ok(Request::Step(1)),
+            "step" => match rest.parse() {
+                Ok(0) | Err(_) => Err(format!(
+                    "step needs a number of generations, not '{}'",
+                    rest
+                )),
+                Ok(n) => Ok(Request::Step(n)),
+            },
+            "load" => argument("a file").map(Request::Load),
+            "save" => argument("a file").map(Request::Save),
+            "rule" => Ok(Request::Rule(argument("a rule")?.parse()?)),
+            _ => Err(format!(
+                "unknown command '{}', use pause, resume, step, load, save or rule",
+                command
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    /// The line sent for the command.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Request::Pause => write!(f, "pause"),
+            Request::Resume => write!(f, "resume"),
+            Request::Step(n) => write!(f, "step {}", n),
+            Request::Load(path) => write!(f, "load {}", path),
+            Request::Save(path) => write!(f, "save {}", path),
+            Request::Rule(rule) => write!(f, "rule {}", rule),
+        }
+    }
+}
+
+/// Where the window listens, and `send` sends to, unless told otherwise.
+pub fn default_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("conways_game_of_life.sock")
+}
+
+#[cfg(not(unix))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the control socket needs Unix sockets",
+    )
+}
+
+/// Reads the first line of `stream`, without its line ending.
+fn read_line<R: io::Read>(stream: R) -> io::Result<String> {
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(line.trim_end().to_string())
+}
+
+#[cfg(unix)]
+pub struct Listener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Listener {
+    /// Listens on the socket at `path`, replacing one left behind by a
+    /// window that has gone, but not one still listening.
+    pub fn bind(path: &Path) -> io::Result<Listener> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "another window is listening there",
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Listener {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The commands sent since the last poll, or why they couldn't be
+    /// understood, each with the connection to answer on.
+    pub fn poll(&mut self) -> Vec<(Result<Request, String>, Connection)> {
+        let mut requests = Vec::new();
+        while let Ok((stream, _)) = self.listener.accept() {
+            let line = stream
+                .set_nonblocking(false)
+                .and_then(|()| stream.set_read_timeout(Some(TIMEOUT)))
+                .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)))
+                .and_then(|()| read_line(&stream));
+            match line {
+                // Another window checking whether this one is still here.
+                Ok(line) if line.is_empty() => (),
+                Ok(line) => requests.push((line.parse(), Connection { stream })),
+                Err(e) => eprintln!("could not read a command: {}", e),
+            }
+        }
+        requests
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The connection a command came in on, to answer it down.
+#[cfg(unix)]
+pub struct Connection {
+    stream: UnixStream,
+}
+
+#[cfg(unix)]
+impl Connection {
+    /// Answers with what the command did or why it couldn't. A client
+    /// that has gone away by now doesn't want to know, so errors are
+    /// ignored.
+    pub fn reply(mut self, answer: Result<String, String>) {
+        let _ = match answer {
+            Ok(message) => writeln!(self.stream, "ok {}", message),
+            Err(message) => writeln!(self.stream, "error {}", message),
+        };
+    }
+}
+
+/// Sends `request` to the window listening at `path`, giving its answer.
+#[cfg(unix)]
+pub fn send(path: &Path, request: &Request) -> io::Result<Result<String, String>> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(ANSWER_TIMEOUT))?;
+    writeln!(stream, "{}", request)?;
+    let answer = read_line(&stream)?;
+    match answer.split_once(' ').unwrap_or((&answer, "")) {
+        ("ok", message) => Ok(Ok(message.to_string())),
+        ("error", message) => Ok(Err(message.to_string())),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected answer '{}'", answer),
+        )),
+    }
+}
+
+#[cfg(not(unix))]
+pub struct Listener {
+    path: PathBuf,
+}
+
+#[cfg(not(unix))]
+impl Listener {
+    pub fn bind(_path: &Path) -> io::Result<Listener> {
+        Err(unsupported())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn poll(&mut self) -> Vec<(Result<Request, String>, Connection)> {
+        Vec::new()
+    }
+}
+
+#[cfg(not(unix))]
+pub struct Connection;
+
+#[cfg(not(unix))]
+impl Connection {
+    pub fn reply(self, _answer: Result<String, String>) {}
+}
+
+#[cfg(not(unix))]
+pub fn send(_path: &Path, _request: &Request) -> io::Result<Result<String, String>> {
+    Err(unsupported())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("pause".parse(), Ok(Request::Pause));
+        assert_eq!(" resume\n".parse(), Ok(Request::Resume));
+        assert_eq!("step".parse(), Ok(Request::Step(1)));
+        assert_eq!("step 40".parse(), Ok(Request::Step(40)));
+        assert_eq!(
+            "load my patterns/gun.rle".parse(),
+            Ok(Request::Load("my patterns/gun.rle".to_string()))
+        );
+        assert_eq!(
+            "save board.json".parse(),
+            Ok(Request::Save("board.json".to_string()))
+        );
+        assert_eq!(
+            "rule b36/s23".parse(),
+            Ok(Request::Rule("B36/S23".parse().unwrap()))
+        );
+        assert!("rule B0/S8".parse::<Request>().is_err());
+
+        for request in &[Request::Step(40), Request::Load("a b.rle".to_string())] {
+            assert_eq!(request.to_string().parse().as_ref(), Ok(request));
+        }
+        assert!("step 0".parse::<Request>().is_err());
+        assert!("step many".parse::<Request>().is_err());
+        assert!("load".parse::<Request>().is_err());
+        assert!("jump".parse::<Request>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn send_test() {
+        let path = std::env::temp_dir().join(format!("life-ipc-test-{}.sock", std::process::id()));
+        let mut listener = Listener::bind(&path).unwrap();
+        assert!(Listener::bind(&path).is_err());
+        let client_path = path.clone();
+        let client = std::thread::spawn(move || send(&client_path, &Request::Step(3)).unwrap());
+
+        let mut requests = Vec::new();
+        while requests.is_empty() {
+            requests = listener.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let (request, connection) = requests.pop().unwrap();
+        assert_eq!(request, Ok(Request::Step(3)));
+        connection.reply(Ok("generation 3".to_string()));
+
+        assert_eq!(client.join().unwrap(), Ok("generation 3".to_string()));
+        drop(listener);
+        assert!(!path.exists());
+    }
+}
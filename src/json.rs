@@ -0,0 +1,181 @@
+//! The JSON representation of a grid, for web frontends and scripts:
+//!
+//! ```json
+//! {"width": 3, "height": 3, "rule": "B3/S23", "cells": [[0, 1], [1, 1], [2, 1]]}
+//! ```
+//!
+//! `cells` lists the living cells as `[row, column]` pairs counted from the
+//! top left corner; every other cell is dead. When streamed, each document
+//! also carries a `generation` number. Boards saved to files are sealed with
+//! a checksum, checked when they are read back.
+
+use crate::checksum;
+use crate::grid::{check_rule, Grid, RULE};
+use crate::rule::Rule;
+use serde::{Deserialize, Serialize};
+
+/// A grid as it appears in JSON, for embedding in other documents.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct JsonGrid {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    generation: Option<u64>,
+    width: usize,
+    height: usize,
+    rule: String,
+    cells: Vec<(usize, usize)>,
+}
+
+impl JsonGrid {
+    pub fn new(grid: &Grid, generation: Option<u64>) -> JsonGrid {
+        JsonGrid {
+            generation,
+            width: grid.width(),
+            height: grid.height(),
+            rule: RULE.to_string(),
+            cells: grid.living_cells(),
+        }
+    }
+
+    /// The grid as run by `rule` rather than Conway's.
+    pub fn with_rule(mut self, rule: Rule) -> JsonGrid {
+        self.rule = rule.to_string();
+        self
+    }
+
+    pub fn generation(&self) -> Option<u64> {
+        self.generation
+    }
+
+    pub fn rule(&self) -> Result<Rule, String> {
+        check_rule(&self.rule)
+    }
+
+    /// The grid, checking its rule and that its cells are inside it.
+    pub fn to_grid(&self) -> Result<Grid, String> {
+        check_rule(&self.rule)?;
+
+        if let Some(&(row, col)) = self
+            .cells
+            .iter()
+            .find(|&&(row, col)| row >= self.height || col >= self.width)
+        {
+            return Err(format!(
+                "cell [{}, {}] is outside the {}x{} grid",
+                row, col, self.width, self.height
+            ));
+        }
+
+        Ok(Grid::seed_rectangle(
+            self.width,
+            self.height,
+            self.cells.clone(),
+        ))
+    }
+}
+
+pub fn to_json(grid: &Grid, generation: Option<u64>) -> String {
+    to_json_under(grid, generation, Rule::default())
+}
+
+/// The JSON of a grid run by `rule`.
+pub fn to_json_under(grid: &Grid, generation: Option<u64>, rule: Rule) -> String {
+    serde_json::to_string(&JsonGrid::new(grid, generation).with_rule(rule))
+        .expect("a grid always serializes")
+}
+
+/// The JSON of a grid run by `rule`, sealed with its checksum for saving.
+pub fn to_sealed_json(grid: &Grid, generation: Option<u64>, rule: Rule) -> String {
+    checksum::to_sealed_json(&JsonGrid::new(grid, generation).with_rule(rule))
+}
+
+/// The grid of `text`, if it runs by Conway's rule.
+pub fn from_json(text: &str) -> Result<Grid, String> {
+    let (grid, rule) = from_json_with_rule(text)?;
+    if !rule.is_conway() {
+        return Err(format!(
+            "unsupported rule '{}', only {} is supported here",
+            rule, RULE
+        ));
+    }
+    Ok(grid)
+}
+
+/// The grid of `text` and the rule it runs by, checking its checksum if it
+/// has one. Errors in the JSON itself say the line and column they were
+/// found at.
+pub fn from_json_with_rule(text: &str) -> Result<(Grid, Rule), String> {
+    let json_grid: JsonGrid = serde_json::from_str(text).map_err(|e| {
+        let position = format!(" at line {} column {}", e.line(), e.column());
+        format!(
+            "invalid JSON grid: {} at line {}, col {}",
+            e.to_string().trim_end_matches(&position),
+            e.line(),
+            e.column()
+        )
+    })?;
+    checksum::check(text)?;
+    Ok((json_grid.to_grid()?, json_grid.rule()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let grid = Grid::seed_rectangle(4, 2, vec![(0, 3), (1, 0)]);
+
+        let text = to_json(&grid, None);
+
+        assert_eq!(
+            text,
+            r#"{"width":4,"height":2,"rule":"B3/S23","cells":[[0,3],[1,0]]}"#
+        );
+        assert_eq!(from_json(&text), Ok(grid));
+    }
+
+    #[test]
+    fn generation_test() {
+        let grid = Grid::seed(1, vec![]);
+
+        assert!(to_json(&grid, Some(7)).starts_with(r#"{"generation":7,"#));
+    }
+
+    #[test]
+    fn bad_grid_test() {
+        assert!(from_json(r#"{"width":2,"height":2,"rule":"B3/S23","cells":[[2,0]]}"#).is_err());
+        assert!(from_json(r#"{"width":2,"height":2,"rule":"B2/S","cells":[]}"#).is_err());
+        assert!(from_json(r#"{"width":2,"height":2,"rule":"B0/S","cells":[]}"#).is_err());
+        assert!(from_json("[]").is_err());
+        assert_eq!(
+            from_json("{\"width\": 2,\n \"height\": x}"),
+            Err("invalid JSON grid: expected value at line 2, col 12".to_string())
+        );
+    }
+
+    #[test]
+    fn sealed_test() {
+        let grid = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let seeds: Rule = "B2/S".parse().unwrap();
+
+        let text = to_sealed_json(&grid, Some(4), seeds);
+
+        assert!(text.contains(r#""checksum":""#), "{}", text);
+        assert_eq!(from_json_with_rule(&text), Ok((grid, seeds)));
+        let moved = text.replace("[2,1]", "[2,2]");
+        assert!(from_json_with_rule(&moved)
+            .unwrap_err()
+            .contains("corrupted"));
+    }
+
+    #[test]
+    fn rule_test() {
+        let grid = Grid::seed(2, vec![(1, 1)]);
+        let seeds: Rule = "B2/S".parse().unwrap();
+
+        let text = to_json_under(&grid, None, seeds);
+
+        assert!(text.contains(r#""rule":"B2/S""#), "{}", text);
+        assert_eq!(from_json_with_rule(&text), Ok((grid, seeds)));
+    }
+}
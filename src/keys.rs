@@ -0,0 +1,631 @@
+//! The keyboard controls of the window, kept in one table so that what the
+//! keys do and what the help overlay says they do can't drift apart.
+
+use conways_game_of_life::starters;
+use ggez::event::{KeyCode, KeyMods};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Something a key can be pressed to do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Pause,
+    Step,
+    StepBack,
+    Denser,
+    Sparser,
+    NewSoup,
+    Grow,
+    Shrink,
+    Follow,
+    ZoomToFit,
+    Centre,
+    Clear,
+    NextGalleryEntry,
+    PreviousGalleryEntry,
+    /// Places the classic pattern at this index of the starters.
+    Starter(usize),
+    Graph,
+    Census,
+    Heatmap,
+    Clusters,
+    NextView,
+    PreviousBranch,
+    NextBranch,
+    BranchTree,
+    StatusBar,
+    Theme,
+    Stats,
+    Mute,
+    Particles,
+    Glow,
+    ClusterColours,
+    SaveFavorite,
+    SaveSvg,
+    Share,
+    Screenshot,
+    Record,
+    RestoreSession,
+    Bookmark,
+    PreviousBookmark,
+    NextBookmark,
+    SaveHeatmap,
+    Help,
+    Menu,
+    Quit,
+}
+
+impl Command {
+    /// Every command, in the order the help lists them by default.
+    pub fn all() -> Vec<Command> {
+        let mut commands = vec![
+            Command::Pause,
+            Command::Step,
+            Command::StepBack,
+            Command::Denser,
+            Command::Sparser,
+            Command::NewSoup,
+            Command::Clear,
+            Command::Grow,
+            Command::Shrink,
+            Command::Centre,
+            Command::Follow,
+            Command::ZoomToFit,
+        ];
+        commands.extend((0..starters::len()).map(Command::Starter));
+        commands.extend(vec![
+            Command::NextGalleryEntry,
+            Command::PreviousGalleryEntry,
+            Command::SaveFavorite,
+            Command::Graph,
+            Command::Census,
+            Command::Heatmap,
+            Command::SaveHeatmap,
+            Command::Clusters,
+            Command::NextView,
+            Command::Screenshot,
+            Command::SaveSvg,
+            Command::Share,
+            Command::Record,
+            Command::Bookmark,
+            Command::PreviousBookmark,
+            Command::NextBookmark,
+            Command::PreviousBranch,
+            Command::NextBranch,
+            Command::BranchTree,
+            Command::StatusBar,
+            Command::Theme,
+            Command::Stats,
+            Command::Mute,
+            Command::Particles,
+            Command::Glow,
+            Command::ClusterColours,
+            Command::RestoreSession,
+            Command::Help,
+            Command::Menu,
+            Command::Quit,
+        ]);
+        commands
+    }
+
+    /// The name the command is bound by in the config file.
+    pub fn name(self) -> String {
+        let name = match self {
+            Command::Pause => "pause",
+            Command::Step => "step",
+            Command::StepBack => "step_back",
+            Command::Denser => "denser",
+            Command::Sparser => "sparser",
+            Command::NewSoup => "new_soup",
+            Command::Grow => "grow",
+            Command::Shrink => "shrink",
+            Command::Follow => "follow",
+            Command::ZoomToFit => "zoom_to_fit",
+            Command::Centre => "centre",
+            Command::Clear => "clear",
+            Command::NextGalleryEntry => "next_gallery_entry",
+            Command::PreviousGalleryEntry => "previous_gallery_entry",
+            Command::Starter(index) => return format!("starter_{}", index + 1),
+            Command::Graph => "graph",
+            Command::Census => "census",
+            Command::Heatmap => "heatmap",
+            Command::Clusters => "clusters",
+            Command::NextView => "next_view",
+            Command::PreviousBranch => "previous_branch",
+            Command::NextBranch => "next_branch",
+            Command::BranchTree => "branch_tree",
+            Command::StatusBar => "status_bar",
+            Command::Theme => "theme",
+            Command::Stats => "stats",
+            Command::Mute => "mute",
+            Command::Particles => "particles",
+            Command::Glow => "glow",
+            Command::ClusterColours => "cluster_colours",
+            Command::SaveFavorite => "save_favorite",
+            Command::SaveSvg => "save_svg",
+            Command::Share => "share",
+            Command::Screenshot => "screenshot",
+            Command::Record => "record",
+            Command::RestoreSession => "restore_session",
+            Command::Bookmark => "bookmark",
+            Command::PreviousBookmark => "previous_bookmark",
+            Command::NextBookmark => "next_bookmark",
+            Command::SaveHeatmap => "save_heatmap",
+            Command::Help => "help",
+            Command::Menu => "menu",
+            Command::Quit => "quit",
+        };
+        name.to_string()
+    }
+
+    /// What the command does, for the help overlay.
+    pub fn description(self) -> &'static str {
+        match self {
+            Command::Pause => "pause or resume",
+            Command::Step => "step forward while paused",
+            Command::StepBack => "step back while paused",
+            Command::Denser => "make new soups denser",
+            Command::Sparser => "make new soups sparser",
+            Command::NewSoup => "start a new random soup",
+            Command::Grow => "grow the board",
+            Command::Shrink => "shrink the board",
+            Command::Follow => "follow the action with --infinite",
+            Command::ZoomToFit => "zoom to fit everything alive with --infinite",
+            Command::Centre => "centre the view with --infinite",
+            Command::Clear => "clear the board",
+            Command::NextGalleryEntry => "next starting point in the gallery",
+            Command::PreviousGalleryEntry => "previous starting point in the gallery",
+            Command::Starter(_) => "place a classic pattern",
+            Command::Graph => "show or hide the population graph",
+            Command::Census => "show or hide the census",
+            Command::Heatmap => "switch to or from the heatmap",
+            Command::Clusters => "show or hide the clusters",
+            Command::NextView => "switch between the flat, sphere and isometric views",
+            Command::PreviousBranch => "previous branch of the timeline",
+            Command::NextBranch => "next branch of the timeline",
+            Command::BranchTree => "show or hide the tree of branches",
+            Command::StatusBar => "show or hide the status bar",
+            Command::Theme => "switch between the dark and light themes",
+            Command::Stats => "show or hide the stats and achievements",
+            Command::Mute => "mute or unmute the sound",
+            Command::Particles => "show or hide the effects of births and deaths",
+            Command::Glow => "switch the neon glow on or off",
+            Command::ClusterColours => "colour each cluster of cells its own way",
+            Command::SaveFavorite => "save the board as a favorite",
+            Command::SaveSvg => "save the board as an SVG",
+            Command::Share => "print the board as text to share",
+            Command::Screenshot => "save a screenshot",
+            Command::Record => "start or stop recording an animation",
+            Command::RestoreSession => "restore the last session",
+            Command::Bookmark => "bookmark the generation or remove its bookmark",
+            Command::PreviousBookmark => "jump to the previous bookmark",
+            Command::NextBookmark => "jump to the next bookmark",
+            Command::SaveHeatmap => "save the heatmap as a PNG",
+            Command::Help => "show or hide this help",
+            Command::Menu => "open the settings menu",
+            Command::Quit => "quit",
+        }
+    }
+}
+
+/// A key, along with whether `Ctrl` or `Shift` must be held with it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Key {
+    const fn plain(code: KeyCode) -> Key {
+        Key {
+            code,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    const fn shift(code: KeyCode) -> Key {
+        Key {
+            code,
+            ctrl: false,
+            shift: true,
+        }
+    }
+
+    const fn ctrl(code: KeyCode) -> Key {
+        Key {
+            code,
+            ctrl: true,
+            shift: false,
+        }
+    }
+
+    /// The key as it is written on the keyboard, like `Ctrl+S` or `[`, which
+    /// is also how it is written in the config file.
+    pub fn name(self) -> String {
+        let shifted = SHIFTED_NAMES
+            .iter()
+            .find(|(code, _)| self.shift && *code == self.code);
+        if let (Some((_, name)), false) = (shifted, self.ctrl) {
+            return name.to_string();
+        }
+        let name = KEYS
+            .iter()
+            .find(|(code, _)| *code == self.code)
+            .map_or_else(|| format!("{:?}", self.code), |(_, name)| name.to_string());
+        match (self.ctrl, self.shift) {
+            (true, _) => format!("Ctrl+{}", name),
+            (false, true) => format!("Shift+{}", name),
+            (false, false) => name,
+        }
+    }
+}
+
+/// Every key that can be bound, by the name it is written with in the help
+/// and the config file.
+const KEYS: &[(KeyCode, &str)] = &[
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+    (KeyCode::Key5, "5"),
+    (KeyCode::Key6, "6"),
+    (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"),
+    (KeyCode::Key9, "9"),
+    (KeyCode::Key0, "0"),
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::F1, "F1"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::F11, "F11"),
+    (KeyCode::F12, "F12"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Return, "Return"),
+    (KeyCode::Back, "Backspace"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Escape, "Esc"),
+    (KeyCode::Equals, "="),
+    (KeyCode::Minus, "-"),
+    (KeyCode::Add, "Numpad +"),
+    (KeyCode::Subtract, "Numpad -"),
+    (KeyCode::Multiply, "Numpad *"),
+    (KeyCode::Divide, "Numpad /"),
+    (KeyCode::LBracket, "["),
+    (KeyCode::RBracket, "]"),
+    (KeyCode::Semicolon, ";"),
+    (KeyCode::Apostrophe, "'"),
+    (KeyCode::Comma, ","),
+    (KeyCode::Period, "."),
+    (KeyCode::Slash, "/"),
+    (KeyCode::Backslash, "\\"),
+    (KeyCode::Grave, "`"),
+];
+
+/// Names for keys held with `Shift` that type something else.
+const SHIFTED_NAMES: &[(KeyCode, &str)] = &[(KeyCode::Slash, "?")];
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Command, String> {
+        Command::all()
+            .into_iter()
+            .find(|command| command.name() == s)
+            .ok_or_else(|| format!("unknown command '{}'", s))
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Reads a key written like `Ctrl+S`, `Shift+Tab`, `?` or `F1`, ignoring
+    /// case.
+    fn from_str(s: &str) -> Result<Key, String> {
+        let mut key = Key::plain(KeyCode::Space);
+        let mut name = s.trim();
+        loop {
+            let lower = name.to_ascii_lowercase();
+            if lower.starts_with("ctrl+") && name.len() > 5 {
+                key.ctrl = true;
+                name = &name[5..];
+            } else if lower.starts_with("shift+") && name.len() > 6 {
+                key.shift = true;
+                name = &name[6..];
+            } else {
+                break;
+            }
+        }
+        let matches = |(_, key_name): &&(KeyCode, &str)| key_name.eq_ignore_ascii_case(name);
+        if let Some((code, _)) = SHIFTED_NAMES.iter().find(matches) {
+            key.code = *code;
+            key.shift = true;
+        } else if let Some((code, _)) = KEYS.iter().find(matches) {
+            key.code = *code;
+        } else {
+            return Err(format!("unknown key '{}'", s));
+        }
+        Ok(key)
+    }
+}
+
+/// Which command each key runs, in the order the help lists them.
+pub struct Keymap {
+    bindings: Vec<(Key, Command)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let mut bindings = vec![
+            (Key::plain(KeyCode::Space), Command::Pause),
+            (Key::plain(KeyCode::Right), Command::Step),
+            (Key::plain(KeyCode::Left), Command::StepBack),
+            (Key::plain(KeyCode::Up), Command::Denser),
+            (Key::plain(KeyCode::Down), Command::Sparser),
+            (Key::plain(KeyCode::N), Command::NewSoup),
+            (Key::plain(KeyCode::Delete), Command::Clear),
+            (Key::plain(KeyCode::Equals), Command::Grow),
+            (Key::plain(KeyCode::Add), Command::Grow),
+            (Key::plain(KeyCode::Minus), Command::Shrink),
+            (Key::plain(KeyCode::Subtract), Command::Shrink),
+            (Key::plain(KeyCode::Home), Command::Centre),
+            (Key::plain(KeyCode::F), Command::Follow),
+            (Key::plain(KeyCode::Z), Command::ZoomToFit),
+        ];
+        let starters = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+        for (index, code) in starters.iter().enumerate() {
+            bindings.push((Key::plain(*code), Command::Starter(index)));
+        }
+        bindings.extend(vec![
+            (Key::plain(KeyCode::Tab), Command::NextGalleryEntry),
+            (Key::shift(KeyCode::Tab), Command::PreviousGalleryEntry),
+            (Key::ctrl(KeyCode::S), Command::SaveFavorite),
+            (Key::plain(KeyCode::P), Command::Graph),
+            (Key::plain(KeyCode::C), Command::Census),
+            (Key::plain(KeyCode::H), Command::Heatmap),
+            (Key::plain(KeyCode::E), Command::SaveHeatmap),
+            (Key::plain(KeyCode::L), Command::Clusters),
+            (Key::plain(KeyCode::V), Command::NextView),
+            (Key::plain(KeyCode::F12), Command::Screenshot),
+            (Key::plain(KeyCode::S), Command::SaveSvg),
+            (Key::plain(KeyCode::X), Command::Share),
+            (Key::plain(KeyCode::G), Command::Record),
+            (Key::plain(KeyCode::B), Command::Bookmark),
+            (Key::plain(KeyCode::LBracket), Command::PreviousBookmark),
+            (Key::plain(KeyCode::RBracket), Command::NextBookmark),
+            (Key::plain(KeyCode::PageUp), Command::PreviousBranch),
+            (Key::plain(KeyCode::PageDown), Command::NextBranch),
+            (Key::plain(KeyCode::T), Command::BranchTree),
+            (Key::plain(KeyCode::I), Command::StatusBar),
+            (Key::plain(KeyCode::D), Command::Theme),
+            (Key::plain(KeyCode::A), Command::Stats),
+            (Key::plain(KeyCode::M), Command::Mute),
+            (Key::plain(KeyCode::K), Command::Particles),
+            (Key::plain(KeyCode::O), Command::Glow),
+            (Key::plain(KeyCode::U), Command::ClusterColours),
+            (Key::plain(KeyCode::R), Command::RestoreSession),
+            (Key::plain(KeyCode::F1), Command::Help),
+            (Key::shift(KeyCode::Slash), Command::Help),
+            (Key::plain(KeyCode::Escape), Command::Menu),
+            (Key::ctrl(KeyCode::Q), Command::Quit),
+        ]);
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// Binds each command named in `keys` to the keys listed for it in
+    /// place of its own, as the `keys` of the config file say. A key taken
+    /// for one command stops doing whatever it did before, and a command
+    /// given no keys can't be run from the keyboard at all.
+    pub fn rebind(&mut self, keys: &BTreeMap<String, Vec<String>>) -> Result<(), String> {
+        for (name, names) in keys {
+            let command: Command = name.parse()?;
+            let keys = names
+                .iter()
+                .map(|name| name.parse())
+                .collect::<Result<Vec<Key>, String>>()?;
+            let at = self
+                .bindings
+                .iter()
+                .position(|(_, bound)| *bound == command)
+                .unwrap_or(self.bindings.len());
+            let before = self.bindings[..at]
+                .iter()
+                .filter(|(key, bound)| *bound == command || keys.contains(key))
+                .count();
+            self.bindings
+                .retain(|(key, bound)| *bound != command && !keys.contains(key));
+            let at = at - before;
+            for (offset, key) in keys.into_iter().enumerate() {
+                self.bindings.insert(at + offset, (key, command));
+            }
+        }
+        Ok(())
+    }
+
+    /// The command bound to `code` pressed with `mods`. `Ctrl` has to match,
+    /// but a key that does something else with `Shift` can still be pressed
+    /// with it, as `=` is to type `+`.
+    pub fn command(&self, code: KeyCode, mods: KeyMods) -> Option<Command> {
+        let ctrl = mods.contains(KeyMods::CTRL);
+        let shift = mods.contains(KeyMods::SHIFT);
+        let find = |shift: bool| {
+            self.bindings
+                .iter()
+                .find(|(key, _)| key.code == code && key.ctrl == ctrl && key.shift == shift)
+                .map(|(_, command)| *command)
+        };
+        find(shift).or_else(|| if shift { find(false) } else { None })
+    }
+
+    /// The lines of the help overlay: the keys that do each thing, in the
+    /// order first bound, and what they do.
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        let mut lines: Vec<(String, &'static str)> = Vec::new();
+        for (key, command) in &self.bindings {
+            let description = command.description();
+            match lines.iter_mut().find(|(_, d)| *d == description) {
+                Some((keys, _)) => {
+                    keys.push_str(", ");
+                    keys.push_str(&key.name());
+                }
+                None => lines.push((key.name(), description)),
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_test() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            keymap.command(KeyCode::S, KeyMods::NONE),
+            Some(Command::SaveSvg)
+        );
+        assert_eq!(
+            keymap.command(KeyCode::S, KeyMods::CTRL),
+            Some(Command::SaveFavorite)
+        );
+        assert_eq!(
+            keymap.command(KeyCode::Tab, KeyMods::SHIFT),
+            Some(Command::PreviousGalleryEntry)
+        );
+        assert_eq!(
+            keymap.command(KeyCode::Equals, KeyMods::SHIFT),
+            Some(Command::Grow)
+        );
+        assert_eq!(
+            keymap.command(KeyCode::Key3, KeyMods::NONE),
+            Some(Command::Starter(2))
+        );
+        assert_eq!(keymap.command(KeyCode::N, KeyMods::CTRL), None);
+        assert_eq!(keymap.command(KeyCode::Q, KeyMods::NONE), None);
+    }
+
+    #[test]
+    fn key_test() {
+        let key = |s: &str| s.parse::<Key>().map(Key::name);
+
+        assert_eq!(key("ctrl+s"), Ok("Ctrl+S".to_string()));
+        assert_eq!(key("Shift+Tab"), Ok("Shift+Tab".to_string()));
+        assert_eq!(key("?"), Ok("?".to_string()));
+        assert_eq!(key("numpad +"), Ok("Numpad +".to_string()));
+        assert_eq!(key("f1"), Ok("F1".to_string()));
+        assert!(key("Hyper+A").is_err());
+        assert!(key("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn command_names_test() {
+        for command in Command::all() {
+            assert_eq!(command.name().parse(), Ok(command));
+        }
+        assert_eq!("starter_9".parse(), Ok(Command::Starter(8)));
+        assert!("fly".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn rebind_test() {
+        let mut keymap = Keymap::default();
+        let mut keys = BTreeMap::new();
+        keys.insert(
+            "pause".to_string(),
+            vec!["P".to_string(), "Return".to_string()],
+        );
+        keys.insert("quit".to_string(), vec![]);
+
+        keymap.rebind(&keys).unwrap();
+
+        assert_eq!(
+            keymap.command(KeyCode::P, KeyMods::NONE),
+            Some(Command::Pause)
+        );
+        assert_eq!(keymap.command(KeyCode::Space, KeyMods::NONE), None);
+        assert_eq!(keymap.command(KeyCode::Q, KeyMods::CTRL), None);
+        assert_eq!(
+            keymap.help()[0],
+            ("P, Return".to_string(), "pause or resume")
+        );
+        assert!(!keymap
+            .help()
+            .iter()
+            .any(|(_, description)| *description == "show or hide the population graph"));
+
+        keys.insert("jump".to_string(), vec![]);
+        assert!(keymap.rebind(&keys).is_err());
+    }
+
+    #[test]
+    fn help_test() {
+        let help = Keymap::default().help();
+
+        assert_eq!(help[0], ("Space".to_string(), "pause or resume"));
+        assert!(help.contains(&("=, Numpad +".to_string(), "grow the board")));
+        assert!(help.contains(&(
+            "1, 2, 3, 4, 5, 6, 7, 8, 9".to_string(),
+            "place a classic pattern"
+        )));
+        assert!(help.contains(&("F1, ?".to_string(), "show or hide this help")));
+        assert!(help.contains(&("Ctrl+S".to_string(), "save the board as a favorite")));
+    }
+}
@@ -0,0 +1,54 @@
+//! The engine behind the Game of Life app: the grid and its rules, pattern
+//! files, and tools for analysing how a board evolves.
+
+pub mod animation;
+pub mod apgcode;
+pub mod api;
+pub mod branches;
+pub mod census;
+pub mod chat;
+pub mod checksum;
+pub mod components;
+pub mod compression;
+pub mod cycle;
+pub mod determinism;
+pub mod experiment;
+pub mod explore;
+pub mod fetch;
+pub mod ffi;
+pub mod gallery;
+pub mod grid;
+pub mod heatmap;
+pub mod history;
+pub mod hunt;
+pub mod immigration;
+pub mod ipc;
+pub mod json;
+pub mod lifespan;
+pub mod logging;
+pub mod metrics;
+pub mod particles;
+pub mod plane;
+pub mod projection;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod render;
+pub mod replay;
+pub mod rle;
+pub mod rule;
+pub mod search;
+pub mod share;
+pub mod shared;
+pub mod socket;
+pub mod sound;
+pub mod soup;
+pub mod stability;
+pub mod starters;
+pub mod stats;
+pub mod sweep;
+pub mod text;
+pub mod theme;
+pub mod thumbnail;
+pub mod topology;
+pub mod video;
+pub mod websocket;
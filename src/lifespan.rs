@@ -0,0 +1,511 @@
+//! How long patterns live before they settle, for hunting methuselahs:
+//! small patterns that take a long time to stabilise. A pattern is run the
+//! way a search runs its soups, on an unbounded plane until its population
+//! repeats and its census stays the same, so the oscillators it leaves
+//! changing phase, or the gliders flying off, don't count as life.
+//!
+//! Over many soups, how long they lived and how many cells they left are
+//! summed up as distributions, for comparing rules and densities.
+
+use crate::rle::Pattern;
+use crate::rule::Rule;
+use crate::search::{self, Search, Settled};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A search through soups for the longest lived.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lifespans {
+    pub search: Search,
+    /// How many of the longest lived soups to keep.
+    pub top: usize,
+}
+
+impl Default for Lifespans {
+    fn default() -> Lifespans {
+        Lifespans {
+            search: Search::default(),
+            top: 10,
+        }
+    }
+}
+
+/// How many bins a distribution's histogram has at most.
+pub const HISTOGRAM_BINS: usize = 10;
+
+/// The percentiles a distribution is summed up by.
+const PERCENTILES: [u64; 5] = [10, 25, 50, 75, 90];
+
+/// How many characters the longest bar of a histogram is.
+const BAR_WIDTH: usize = 40;
+
+/// How long one soup or pattern lived.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lived {
+    /// The soup's number, or 0 for a pattern.
+    pub soup: u64,
+    pub settled: Settled,
+}
+
+impl Lived {
+    /// The ordering of the longest lived first, those that hadn't settled
+    /// by the end before any that had, then by soup number.
+    fn rank(&self) -> (bool, Reverse<u64>, u64) {
+        (
+            self.settled.generation.is_some(),
+            Reverse(self.settled.generation.unwrap_or(0)),
+            self.soup,
+        )
+    }
+}
+
+impl fmt::Display for Lived {
+    /// Like `stabilises at generation 1103 with 116 cells: 8 blocks, ...`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.settled.generation {
+            Some(generation) => write!(f, "stabilises at generation {}", generation)?,
+            None => write!(
+                f,
+                "still going after {} generations",
+                search::MOST_GENERATIONS
+            )?,
+        }
+        write!(
+            f,
+            " with {} cells: {}",
+            self.settled.population, self.settled.census
+        )
+    }
+}
+
+/// How long `pattern` lives by `rule`, until it settles into cycles with
+/// periods up to `longest_period`.
+pub fn of_pattern(pattern: &Pattern, rule: Rule, longest_period: u64) -> Lived {
+    let grid = pattern.fitted(1, 1, 0);
+    Lived {
+        soup: 0,
+        settled: search::settle(&grid, rule, longest_period),
+    }
+}
+
+/// A set of counts, such as how long soups lived, to be summed up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Distribution {
+    /// In order, smallest first.
+    values: Vec<u64>,
+}
+
+/// One bar of a histogram: how many values were from `from` up to but not
+/// including `to`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bin {
+    pub from: u64,
+    pub to: u64,
+    pub count: usize,
+}
+
+impl Distribution {
+    pub fn new(mut values: Vec<u64>) -> Distribution {
+        values.sort_unstable();
+        Distribution { values }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.values.is_empty() {
+            return None;
+        }
+        Some(self.values.iter().sum::<u64>() as f64 / self.values.len() as f64)
+    }
+
+    /// The smallest value at least `percent` of them are no bigger than.
+    pub fn percentile(&self, percent: u64) -> Option<u64> {
+        let rank = (percent as usize * self.values.len()).div_ceil(100);
+        self.values.get(rank.max(1) - 1).copied()
+    }
+
+    /// The values counted in up to `bins` bins of the same width, from the
+    /// smallest value to the biggest.
+    pub fn histogram(&self, bins: usize) -> Vec<Bin> {
+        let (min, max) = match (self.values.first(), self.values.last()) {
+            (Some(&min), Some(&max)) => (min, max),
+            _ => return Vec::new(),
+        };
+        let width = (max - min) / bins.max(1) as u64 + 1;
+        let mut histogram: Vec<Bin> = (0..=(max - min) / width)
+            .map(|bin| Bin {
+                from: min + bin * width,
+                to: min + (bin + 1) * width,
+                count: 0,
+            })
+            .collect();
+        for &value in &self.values {
+            histogram[((value - min) / width) as usize].count += 1;
+        }
+        histogram
+    }
+
+    /// The summary and histogram as JSON.
+    pub fn to_json(&self) -> Value {
+        let percentiles: serde_json::Map<String, Value> = PERCENTILES
+            .iter()
+            .map(|&percent| (percent.to_string(), json!(self.percentile(percent))))
+            .collect();
+        let histogram: Vec<Value> = self
+            .histogram(HISTOGRAM_BINS)
+            .iter()
+            .map(|bin| json!({"from": bin.from, "to": bin.to, "count": bin.count}))
+            .collect();
+        json!({
+            "count": self.len(),
+            "mean": self.mean(),
+            "min": self.values.first(),
+            "max": self.values.last(),
+            "percentiles": percentiles,
+            "histogram": histogram,
+        })
+    }
+
+    /// The histogram drawn with a bar of `#` for each bin.
+    pub fn histogram_text(&self) -> String {
+        let histogram = self.histogram(HISTOGRAM_BINS);
+        let most = histogram
+            .iter()
+            .map(|bin| bin.count)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let mut text = String::new();
+        for bin in histogram {
+            let bar = "#".repeat((bin.count * BAR_WIDTH).div_ceil(most));
+            text.push_str(&format!(
+                "{:>8} - {:<8} {:<width$} {}\n",
+                bin.from,
+                bin.to - 1,
+                bar,
+                bin.count,
+                width = BAR_WIDTH
+            ));
+        }
+        text
+    }
+}
+
+impl fmt::Display for Distribution {
+    /// Like `mean 312.4, min 0, 10% 20, 25% 41, 50% 150, 75% 402, 90% 810,
+    /// max 3002`, or `none` if there are no values.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mean, min, max) = match (self.mean(), self.values.first(), self.values.last()) {
+            (Some(mean), Some(min), Some(max)) => (mean, min, max),
+            _ => return write!(f, "none"),
+        };
+        write!(f, "mean {:.1}, min {}", mean, min)?;
+        for &percent in &PERCENTILES {
+            write!(
+                f,
+                ", {}% {}",
+                percent,
+                self.percentile(percent).unwrap_or(0)
+            )?;
+        }
+        write!(f, ", max {}", max)
+    }
+}
+
+/// What a run of soups came to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Survey {
+    /// The longest lived soups, longest first.
+    pub longest: Vec<Lived>,
+    /// The generation each soup that settled settled at.
+    pub lifespans: Distribution,
+    /// The cells each soup left, settled or not.
+    pub populations: Distribution,
+    /// How many soups hadn't settled by `search::MOST_GENERATIONS`.
+    pub unsettled: u64,
+}
+
+/// What one thread's soups came to.
+#[derive(Default)]
+struct Tally {
+    longest: Vec<Lived>,
+    lifespans: Vec<u64>,
+    populations: Vec<u64>,
+    unsettled: u64,
+}
+
+impl Lifespans {
+    /// Whether the soups can be run.
+    pub fn check(&self) -> Result<(), String> {
+        self.search.check()?;
+        if self.top == 0 {
+            return Err("--top must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Runs every soup, telling `progress` how many are done about once a
+    /// second, and gives the `top` longest lived, longest first, with the
+    /// distributions of how long they all lived and what they left.
+    pub fn run<F: FnMut(u64)>(&self, progress: F) -> Survey {
+        let top = self.top;
+        let tallies = self
+            .search
+            .run_with(progress, |tally: &mut Tally, soup, settled| {
+                match settled.generation {
+                    Some(generation) => tally.lifespans.push(generation),
+                    None => tally.unsettled += 1,
+                }
+                tally.populations.push(settled.population as u64);
+                tally.longest.push(Lived {
+                    soup,
+                    settled: settled.clone(),
+                });
+                if tally.longest.len() > 2 * top.max(1) {
+                    tally.longest.sort_by_key(Lived::rank);
+                    tally.longest.truncate(top);
+                }
+            });
+        let mut longest = Vec::new();
+        let (mut lifespans, mut populations, mut unsettled) = (Vec::new(), Vec::new(), 0);
+        for tally in tallies {
+            longest.extend(tally.longest);
+            lifespans.extend(tally.lifespans);
+            populations.extend(tally.populations);
+            unsettled += tally.unsettled;
+        }
+        longest.sort_by_key(Lived::rank);
+        longest.truncate(top);
+        Survey {
+            longest,
+            lifespans: Distribution::new(lifespans),
+            populations: Distribution::new(populations),
+            unsettled,
+        }
+    }
+}
+
+/// A summary of `survey`, then a histogram of how long the soups lived and
+/// another of what they left.
+pub fn summary(survey: &Survey) -> String {
+    let soups = survey.populations.len();
+    let mut text = format!(
+        "{} soups, {} settled\nlifespan          {}\nfinal population  {}\n",
+        soups,
+        survey.lifespans.len(),
+        survey.lifespans,
+        survey.populations
+    );
+    if soups > 0 {
+        text.push_str("\nlifespan\n");
+        text.push_str(&survey.lifespans.histogram_text());
+        text.push_str("\nfinal population\n");
+        text.push_str(&survey.populations.histogram_text());
+    }
+    text
+}
+
+/// `survey` of the soups of `search` as JSON, with what the soups were.
+pub fn to_json(survey: &Survey, search: &Search) -> String {
+    let json = json!({
+        "rule": search.rule.to_string(),
+        "density": search.density,
+        "size": search.size,
+        "seed": search.seed,
+        "soups": search.soups,
+        "unsettled": survey.unsettled,
+        "lifespans": survey.lifespans.to_json(),
+        "populations": survey.populations.to_json(),
+    });
+    serde_json::to_string_pretty(&json).unwrap_or_default()
+}
+
+/// Writes the soup of each of `lived` from `search` to its own RLE file in
+/// `dir`, such as `soup-42.rle`, creating it if need be, and gives the
+/// paths written.
+pub fn save(lived: &[Lived], search: &Search, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+    let mut paths = Vec::new();
+    for lived in lived {
+        let path = dir.join(format!("soup-{}.rle", lived.soup));
+        let pattern = Pattern {
+            rule: search.rule,
+            ..Pattern::from_grid(&search.soup(lived.soup))
+        };
+        let text = format!(
+            "#N soup {} of seed {}\n#C It {}.\n{}",
+            lived.soup,
+            search.seed,
+            lived,
+            pattern.to_rle()
+        );
+        fs::write(&path, text).map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// A table of `lived`, one soup to a row.
+pub fn table(lived: &[Lived]) -> String {
+    let mut table = format!(
+        "{:>10}{:>12}{:>12}  {}\n",
+        "soup", "lifespan", "population", "census"
+    );
+    for lived in lived {
+        let lifespan = lived.settled.generation.map_or_else(
+            || "unsettled".to_string(),
+            |generation| generation.to_string(),
+        );
+        table.push_str(&format!(
+            "{:>10}{:>12}{:>12}  {}\n",
+            lived.soup, lifespan, lived.settled.population, lived.settled.census
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle;
+    use crate::stability::DEFAULT_LONGEST_PERIOD;
+
+    #[test]
+    fn of_pattern_test() {
+        let diehard = rle::parse("6bo$2o$bo3b3o!").unwrap();
+        let lived = of_pattern(&diehard, Rule::default(), DEFAULT_LONGEST_PERIOD);
+        assert_eq!(lived.settled.generation, Some(130));
+        assert_eq!(lived.settled.population, 0);
+        assert_eq!(
+            lived.to_string(),
+            "stabilises at generation 130 with 0 cells: no objects"
+        );
+
+        // The R-pentomino leaves gliders flying off, which don't keep it
+        // from having settled.
+        let r_pentomino = rle::parse("b2o$2o$bo!").unwrap();
+        let lived = of_pattern(&r_pentomino, Rule::default(), DEFAULT_LONGEST_PERIOD);
+        let generation = lived.settled.generation.unwrap();
+        assert!((1103..1200).contains(&generation), "{}", lived);
+        assert_eq!(lived.settled.population, 116);
+        assert!(lived.to_string().contains("6 gliders"), "{}", lived);
+    }
+
+    #[test]
+    fn distribution_test() {
+        let distribution = Distribution::new(vec![30, 10, 20, 40, 0, 100, 50, 60, 70, 80, 90]);
+        assert_eq!(distribution.len(), 11);
+        assert_eq!(distribution.mean(), Some(50.0));
+        assert_eq!(distribution.percentile(50), Some(50));
+        assert_eq!(distribution.percentile(10), Some(10));
+        assert_eq!(distribution.percentile(0), Some(0));
+        assert_eq!(distribution.percentile(100), Some(100));
+        assert_eq!(
+            distribution.to_string(),
+            "mean 50.0, min 0, 10% 10, 25% 20, 50% 50, 75% 80, 90% 90, max 100"
+        );
+
+        let histogram = distribution.histogram(10);
+        assert_eq!(histogram.len(), 10);
+        assert_eq!(
+            histogram[0],
+            Bin {
+                from: 0,
+                to: 11,
+                count: 2
+            }
+        );
+        assert_eq!(histogram.iter().map(|bin| bin.count).sum::<usize>(), 11);
+        assert_eq!(histogram[9].to, 110);
+        let text = distribution.histogram_text();
+        assert_eq!(text.lines().count(), 10);
+        assert!(text.starts_with("       0 - 10       ####"), "{}", text);
+
+        let same = Distribution::new(vec![7, 7, 7]);
+        assert_eq!(
+            same.histogram(10),
+            vec![Bin {
+                from: 7,
+                to: 8,
+                count: 3
+            }]
+        );
+
+        let empty = Distribution::default();
+        assert_eq!(empty.to_string(), "none");
+        assert_eq!(empty.percentile(50), None);
+        assert!(empty.histogram(10).is_empty());
+        assert_eq!(empty.to_json()["mean"], Value::Null);
+        let json = distribution.to_json();
+        assert_eq!(json["percentiles"]["50"], 50);
+        assert_eq!(json["histogram"][0]["count"], 2);
+    }
+
+    #[test]
+    fn run_test() {
+        let lifespans = Lifespans {
+            search: Search {
+                soups: 6,
+                size: 6,
+                seed: 2,
+                threads: 2,
+                ..Search::default()
+            },
+            top: 3,
+        };
+        let survey = lifespans.run(|_| ());
+        let lived = &survey.longest;
+        assert_eq!(lived.len(), 3);
+        assert!(lived
+            .windows(2)
+            .all(|pair| pair[0].settled.generation >= pair[1].settled.generation));
+        let alone = Lifespans {
+            search: Search {
+                threads: 1,
+                ..lifespans.search.clone()
+            },
+            ..lifespans
+        };
+        assert_eq!(alone.run(|_| ()), survey);
+        assert_eq!(survey.populations.len(), 6);
+        assert_eq!(survey.lifespans.len() as u64 + survey.unsettled, 6);
+        assert_eq!(
+            survey.lifespans.percentile(100),
+            lived[0].settled.generation
+        );
+
+        let table = table(lived);
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.starts_with("      soup    lifespan"), "{}", table);
+
+        let dir = std::env::temp_dir().join(format!("lifespan-save-{}", std::process::id()));
+        let paths = save(&lived[..1], &lifespans.search, &dir).unwrap();
+
+        let summary = summary(&survey);
+        assert!(summary.starts_with("6 soups, "), "{}", summary);
+        assert!(summary.contains("\nfinal population  mean "), "{}", summary);
+        let json: Value = serde_json::from_str(&to_json(&survey, &lifespans.search)).unwrap();
+        assert_eq!(json["soups"], 6);
+        assert_eq!(json["rule"], "B3/S23");
+        assert_eq!(json["populations"]["count"], 6);
+        assert_eq!(paths, vec![dir.join(format!("soup-{}.rle", lived[0].soup))]);
+        let soup = rle::parse(&fs::read_to_string(&paths[0]).unwrap()).unwrap();
+        assert_eq!(
+            of_pattern(&soup, soup.rule, DEFAULT_LONGEST_PERIOD).settled,
+            lived[0].settled
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
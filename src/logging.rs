@@ -0,0 +1,230 @@
+//! Logging to stderr through the `log` facade, for finding out what a run
+//! without a window is doing and how long it takes. What is logged is set
+//! by a filter in the form `RUST_LOG` takes: a level for everything, then
+//! levels for the modules whose paths start with a prefix, like
+//! `info,conways_game_of_life::stream=trace`. Spans log how long what they
+//! were held around took, with the fields they were given:
+//!
+//! ```text
+//!    0.412s DEBUG conways_game_of_life: step generation=41 population=212 took 1.208ms
+//! ```
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::Instant;
+
+/// What is logged, from modules whose paths start with each prefix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    /// The level for modules no prefix matches.
+    default: LevelFilter,
+    /// Levels by prefix, the longest matching prefix winning.
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// The filter of `-v` given `verbosity` times: warnings only, then
+    /// info, debug and trace from this crate.
+    pub fn verbosity(verbosity: u8) -> Filter {
+        let level = match verbosity {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        };
+        Filter {
+            default: LevelFilter::Warn,
+            modules: vec![(env!("CARGO_PKG_NAME").to_string(), level)],
+        }
+    }
+
+    /// The most detailed level anything is logged at.
+    pub fn max(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+
+    /// Whether to log at `level` from `target`.
+    pub fn enabled(&self, target: &str, level: Level) -> bool {
+        let matched = self
+            .modules
+            .iter()
+            .filter(|(prefix, _)| {
+                target.starts_with(prefix.as_str())
+                    && target[prefix.len()..]
+                        .chars()
+                        .next()
+                        .is_none_or(|c| c == ':')
+            })
+            .max_by_key(|(prefix, _)| prefix.len());
+        level <= matched.map_or(self.default, |(_, level)| *level)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Filter, String> {
+        let mut filter = Filter {
+            default: LevelFilter::Error,
+            modules: Vec::new(),
+        };
+        let level = |text: &str| {
+            text.trim()
+                .parse::<LevelFilter>()
+                .map_err(|_| format!("unknown log level '{}'", text.trim()))
+        };
+        for directive in s
+            .split(',')
+            .filter(|directive| !directive.trim().is_empty())
+        {
+            match directive.split_once('=') {
+                Some((module, text)) => filter
+                    .modules
+                    .push((module.trim().to_string(), level(text)?)),
+                None => filter.default = level(directive)?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Writes what the filter lets through to stderr, with the seconds since
+/// logging started.
+struct Logger {
+    filter: Filter,
+    start: Instant,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata.target(), metadata.level())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let _ = writeln!(
+            io::stderr().lock(),
+            "{:>8.3}s {:<5} {}: {}",
+            self.start.elapsed().as_secs_f64(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Starts logging by `filter`, for the rest of the run. Anything logged
+/// before it is lost.
+pub fn init(filter: Filter) -> Result<(), String> {
+    let max = filter.max();
+    let logger = Logger {
+        filter,
+        start: Instant::now(),
+    };
+    log::set_boxed_logger(Box::new(logger)).map_err(|e| e.to_string())?;
+    log::set_max_level(max);
+    Ok(())
+}
+
+/// Times what it is held around, logging at its level how long that took
+/// once it is dropped, but only if that level is logged.
+#[must_use = "a span times what happens while it is held"]
+pub struct Span {
+    target: &'static str,
+    level: Level,
+    name: &'static str,
+    /// The fields, and when the span was entered, if it is logged.
+    entered: Option<(String, Instant)>,
+}
+
+impl Span {
+    /// Enters the span `name` in the module `target`, with `fields` such as
+    /// `format_args!("generation={}", generation)`, which are only written
+    /// out if the span is logged.
+    pub fn enter(
+        target: &'static str,
+        level: Level,
+        name: &'static str,
+        fields: fmt::Arguments,
+    ) -> Span {
+        let entered = log::log_enabled!(target: target, level).then(|| {
+            let fields = fields.to_string();
+            log::trace!(target: target, "{} {} started", name, fields);
+            (fields, Instant::now())
+        });
+        Span {
+            target,
+            level,
+            name,
+            entered,
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some((fields, entered)) = &self.entered {
+            let took = entered.elapsed();
+            let space = if fields.is_empty() { "" } else { " " };
+            log::log!(
+                target: self.target,
+                self.level,
+                "{}{}{} took {:.3?}",
+                self.name,
+                space,
+                fields,
+                took
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_test() {
+        let filter: Filter = "info,conways_game_of_life::stream=trace,ggez=off"
+            .parse()
+            .unwrap();
+        assert!(filter.enabled("conways_game_of_life", Level::Info));
+        assert!(!filter.enabled("conways_game_of_life", Level::Debug));
+        assert!(filter.enabled("conways_game_of_life::stream", Level::Trace));
+        assert!(!filter.enabled("conways_game_of_life::streamer", Level::Debug));
+        assert!(!filter.enabled("ggez::graphics", Level::Error));
+        assert_eq!(filter.max(), LevelFilter::Trace);
+
+        let only: Filter = "debug".parse().unwrap();
+        assert!(only.enabled("gfx", Level::Debug) && !only.enabled("gfx", Level::Trace));
+        assert_eq!(
+            "".parse::<Filter>().map(|filter| filter.max()),
+            Ok(LevelFilter::Error)
+        );
+        assert!("loud".parse::<Filter>().is_err());
+        assert!("conways_game_of_life=loud".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn verbosity_test() {
+        let quiet = Filter::verbosity(0);
+        assert!(quiet.enabled("conways_game_of_life::stream", Level::Warn));
+        assert!(!quiet.enabled("conways_game_of_life::stream", Level::Info));
+        let debug = Filter::verbosity(2);
+        assert!(debug.enabled("conways_game_of_life::stream", Level::Debug));
+        assert!(!debug.enabled("conways_game_of_life", Level::Trace));
+        assert!(!debug.enabled("ggez", Level::Info));
+        assert_eq!(Filter::verbosity(9).max(), LevelFilter::Trace);
+    }
+}
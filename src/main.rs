@@ -1,256 +1,3991 @@
-use ggez;
-use ggez::conf::{WindowMode, WindowSetup};
-use ggez::event;
-use ggez::graphics::{clear, draw, present, Color, DrawMode, MeshBuilder, Rect};
+mod achievements;
+mod cli;
+mod config;
+mod favorites;
+mod glow;
+mod i18n;
+mod keys;
+mod menu;
+mod session;
+mod stream;
+mod terminal;
+
+use achievements::{Achievement, Stats};
+use cli::Options;
+use config::Config;
+use conways_game_of_life::animation::{AnimationFormat, FrameStyle, Recording};
+use conways_game_of_life::branches::{Branch, BranchTree};
+use conways_game_of_life::census::Census;
+use conways_game_of_life::chat::{Chat, Edit, RateLimit};
+use conways_game_of_life::components::{self, Components, Tracker};
+use conways_game_of_life::compression;
+use conways_game_of_life::cycle::{Behaviour, CycleDetector, DEFAULT_WINDOW};
+use conways_game_of_life::determinism::{self, SeededRng};
+use conways_game_of_life::experiment::{self, Experiment};
+use conways_game_of_life::gallery::{Gallery, Seed};
+use conways_game_of_life::grid::{self, Grid, State, DEFAULT_DENSITY};
+use conways_game_of_life::heatmap::{Colormap, Heatmap};
+use conways_game_of_life::history::History;
+use conways_game_of_life::immigration::{self, Game, Phase};
+use conways_game_of_life::ipc::{self, Request};
+use conways_game_of_life::json::{self, JsonGrid};
+use conways_game_of_life::lifespan::{self, Lifespans};
+use conways_game_of_life::logging::{self, Filter, Span};
+use conways_game_of_life::particles::{Kind, Particles};
+use conways_game_of_life::plane::{self, Plane};
+use conways_game_of_life::projection::{self, Ages, View};
+use conways_game_of_life::render::{self, Palette};
+use conways_game_of_life::replay::{Action, Player, Replay};
+use conways_game_of_life::rle::{self, Pattern};
+use conways_game_of_life::rule::Rule;
+use conways_game_of_life::shared::{self, Guest, Host};
+use conways_game_of_life::sound;
+use conways_game_of_life::soup::Soup;
+use conways_game_of_life::stability::{Stability, DEFAULT_LONGEST_PERIOD};
+use conways_game_of_life::stats::{Changes, Complexity, PopulationHistory, StatsCsv};
+use conways_game_of_life::theme::Theme;
+use conways_game_of_life::topology::Topology;
+use conways_game_of_life::video::Video;
+use conways_game_of_life::websocket::Server;
+use conways_game_of_life::{apgcode, explore, fetch, hunt, starters, sweep, text, thumbnail};
+use ggez::audio::{self, SoundSource};
+use ggez::conf::{FullscreenType, WindowMode, WindowSetup};
+use ggez::event::{self, KeyCode, KeyMods, MouseButton};
+use ggez::graphics::{
+    clear, draw, drawable_size, present, set_drawable_size, set_screen_coordinates,
+    set_window_title, Align, Color, DrawMode, DrawParam, MeshBuilder, Rect, Text,
+};
+use ggez::input::mouse;
 use ggez::nalgebra as na;
-use ggez::{Context, GameResult};
-use rand;
-use std::time::Duration;
-use std::{fmt, fmt::Write};
-
-#[derive(Clone, Debug, PartialEq)]
-enum State {
-    Alive,
-    Dead,
+use ggez::{timer, Context, GameResult};
+use glow::Glow;
+use i18n::Language;
+use keys::{Command, Keymap};
+use log::Level;
+use menu::{Item, Menu, Settings};
+use rand::seq::SliceRandom;
+use session::Session;
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Height of the strip below the grid used for the generation and
+/// population counters.
+const HUD_HEIGHT: f32 = 30.0;
+
+/// Height of the timeline bar below the HUD.
+const TIMELINE_HEIGHT: f32 = 24.0;
+
+const GENERATIONS_PER_SECOND: u32 = 2;
+
+/// How many generations the population graph looks back over.
+const GRAPH_HISTORY: usize = 200;
+
+/// How many generations restoring the last session stays on offer for.
+const SESSION_OFFER_GENERATIONS: u64 = 20;
+
+/// The longest name a favorite can be given.
+const FAVORITE_NAME_LENGTH: usize = 40;
+
+/// How far the view moves towards where cells are changing each
+/// generation while following them, as a fraction of the way there. Easing
+/// in like this keeps the view from jumping about with every flicker.
+const FOLLOW_EASING: f64 = 0.25;
+
+/// How often the config file is looked at for changes.
+const CONFIG_CHECK: Duration = Duration::from_secs(1);
+
+const TITLE: &str = "Conway's Game of Life";
+
+/// How long the status bar shows an achievement once it is unlocked.
+const ACHIEVEMENT_SHOWN: Duration = Duration::from_secs(5);
+
+/// How long the status bar shows what went wrong loading a file.
+const PROBLEM_SHOWN: Duration = Duration::from_secs(10);
+
+/// How often the window's title is brought up to date.
+const TITLE_EVERY: Duration = Duration::from_secs(1);
+
+/// How many dead cells zooming to fit leaves around the living ones.
+const ZOOM_MARGIN: i64 = 5;
+
+/// The smallest cells zooming out can draw, in pixels.
+const MIN_CELL_SIZE: f32 = 1.0;
+
+/// How fast the sphere view spins, in radians a second.
+const SPIN_SPEED: f32 = 0.3;
+
+/// How tall the cubes of the isometric view are, as a fraction of the width
+/// of a cell.
+const CUBE_HEIGHT: f32 = 0.3;
+
+/// The age after which cells stacked by `--age-height` grow no taller, so
+/// still lifes don't tower over everything.
+const TALLEST_AGE: u32 = 16;
+
+/// The most characters that can be typed into a line of the settings menu.
+const MENU_ENTRY_LENGTH: usize = 40;
+
+/// The height of each line of the help overlay, in pixels.
+const HELP_LINE_HEIGHT: f32 = 20.0;
+
+/// How many cells `+` and `-` grow and shrink the board by each way, which
+/// is also the smallest it can be shrunk to.
+const RESIZE_STEP: usize = 10;
+
+/// How much `Up` and `Down` change the density of new soups by.
+const DENSITY_STEP: f64 = 0.05;
+
+/// The most generations `--kiosk` shows a board for, so guns and puffers
+/// that never stagnate still make way for the next one.
+const KIOSK_LONGEST: u64 = 3000;
+
+/// The pitches of the ticks births and deaths make, in hertz.
+const BIRTH_TICK: f32 = 1320.0;
+const DEATH_TICK: f32 = 330.0;
+
+/// How long each tick lasts, in seconds.
+const TICK_LENGTH: f32 = 0.03;
+
+/// How loud the loudest tick is, kept low so the ticks stay in the
+/// background.
+const TICK_VOLUME: f32 = 0.25;
+
+/// The colour of dead cells under the glow, dark so the glow stands out.
+const GLOW_DEAD: (u8, u8, u8) = (12, 12, 24);
+
+/// The most edits from chat made in one update, so a busy chat doesn't
+/// hold up the window.
+const CHAT_EDITS_PER_UPDATE: usize = 5;
+
+/// The most edits from chat kept waiting, past which more are dropped.
+const MOST_CHAT_EDITS: usize = 200;
+
+/// How many edits each person in chat can ask for in `CHAT_LIMIT_PER`,
+/// past which theirs are dropped.
+const CHAT_LIMIT: usize = 10;
+
+const CHAT_LIMIT_PER: Duration = Duration::from_secs(30);
+
+/// The most particles shown at once by `K`.
+const MOST_PARTICLES: usize = 20_000;
+
+/// How often a note of the population is played with `--sonify`.
+const NOTE_EVERY: Duration = Duration::from_millis(200);
+
+/// How long each note lasts, in seconds, running on into the next so the
+/// tone doesn't break up.
+const NOTE_LENGTH: f32 = 0.3;
+
+/// How loud the loudest note is.
+const NOTE_VOLUME: f32 = 0.4;
+
+/// How loud music from the config file is, unless it says otherwise.
+const MUSIC_VOLUME: f32 = 0.5;
+
+/// The ticks played each generation, louder the more cells are born or die.
+struct Ticks {
+    births: audio::Source,
+    deaths: audio::Source,
+}
+
+impl Ticks {
+    fn new(ctx: &mut Context) -> GameResult<Ticks> {
+        let tick = |ctx: &mut Context, frequency| {
+            let data = audio::SoundData::from_bytes(&sound::tone(frequency, TICK_LENGTH));
+            audio::Source::from_data(ctx, data)
+        };
+        Ok(Ticks {
+            births: tick(ctx, BIRTH_TICK)?,
+            deaths: tick(ctx, DEATH_TICK)?,
+        })
+    }
+
+    fn play(&mut self, changes: Changes) -> GameResult {
+        for (source, count) in &mut [
+            (&mut self.births, changes.births),
+            (&mut self.deaths, changes.deaths),
+        ] {
+            if *count > 0 {
+                source.set_volume(sound::tick_volume(*count) * TICK_VOLUME);
+                source.play_detached()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct MainState {
+    /// The board on screen, which with `--infinite` is the view of `plane`
+    /// at `camera`.
+    grid: Grid,
+    plane: Option<Plane>,
+    /// The row and column of the plane at the top left corner of the view.
+    camera: (i64, i64),
+    /// The point of the plane the centre of the view is drifting to follow
+    /// the changing cells, while following them.
+    follow: Option<(f64, f64)>,
+    /// Where a right button drag of the view started from, and the camera
+    /// position then.
+    pan_from: Option<((f32, f32), (i64, i64))>,
+    /// The width and height in cells of the board the window is sized for.
+    window_cells: (usize, usize),
+    /// Pixels across each cell in the window.
+    cell_size: f32,
+    /// Whether the board is resized to fill the window whenever the window
+    /// is resized.
+    fill_window: bool,
+    generation: u64,
+    paused: bool,
+    /// The generations of the run so far, for stepping backwards and
+    /// scrubbing along the timeline.
+    history: History,
+    /// Whether the timeline handle is being dragged.
+    scrubbing: bool,
+    /// Generations marked with `B` to jump back and forth between.
+    bookmarks: BTreeSet<u64>,
+    /// The other branches of the timeline, started by editing the past.
+    branches: BranchTree,
+    show_branches: bool,
+    population_history: PopulationHistory,
+    show_graph: bool,
+    show_census: bool,
+    show_clusters: bool,
+    show_heatmap: bool,
+    /// What each key does, listed by the help overlay.
+    keymap: Keymap,
+    /// Generations run each second.
+    speed: u32,
+    /// The language the window's text is in.
+    language: Language,
+    /// The config file, so changes to it can be applied straight away.
+    config_watcher: Option<config::Watcher>,
+    /// When the window's title was last changed, and how many generations
+    /// have been run since, to work out how fast the game is going.
+    title_changed: Instant,
+    steps_since_title: u32,
+    show_help: bool,
+    /// The totals kept from game to game, and where they are saved.
+    totals: Stats,
+    totals_path: Option<PathBuf>,
+    show_totals: bool,
+    /// The last achievement unlocked, and when.
+    unlocked: Option<(Achievement, Instant)>,
+    /// What last went wrong loading a file, and when.
+    problem: Option<(String, Instant)>,
+    /// The settings menu, while it is open.
+    menu: Option<Menu>,
+    /// Whether the line of counters below the board is showing.
+    show_hud: bool,
+    /// The cell under the mouse pointer, if it is over the board.
+    cursor: Option<(usize, usize)>,
+    view: View,
+    theme: Theme,
+    /// Drawn in place of the board and its border with `--chroma-key`.
+    chroma_key: Option<(u8, u8, u8)>,
+    /// The game of Immigration being played, with `--versus`.
+    game: Option<Game>,
+    /// How long each cell on screen has been alive, for `--age-height`.
+    ages: Ages,
+    age_height: bool,
+    heatmap: Heatmap,
+    colormap: Colormap,
+    /// Pixels per cell in screenshots and saved heatmaps.
+    scale: u32,
+    /// The census of the generation it was last taken for.
+    census: Option<(u64, Census)>,
+    /// The births and deaths of the last step.
+    changes: Changes,
+    /// How complex the generation it was last measured for is.
+    complexity: Option<(u64, Complexity)>,
+    stats_csv: Option<StatsCsv>,
+    cycle_detector: CycleDetector,
+    /// What the board settled into, once it has repeated.
+    behaviour: Option<Behaviour>,
+    /// The generation at which `behaviour` was found.
+    settled_at: u64,
+    /// Watches for the board settling into cycles up to `settle_period`,
+    /// which it can without ever repeating exactly.
+    stability: Stability,
+    settle_period: u64,
+    /// The generation the board was found to have settled at, if it has.
+    stable_at: Option<u64>,
+    auto_restart: Option<u64>,
+    /// The sounds of births and deaths, unless they couldn't be made.
+    ticks: Option<Ticks>,
+    muted: bool,
+    /// Which cluster is which, while `U` colours them.
+    cluster_colours: Option<Tracker>,
+    /// The neon glow round living cells, while `O` has it on.
+    glow: Option<Glow>,
+    /// Where generations are sent to WebSocket clients, with `--serve`.
+    server: Option<Server>,
+    /// Where others join the board, with `--host`.
+    host: Option<Host>,
+    /// The shared board joined, with `--join`, whose host runs it.
+    guest: Option<Guest>,
+    /// The IRC chat that can edit the board, with `--chat`.
+    chat: Option<Chat>,
+    /// The edits from chat waiting to be made, with who asked for them.
+    chat_edits: VecDeque<(String, Edit)>,
+    chat_limit: RateLimit,
+    /// Where commands from `ctl` come in, with `--control`.
+    control: Option<ipc::Listener>,
+    /// The pops and sparks of births and deaths, while `K` shows them.
+    particles: Option<Particles>,
+    /// The music playing behind the game, from the config file.
+    music: Option<audio::Source>,
+    /// The music the config file asks for, and what is playing, which may
+    /// differ until the next frame starts it.
+    music_wanted: Option<String>,
+    music_playing: Option<String>,
+    music_volume: f32,
+    /// Playing the population as a tone, for `--sonify`.
+    sonify: bool,
+    note_played: Instant,
+    /// The population when the last note was played.
+    sonified_population: usize,
+    /// Running as a screensaver with `--kiosk`: any key or click quits,
+    /// and stagnant boards give way to soups and patterns in turn.
+    kiosk: bool,
+    /// How many boards `--kiosk` has moved on to, which picks whether the
+    /// next is a soup or a pattern.
+    kiosk_boards: u64,
+    /// How the edges of the board are joined.
+    topology: Topology,
+    /// The rule the board runs by.
+    rule: Rule,
+    /// How many cells to grow the board by when living cells reach an edge.
+    auto_expand: Option<usize>,
+    /// The chance of each cell of new random soups being alive.
+    soup: Soup,
+    gallery: Gallery,
+    /// The name being typed for a favorite, while saving one.
+    favorite_name: Option<String>,
+    /// The gallery entry on screen, if the board was started from one.
+    gallery_index: Option<usize>,
+    /// Where new random soups come from, seeded from `--seed`.
+    rng: SeededRng,
+    /// The session so far, for `--record`.
+    recording: Option<Replay>,
+    /// The replay being played back, for `--play`.
+    player: Option<Player>,
+    /// The generations being recorded as an animation, started by `G`,
+    /// `--gif` or `--apng`.
+    animation: Option<Recording>,
+    /// Where the animation goes, from `--gif` or `--apng`, or a timestamped
+    /// name if not given.
+    animation_path: Option<String>,
+    animation_format: AnimationFormat,
+    animation_frames: Option<usize>,
+    frame_style: FrameStyle,
+    /// The video being encoded with `--video`.
+    video: Option<Video>,
+    /// Where every `frames_every`th generation is saved as a PNG, with
+    /// `--frames-dir`.
+    frames_dir: Option<PathBuf>,
+    frames_every: u64,
+    /// The session saved when the game was last closed, while `R` can
+    /// still restore it.
+    saved_session: Option<Session>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct Grid {
-    cells: Vec<Vec<State>>,
-}
+impl MainState {
+    fn new(
+        grid: Grid,
+        options: &Options,
+        stats_csv: Option<StatsCsv>,
+        rng: SeededRng,
+    ) -> GameResult<MainState> {
+        let (width, height) = (grid.width(), grid.height());
+        let mut s = MainState {
+            heatmap: Heatmap::new(grid.width(), grid.height()),
+            ages: Ages::new(0, &grid),
+            window_cells: (grid.width(), grid.height()),
+            cell_size: options.cell_size as f32,
+            fill_window: options.fill_window,
+            plane: if options.infinite {
+                Some(Plane::from_grid(&grid, 0, 0))
+            } else {
+                None
+            },
+            grid,
+            camera: (0, 0),
+            follow: None,
+            pan_from: None,
+            generation: 0,
+            paused: options.empty,
+            history: History::with_memory_mb(options.history_mb),
+            scrubbing: false,
+            bookmarks: BTreeSet::new(),
+            branches: BranchTree::new(),
+            show_branches: false,
+            population_history: PopulationHistory::new(GRAPH_HISTORY),
+            show_graph: false,
+            show_census: false,
+            show_clusters: false,
+            show_heatmap: false,
+            keymap: Keymap::default(),
+            speed: GENERATIONS_PER_SECOND,
+            language: Language::default(),
+            config_watcher: None,
+            title_changed: Instant::now(),
+            steps_since_title: 0,
+            show_help: false,
+            totals: Stats::default(),
+            totals_path: None,
+            show_totals: false,
+            unlocked: None,
+            problem: None,
+            menu: None,
+            show_hud: !options.kiosk,
+            cursor: None,
+            view: options.view,
+            age_height: options.age_height,
+            colormap: options.colormap,
+            theme: options.theme.or_else(Theme::system).unwrap_or_default(),
+            chroma_key: options.chroma_key,
+            game: options.versus.map(|budget| {
+                let game = Game::new(
+                    width,
+                    height,
+                    options.topology,
+                    budget,
+                    options.versus_generations,
+                );
+                match options.battle {
+                    Some(battle) => game.battle(battle.per_turn, battle.between),
+                    None => game,
+                }
+            }),
+            scale: options.scale,
+            census: None,
+            changes: Changes::default(),
+            complexity: None,
+            stats_csv,
+            cycle_detector: CycleDetector::new(DEFAULT_WINDOW),
+            behaviour: None,
+            settled_at: 0,
+            stability: Stability::new(options.settle_period),
+            settle_period: options.settle_period,
+            stable_at: None,
+            auto_restart: options.auto_restart,
+            kiosk: options.kiosk,
+            kiosk_boards: 0,
+            ticks: None,
+            muted: options.mute,
+            cluster_colours: None,
+            glow: None,
+            server: None,
+            host: None,
+            guest: None,
+            chat: None,
+            chat_edits: VecDeque::new(),
+            chat_limit: RateLimit::new(CHAT_LIMIT, CHAT_LIMIT_PER),
+            control: None,
+            particles: None,
+            music: None,
+            music_wanted: None,
+            music_playing: None,
+            music_volume: MUSIC_VOLUME,
+            sonify: options.sonify,
+            note_played: Instant::now(),
+            sonified_population: 0,
+            topology: options.topology,
+            rule: options.rule.unwrap_or_default(),
+            auto_expand: options.auto_expand,
+            soup: options.soup,
+            gallery: gallery(),
+            favorite_name: None,
+            gallery_index: None,
+            rng,
+            recording: None,
+            player: None,
+            saved_session: None,
+            animation: None,
+            animation_path: options.animation.clone(),
+            animation_format: options.animation_format,
+            animation_frames: options.frames,
+            frame_style: stream::frame_style(options),
+            video: None,
+            frames_dir: options.frames_dir.as_ref().map(PathBuf::from),
+            frames_every: options.every,
+        };
+        if let Some(dir) = &s.frames_dir {
+            fs::create_dir_all(dir)?;
+        }
+        s.save_frame();
+        if s.animation_path.is_some() {
+            s.toggle_recording();
+        }
+        s.start()?;
+        Ok(s)
+    }
+
+    /// Records the current grid as the first generation of a new run.
+    fn start(&mut self) -> GameResult {
+        self.remember();
+        self.population_history.record(self.population());
+        self.heatmap.record(&self.grid);
+        self.cycle_detector.observe(self.generation, &self.grid);
+        if let Some(stats_csv) = self.stats_csv.as_mut() {
+            stats_csv.record(self.generation, &self.grid, Changes::default())?;
+            stats_csv.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Tells the player about achievements they have just unlocked, and
+    /// saves the totals straight away so they aren't lost.
+    fn announce(&mut self, unlocked: Vec<Achievement>) {
+        if unlocked.is_empty() {
+            return;
+        }
+        for achievement in &unlocked {
+            println!("achievement unlocked: {}", achievement.description());
+            self.unlocked = Some((*achievement, Instant::now()));
+        }
+        self.save_totals();
+    }
+
+    /// Tells the player what went wrong loading a file, on stderr and in
+    /// the status bar, carrying on as before.
+    fn report(&mut self, message: String) {
+        eprintln!("{}", message);
+        self.problem = Some((message, Instant::now()));
+    }
+
+    fn save_totals(&self) {
+        if let Some(path) = &self.totals_path {
+            if let Err(e) = self.totals.save(path) {
+                eprintln!("could not save the stats to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Puts the run on `plane`, read straight onto it, with the top left of
+    /// the view at `camera`. The board on screen is already that view.
+    fn start_on(&mut self, plane: Plane, camera: (i64, i64)) {
+        self.camera = camera;
+        self.plane = Some(plane);
+    }
+
+    fn restart(&mut self, grid: Grid) -> GameResult {
+        self.restart_from(grid, 0)
+    }
+
+    /// Starts a new run from `grid`, numbering it from `generation`.
+    fn restart_from(&mut self, grid: Grid, generation: u64) -> GameResult {
+        if let Some(plane) = self.plane.as_mut() {
+            *plane = Plane::from_grid(&grid, self.camera.0, self.camera.1);
+        }
+        self.grid = grid;
+        self.generation = generation;
+        self.history.clear();
+        self.bookmarks.clear();
+        self.branches = BranchTree::new();
+        self.population_history = PopulationHistory::new(GRAPH_HISTORY);
+        self.heatmap = Heatmap::new(self.grid.width(), self.grid.height());
+        self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+        self.behaviour = None;
+        self.stability = Stability::new(self.settle_period);
+        self.stable_at = None;
+        self.census = None;
+        self.complexity = None;
+        if let Some(particles) = self.particles.as_mut() {
+            particles.clear();
+        }
+        self.start()
+    }
+
+    fn step(&mut self) -> GameResult {
+        let _span = Span::enter(
+            module_path!(),
+            Level::Debug,
+            "step",
+            format_args!("generation={}", self.generation),
+        );
+        let next_gen = match self.plane.take() {
+            Some(plane) => {
+                let next = plane.next_generation_under(self.rule);
+                if self.follow.is_some() {
+                    self.follow_changes(&plane.difference(&next));
+                }
+                let view = next.view(
+                    self.camera.0,
+                    self.camera.1,
+                    self.grid.width(),
+                    self.grid.height(),
+                );
+                self.plane = Some(next);
+                view
+            }
+            None => self.grid.next_generation_under(self.topology, self.rule),
+        };
+        let changes = Changes::between(&self.grid, &next_gen);
+        self.changes = changes;
+        if let (Some(particles), View::Flat) = (self.particles.as_mut(), self.view) {
+            // Not the board's own generator, so a replay's soups come out
+            // the same whether or not the effects were on.
+            particles.spawn(&self.grid, &next_gen, &mut rand::thread_rng());
+        }
+        if let (Some(ticks), false) = (self.ticks.as_mut(), self.muted) {
+            if let Err(e) = ticks.play(changes) {
+                eprintln!("could not play the sounds of births and deaths: {}", e);
+                self.ticks = None;
+            }
+        }
+        self.grid = match self.expansion(&next_gen) {
+            Some(expanded) => expanded,
+            None => next_gen,
+        };
+        self.generation += 1;
+        let unlocked = self.totals.record_generation();
+        self.announce(unlocked);
+        self.match_heatmap();
+        self.remember();
+        self.population_history.record(self.population());
+        self.heatmap.record(&self.grid);
+        self.ages.update(self.generation, &self.grid);
+        if let Some(animation) = self.animation.as_mut() {
+            animation.capture(&self.grid);
+            if animation.is_full() {
+                self.toggle_recording();
+            }
+        }
+        self.add_video_frame();
+        self.save_frame();
+        if self.generation >= SESSION_OFFER_GENERATIONS {
+            self.saved_session = None;
+        }
+        // A spaceship on a bounded grid will crash into something sooner or
+        // later, so keep watching for what it becomes.
+        if let None | Some(Behaviour::Spaceship { .. }) = self.behaviour {
+            if let Some(cycle) = self.cycle_detector.observe(self.generation, &self.grid) {
+                let behaviour = cycle.behaviour(self.grid.population());
+                if let Behaviour::Spaceship { .. } = behaviour {
+                    self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+                    self.cycle_detector.observe(self.generation, &self.grid);
+                }
+                self.behaviour = Some(behaviour);
+                self.settled_at = self.generation;
+                let unlocked = self.totals.record_settled(cycle.first_seen, behaviour);
+                self.announce(unlocked);
+            }
+        }
+        if self.stable_at.is_none() {
+            let grid = &self.grid;
+            let stable = self
+                .stability
+                .observe(grid.population(), || Census::take(grid));
+            if stable.is_some() {
+                self.stable_at = Some(self.generation);
+            }
+        }
+        if let Some(stats_csv) = self.stats_csv.as_mut() {
+            stats_csv.record(self.generation, &self.grid, changes)?;
+            stats_csv.flush()?;
+        }
+        Ok(())
+    }
+
+    /// `grid` grown with `--auto-expand`, if living cells have reached its
+    /// edge. Frames of an animation or video have to stay one size, so it
+    /// doesn't grow while one is being recorded.
+    fn expansion(&self, grid: &Grid) -> Option<Grid> {
+        if self.animation.is_some() || self.video.is_some() {
+            return None;
+        }
+        grid.expanded(self.auto_expand?)
+    }
+
+    /// Stores the current generation in the history. There is none for an
+    /// `--infinite` plane, whose generations can be any size, so there is
+    /// no stepping back or timeline with one.
+    fn remember(&mut self) {
+        if self.plane.is_none() {
+            self.history.push(self.generation, &self.grid);
+        }
+    }
+
+    /// How many cells are alive, off screen as well as on with `--infinite`.
+    fn population(&self) -> usize {
+        self.plane
+            .as_ref()
+            .map_or_else(|| self.grid.population(), Plane::population)
+    }
+
+    /// Moves the view of the plane so its top left corner is at `row` and
+    /// `col`. The heatmap and cycle detection only cover the view, so they
+    /// start again.
+    fn move_camera(&mut self, row: i64, col: i64) {
+        let plane = match &self.plane {
+            Some(plane) => plane,
+            None => return,
+        };
+        self.camera = (row, col);
+        self.grid = plane.view(row, col, self.grid.width(), self.grid.height());
+        self.heatmap = Heatmap::new(self.grid.width(), self.grid.height());
+        self.heatmap.record(&self.grid);
+        self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+        self.cycle_detector.observe(self.generation, &self.grid);
+        self.behaviour = None;
+        self.stability = Stability::new(self.settle_period);
+        self.stable_at = None;
+        self.census = None;
+        self.complexity = None;
+    }
+
+    /// Starts the heatmap again if going back or to another branch has
+    /// brought back a board of another size.
+    fn match_heatmap(&mut self) {
+        if (self.heatmap.width(), self.heatmap.height()) != (self.grid.width(), self.grid.height())
+        {
+            self.heatmap = Heatmap::new(self.grid.width(), self.grid.height());
+            self.heatmap.record(&self.grid);
+        }
+    }
+
+    /// Sizes the window to fit the board, if it has changed size.
+    fn fit_window(&mut self, ctx: &mut Context) -> GameResult {
+        let cells = (self.grid.width(), self.grid.height());
+        if cells == self.window_cells {
+            return Ok(());
+        }
+        self.window_cells = cells;
+        let (width, height) = window_size(&self.grid, self.cell_size);
+        set_drawable_size(ctx, width, height)?;
+        set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height))
+    }
+
+    /// Eases the view towards the middle of the cells that have just
+    /// changed. The heatmap and cycle detection only cover the view, so they
+    /// start again whenever it moves.
+    fn follow_changes(&mut self, changes: &Plane) {
+        let (centre, target) = match (self.follow, changes.centroid()) {
+            (Some(centre), Some(target)) => (centre, target),
+            _ => return,
+        };
+        let centre = (
+            centre.0 + (target.0 - centre.0) * FOLLOW_EASING,
+            centre.1 + (target.1 - centre.1) * FOLLOW_EASING,
+        );
+        self.follow = Some(centre);
+        let camera = (
+            (centre.0 - self.grid.height() as f64 / 2.0).round() as i64,
+            (centre.1 - self.grid.width() as f64 / 2.0).round() as i64,
+        );
+        if camera != self.camera {
+            self.camera = camera;
+            self.heatmap = Heatmap::new(self.grid.width(), self.grid.height());
+            self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+            self.behaviour = None;
+            self.stability = Stability::new(self.settle_period);
+            self.stable_at = None;
+        }
+    }
+
+    /// Starts following the changing cells from where the view is now, or
+    /// stops.
+    fn toggle_follow(&mut self) {
+        self.follow = match self.follow {
+            Some(_) => None,
+            None => Some((
+                self.camera.0 as f64 + self.grid.height() as f64 / 2.0,
+                self.camera.1 as f64 + self.grid.width() as f64 / 2.0,
+            )),
+        };
+    }
+
+    /// Where to move the view to centre it on everything alive.
+    fn centred_camera(&self) -> Option<(i64, i64)> {
+        let (top, left, bottom, right) = self.plane.as_ref()?.bounds()?;
+        Some((
+            top.midpoint(bottom)
+                .saturating_sub(self.grid.height() as i64 / 2),
+            left.midpoint(right)
+                .saturating_sub(self.grid.width() as i64 / 2),
+        ))
+    }
+
+    /// Where the cell at `row` and `col` of the view is on the plane, if
+    /// the view hasn't gone past its edge there.
+    fn plane_cell(&self, row: usize, col: usize) -> Option<(i64, i64)> {
+        plane::offset(self.camera.0, row).zip(plane::offset(self.camera.1, col))
+    }
+
+    /// Zooms so the view frames everything alive with a margin around it.
+    /// The window keeps its size and shape, so the view gets as many more
+    /// or fewer cells as the cell size shrinks or grows by.
+    fn zoom_to_fit(&mut self) {
+        let (top, left, bottom, right) = match self.plane.as_ref().and_then(Plane::bounds) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let (pixel_width, pixel_height) = (
+            self.grid.width() as f32 * self.cell_size,
+            self.grid.height() as f32 * self.cell_size,
+        );
+        let cells_wide = (right - left + 1 + 2 * ZOOM_MARGIN) as f32;
+        let cells_high = (bottom - top + 1 + 2 * ZOOM_MARGIN) as f32;
+        self.cell_size = (pixel_width / cells_wide)
+            .min(pixel_height / cells_high)
+            .max(MIN_CELL_SIZE);
+        let width = ((pixel_width / self.cell_size) as usize).max(1);
+        let height = ((pixel_height / self.cell_size) as usize).max(1);
+        self.resize(width, height);
+        if let Some((row, col)) = self.centred_camera() {
+            self.move_camera(row, col);
+        }
+    }
+
+    /// The colour a cell is drawn in, which follows the heatmap while that
+    /// is showing.
+    fn cell_colour(&self, row: usize, col: usize) -> Color {
+        if let Some(owner) = self
+            .game
+            .as_ref()
+            .and_then(|game| game.board.owner(row, col))
+        {
+            return rgb(owner.colour());
+        }
+        if self.show_heatmap {
+            let (r, g, b) = self.colormap.colour(self.heatmap.intensity(row, col));
+            return Color::from_rgb(r, g, b);
+        }
+        if let Some(id) = self
+            .cluster_colours
+            .as_ref()
+            .and_then(|tracker| tracker.id_at(row, col))
+        {
+            return rgb(components::colour(id));
+        }
+        let palette = self.theme.palette();
+        let (r, g, b) = match self.grid.cells[row][col] {
+            State::Alive => palette.alive,
+            State::Dead if self.glow.is_some() => GLOW_DEAD,
+            State::Dead => palette.dead,
+        };
+        Color::from_rgb(r, g, b)
+    }
+
+    /// Whether a cell stands out from the board, by being alive or, while
+    /// the heatmap is showing, ever having been.
+    fn is_shown(&self, row: usize, col: usize) -> bool {
+        if self.show_heatmap {
+            self.heatmap.intensity(row, col) > 0.0
+        } else {
+            self.grid.cells[row][col] == State::Alive
+        }
+    }
+
+    /// Draws the glow round the living cells of the flat board, if it's on.
+    fn draw_glow(&mut self, ctx: &mut Context) -> GameResult {
+        let glow = match self.glow.as_mut() {
+            Some(glow) => glow,
+            None => return Ok(()),
+        };
+        let living = self.grid.living_cells();
+        if living.is_empty() {
+            return Ok(());
+        }
+        let colour = rgb(self.theme.palette().alive);
+        let mut builder = MeshBuilder::new();
+        for (row, col) in living {
+            let (x, y) = (col as f32 * self.cell_size, row as f32 * self.cell_size);
+            builder.rectangle(
+                DrawMode::fill(),
+                Rect::new(x, y, self.cell_size, self.cell_size),
+                colour,
+            );
+        }
+        let cells = builder.build(ctx)?;
+        glow.draw(
+            ctx,
+            &cells,
+            DrawParam::new().dest(na::Point2::new(10.0, 10.0)),
+        )
+    }
+
+    /// Moves the particles of births and deaths on since the last frame and
+    /// draws them over the board: a ring spreading out from each birth and
+    /// specks flying off from each death, fading as they go.
+    fn draw_particles(&mut self, ctx: &mut Context) -> GameResult {
+        let particles = match self.particles.as_mut() {
+            Some(particles) => particles,
+            None => return Ok(()),
+        };
+        particles.update(timer::delta(ctx).as_secs_f32());
+        if particles.is_empty() {
+            return Ok(());
+        }
+        let (birth, death) = (self.theme.palette().alive, self.theme.highlight());
+        let mut builder = MeshBuilder::new();
+        for particle in particles.iter() {
+            let centre = na::Point2::new(
+                10.0 + particle.col * self.cell_size,
+                10.0 + particle.row * self.cell_size,
+            );
+            let life = particle.life();
+            match particle.kind {
+                Kind::Pop => {
+                    let mut colour = rgb(birth);
+                    colour.a = life;
+                    let radius = self.cell_size * (1.5 - life);
+                    builder.circle(DrawMode::stroke(1.0), centre, radius, 0.5, colour);
+                }
+                Kind::Spark => {
+                    let mut colour = rgb(death);
+                    colour.a = life;
+                    let radius = (self.cell_size * 0.15).max(1.0);
+                    builder.circle(DrawMode::fill(), centre, radius, 0.5, colour);
+                }
+            }
+        }
+        let mesh = builder.build(ctx)?;
+        draw(ctx, &mesh, (na::Point2::new(0.0, 0.0),))
+    }
+
+    /// Draws the board seen from above its top corner, each cell that stands
+    /// out as a cube on it, stacked taller the longer it has lived with
+    /// `--age-height`. Rows are drawn from the back, so nearer cubes hide
+    /// those behind them.
+    fn draw_isometric(&self, ctx: &mut Context) -> GameResult {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let area_width = width as f32 * self.cell_size;
+        let area_height = height as f32 * self.cell_size;
+        let tallest = if self.age_height {
+            CUBE_HEIGHT * (1.0 + (TALLEST_AGE - 1) as f32 / 4.0)
+        } else {
+            CUBE_HEIGHT
+        };
+        // The board is `across` units wide, each half as wide as a cell's
+        // square.
+        let across = (width + height).max(1) as f32;
+        let unit = (area_width / across).min(area_height / (across / 2.0 + 2.0 * tallest));
+        let origin = (
+            10.0 + (area_width - across * unit) / 2.0 + height as f32 * unit,
+            10.0 + 2.0 * tallest * unit,
+        );
+        let corner = |row: usize, col: usize, lift: f32| {
+            let (x, y) = projection::isometric(row, col);
+            na::Point2::new(origin.0 + x * unit, origin.1 + y * unit - lift)
+        };
+
+        let (r, g, b) = self.theme.palette().dead;
+        let mut builder = MeshBuilder::new();
+        let mut drawn = self.chroma_key.is_none();
+        if drawn {
+            builder.polygon(
+                DrawMode::fill(),
+                &[
+                    corner(0, 0, 0.0),
+                    corner(0, width, 0.0),
+                    corner(height, width, 0.0),
+                    corner(height, 0, 0.0),
+                ],
+                Color::from_rgb(r, g, b),
+            )?;
+        }
+        for row in 0..height {
+            for col in 0..width {
+                if !self.is_shown(row, col) {
+                    continue;
+                }
+                let age = self.ages.age(row, col).clamp(1, TALLEST_AGE);
+                let stack = if self.age_height {
+                    1.0 + (age - 1) as f32 / 4.0
+                } else {
+                    1.0
+                };
+                let lift = 2.0 * CUBE_HEIGHT * stack * unit;
+                let colour = self.cell_colour(row, col);
+                let shade = |amount: f32| {
+                    Color::new(colour.r * amount, colour.g * amount, colour.b * amount, 1.0)
+                };
+                let (top, right) = (corner(row, col, lift), corner(row, col + 1, lift));
+                let (bottom, left) = (corner(row + 1, col + 1, lift), corner(row + 1, col, lift));
+                let ground_right = corner(row, col + 1, 0.0);
+                let ground_bottom = corner(row + 1, col + 1, 0.0);
+                let ground_left = corner(row + 1, col, 0.0);
+                builder.polygon(
+                    DrawMode::fill(),
+                    &[left, bottom, ground_bottom, ground_left],
+                    shade(0.7),
+                )?;
+                builder.polygon(
+                    DrawMode::fill(),
+                    &[bottom, right, ground_right, ground_bottom],
+                    shade(0.5),
+                )?;
+                builder.polygon(DrawMode::fill(), &[top, right, bottom, left], colour)?;
+                drawn = true;
+            }
+        }
+        if !drawn {
+            return Ok(());
+        }
+        let board = builder.build(ctx)?;
+        draw(ctx, &board, (na::Point2::new(0.0, 0.0),))
+    }
+
+    /// Draws the board wrapped round a sphere filling the space the flat
+    /// grid would, turned `rotation` radians about its axis. Only the cells
+    /// that stand out from the globe behind them are drawn, shrinking
+    /// towards its rim.
+    fn draw_sphere(&self, ctx: &mut Context, rotation: f32) -> GameResult {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let area_width = width as f32 * self.cell_size;
+        let area_height = height as f32 * self.cell_size;
+        let centre = (10.0 + area_width / 2.0, 10.0 + area_height / 2.0);
+        let radius = area_width.min(area_height) / 2.0 * 0.95;
+        let cell_side = radius * std::f32::consts::PI / height.max(1) as f32;
+
+        let (r, g, b) = self.theme.palette().dead;
+        let mut builder = MeshBuilder::new();
+        let mut drawn = self.chroma_key.is_none();
+        if drawn {
+            builder.circle(
+                DrawMode::fill(),
+                na::Point2::new(centre.0, centre.1),
+                radius,
+                0.5,
+                Color::from_rgb(r, g, b),
+            );
+        }
+        for row in 0..height {
+            for col in 0..width {
+                if !self.is_shown(row, col) {
+                    continue;
+                }
+                if let Some((x, y, facing)) =
+                    projection::on_sphere(width, height, row, col, rotation)
+                {
+                    let side = (cell_side * facing.sqrt()).max(1.0);
+                    builder.rectangle(
+                        DrawMode::fill(),
+                        Rect::new(
+                            centre.0 + x * radius - side / 2.0,
+                            centre.1 + y * radius - side / 2.0,
+                            side,
+                            side,
+                        ),
+                        self.cell_colour(row, col),
+                    );
+                    drawn = true;
+                }
+            }
+        }
+        if !drawn {
+            return Ok(());
+        }
+        let sphere = builder.build(ctx)?;
+        draw(ctx, &sphere, (na::Point2::new(0.0, 0.0),))
+    }
+
+    /// Applies the config file's settings, the keys on top of the usual
+    /// ones.
+    fn apply_config(&mut self, config: &Config) -> Result<(), String> {
+        let mut keymap = Keymap::default();
+        keymap.rebind(&config.keys)?;
+        self.keymap = keymap;
+        self.speed = config.speed.unwrap_or(GENERATIONS_PER_SECOND);
+        self.language = config.language;
+        if let Some(theme) = config.theme {
+            self.theme = theme;
+        }
+        self.music_wanted = config.music.clone();
+        self.music_volume = config.music_volume.unwrap_or(MUSIC_VOLUME);
+        if let Some(music) = self.music.as_mut() {
+            music.set_volume(self.music_volume);
+        }
+        Ok(())
+    }
+
+    /// Starts the music the config file asks for, if it isn't already
+    /// playing. Music that can't be played is reported once, rather than
+    /// tried again every frame.
+    fn update_music(&mut self, ctx: &mut Context) {
+        if self.music_wanted == self.music_playing {
+            return;
+        }
+        self.music_playing = self.music_wanted.clone();
+        self.music = None;
+        let wanted = match &self.music_wanted {
+            Some(wanted) => wanted,
+            None => return,
+        };
+        let data = if wanted == "ambient" {
+            Ok(sound::ambient_loop())
+        } else {
+            fs::read(wanted).map_err(|e| format!("could not read {}: {}", wanted, e))
+        };
+        let music = data.and_then(|data| {
+            let mut music = audio::Source::from_data(ctx, audio::SoundData::from_bytes(&data))
+                .map_err(|e| format!("could not play {}: {}", wanted, e))?;
+            music.set_repeat(true);
+            music.set_volume(self.music_volume);
+            music
+                .play()
+                .map_err(|e| format!("could not play {}: {}", wanted, e))?;
+            if self.muted {
+                music.pause();
+            }
+            Ok(music)
+        });
+        match music {
+            Ok(music) => self.music = Some(music),
+            Err(message) => eprintln!("{}", message),
+        }
+    }
+
+    /// Applies the config file again if it has changed. A broken config is
+    /// reported and the settings left as they were, to try again once it
+    /// has been fixed.
+    fn reload_config(&mut self) {
+        let changed = match self
+            .config_watcher
+            .as_mut()
+            .and_then(config::Watcher::changed)
+        {
+            Some(changed) => changed,
+            None => return,
+        };
+        match changed.and_then(|config| self.apply_config(&config)) {
+            Ok(()) => println!("applied the changed config file"),
+            Err(message) => self.report(message),
+        }
+    }
+
+    /// Runs the game of Immigration on a generation, once its cells have all
+    /// been placed, and shows the board it leaves.
+    fn step_game(&mut self) {
+        let game = match self.game.as_mut() {
+            Some(game) => game,
+            None => return,
+        };
+        if let Phase::Advancing(_) | Phase::Running(_) = game.phase() {
+            game.step();
+            self.steps_since_title += 1;
+            self.show_game();
+            self.population_history.record(self.population());
+        }
+    }
+
+    /// Puts the game of Immigration's board on screen.
+    fn show_game(&mut self) {
+        if let Some(game) = &self.game {
+            self.grid = game.board.to_grid();
+            self.generation = game.generation();
+        }
+    }
+
+    /// What the game of Immigration is waiting for, and the score, for the
+    /// status bar.
+    fn scoreboard(&self, game: &Game) -> String {
+        let language = self.language;
+        let name = |player: immigration::Player| match player {
+            immigration::Player::Red => language.tr("Red"),
+            immigration::Player::Blue => language.tr("Blue"),
+        };
+        let (red, blue) = game.board.score();
+        let mut text = language.fill(
+            "   {} {} - {} {}",
+            &[
+                &name(immigration::Player::Red),
+                &red,
+                &blue,
+                &name(immigration::Player::Blue),
+            ],
+        );
+        text.push_str(&match game.phase() {
+            Phase::Placing if game.is_battle() => {
+                let turn = game.turn();
+                language.fill(
+                    "   {} places {} more this turn, away from the other's cells, with {} left in all",
+                    &[&name(turn), &game.remaining_this_turn(), &game.remaining(turn)],
+                )
+            }
+            Phase::Placing => {
+                let turn = game.turn();
+                let half = match turn {
+                    immigration::Player::Red => language.tr("left"),
+                    immigration::Player::Blue => language.tr("right"),
+                };
+                language.fill(
+                    "   {} places {} more on the {} half",
+                    &[&name(turn), &game.remaining(turn), &half],
+                )
+            }
+            Phase::Advancing(left) => {
+                language.fill("   {} generations to the next turn", &[&left])
+            }
+            Phase::Running(left) => language.fill("   {} generations to go", &[&left]),
+            Phase::Over => match game.winner() {
+                Some(winner) => language.fill("   {} wins, Return plays again", &[&name(winner)]),
+                None => language.tr("   A draw, Return plays again").to_string(),
+            },
+        });
+        text
+    }
+
+    /// Puts the generation, population and how many generations a second
+    /// are being run in the window's title, once a second, so they can be
+    /// seen with the status bar hidden or the window too small for it.
+    fn update_title(&mut self, ctx: &Context) {
+        let elapsed = self.title_changed.elapsed();
+        if elapsed < TITLE_EVERY {
+            return;
+        }
+        let rate = format!(
+            "{:.1}",
+            self.steps_since_title as f64 / elapsed.as_secs_f64()
+        );
+        let title = self.language.fill(
+            "{} - generation {}, population {}, {} gen/s",
+            &[
+                &self.language.tr(TITLE),
+                &self.generation,
+                &self.population(),
+                &rate,
+            ],
+        );
+        set_window_title(ctx, &title);
+        self.title_changed = Instant::now();
+        self.steps_since_title = 0;
+    }
+
+    /// Plays the next note of the population with `--sonify`, while the
+    /// board is running and sound isn't muted.
+    fn play_note(&mut self, ctx: &mut Context) -> GameResult {
+        if !self.sonify || self.muted || self.paused || self.note_played.elapsed() < NOTE_EVERY {
+            return Ok(());
+        }
+        self.note_played = Instant::now();
+        let population = self.population();
+        let note = sound::Note::new(
+            population,
+            self.sonified_population,
+            self.grid.width() * self.grid.height(),
+        );
+        self.sonified_population = population;
+        let data = audio::SoundData::from_bytes(&sound::tone(note.frequency, NOTE_LENGTH));
+        let mut source = audio::Source::from_data(ctx, data)?;
+        source.set_volume(note.volume * NOTE_VOLUME);
+        source.play_detached()
+    }
+
+    /// The colour of the panels behind text, `alpha` opaque.
+    fn panel_colour(&self, alpha: f32) -> Color {
+        let mut colour = rgb(self.theme.panel());
+        colour.a = alpha;
+        colour
+    }
+
+    /// Shades the whole window, to draw an overlay on top.
+    fn draw_shade(&self, ctx: &mut Context) -> GameResult {
+        let (width, height) = window_size(&self.grid, self.cell_size);
+        let shade = MeshBuilder::new()
+            .rectangle(
+                DrawMode::fill(),
+                Rect::new(0.0, 0.0, width, height),
+                self.panel_colour(0.85),
+            )
+            .build(ctx)?;
+        draw(ctx, &shade, (na::Point2::new(0.0, 0.0),))
+    }
+
+    /// Draws the list of keys and what they do over the whole window, in as
+    /// many columns as it takes to fit them all in.
+    fn draw_help(&self, ctx: &mut Context) -> GameResult {
+        let (_, height) = window_size(&self.grid, self.cell_size);
+        self.draw_shade(ctx)?;
+
+        let lines = self.keymap.help();
+        let per_column = (((height - 20.0) / HELP_LINE_HEIGHT) as usize).max(1);
+        let mut x = 10.0;
+        for column in lines.chunks(per_column) {
+            let keys = column.iter().map(|(keys, _)| keys.as_str());
+            let keys = Text::new(keys.collect::<Vec<_>>().join("\n"));
+            let descriptions = column
+                .iter()
+                .map(|(_, description)| self.language.tr(description));
+            let descriptions = Text::new(descriptions.collect::<Vec<_>>().join("\n"));
+            let keys_width = keys.width(ctx) as f32 + 12.0;
+            draw(
+                ctx,
+                &keys,
+                (na::Point2::new(x, 10.0), rgb(self.theme.text())),
+            )?;
+            draw(
+                ctx,
+                &descriptions,
+                (
+                    na::Point2::new(x + keys_width, 10.0),
+                    rgb(self.theme.text()),
+                ),
+            )?;
+            x += keys_width + descriptions.width(ctx) as f32 + 24.0;
+        }
+        Ok(())
+    }
+
+    /// Draws the stats screen over the window.
+    fn draw_totals(&self, ctx: &mut Context) -> GameResult {
+        self.draw_shade(ctx)?;
+        let text = Text::new(self.totals.lines(self.language).join("\n"));
+        draw(
+            ctx,
+            &text,
+            (na::Point2::new(20.0, 20.0), rgb(self.theme.text())),
+        )
+    }
+
+    /// Draws the settings menu over the window.
+    fn draw_menu(&self, ctx: &mut Context, menu: &Menu) -> GameResult {
+        self.draw_shade(ctx)?;
+        let settings = Settings {
+            rule: self.rule.to_string(),
+            width: self.grid.width(),
+            height: self.grid.height(),
+            topology: self.topology.to_string(),
+            theme: self.theme,
+        };
+        let text = Text::new(menu.lines(&settings, self.language).join("\n"));
+        draw(
+            ctx,
+            &text,
+            (na::Point2::new(20.0, 20.0), rgb(self.theme.text())),
+        )
+    }
+
+    /// Handles a key pressed while the settings menu is open. `Up` and
+    /// `Down` choose a line and `Return` changes it, either by starting to
+    /// type a new value or, for the topology, stepping to the next one.
+    /// `Esc` closes the menu, or stops typing without changing anything.
+    fn menu_key(&mut self, ctx: &mut Context, keycode: KeyCode) {
+        let mut menu = match self.menu.take() {
+            Some(menu) => menu,
+            None => return,
+        };
+        if let Some(text) = menu.editing.as_mut() {
+            match keycode {
+                KeyCode::Escape => menu.editing = None,
+                KeyCode::Back => {
+                    text.pop();
+                }
+                KeyCode::Return | KeyCode::NumpadEnter => {
+                    let text = menu.editing.take().unwrap_or_default();
+                    menu.message = Some(self.change_setting(menu.selected(), &text));
+                }
+                _ => (),
+            }
+            self.menu = Some(menu);
+            return;
+        }
+        match keycode {
+            KeyCode::Escape => return,
+            KeyCode::Up => menu.up(),
+            KeyCode::Down => menu.down(),
+            KeyCode::Return | KeyCode::NumpadEnter => match menu.selected() {
+                Item::Rule => menu.editing = Some(self.rule.to_string()),
+                Item::Size => {
+                    menu.editing = Some(format!("{}x{}", self.grid.width(), self.grid.height()))
+                }
+                Item::Topology | Item::Theme => {
+                    menu.message = Some(self.change_setting(menu.selected(), ""))
+                }
+                Item::Resume => return,
+                Item::Quit => {
+                    event::quit(ctx);
+                    return;
+                }
+            },
+            _ => (),
+        }
+        self.menu = Some(menu);
+    }
+
+    /// Changes the setting on a line of the menu to `text`, saying how it
+    /// went.
+    fn change_setting(&mut self, item: Item, text: &str) -> String {
+        let language = self.language;
+        if item == Item::Theme {
+            // Only the look changes, so it can even be done during a replay.
+            self.theme = self.theme.toggle();
+            return language.fill("the theme is now {}", &[&language.tr(self.theme.name())]);
+        }
+        if self.is_playing_back() {
+            return language.tr("wait for the replay to finish").to_string();
+        }
+        let action = match item {
+            Item::Rule if self.game.is_some() => {
+                return language
+                    .tr("the Immigration Game is played by B3/S23")
+                    .to_string();
+            }
+            Item::Rule => match grid::check_rule(text) {
+                Ok(rule) => Action::SetRule { rule },
+                Err(message) => return message,
+            },
+            Item::Size => match menu::parse_size(text, language) {
+                Ok(_) if self.animation.is_some() || self.video.is_some() => {
+                    return language
+                        .tr("finish recording before resizing the board")
+                        .to_string();
+                }
+                Ok((width, height)) => Action::Resize { width, height },
+                Err(message) => return message,
+            },
+            Item::Topology if self.plane.is_some() => {
+                return language
+                    .tr("the infinite plane has no edges to join")
+                    .to_string();
+            }
+            Item::Topology if self.auto_expand.is_some() => {
+                return language
+                    .tr("--auto-expand only grows a board without joined edges")
+                    .to_string();
+            }
+            Item::Topology => Action::SetTopology {
+                topology: self.topology.next_named(),
+            },
+            Item::Theme | Item::Resume | Item::Quit => return String::new(),
+        };
+        if let Err(e) = self.perform(action) {
+            return language.fill("could not change the setting: {}", &[&e]);
+        }
+        match item {
+            Item::Rule => language.fill("the rule is {}", &[&self.rule]),
+            Item::Size => language.fill(
+                "the board is now {}x{}",
+                &[&self.grid.width(), &self.grid.height()],
+            ),
+            _ => language.fill("the topology is now {}", &[&self.topology]),
+        }
+    }
+
+    /// Draws a note beside the cell under the mouse while paused, saying
+    /// how many of its neighbours are alive and what that makes it next
+    /// generation, to show the rules at work.
+    fn draw_tooltip(&self, ctx: &mut Context) -> GameResult {
+        let (row, col) = match self.cursor {
+            Some(cell) if self.paused => cell,
+            _ => return Ok(()),
+        };
+        let (state, neighbours, (shown_row, shown_col)) = match &self.plane {
+            Some(plane) => {
+                let (row, col) = match self.plane_cell(row, col) {
+                    Some(cell) => cell,
+                    None => return Ok(()),
+                };
+                let state = if plane.is_alive(row, col) {
+                    State::Alive
+                } else {
+                    State::Dead
+                };
+                (state, plane.live_neighbours(row, col), (row, col))
+            }
+            None => (
+                self.grid.cells[row][col].clone(),
+                self.grid.live_neighbours(row, col, self.topology),
+                (row as i64, col as i64),
+            ),
+        };
+        let language = self.language;
+        let fate = match (&state, self.rule.next_state(&state, neighbours)) {
+            (State::Alive, State::Alive) => "survives",
+            (State::Alive, State::Dead) => "dies",
+            (State::Dead, State::Alive) => "is born",
+            (State::Dead, State::Dead) => "stays dead",
+        };
+        let lines = [
+            language.fill("Cell {}, {}", &[&shown_row, &shown_col]),
+            language
+                .tr(match state {
+                    State::Alive => "alive",
+                    State::Dead => "dead",
+                })
+                .to_string(),
+            language.fill("{} live neighbours", &[&neighbours]),
+            language.fill("next generation: {}", &[&language.tr(fate)]),
+        ];
+        let text = Text::new(lines.join("\n"));
+        let (width, height) = text.dimensions(ctx);
+        let (width, height) = (width as f32 + 8.0, height as f32 + 4.0);
+        let (window_width, _) = window_size(&self.grid, self.cell_size);
+        let mut left = 10.0 + (col + 1) as f32 * self.cell_size + 4.0;
+        if left + width > window_width {
+            left = (10.0 + col as f32 * self.cell_size - width - 4.0).max(0.0);
+        }
+        let top = 10.0 + row as f32 * self.cell_size;
+
+        let background = MeshBuilder::new()
+            .rectangle(
+                DrawMode::fill(),
+                Rect::new(left, top, width, height),
+                self.panel_colour(0.85),
+            )
+            .build(ctx)?;
+        draw(ctx, &background, (na::Point2::new(0.0, 0.0),))?;
+        draw(
+            ctx,
+            &text,
+            (
+                na::Point2::new(left + 4.0, top + 2.0),
+                rgb(self.theme.text()),
+            ),
+        )
+    }
+
+    /// Draws the census of the current generation below where the
+    /// population graph goes, taking it afresh when the generation changes.
+    fn draw_census(&mut self, ctx: &mut Context) -> GameResult {
+        if self.census.as_ref().map(|census| census.0) != Some(self.generation) {
+            self.census = Some((self.generation, Census::take(&self.grid)));
+        }
+        let census = match &self.census {
+            Some((_, census)) => census,
+            None => return Ok(()),
+        };
+
+        let mut text = Text::new(census.to_string());
+        text.set_bounds(
+            na::Point2::new(
+                self.grid.width() as f32 * self.cell_size - 30.0,
+                f32::INFINITY,
+            ),
+            Align::Left,
+        );
+        let (width, height) = text.dimensions(ctx);
+        let (left, top) = (20.0, 110.0);
+
+        let background = MeshBuilder::new()
+            .rectangle(
+                DrawMode::fill(),
+                Rect::new(left, top, width as f32 + 8.0, height as f32 + 4.0),
+                self.panel_colour(0.7),
+            )
+            .build(ctx)?;
+        draw(ctx, &background, (na::Point2::new(0.0, 0.0),))?;
+        draw(
+            ctx,
+            &text,
+            (
+                na::Point2::new(left + 4.0, top + 2.0),
+                rgb(self.theme.text()),
+            ),
+        )
+    }
+
+    /// The board and view settings, to be saved on exit.
+    fn session(&self) -> Session {
+        Session {
+            version: session::VERSION,
+            grid: JsonGrid::new(&self.grid, Some(self.generation)),
+            paused: self.paused,
+            show_graph: self.show_graph,
+            show_census: self.show_census,
+            show_clusters: self.show_clusters,
+            show_heatmap: self.show_heatmap,
+            plane: self.plane.as_ref().map(Plane::living_cells),
+            camera: self.camera,
+            topology: self.topology,
+            rule: self.rule,
+            view: self.view,
+            theme: self.theme,
+            speed: Some(self.speed),
+            soup: self.soup,
+        }
+    }
+
+    /// Goes back to the last session, resizing the board to the size it was
+    /// and taking up the whole plane again if it was infinite.
+    fn restore_session(&mut self, session: Session) -> GameResult {
+        self.camera = session.camera;
+        self.follow = None;
+        if let Ok(grid) = session.grid.to_grid() {
+            self.restart_from(grid, session.grid.generation().unwrap_or(0))?;
+        }
+        if let Some(plane) = session.plane() {
+            self.plane = Some(plane);
+        }
+        self.topology = session.topology;
+        self.rule = session.rule;
+        self.view = session.view;
+        self.theme = session.theme;
+        self.speed = session.speed.unwrap_or(self.speed);
+        self.soup = session.soup;
+        self.paused = session.paused;
+        self.show_graph = session.show_graph;
+        self.show_census = session.show_census;
+        self.show_clusters = session.show_clusters;
+        self.show_heatmap = session.show_heatmap;
+        Ok(())
+    }
+
+    /// Records `action` if the session is being recorded, then takes it.
+    fn perform(&mut self, action: Action) -> GameResult {
+        if self.guest.is_some() {
+            return self.ask_host(action);
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.record(self.generation, action);
+        }
+        self.apply(action)
+    }
+
+    fn apply(&mut self, action: Action) -> GameResult {
+        match action {
+            Action::ToggleCell { row, col } => self.toggle_cell(row, col),
+            Action::TogglePause => self.paused = !self.paused,
+            Action::Step => self.step()?,
+            Action::JumpTo { generation } => self.jump_to(generation),
+            Action::SwitchBranch { branch } => self.switch_branch(branch),
+            Action::ToggleGraph => self.show_graph = !self.show_graph,
+            Action::ToggleCensus => self.show_census = !self.show_census,
+            Action::ToggleHeatmap => self.show_heatmap = !self.show_heatmap,
+            Action::NextView => self.view = self.view.next(),
+            Action::SetTopology { topology } => {
+                self.topology = topology;
+                if let Some(game) = self.game.as_mut() {
+                    game.board.topology = topology;
+                }
+                self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+                self.cycle_detector.observe(self.generation, &self.grid);
+                self.behaviour = None;
+                self.stability = Stability::new(self.settle_period);
+                self.stable_at = None;
+            }
+            Action::SetRule { rule } => {
+                self.rule = rule;
+                self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+                self.cycle_detector.observe(self.generation, &self.grid);
+                self.behaviour = None;
+                self.stability = Stability::new(self.settle_period);
+                self.stable_at = None;
+            }
+            Action::ToggleClusters => self.show_clusters = !self.show_clusters,
+            Action::SetDensity { density } => self.soup.density = density,
+            Action::NewSoup => {
+                let soup = self.random_soup();
+                self.restart(soup)?;
+                self.gallery_index = None;
+            }
+            Action::PlaceStarter { index } => {
+                self.place_starter(index)?;
+                self.gallery_index = None;
+            }
+            Action::Clear => {
+                let empty = Grid::seed_rectangle(self.grid.width(), self.grid.height(), vec![]);
+                self.restart(empty)?;
+                self.paused = true;
+                self.gallery_index = None;
+            }
+            Action::ShowGalleryEntry { index } => self.show_gallery_entry(index)?,
+            Action::Resize { width, height } => self.resize(width, height),
+            // Moving the view by hand stops it following the action.
+            Action::MoveCamera { row, col } => {
+                self.follow = None;
+                self.move_camera(row, col);
+            }
+            Action::ToggleFollow => self.toggle_follow(),
+            Action::ZoomToFit => {
+                self.follow = None;
+                self.zoom_to_fit();
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a replay is still being played back, during which the
+    /// controls are ignored.
+    fn is_playing_back(&self) -> bool {
+        self.player
+            .as_ref()
+            .is_some_and(|player| !player.is_finished())
+    }
+
+    /// Joined to a shared board, edits to it are asked of the host, and
+    /// whatever else would change the board is left to the host too, so
+    /// only what is on show is done here.
+    fn ask_host(&mut self, action: Action) -> GameResult {
+        match action {
+            Action::ToggleCell { row, col } => {
+                let alive =
+                    self.grid.cells.get(row).and_then(|cells| cells.get(col)) == Some(&State::Dead);
+                if let Some(guest) = self.guest.as_mut() {
+                    if let Err(e) = guest.set(row, col, alive) {
+                        eprintln!("lost the shared board: {}", e);
+                        self.guest = None;
+                    }
+                }
+                Ok(())
+            }
+            Action::ToggleGraph
+            | Action::ToggleCensus
+            | Action::ToggleHeatmap
+            | Action::ToggleClusters
+            | Action::NextView => self.apply(action),
+            _ => Ok(()),
+        }
+    }
+
+    /// Makes the edits the guests of a shared board have asked for, or
+    /// catches up with the host's board when joined to one.
+    fn take_shared(&mut self) -> GameResult {
+        if let Some(host) = self.host.as_mut() {
+            for (_, (row, col), alive) in host.poll() {
+                let wanted = if alive { State::Alive } else { State::Dead };
+                if self
+                    .grid
+                    .cells
+                    .get(row)
+                    .and_then(|cells| cells.get(col))
+                    .is_some_and(|state| *state != wanted)
+                {
+                    self.perform(Action::ToggleCell { row, col })?;
+                }
+            }
+        }
+        let boards = match self.guest.as_mut().map(Guest::poll) {
+            Some(Ok(boards)) => boards,
+            Some(Err(e)) => {
+                eprintln!("lost the shared board: {}", e);
+                self.guest = None;
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        for (generation, grid) in boards {
+            self.follow_host(generation, grid)?;
+        }
+        Ok(())
+    }
+
+    /// Shows the host's board at `generation`, as the next generation if it
+    /// is one, an edit if it is still the same one, or otherwise a new run.
+    fn follow_host(&mut self, generation: u64, grid: Grid) -> GameResult {
+        let same_size = (grid.width(), grid.height()) == (self.grid.width(), self.grid.height());
+        if same_size && generation == self.generation + 1 {
+            self.grid = grid;
+            self.generation = generation;
+            self.remember();
+            self.population_history.record(self.population());
+            self.heatmap.record(&self.grid);
+            self.ages.update(self.generation, &self.grid);
+        } else if same_size && generation == self.generation {
+            if grid != self.grid {
+                self.edit(|s| s.grid = grid);
+            }
+        } else {
+            self.restart_from(grid, generation)?;
+        }
+        Ok(())
+    }
+
+    /// Tells the others on a shared board where the cursor is.
+    fn share_cursor(&mut self) {
+        if let Some(host) = self.host.as_mut() {
+            host.move_cursor(self.cursor);
+        }
+        if let Some(guest) = self.guest.as_mut() {
+            if let Err(e) = guest.move_cursor(self.cursor) {
+                eprintln!("lost the shared board: {}", e);
+                self.guest = None;
+            }
+        }
+    }
+
+    /// Outlines the cells under everyone else's cursors on a shared board,
+    /// each in their own colour.
+    fn draw_cursors(&self, ctx: &mut Context) -> GameResult {
+        let (cursors, me) = match (&self.host, &self.guest) {
+            (Some(host), _) => (host.cursors(), Some(shared::HOST)),
+            (None, Some(guest)) => (guest.cursors(), guest.user()),
+            (None, None) => return Ok(()),
+        };
+        let mut builder = MeshBuilder::new();
+        let mut any = false;
+        for (&user, &(row, col)) in cursors {
+            if Some(user) == me || row >= self.grid.height() || col >= self.grid.width() {
+                continue;
+            }
+            builder.rectangle(
+                DrawMode::stroke(2.0),
+                Rect::new(
+                    col as f32 * self.cell_size,
+                    row as f32 * self.cell_size,
+                    self.cell_size,
+                    self.cell_size,
+                ),
+                rgb(shared::colour(user)),
+            );
+            any = true;
+        }
+        if !any {
+            return Ok(());
+        }
+        let cursors = builder.build(ctx)?;
+        draw(ctx, &cursors, (na::Point2::new(10.0, 10.0),))
+    }
+
+    /// Queues the edits asked for in chat since the last update, then makes
+    /// the next few of them.
+    fn take_chat_edits(&mut self) {
+        let chat = match self.chat.as_mut() {
+            Some(chat) => chat,
+            None => return,
+        };
+        match chat.poll() {
+            Ok(edits) => {
+                let now = Instant::now();
+                for (nick, edit) in edits {
+                    if self.chat_edits.len() < MOST_CHAT_EDITS && self.chat_limit.allow(&nick, now)
+                    {
+                        self.chat_edits.push_back((nick, edit));
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("lost the chat: {}", e);
+                self.chat = None;
+            }
+        }
+        if self.is_playing_back() || self.game.is_some() {
+            return;
+        }
+        for _ in 0..CHAT_EDITS_PER_UPDATE {
+            let edit = match self.chat_edits.pop_front() {
+                Some((_, edit)) => edit,
+                None => break,
+            };
+            let cells = match edit.cells_on(self.grid.width(), self.grid.height()) {
+                Some(cells) => cells,
+                None => continue,
+            };
+            // Only cells that are dead are toggled, so recordings replay
+            // the edit as the same toggles.
+            for (row, col) in cells {
+                if self.grid.cells[row][col] == State::Dead {
+                    if let Err(e) = self.perform(Action::ToggleCell { row, col }) {
+                        eprintln!("could not make an edit from chat: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Carries out the commands sent to the control socket since the last
+    /// update, answering each with how it went.
+    fn take_commands(&mut self) {
+        let requests = match self.control.as_mut() {
+            Some(control) => control.poll(),
+            None => return,
+        };
+        for (request, connection) in requests {
+            connection.reply(request.and_then(|request| self.take_command(request)));
+        }
+    }
+
+    fn take_command(&mut self, request: Request) -> Result<String, String> {
+        if self.is_playing_back() {
+            return Err("wait for the replay to finish".to_string());
+        }
+        match request {
+            Request::Pause | Request::Resume => {
+                if self.paused != (request == Request::Pause) {
+                    self.perform(Action::TogglePause)
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            Request::Step(n) => {
+                if !self.paused {
+                    return Err("pause before stepping".to_string());
+                }
+                for _ in 0..n {
+                    self.perform(Action::Step).map_err(|e| e.to_string())?;
+                }
+            }
+            Request::Load(path) => {
+                if self.recording.is_some() || self.game.is_some() {
+                    return Err("a pattern can't be loaded while recording or playing".to_string());
+                }
+                let grid = thumbnail::load_pattern(&path).inspect_err(|message| {
+                    self.problem = Some((message.clone(), Instant::now()));
+                })?;
+                let pattern = Pattern::from_grid(&grid);
+                let grid = pattern
+                    .centred(self.grid.width(), self.grid.height())
+                    .ok_or_else(|| {
+                        format!(
+                            "the {}x{} pattern does not fit the grid",
+                            pattern.width, pattern.height
+                        )
+                    })?;
+                self.restart(grid).map_err(|e| e.to_string())?;
+                self.gallery_index = None;
+            }
+            Request::Save(path) => {
+                compression::write(
+                    Path::new(&path),
+                    &json::to_sealed_json(&self.grid, Some(self.generation), self.rule),
+                )
+                .map_err(|e| format!("could not save {}: {}", path, e))?;
+                return Ok(format!("saved generation {} to {}", self.generation, path));
+            }
+            Request::Rule(rule) => {
+                if self.game.is_some() {
+                    return Err("the Immigration Game is played by B3/S23".to_string());
+                }
+                self.perform(Action::SetRule { rule })
+                    .map_err(|e| e.to_string())?;
+                return Ok(format!("the rule is {}", self.rule));
+            }
+        }
+        Ok(format!(
+            "generation {}, population {}{}",
+            self.generation,
+            self.grid.population(),
+            if self.paused { ", paused" } else { "" }
+        ))
+    }
+
+    /// Takes the replay actions due at the current generation, stopping
+    /// after each one while paused so that recorded steps play out one
+    /// update at a time.
+    fn play_back(&mut self) -> GameResult {
+        loop {
+            let (generation, paused) = (self.generation, self.paused);
+            let action = match self
+                .player
+                .as_mut()
+                .and_then(|p| p.next(generation, paused))
+            {
+                Some(action) => action,
+                None => break,
+            };
+            self.apply(action)?;
+            if self.paused {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flips a cell of the current generation.
+    fn toggle_cell(&mut self, row: usize, col: usize) {
+        // Replays are checked against the biggest the board gets, so an
+        // edit can fall outside it while it is smaller.
+        if row >= self.grid.height() || col >= self.grid.width() {
+            return;
+        }
+        self.edit(|s| {
+            s.grid.toggle(row, col);
+            let cell = s.plane_cell(row, col);
+            if let (Some(plane), Some((row, col))) = (s.plane.as_mut(), cell) {
+                plane.toggle(row, col);
+            }
+        });
+    }
+
+    /// Grows or shrinks the board about its centre to `width` by `height`,
+    /// keeping what is on it. With `--infinite` the view grows or shrinks
+    /// instead.
+    fn resize(&mut self, width: usize, height: usize) {
+        self.edit(|s| {
+            let (old_width, old_height) = (s.grid.width(), s.grid.height());
+            s.grid = match &s.plane {
+                Some(plane) => {
+                    let camera = s.camera;
+                    s.camera = (
+                        camera
+                            .0
+                            .saturating_sub((height as i64 - old_height as i64) / 2),
+                        camera
+                            .1
+                            .saturating_sub((width as i64 - old_width as i64) / 2),
+                    );
+                    plane.view(s.camera.0, s.camera.1, width, height)
+                }
+                None => s.grid.resized(width, height),
+            };
+            s.heatmap = Heatmap::new(width, height);
+            s.heatmap.record(&s.grid);
+        });
+    }
+
+    /// Changes the current generation with `change`. If later generations
+    /// have already been run, they are kept on a branch of their own and
+    /// the edit starts a new one.
+    fn edit<F: FnOnce(&mut MainState)>(&mut self, change: F) {
+        if self
+            .history
+            .last_generation()
+            .is_some_and(|last| last > self.generation)
+        {
+            let branch = Branch {
+                history: self.history.clone(),
+                generation: self.generation,
+                bookmarks: self.bookmarks.clone(),
+            };
+            self.branches.fork(branch, self.generation);
+        }
+        change(self);
+        self.remember();
+        self.bookmarks.split_off(&(self.generation + 1));
+        self.population_history.pop();
+        self.population_history.record(self.population());
+        self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+        self.cycle_detector.observe(self.generation, &self.grid);
+        self.behaviour = None;
+        self.stability = Stability::new(self.settle_period);
+        self.stable_at = None;
+        self.census = None;
+        self.complexity = None;
+    }
+
+    /// The `(row, column)` of the cell under a point in the window.
+    fn cell_at(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        if self.view != View::Flat || x < 10.0 || y < 10.0 {
+            return None;
+        }
+        let (row, col) = (
+            ((y - 10.0) / self.cell_size) as usize,
+            ((x - 10.0) / self.cell_size) as usize,
+        );
+        if row < self.grid.height() && col < self.grid.width() {
+            Some((row, col))
+        } else {
+            None
+        }
+    }
+
+    /// Shows the branch at `index` where it was left, parking the current
+    /// one.
+    fn switch_branch(&mut self, index: usize) {
+        let mut branch = Branch {
+            history: mem::replace(&mut self.history, History::new(0)),
+            generation: self.generation,
+            bookmarks: mem::take(&mut self.bookmarks),
+        };
+        self.branches.switch(&mut branch, index);
+        self.history = branch.history;
+        self.bookmarks = branch.bookmarks;
+        self.generation = branch.generation;
+        if let Some(grid) = self.history.get(self.generation) {
+            self.grid = grid;
+        }
+        self.match_heatmap();
+
+        self.population_history = PopulationHistory::new(GRAPH_HISTORY);
+        let first = self.generation.saturating_sub(GRAPH_HISTORY as u64 - 1);
+        for generation in first..=self.generation {
+            if let Some(population) = self.history.population(generation) {
+                self.population_history.record(population);
+            }
+        }
+        self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+        self.cycle_detector.observe(self.generation, &self.grid);
+        self.behaviour = None;
+        self.stability = Stability::new(self.settle_period);
+        self.stable_at = None;
+        self.census = None;
+        self.complexity = None;
+    }
+
+    /// Draws the tree of branches in the top right corner, each indented
+    /// under the one it split off from and the current one marked.
+    fn draw_branches(&self, ctx: &mut Context) -> GameResult {
+        let lines: Vec<String> = (0..self.branches.len())
+            .map(|index| {
+                let marker = if index == self.branches.current() {
+                    ">"
+                } else {
+                    " "
+                };
+                let indent = "  ".repeat(self.branches.depth(index));
+                match self.branches.parent(index) {
+                    Some((_, forked_at)) => self.language.fill(
+                        "{} {}branch {} from generation {}",
+                        &[&marker, &indent, &(index + 1), &forked_at],
+                    ),
+                    None => self.language.fill("{} branch 1", &[&marker]),
+                }
+            })
+            .collect();
+        let text = Text::new(lines.join("\n"));
+        let (width, height) = text.dimensions(ctx);
+        let right = self.grid.width() as f32 * self.cell_size;
+        let (left, top) = ((right - width as f32 - 8.0).max(20.0), 20.0);
+
+        let background = MeshBuilder::new()
+            .rectangle(
+                DrawMode::fill(),
+                Rect::new(left, top, width as f32 + 8.0, height as f32 + 4.0),
+                self.panel_colour(0.7),
+            )
+            .build(ctx)?;
+        draw(ctx, &background, (na::Point2::new(0.0, 0.0),))?;
+        draw(
+            ctx,
+            &text,
+            (
+                na::Point2::new(left + 4.0, top + 2.0),
+                rgb(self.theme.text()),
+            ),
+        )
+    }
+
+    /// Saves a clean render of the board, or of the heatmap when that is
+    /// showing, as a timestamped PNG.
+    fn save_screenshot(&self) {
+        let path = timestamped_file_name("screenshot", "png");
+        let result = if self.show_heatmap {
+            self.heatmap.save_png(&path, self.colormap, self.scale)
+        } else {
+            render::save_png(&self.grid, self.theme.palette(), self.scale, &path)
+        };
+        match result {
+            Ok(()) => println!("saved a screenshot to {}", path),
+            Err(e) => eprintln!("could not save a screenshot to {}: {}", path, e),
+        }
+    }
+
+    /// Sends the current generation to the video, if one is being encoded,
+    /// finishing it once it has `--frames` frames.
+    fn add_video_frame(&mut self) {
+        let video = match self.video.as_mut() {
+            Some(video) => video,
+            None => return,
+        };
+        if let Err(e) = video.add_frame(&self.grid) {
+            eprintln!("could not add to the video: {}", e);
+            self.video = None;
+            return;
+        }
+        if self
+            .animation_frames
+            .is_some_and(|limit| video.frames() >= limit)
+        {
+            self.finish_video();
+        }
+    }
+
+    /// Saves the current generation to the `--frames-dir` if it is one of
+    /// every `--every`.
+    fn save_frame(&self) {
+        let dir = match &self.frames_dir {
+            Some(dir) if self.generation.is_multiple_of(self.frames_every) => dir,
+            _ => return,
+        };
+        let style = self.frame_style;
+        if let Err(e) =
+            render::save_numbered_png(dir, self.generation, &self.grid, style.palette, style.scale)
+        {
+            eprintln!("could not save generation {}: {}", self.generation, e);
+        }
+    }
+
+    fn finish_video(&mut self) {
+        if let Some(video) = self.video.take() {
+            let frames = video.frames();
+            match video.finish() {
+                Ok(()) => println!("encoded {} frames of video", frames),
+                Err(e) => eprintln!("could not finish the video: {}", e),
+            }
+        }
+    }
+
+    /// Starts recording an animation from the current generation, or stops
+    /// and saves the one being recorded.
+    fn toggle_recording(&mut self) {
+        match self.animation.take() {
+            Some(animation) => {
+                let format = self.animation_format;
+                let path = self
+                    .animation_path
+                    .take()
+                    .unwrap_or_else(|| timestamped_file_name("recording", format.extension()));
+                match animation.save(&path, format, self.frame_style) {
+                    Ok(()) => println!("saved {} frames to {}", animation.len(), path),
+                    Err(e) => eprintln!("could not save the recording to {}: {}", path, e),
+                }
+            }
+            None => {
+                let mut animation = Recording::new(self.animation_frames);
+                animation.capture(&self.grid);
+                self.animation = Some(animation);
+            }
+        }
+    }
+
+    /// Bookmarks the current generation, or removes its bookmark.
+    fn toggle_bookmark(&mut self) {
+        if !self.bookmarks.remove(&self.generation) {
+            self.bookmarks.insert(self.generation);
+        }
+    }
+
+    /// The closest bookmark after the current generation, or before it if
+    /// `forwards` is false.
+    fn next_bookmark(&self, forwards: bool) -> Option<u64> {
+        if forwards {
+            self.bookmarks.range(self.generation + 1..).next().copied()
+        } else {
+            self.bookmarks.range(..self.generation).next_back().copied()
+        }
+    }
+
+    /// Jumps to the stored generation under `x` on the timeline.
+    fn scrub_to(&mut self, x: f32) -> GameResult {
+        match self.generation_at(x) {
+            Some(generation) if generation != self.generation => {
+                self.perform(Action::JumpTo { generation })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Shows `generation` from the history, which running on from replaces
+    /// any later generations. Cycle detection starts again from there, as
+    /// that generation has already been seen once.
+    fn jump_to(&mut self, generation: u64) {
+        let grid = match self.history.get(generation) {
+            Some(grid) => grid,
+            None => return,
+        };
+        if generation < self.generation {
+            for _ in generation..self.generation {
+                self.population_history.pop();
+            }
+        } else {
+            for later in self.generation + 1..=generation {
+                if let Some(population) = self.history.population(later) {
+                    self.population_history.record(population);
+                }
+            }
+        }
+        self.grid = grid;
+        self.generation = generation;
+        self.match_heatmap();
+        self.cycle_detector = CycleDetector::new(DEFAULT_WINDOW);
+        self.cycle_detector.observe(self.generation, &self.grid);
+        self.behaviour = None;
+        self.stability = Stability::new(self.settle_period);
+        self.stable_at = None;
+    }
+
+    /// Where the timeline bar is drawn, below the HUD and as wide as the
+    /// grid.
+    fn timeline_rect(&self) -> Rect {
+        let top = self.grid.height() as f32 * self.cell_size + 10.0 + HUD_HEIGHT;
+        Rect::new(
+            10.0,
+            top,
+            self.grid.width() as f32 * self.cell_size,
+            TIMELINE_HEIGHT - 6.0,
+        )
+    }
+
+    /// The stored generation under the horizontal position `x` on the
+    /// timeline.
+    fn generation_at(&self, x: f32) -> Option<u64> {
+        let first = self.history.first_generation()?;
+        let last = self.history.last_generation()?;
+        let rect = self.timeline_rect();
+        let fraction = ((x - rect.x) / rect.w).clamp(0.0, 1.0);
+        Some(first + (fraction * (last - first) as f32).round() as u64)
+    }
+
+    /// Draws the timeline of stored generations, filled up to the one
+    /// shown and with bookmarks marked on it, with the first and last
+    /// stored generations at either end.
+    fn draw_timeline(&self, ctx: &mut Context) -> GameResult {
+        let (first, last) = match (
+            self.history.first_generation(),
+            self.history.last_generation(),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(()),
+        };
+        let rect = self.timeline_rect();
+        let position = |generation: u64| {
+            if last > first {
+                rect.x + rect.w * (generation - first) as f32 / (last - first) as f32
+            } else {
+                rect.right()
+            }
+        };
+        let handle_x = position(self.generation);
+
+        let mut timeline_builder = MeshBuilder::new();
+        timeline_builder.rectangle(DrawMode::fill(), rect, self.panel_colour(0.7));
+        timeline_builder.rectangle(
+            DrawMode::fill(),
+            Rect::new(rect.x, rect.y, handle_x - rect.x, rect.h),
+            rgb(self.theme.progress()),
+        );
+        for &bookmark in self.bookmarks.range(first..=last) {
+            timeline_builder.rectangle(
+                DrawMode::fill(),
+                Rect::new(position(bookmark) - 1.0, rect.y, 2.0, rect.h),
+                rgb(self.theme.highlight()),
+            );
+        }
+        timeline_builder.rectangle(
+            DrawMode::fill(),
+            Rect::new(handle_x - 3.0, rect.y - 2.0, 6.0, rect.h + 4.0),
+            rgb(self.theme.text()),
+        );
+        let timeline = timeline_builder.build(ctx)?;
+        draw(ctx, &timeline, (na::Point2::new(0.0, 0.0),))?;
+
+        let first_label = Text::new(first.to_string());
+        draw(
+            ctx,
+            &first_label,
+            (
+                na::Point2::new(rect.x + 4.0, rect.y + 1.0),
+                rgb(self.theme.text()),
+            ),
+        )?;
+        let last_label = Text::new(last.to_string());
+        let (label_width, _) = last_label.dimensions(ctx);
+        let right = rect.right() - label_width as f32 - 4.0;
+        draw(
+            ctx,
+            &last_label,
+            (na::Point2::new(right, rect.y + 1.0), rgb(self.theme.text())),
+        )
+    }
+
+    /// Clears the board and places the starter at `index` in the middle.
+    fn place_starter(&mut self, index: usize) -> GameResult {
+        let (name, pattern) = match starters::starter(index) {
+            Some(starter) => starter,
+            None => return Ok(()),
+        };
+        match pattern.centred(self.grid.width(), self.grid.height()) {
+            Some(grid) => self.restart(grid),
+            None => {
+                eprintln!(
+                    "the {}x{} {} does not fit the grid, try a bigger --size",
+                    pattern.width, pattern.height, name
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Starts the board from the gallery entry at `index`. Picking a soup
+    /// also makes it how later soups are made, so `N` and `--auto-restart`
+    /// carry on in the same style.
+    fn show_gallery_entry(&mut self, index: usize) -> GameResult {
+        let entry = match self.gallery.get(index) {
+            Some(entry) => entry.clone(),
+            None => return Ok(()),
+        };
+        self.gallery_index = Some(index);
+        if let Seed::Soup(soup) = entry.seed {
+            self.soup = soup;
+        }
+        match entry.grid(self.grid.width(), self.grid.height(), &mut self.rng) {
+            Some(grid) => self.restart(grid),
+            None => {
+                eprintln!(
+                    "the {} does not fit the grid, try a bigger --size",
+                    entry.name
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Handles a key while the name of a favorite is being typed: `Return`
+    /// saves the board under it and adds it to the gallery, `Escape` gives
+    /// up.
+    fn edit_favorite_name(&mut self, keycode: KeyCode) {
+        match keycode {
+            KeyCode::Escape => self.favorite_name = None,
+            KeyCode::Back => {
+                if let Some(name) = self.favorite_name.as_mut() {
+                    name.pop();
+                }
+            }
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                let name = self.favorite_name.take().unwrap_or_default();
+                let dir = match favorites::dir() {
+                    Some(dir) => dir,
+                    None => {
+                        eprintln!("could not find a directory to save favorites in");
+                        return;
+                    }
+                };
+                match favorites::save(&dir, &name, &self.grid) {
+                    Ok(path) => {
+                        println!("saved the board as a favorite in {}", path.display());
+                        self.gallery.add(favorites::entry(name.trim(), &self.grid));
+                    }
+                    Err(message) => eprintln!("{}", message),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// A random soup the size of the board, made the current way.
+    fn random_soup(&mut self) -> Grid {
+        self.soup
+            .generate(self.grid.width(), self.grid.height(), &mut self.rng)
+    }
+
+    /// Moves `--kiosk` on to the next board, a random soup and a random
+    /// pattern from the gallery in turn.
+    fn next_kiosk_board(&mut self) -> GameResult {
+        self.kiosk_boards += 1;
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let patterns: Vec<Grid> = if self.kiosk_boards.is_multiple_of(2) {
+            (0..self.gallery.len())
+                .filter_map(|index| match &self.gallery.get(index)?.seed {
+                    Seed::Pattern(pattern) => pattern.centred(width, height),
+                    Seed::Soup(_) => None,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        // A board too small for any of the patterns gets another soup.
+        let grid = match patterns.choose(&mut self.rng) {
+            Some(grid) => grid.clone(),
+            None => self.random_soup(),
+        };
+        self.restart(grid)
+    }
+
+    /// Whether the board has been extinct, stuck in a cycle of up to
+    /// `--settle-period` or otherwise settled for the `--auto-restart`
+    /// number of generations.
+    fn is_stagnant(&self) -> bool {
+        let stuck_at = match self.behaviour {
+            Some(Behaviour::Extinct) | Some(Behaviour::StillLife) => Some(self.settled_at),
+            Some(Behaviour::Oscillator { period }) if period <= self.settle_period => {
+                Some(self.settled_at)
+            }
+            _ => None,
+        };
+        match (
+            stuck_at.into_iter().chain(self.stable_at).min(),
+            self.auto_restart,
+        ) {
+            (Some(stuck_at), Some(generations)) => self.generation - stuck_at >= generations,
+            _ => false,
+        }
+    }
+
+    /// Draws the population history as a line chart in the top left corner
+    /// of the grid, scaled to the largest population it holds, with how
+    /// complex the current generation is, measured afresh when the
+    /// generation changes.
+    fn draw_population_graph(&mut self, ctx: &mut Context) -> GameResult {
+        if self.complexity.map(|complexity| complexity.0) != Some(self.generation) {
+            let complexity = Complexity::measure(&self.grid, self.changes);
+            self.complexity = Some((self.generation, complexity));
+        }
+        let (left, top, width, height) = (20.0, 20.0, 200.0, 80.0);
+        let background = self.panel_colour(0.7);
+        let line_colour = rgb(self.theme.highlight());
+
+        let mut graph_builder = MeshBuilder::new();
+        graph_builder.rectangle(
+            DrawMode::fill(),
+            Rect::new(left, top, width, height),
+            background,
+        );
+
+        if self.population_history.len() > 1 {
+            let max = self.population_history.max().max(1) as f32;
+            let step = width / (GRAPH_HISTORY - 1) as f32;
+            let points: Vec<na::Point2<f32>> = self
+                .population_history
+                .counts()
+                .enumerate()
+                .map(|(i, count)| {
+                    na::Point2::new(
+                        left + i as f32 * step,
+                        top + height * (1.0 - count as f32 / max),
+                    )
+                })
+                .collect();
+            graph_builder.line(&points, 1.5, line_colour)?;
+        }
+
+        let graph = graph_builder.build(ctx)?;
+        draw(ctx, &graph, (na::Point2::new(0.0, 0.0),))?;
+
+        let complexity = self
+            .complexity
+            .map(|complexity| complexity.1)
+            .unwrap_or_default();
+        let language = self.language;
+        let label = Text::new(
+            [
+                language.fill("max {}", &[&self.population_history.max()]),
+                language.fill("entropy {}", &[&format!("{:.2}", complexity.block_entropy)]),
+                language.fill(
+                    "change {}%",
+                    &[&format!("{:.1}", 100.0 * complexity.change_rate)],
+                ),
+                language.fill("{} bytes deflated", &[&complexity.compressed_size]),
+            ]
+            .join("\n"),
+        );
+        draw(
+            ctx,
+            &label,
+            (
+                na::Point2::new(left + 4.0, top + 2.0),
+                rgb(self.theme.text()),
+            ),
+        )
+    }
+}
+
+impl event::EventHandler for MainState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        self.reload_config();
+        self.take_commands();
+        self.take_chat_edits();
+        self.take_shared()?;
+        self.update_music(ctx);
+        // The host runs a shared board, so there is nothing to step.
+        if self.game.is_some() || self.guest.is_some() {
+            while timer::check_update_time(ctx, self.speed) {
+                if !self.paused {
+                    self.step_game();
+                }
+            }
+            self.update_title(ctx);
+            return Ok(());
+        }
+        while timer::check_update_time(ctx, self.speed) {
+            self.play_back()?;
+            if self.paused || self.scrubbing {
+                continue;
+            }
+            self.step()?;
+            self.steps_since_title += 1;
+            if self.kiosk && (self.is_stagnant() || self.generation >= KIOSK_LONGEST) {
+                self.next_kiosk_board()?;
+            } else if self.is_stagnant() {
+                let soup = self.random_soup();
+                self.restart(soup)?;
+            }
+        }
+        if let Some(server) = self.server.as_mut() {
+            server.publish(self.generation, &self.grid);
+        }
+        if let Some(host) = self.host.as_mut() {
+            host.publish(self.generation, &self.grid);
+        }
+        if let Err(e) = self.play_note(ctx) {
+            eprintln!("could not play the population: {}", e);
+            self.sonify = false;
+        }
+        self.update_title(ctx);
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        if self.kiosk {
+            event::quit(ctx);
+            return;
+        }
+        if self.favorite_name.is_some() {
+            self.edit_favorite_name(keycode);
+            return;
+        }
+        if self.menu.is_some() {
+            self.menu_key(ctx, keycode);
+            return;
+        }
+        if let Some(game) = &self.game {
+            if keycode == KeyCode::Return && game.phase() == Phase::Over {
+                self.game = Some(game.rematch());
+                self.show_game();
+                self.population_history = PopulationHistory::new(GRAPH_HISTORY);
+                return;
+            }
+        }
+        let command = match self.keymap.command(keycode, keymods) {
+            Some(command) => command,
+            None => return,
+        };
+        match command {
+            Command::Quit => event::quit(ctx),
+            Command::Help => self.show_help = !self.show_help,
+            Command::Menu if self.game.is_none() => self.menu = Some(Menu::new()),
+            _ => (),
+        }
+        if self.is_playing_back() {
+            return;
+        }
+        // The game of Immigration runs itself, so only the keys that pause
+        // it or change how it looks do anything.
+        if self.game.is_some()
+            && !matches!(
+                command,
+                Command::Pause
+                    | Command::Graph
+                    | Command::StatusBar
+                    | Command::Theme
+                    | Command::Stats
+                    | Command::Mute
+                    | Command::Screenshot
+            )
+        {
+            return;
+        }
+        let action = match command {
+            Command::Pause => Action::TogglePause,
+            Command::Step if self.paused => Action::Step,
+            Command::StepBack if self.paused && self.generation > 0 => Action::JumpTo {
+                generation: self.generation - 1,
+            },
+            Command::Denser | Command::Sparser => {
+                let change = if command == Command::Denser {
+                    DENSITY_STEP
+                } else {
+                    -DENSITY_STEP
+                };
+                // Rounded to whole percentages so repeated steps don't drift.
+                let density =
+                    ((self.soup.density + change).clamp(0.0, 1.0) * 100.0).round() / 100.0;
+                Action::SetDensity { density }
+            }
+            Command::NewSoup => Action::NewSoup,
+            Command::Grow | Command::Shrink => {
+                if self.animation.is_some() || self.video.is_some() {
+                    eprintln!("finish recording before resizing the board");
+                    return;
+                }
+                let resize = |size: usize| match command {
+                    Command::Grow => size + RESIZE_STEP,
+                    _ => size.saturating_sub(RESIZE_STEP).max(RESIZE_STEP),
+                };
+                let (width, height) = (resize(self.grid.width()), resize(self.grid.height()));
+                if (width, height) == (self.grid.width(), self.grid.height()) {
+                    return;
+                }
+                Action::Resize { width, height }
+            }
+            Command::Follow if self.plane.is_some() => Action::ToggleFollow,
+            Command::ZoomToFit if self.plane.is_some() => {
+                if self.animation.is_some() || self.video.is_some() {
+                    eprintln!("finish recording before resizing the board");
+                    return;
+                }
+                Action::ZoomToFit
+            }
+            Command::Centre => match self.centred_camera() {
+                Some((row, col)) => Action::MoveCamera { row, col },
+                None => return,
+            },
+            Command::Clear => Action::Clear,
+            Command::NextGalleryEntry | Command::PreviousGalleryEntry
+                if !self.gallery.is_empty() =>
+            {
+                let backwards = command == Command::PreviousGalleryEntry;
+                let len = self.gallery.len();
+                let index = match self.gallery_index {
+                    Some(index) if backwards => (index + len - 1) % len,
+                    Some(index) => (index + 1) % len,
+                    None if backwards => len - 1,
+                    None => 0,
+                };
+                Action::ShowGalleryEntry { index }
+            }
+            Command::Starter(index) => Action::PlaceStarter { index },
+            Command::Graph => Action::ToggleGraph,
+            Command::Census => Action::ToggleCensus,
+            Command::Heatmap => Action::ToggleHeatmap,
+            Command::Clusters => Action::ToggleClusters,
+            Command::NextView => Action::NextView,
+            Command::PreviousBranch if self.branches.current() > 0 => Action::SwitchBranch {
+                branch: self.branches.current() - 1,
+            },
+            Command::NextBranch if self.branches.current() + 1 < self.branches.len() => {
+                Action::SwitchBranch {
+                    branch: self.branches.current() + 1,
+                }
+            }
+            Command::StatusBar => {
+                self.show_hud = !self.show_hud;
+                return;
+            }
+            Command::Theme => {
+                self.theme = self.theme.toggle();
+                return;
+            }
+            Command::Stats => {
+                self.show_totals = !self.show_totals;
+                return;
+            }
+            Command::ClusterColours => {
+                self.cluster_colours = match self.cluster_colours {
+                    Some(_) => None,
+                    None => Some(Tracker::new()),
+                };
+                return;
+            }
+            Command::Glow => {
+                self.glow = match self.glow {
+                    Some(_) => None,
+                    None => match Glow::new(ctx) {
+                        Ok(glow) => Some(glow),
+                        Err(e) => {
+                            eprintln!("could not make the glow: {}", e);
+                            None
+                        }
+                    },
+                };
+                return;
+            }
+            Command::Particles => {
+                self.particles = match self.particles {
+                    Some(_) => None,
+                    None => Some(Particles::new(MOST_PARTICLES)),
+                };
+                return;
+            }
+            Command::Mute => {
+                self.muted = !self.muted;
+                if let Some(music) = &self.music {
+                    if self.muted {
+                        music.pause();
+                    } else {
+                        music.resume();
+                    }
+                }
+                return;
+            }
+            Command::BranchTree => {
+                self.show_branches = !self.show_branches;
+                return;
+            }
+            Command::SaveFavorite => {
+                self.favorite_name = Some(String::new());
+                return;
+            }
+            Command::SaveSvg => {
+                let path = timestamped_file_name("board", "svg");
+                match render::save_svg(&self.grid, self.theme.palette(), &path) {
+                    Ok(()) => println!("saved the board to {}", path),
+                    Err(e) => eprintln!("could not save the board to {}: {}", path, e),
+                }
+                return;
+            }
+            Command::Share => {
+                println!("{}", self.grid.to_share_string_under(self.rule));
+                return;
+            }
+            Command::Screenshot => {
+                self.save_screenshot();
+                return;
+            }
+            Command::Record => {
+                self.toggle_recording();
+                return;
+            }
+            Command::RestoreSession if self.saved_session.is_some() => {
+                if let Some(session) = self.saved_session.take() {
+                    if let Err(e) = self.restore_session(session) {
+                        eprintln!("could not restore the last session: {}", e);
+                    }
+                }
+                return;
+            }
+            Command::Bookmark => {
+                self.toggle_bookmark();
+                return;
+            }
+            Command::PreviousBookmark | Command::NextBookmark => {
+                match self.next_bookmark(command == Command::NextBookmark) {
+                    Some(generation) => Action::JumpTo { generation },
+                    None => return,
+                }
+            }
+            Command::SaveHeatmap => {
+                let path = timestamped_file_name("heatmap", "png");
+                match self.heatmap.save_png(&path, self.colormap, self.scale) {
+                    Ok(()) => println!("saved the heatmap to {}", path),
+                    Err(e) => eprintln!("could not save the heatmap to {}: {}", path, e),
+                }
+                return;
+            }
+            _ => return,
+        };
+        if let Err(e) = self.perform(action) {
+            eprintln!("could not step forward: {}", e);
+        }
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if self.kiosk {
+            event::quit(ctx);
+            return;
+        }
+        if self.is_playing_back() {
+            return;
+        }
+        if button == MouseButton::Right && self.plane.is_some() {
+            self.pan_from = Some(((x, y), self.camera));
+            return;
+        }
+        if button != MouseButton::Left {
+            return;
+        }
+        let cell = self.cell_at(x, y);
+        if let Some(game) = self.game.as_mut() {
+            if cell.is_some_and(|(row, col)| game.place(row, col)) {
+                self.show_game();
+            }
+            return;
+        }
+        let rect = self.timeline_rect();
+        let result = if y >= rect.y - 2.0 && y <= rect.bottom() + 2.0 {
+            self.scrubbing = true;
+            self.scrub_to(x)
+        } else if let Some((row, col)) = cell {
+            self.perform(Action::ToggleCell { row, col })
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        if let Some(text) = self.menu.as_mut().and_then(|menu| menu.editing.as_mut()) {
+            if !character.is_control() && text.chars().count() < MENU_ENTRY_LENGTH {
+                text.push(character);
+            }
+        }
+        if let Some(name) = self.favorite_name.as_mut() {
+            if favorites::is_name_char(character) && name.chars().count() < FAVORITE_NAME_LENGTH {
+                name.push(character);
+            }
+        }
+    }
 
-impl Grid {
-    fn seed(size: usize, living_cells: Vec<(usize, usize)>) -> Grid {
-        let row = vec![State::Dead; size];
-        let mut cells = vec![row; size];
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        match button {
+            MouseButton::Left => self.scrubbing = false,
+            // The view follows the drag as it goes, but only where it ends
+            // up is recorded.
+            MouseButton::Right => {
+                if let Some((_, start)) = self.pan_from.take() {
+                    let (row, col) = self.camera;
+                    if (row, col) != start {
+                        if let Err(e) = self.perform(Action::MoveCamera { row, col }) {
+                            eprintln!("{}", e);
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
 
-        for position in living_cells {
-            cells[position.0][position.1] = State::Alive;
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        let cursor = self.cell_at(x, y);
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.share_cursor();
+        }
+        if let Some(((start_x, start_y), (row, col))) = self.pan_from {
+            let rows = ((y - start_y) / self.cell_size).round() as i64;
+            let cols = ((x - start_x) / self.cell_size).round() as i64;
+            let camera = (row.saturating_sub(rows), col.saturating_sub(cols));
+            if camera != self.camera {
+                self.move_camera(camera.0, camera.1);
+            }
+        }
+        if self.scrubbing {
+            if let Err(e) = self.scrub_to(x) {
+                eprintln!("{}", e);
+            }
         }
+    }
 
-        Grid { cells }
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        if !self.fill_window {
+            return;
+        }
+        if let Err(e) = set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height)) {
+            eprintln!("could not resize the window: {}", e);
+        }
+        let (cols, rows) = cells_to_fill(width, height, self.cell_size);
+        // The window is already the size it should be, so it is left alone
+        // rather than fitted to the board.
+        self.window_cells = (cols, rows);
+        if self.is_playing_back()
+            || self.animation.is_some()
+            || self.video.is_some()
+            || (cols, rows) == (self.grid.width(), self.grid.height())
+        {
+            return;
+        }
+        if let Err(e) = self.perform(Action::Resize {
+            width: cols,
+            height: rows,
+        }) {
+            eprintln!("{}", e);
+        }
     }
 
-    fn random_grid(size: usize) -> Grid {
-        let mut cells = Vec::new();
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let _span = Span::enter(
+            module_path!(),
+            Level::Debug,
+            "render",
+            format_args!("generation={}", self.generation),
+        );
+        self.fit_window(ctx)?;
+        clear(
+            ctx,
+            rgb(self.chroma_key.unwrap_or_else(|| self.theme.background())),
+        );
+        if let Some(tracker) = self.cluster_colours.as_mut() {
+            tracker.update(&self.grid);
+        }
+
+        let width = self.cell_size;
+        let height = self.cell_size;
 
-        for _ in 0..size {
-            let mut row = Vec::new();
-            for _ in 0..size {
-                if rand::random() {
-                    row.push(State::Alive)
-                } else {
-                    row.push(State::Dead)
+        let mut x = 0.0;
+        let mut y = 0.0;
+
+        match self.view {
+            View::Flat => {
+                let mut grid_builder = MeshBuilder::new();
+                let mut drawn = false;
+
+                for row_idx in 0..self.grid.height() {
+                    for col_idx in 0..self.grid.width() {
+                        if self.chroma_key.is_some() && !self.is_shown(row_idx, col_idx) {
+                            x += width;
+                            continue;
+                        }
+                        let colour = self.cell_colour(row_idx, col_idx);
+                        grid_builder.rectangle(
+                            DrawMode::fill(),
+                            Rect::new(x, y, width, height),
+                            colour,
+                        );
+                        drawn = true;
+                        x += width;
+                    }
+                    x = 0.0;
+                    y += height;
+                }
+
+                // A mesh can't be empty, which the board is with a chroma
+                // key and nothing alive.
+                if drawn {
+                    let grid = grid_builder.build(ctx)?;
+                    draw(ctx, &grid, (na::Point2::new(10.0, 10.0),))?;
                 }
+                self.draw_glow(ctx)?;
+                self.draw_particles(ctx)?;
+                self.draw_cursors(ctx)?;
+            }
+            View::Sphere => {
+                let rotation = timer::time_since_start(ctx).as_secs_f32() * SPIN_SPEED;
+                self.draw_sphere(ctx, rotation)?;
+                y = self.grid.height() as f32 * height;
+            }
+            View::Isometric => {
+                self.ages.update(self.generation, &self.grid);
+                self.draw_isometric(ctx)?;
+                y = self.grid.height() as f32 * height;
+            }
+        }
+
+        let language = self.language;
+        let mut hud_text = language.fill(
+            "Generation: {}   Population: {}   Speed: {}/s   Rule: {}",
+            &[
+                &self.generation,
+                &self.population(),
+                &self.speed,
+                &self.rule,
+            ],
+        );
+        let cell = self.cursor.and_then(|(row, col)| match self.plane {
+            Some(_) => self.plane_cell(row, col),
+            None => Some((row as i64, col as i64)),
+        });
+        if let Some((row, col)) = cell {
+            hud_text.push_str(&language.fill("   Cell: {}, {}", &[&row, &col]));
+        }
+        if !self.topology.is_bounded() {
+            hud_text.push_str(&format!("   {}", self.topology));
+        }
+        if self.view != View::Flat {
+            hud_text.push_str(&language.fill("   {} view", &[&language.tr(self.view.name())]));
+        }
+        if self.plane.is_some() {
+            hud_text
+                .push_str(&language.fill("   View at {}, {}", &[&self.camera.0, &self.camera.1]));
+            if self.follow.is_some() {
+                hud_text.push_str(language.tr(" following"));
+            }
+        }
+        if self.paused {
+            hud_text.push_str(language.tr("   Paused"));
+        }
+        if let Some(host) = &self.host {
+            hud_text.push_str(&language.fill("   Shared with {}", &[&host.guests()]));
+        }
+        if let Some(user) = self.guest.as_ref().and_then(Guest::user) {
+            hud_text.push_str(&language.fill("   Joined as {}", &[&user]));
+        }
+        if let Some(index) = self.gallery_index {
+            if let Some(entry) = self.gallery.get(index) {
+                hud_text.push_str(&language.fill(
+                    "   {} of {}: {}",
+                    &[&(index + 1), &self.gallery.len(), &entry.name],
+                ));
+            }
+        }
+        if let Some(name) = &self.favorite_name {
+            hud_text.push_str(
+                &language.fill("   Favorite name: {}_   Return saves, Esc cancels", &[name]),
+            );
+        }
+        if self.soup.density != DEFAULT_DENSITY {
+            let percent = format!("{:.0}", self.soup.density * 100.0);
+            hud_text.push_str(&language.fill("   Density {}%", &[&percent]));
+        }
+        if self.show_clusters {
+            let components = Components::label(&self.grid);
+            hud_text.push_str(&language.fill(
+                "   Clusters: {} (largest {})",
+                &[&components.count(), &components.largest()],
+            ));
+        }
+        if self.branches.len() > 1 {
+            hud_text.push_str(&language.fill(
+                "   Branch {} of {}",
+                &[&(self.branches.current() + 1), &self.branches.len()],
+            ));
+        }
+        if self.animation.is_some() {
+            hud_text.push_str(language.tr("   Recording"));
+        }
+        if self.muted {
+            hud_text.push_str(language.tr("   Muted"));
+        }
+        if self.saved_session.is_some() {
+            hud_text.push_str(language.tr("   R restores the last session"));
+        }
+        if let Some(game) = &self.game {
+            hud_text.push_str(&self.scoreboard(game));
+        }
+        if let Some((achievement, at)) = self.unlocked {
+            if at.elapsed() < ACHIEVEMENT_SHOWN {
+                hud_text.push_str(&language.fill(
+                    "   Achievement unlocked: {}",
+                    &[&language.tr(achievement.description())],
+                ));
             }
-            cells.push(row)
+        }
+        if let Some((message, at)) = &self.problem {
+            if at.elapsed() < PROBLEM_SHOWN {
+                hud_text.push_str("   ");
+                hud_text.push_str(message);
+            }
+        }
+        if let Some(behaviour) = self.behaviour {
+            hud_text.push_str("   ");
+            hud_text.push_str(&describe(behaviour, language));
+        }
+        if self.show_hud {
+            let hud = Text::new(hud_text);
+            draw(
+                ctx,
+                &hud,
+                (na::Point2::new(10.0, y + 18.0), rgb(self.theme.text())),
+            )?;
+        }
+        if !self.kiosk {
+            self.draw_timeline(ctx)?;
+        }
+
+        if self.show_graph {
+            self.draw_population_graph(ctx)?;
+        }
+        if self.show_census {
+            self.draw_census(ctx)?;
+        }
+        if self.show_branches {
+            self.draw_branches(ctx)?;
+        }
+        self.draw_tooltip(ctx)?;
+        if self.show_totals {
+            self.draw_totals(ctx)?;
+        }
+        if self.show_help {
+            self.draw_help(ctx)?;
+        }
+        if let Some(menu) = &self.menu {
+            self.draw_menu(ctx, menu)?;
         }
 
-        Grid { cells }
+        present(ctx)?;
+        timer::yield_now();
+        Ok(())
     }
+}
+
+/// A colour of the theme, as drawing takes it.
+fn rgb((r, g, b): (u8, u8, u8)) -> Color {
+    Color::from_rgb(r, g, b)
+}
 
-    fn next_generation(&self) -> Grid {
-        let mut new_cells = Vec::new();
+/// What the board settled into, in `language`.
+fn describe(behaviour: Behaviour, language: Language) -> String {
+    match behaviour {
+        Behaviour::Extinct => language.tr("extinct").to_string(),
+        Behaviour::StillLife => language.tr("still life").to_string(),
+        Behaviour::Oscillator { period } => language.fill("period {} oscillator", &[&period]),
+        Behaviour::Spaceship {
+            period,
+            displacement: (rows, cols),
+        } => language.fill(
+            "period {} spaceship moving ({}, {})",
+            &[&period, &rows, &cols],
+        ),
+    }
+}
 
-        for (row_idx, row) in self.cells.iter().enumerate() {
-            let mut new_row = Vec::new();
-            for (col_idx, state) in row.iter().enumerate() {
-                new_row.push(state_based_on_neighbours(
-                    (row_idx, col_idx),
-                    &self.cells,
-                    state,
-                ))
-            }
-            new_cells.push(new_row)
+/// The size of a window showing `grid` with `cell_size` pixel cells, with
+/// room for the HUD and the timeline below it.
+fn window_size(grid: &Grid, cell_size: f32) -> (f32, f32) {
+    (
+        grid.width() as f32 * cell_size + 20.0,
+        grid.height() as f32 * cell_size + 20.0 + HUD_HEIGHT + TIMELINE_HEIGHT,
+    )
+}
+
+/// The width and height of the biggest board with `cell_size` pixel cells
+/// that fits a `width` by `height` window.
+fn cells_to_fill(width: f32, height: f32, cell_size: f32) -> (usize, usize) {
+    (
+        ((width - 20.0) / cell_size).max(1.0) as usize,
+        ((height - 20.0 - HUD_HEIGHT - TIMELINE_HEIGHT) / cell_size).max(1.0) as usize,
+    )
+}
+
+/// A file name like `heatmap-1571234567.png`, made unique by the number of
+/// seconds since the Unix epoch.
+fn timestamped_file_name(prefix: &str, extension: &str) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    format!("{}-{}.{}", prefix, seconds, extension)
+}
+
+/// The curated seeds followed by the user's favorites.
+fn gallery() -> Gallery {
+    let mut gallery = Gallery::curated();
+    if let Some(dir) = favorites::dir() {
+        for entry in favorites::load(&dir) {
+            gallery.add(entry);
+        }
+    }
+    gallery
+}
+
+/// The board to start from with any `--place` patterns put on it, and the
+/// rule it runs by: `--rule`, or else the rule of the pattern it was
+/// started from.
+fn initial_grid(options: &Options, rng: &mut SeededRng) -> Result<(Grid, Rule), String> {
+    let (mut grid, rule) = if options.placements.is_empty()
+        || options.pattern.is_some()
+        || options.text.is_some()
+        || options.share.is_some()
+    {
+        base_grid(options, rng)?
+    } else {
+        let (width, height) = options.dimensions();
+        (Grid::seed_rectangle(width, height, vec![]), None)
+    };
+    for placement in &options.placements {
+        let mut pattern = Pattern::from_grid(&thumbnail::load_pattern(&placement.path)?);
+        for transform in &placement.transforms {
+            pattern = pattern.transformed(*transform);
         }
+        pattern
+            .place(&mut grid, placement.row, placement.col)
+            .map_err(|e| format!("{}: {}", placement.path, e))?;
+    }
+    Ok((grid, options.rule.or(rule).unwrap_or_default()))
+}
 
-        Grid { cells: new_cells }
+/// Builds the starting grid, either from the RLE or JSON pattern named on
+/// the command line or as a random soup, with the rule the pattern says it
+/// runs by.
+fn base_grid(options: &Options, rng: &mut SeededRng) -> Result<(Grid, Option<Rule>), String> {
+    if let Some(text) = &options.share {
+        return Grid::from_share_string_with_rule(text).map(|(grid, rule)| (grid, Some(rule)));
+    }
+    let (width, height) = options.dimensions();
+    if let Some(text) = &options.text {
+        return Ok((text::text_grid(text, width, height)?, None));
+    }
+    if options.empty || options.versus.is_some() {
+        return Ok((Grid::seed_rectangle(width, height, vec![]), None));
     }
+    if let Some(spec) = &options.fetch {
+        if let Some(pattern) = apgcode::from_spec(spec) {
+            let pattern = pattern?;
+            return placed_pattern(options, &pattern, spec).map(|grid| (grid, Some(pattern.rule)));
+        }
+        let cache = dirs::cache_dir().map(|dir| dir.join("conways_game_of_life").join("patterns"));
+        let text = fetch::fetch(spec, cache.as_deref())?;
+        return pattern_grid(options, &text, spec).map(|(grid, rule)| (grid, Some(rule)));
+    }
+    let path = match &options.pattern {
+        Some(path) => path,
+        None => return Ok((options.soup.generate(width, height, rng), None)),
+    };
+
+    let text = if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .and_then(|_| compression::decode(bytes))
+            .map_err(|e| format!("could not read pattern from stdin: {}", e))?
+    } else {
+        compression::read_to_string(Path::new(path))
+            .map_err(|e| format!("could not read {}: {}", path, e))?
+    };
+    let name = if path == "-" { "stdin" } else { path };
+    pattern_grid(options, &text, name).map(|(grid, rule)| (grid, Some(rule)))
 }
 
-fn state_based_on_neighbours(
-    current_position: (usize, usize),
-    cells: &[Vec<State>],
-    current_state: &State,
-) -> State {
-    let neighbours_directions = [
-        Direction::N,
-        Direction::NE,
-        Direction::E,
-        Direction::SE,
-        Direction::S,
-        Direction::SW,
-        Direction::W,
-        Direction::NW,
-    ];
-
-    let nmb_alive_neighbours = neighbours_directions
+/// Whether the board starts from just the pattern named on the command
+/// line, on the `--infinite` plane, where `initial_plane` reads it.
+fn starts_on_plane(options: &Options) -> bool {
+    let elsewhere = options.text.is_some()
+        || options.share.is_some()
+        || options.fetch.is_some()
+        || options.empty
+        || options.versus.is_some()
+        || !options.placements.is_empty();
+    let windowless = options.stream || options.terminal;
+    options.infinite && options.pattern.is_some() && !windowless && !options.fit && !elsewhere
+}
+
+/// With `--infinite`, the pattern named on the command line read straight
+/// onto the plane rather than onto a board first, so that RLE patterns far
+/// bigger than any board load a little at a time. Gives the plane, where
+/// to put the top left of the view to have the pattern in the middle of it
+/// and the rule it runs by: `--rule`, or else the pattern's.
+fn initial_plane(options: &Options) -> Result<(Plane, (i64, i64), Rule), String> {
+    let path = options.pattern.as_deref().unwrap_or("-");
+    let name = if path == "-" { "stdin" } else { path };
+    let unreadable = |e: io::Error| format!("could not read {}: {}", name, e);
+    let mut input = if path == "-" {
+        compression::reader(io::stdin())
+    } else {
+        compression::open(Path::new(path))
+    }
+    .map_err(unreadable)?;
+    let first = input
+        .fill_buf()
+        .map_err(unreadable)?
         .iter()
-        .map(|neighbours_direction| {
-            neighbours_state(current_position, cells, neighbours_direction.translation())
-        })
-        .filter(|state| *state == State::Alive)
-        .count();
+        .find(|byte| !byte.is_ascii_whitespace())
+        .copied();
+    let (plane, (width, height, rule)) = if first == Some(b'{') {
+        let mut text = String::new();
+        input.read_to_string(&mut text).map_err(unreadable)?;
+        let (grid, rule) =
+            json::from_json_with_rule(&text).map_err(|e| format!("{}: {}", name, e))?;
+        let shape = (grid.width(), grid.height(), rule);
+        (Plane::from_grid(&grid, 0, 0), shape)
+    } else {
+        rle::read_plane(input).map_err(|e| format!("{}: {}", name, e))?
+    };
+    let (view_width, view_height) = options.dimensions();
+    let centre = |size: usize, view: usize| {
+        let size = plane::offset(0, size).unwrap_or(i64::MAX);
+        size.saturating_sub(view as i64) / 2
+    };
+    let camera = (centre(height, view_height), centre(width, view_width));
+    Ok((plane, camera, options.rule.unwrap_or(rule)))
+}
 
-    match current_state {
-        State::Alive => match nmb_alive_neighbours {
-            2 | 3 => State::Alive,
-            _ => State::Dead,
-        },
-        State::Dead => match nmb_alive_neighbours {
-            3 => State::Alive,
-            _ => State::Dead,
-        },
+/// The board of `text`, a JSON grid or an RLE pattern placed as `options`
+/// say, and the rule it runs by, reporting errors as coming from `name`.
+fn pattern_grid(options: &Options, text: &str, name: &str) -> Result<(Grid, Rule), String> {
+    if text.trim_start().starts_with('{') {
+        return json::from_json_with_rule(text).map_err(|e| format!("{}: {}", name, e));
     }
+    let pattern = rle::parse(text).map_err(|e| format!("{}: {}", name, e))?;
+    placed_pattern(options, &pattern, name).map(|grid| (grid, pattern.rule))
 }
 
-fn neighbours_state(
-    current_position: (usize, usize),
-    cells: &[Vec<State>],
-    translation: (i32, i32),
-) -> State {
-    let new_position = (
-        current_position.0 as i32 + translation.0,
-        current_position.1 as i32 + translation.1,
-    );
-    let size = cells.len() as i32;
-    if (new_position.0 < 0)
-        | (new_position.1 < 0)
-        | (new_position.0 == size)
-        | (new_position.1 == size)
-    {
-        State::Dead
-    } else {
-        cells[new_position.0 as usize][new_position.1 as usize].clone()
+/// A board the size `options` asks for with `pattern` in the middle, or
+/// one just big enough with `--fit`.
+fn placed_pattern(options: &Options, pattern: &Pattern, name: &str) -> Result<Grid, String> {
+    let (width, height) = options.dimensions();
+    if options.fit {
+        return Ok(pattern.fitted(width, height, options.margin));
     }
+    pattern.centred(width, height).ok_or_else(|| {
+        format!(
+            "{}: the {}x{} pattern does not fit a {}x{} grid, try --fit or a bigger --size",
+            name, pattern.width, pattern.height, width, height
+        )
+    })
 }
 
-enum Direction {
-    N,
-    NE,
-    E,
-    SE,
-    S,
-    SW,
-    W,
-    NW,
+/// Runs the experiment the manifest at `path` describes and prints its
+/// results, writing them to `out` as CSV as well if given, and gives the
+/// exit code: 0 if it could.
+fn run_sweep(manifest: &sweep::Manifest, out: Option<&Path>) -> i32 {
+    let combinations = manifest.combinations().len();
+    let rows = manifest.run(|done| eprint!("\rran {} of {} combinations", done, combinations));
+    eprintln!();
+    print!("{}", sweep::table(&rows));
+    if let Some(out) = out {
+        if let Err(e) = fs::write(out, sweep::csv(&rows)) {
+            eprintln!("couldn't write {}: {}", out.display(), e);
+            return 1;
+        }
+    }
+    0
 }
 
-impl Direction {
-    fn translation(&self) -> (i32, i32) {
-        match self {
-            Direction::N => (-1, 0),
-            Direction::NE => (-1, 1),
-            Direction::E => (0, 1),
-            Direction::SE => (1, 1),
-            Direction::S => (1, 0),
-            Direction::SW => (1, -1),
-            Direction::W => (0, -1),
-            Direction::NW => (-1, -1),
+/// Prints how long the pattern `spec` names lives, giving the exit code: 0
+/// if it could.
+fn measure_lifespan(options: &Options, spec: &str) -> i32 {
+    let pattern = apgcode::from_spec(spec).unwrap_or_else(|| {
+        fs::read_to_string(spec)
+            .map_err(|e| format!("could not read {}: {}", spec, e))
+            .and_then(|text| rle::parse(&text).map_err(|e| format!("{}: {}", spec, e)))
+    });
+    match pattern {
+        Ok(pattern) => {
+            let rule = options.rule.unwrap_or(pattern.rule);
+            let longest_period = options
+                .lifespan
+                .as_ref()
+                .map_or(DEFAULT_LONGEST_PERIOD, |lifespans| {
+                    lifespans.search.longest_period
+                });
+            let lived = lifespan::of_pattern(&pattern, rule, longest_period);
+            println!("{} {}", spec, lived);
+            0
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            1
         }
     }
 }
 
-impl fmt::Display for Grid {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut text = String::new();
-        for row in &self.cells {
-            for cell in row {
-                write!(text, "{:?} ", cell)?
-            }
-            text.push_str("\n")
+/// Runs the soups of `lifespans` and lists the longest lived, giving the
+/// exit code: 0 if it could.
+fn measure_lifespans(options: &Options, lifespans: &Lifespans) -> i32 {
+    let search = &lifespans.search;
+    let survey = lifespans.run(|done| eprint!("\rran {} of {} soups", done, search.soups));
+    eprintln!();
+    print!("{}", lifespan::table(&survey.longest));
+    println!();
+    print!("{}", lifespan::summary(&survey));
+    if let Some(dir) = &options.lifespan_out {
+        if let Err(message) = lifespan::save(&survey.longest, search, dir) {
+            eprintln!("{}", message);
+            return 1;
+        }
+    }
+    if let Some(path) = &options.lifespan_json {
+        if let Err(e) = fs::write(path, lifespan::to_json(&survey, search)) {
+            eprintln!("could not write {}: {}", path.display(), e);
+            return 1;
         }
-        write!(f, "{}", text)
     }
+    0
 }
 
-struct MainState {
-    grid: Grid,
+/// The batch run the command line asks for, if it is one: a manifest to run
+/// again, or a search, hunt, lifespan, explore or sweep.
+fn experiment(options: &Options) -> Result<Option<Experiment>, String> {
+    if let Some(path) = &options.reproduce {
+        let manifest = experiment::Manifest::load(path)?;
+        if manifest.is_other_version() {
+            eprintln!(
+                "{} was made by version {} and this is {}, so the results may differ",
+                path.display(),
+                manifest.version,
+                experiment::VERSION
+            );
+        }
+        return Ok(Some(manifest.experiment));
+    }
+    Ok(if let Some(search) = &options.search {
+        Some(Experiment::Search(search.clone()))
+    } else if let Some(hunt) = &options.hunt {
+        Some(Experiment::Hunt(hunt.clone()))
+    } else if let Some(lifespans) = &options.lifespan {
+        Some(Experiment::Lifespan(lifespans.clone()))
+    } else if let Some(explore) = &options.explore {
+        Some(Experiment::Explore(explore.clone()))
+    } else if let Some(path) = &options.sweep {
+        Some(Experiment::Sweep(sweep::Manifest::load(path)?))
+    } else {
+        None
+    })
 }
 
-impl MainState {
-    fn new() -> GameResult<MainState> {
-        let seeded_grid = Grid::random_grid(50);
-        let s = MainState { grid: seeded_grid };
-        Ok(s)
+/// Runs `experiment`, first writing its manifest if asked to, giving the
+/// exit code: 0 if it could.
+fn run_experiment(options: &Options, experiment: &Experiment) -> i32 {
+    if let Some(path) = &options.save_manifest {
+        if let Err(message) = experiment::Manifest::new(experiment.clone()).save(path) {
+            eprintln!("{}", message);
+            return 1;
+        }
+    }
+    match experiment {
+        Experiment::Search(search) => {
+            let results =
+                search.run(|done| eprint!("\rsearched {} of {} soups", done, search.soups));
+            eprintln!();
+            print!("{}", results);
+            0
+        }
+        Experiment::Explore(explore) => {
+            let best = explore.run(|done| eprint!("\rtried {} of {} rules", done, explore.rules));
+            eprintln!();
+            for (rank, scored) in best.iter().enumerate() {
+                println!(
+                    "{}. {}  score {:.3}  activity {:.3}  structure {:.3}  growth {:.3}",
+                    rank + 1,
+                    scored.rule,
+                    scored.score,
+                    scored.activity,
+                    scored.structure,
+                    scored.growth
+                );
+                println!("{}", explore::preview(&scored.preview));
+            }
+            0
+        }
+        Experiment::Sweep(manifest) => run_sweep(manifest, options.sweep_out.as_deref()),
+        Experiment::Lifespan(lifespans) => measure_lifespans(options, lifespans),
+        Experiment::Hunt(hunt) => {
+            let finds =
+                hunt.run(|done| eprint!("\rtried {} of {} patterns", done, hunt.candidates()));
+            eprintln!();
+            match hunt::save(&finds, &hunt.out) {
+                Ok(paths) => {
+                    for (find, path) in finds.iter().zip(&paths) {
+                        println!(
+                            "{}, {} cells, {}: {}",
+                            find.behaviour,
+                            find.pattern.living_cells.len(),
+                            find.apgcode,
+                            path.display()
+                        );
+                    }
+                    println!("{} found", finds.len());
+                    0
+                }
+                Err(message) => {
+                    eprintln!("{}", message);
+                    1
+                }
+            }
+        }
     }
 }
 
-impl event::EventHandler for MainState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        let next_gen = self.grid.next_generation();
-        self.grid = next_gen;
-        ggez::timer::sleep(Duration::from_millis(500));
-        Ok(())
+/// Sends `request` to the window taking commands on the control socket at
+/// `path` and prints its answer, giving the exit code: 0 if it was done.
+fn send_command(path: &Path, request: &Request) -> i32 {
+    // The window may have been started from another directory.
+    let absolute = |file: &str| {
+        std::env::current_dir()
+            .map(|dir| dir.join(file).display().to_string())
+            .unwrap_or_else(|_| file.to_string())
+    };
+    let request = match request {
+        Request::Load(file) => Request::Load(absolute(file)),
+        Request::Save(file) => Request::Save(absolute(file)),
+        request => request.clone(),
+    };
+    match ipc::send(path, &request) {
+        Ok(Ok(message)) => {
+            println!("{}", message);
+            0
+        }
+        Ok(Err(message)) => {
+            eprintln!("{}", message);
+            1
+        }
+        Err(e) => {
+            eprintln!("could not reach a window on {}: {}", path.display(), e);
+            1
+        }
     }
+}
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        clear(ctx, [0.1, 0.2, 0.3, 1.0].into());
+/// Draws a thumbnail of each pattern in `dir` into `dir/thumbnails`,
+/// reporting any that fail and carrying on with the rest.
+fn save_thumbnails(dir: &str, size: u32) {
+    let out_dir = Path::new(dir).join("thumbnails");
+    match thumbnail::save_thumbnails(dir, &out_dir, size, Palette::default()) {
+        Ok(results) => {
+            for result in results {
+                match result {
+                    Ok(path) => println!("saved {}", path.display()),
+                    Err(message) => eprintln!("{}", message),
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("could not make thumbnails of {}: {}", dir, e);
+            process::exit(1);
+        }
+    }
+}
 
-        let width = 10.0;
-        let height = 10.0;
+fn load_replay(path: &str) -> Result<Replay, String> {
+    let text = compression::read_to_string(Path::new(path))
+        .map_err(|e| format!("could not read {}: {}", path, e))?;
+    Replay::from_json(&text).map_err(|e| format!("{}: {}", path, e))
+}
 
-        let mut x = 0.0;
-        let mut y = 0.0;
+/// Opens the window `cb` describes. Without a display, winit panics rather
+/// than returning an error, so its panic is caught and kept quiet too.
+fn open_window(cb: ggez::ContextBuilder) -> Result<(Context, event::EventsLoop), String> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| ()));
+    let built = panic::catch_unwind(AssertUnwindSafe(|| cb.build()));
+    panic::set_hook(hook);
+    match built {
+        Ok(built) => built.map_err(|e| e.to_string()),
+        Err(payload) => Err(payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "the window system failed".to_string())),
+    }
+}
 
-        let grey = Color::from_rgb(77,77,77);
-        let blue = Color::from_rgb(51, 153, 255);
+/// A flag set once Ctrl+C is pressed, for runs without a window to stop
+/// cleanly on.
+fn interrupt_flag() -> Arc<AtomicBool> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler_flag = Arc::clone(&interrupted);
+    if let Err(e) = ctrlc::set_handler(move || handler_flag.store(true, Ordering::SeqCst)) {
+        eprintln!("could not install the Ctrl+C handler: {}", e);
+    }
+    interrupted
+}
 
-        let mut grid_builder = MeshBuilder::new();
+/// Logs as `RUST_LOG` says if it is set, or else as much as `-v` was given
+/// `verbosity` times for.
+fn start_logging(verbosity: u8) {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(spec) if !spec.trim().is_empty() => spec.parse().unwrap_or_else(|message| {
+            eprintln!("RUST_LOG: {}", message);
+            Filter::verbosity(verbosity)
+        }),
+        _ => Filter::verbosity(verbosity),
+    };
+    if let Err(message) = logging::init(filter) {
+        eprintln!("could not start logging: {}", message);
+    }
+}
 
-        for row in self.grid.cells.iter() {
-            for cell in row {
-                let colour = match cell {
-                    State::Alive => blue,
-                    State::Dead => grey,
-                };
-                grid_builder.rectangle(DrawMode::fill(), Rect::new(x, y, width, height), colour);
-                x += width;
-            }
-            x = 0.0;
-            y += height;
+fn main() -> GameResult {
+    let mut options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
         }
+    };
+    start_logging(options.verbosity);
 
-        let grid = grid_builder.build(ctx)?;
+    if let Some(request) = &options.ctl {
+        process::exit(send_command(&options.control_path(), request));
+    }
+    if let (Some(_), Some(spec)) = (&options.lifespan, &options.pattern) {
+        process::exit(measure_lifespan(&options, spec));
+    }
+    match experiment(&options) {
+        Ok(Some(experiment)) => process::exit(run_experiment(&options, &experiment)),
+        Ok(None) => {}
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    }
+    if let Some(dir) = &options.thumbnails {
+        save_thumbnails(dir, options.thumbnail_size);
+        return Ok(());
+    }
 
-        draw(ctx, &grid, (na::Point2::new(10.0, 10.0),))?;
+    let load = Span::enter(
+        module_path!(),
+        Level::Info,
+        "load",
+        format_args!(
+            "pattern={}",
+            options
+                .play
+                .as_ref()
+                .or(options.pattern.as_ref())
+                .map_or("none", String::as_str)
+        ),
+    );
+    let replay = match &options.play {
+        Some(path) => match load_replay(path) {
+            Ok(replay) => Some(replay),
+            Err(message) => {
+                eprintln!("{}", message);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    if let Some(replay) = &replay {
+        options.seed = Some(replay.seed);
+        options.auto_restart = replay.auto_restart;
+        options.soup = replay.soup;
+        options.history_mb = replay.history_mb;
+        options.infinite = replay.infinite;
+        options.auto_expand = replay.auto_expand;
+        options.settle_period = replay.settle_period;
+        options.topology = replay.topology;
+        options.rule = Some(replay.rule);
+    }
 
-        present(ctx)?;
-        Ok(())
+    if options.fill_window && replay.is_none() {
+        let window = WindowMode::default();
+        options.shape = Some(cells_to_fill(
+            window.width,
+            window.height,
+            options.cell_size as f32,
+        ));
     }
-}
 
-fn main() -> GameResult {
-    let cb = ggez::ContextBuilder::new("conways game of life", "huw")
-        .window_setup(WindowSetup::default().title("Conway's Game of Life"))
-        .window_mode(WindowMode::default().dimensions(520.0, 520.0));
-    let (ctx, event_loop) = &mut cb.build()?;
-    let state = &mut MainState::new()?;
-    event::run(ctx, event_loop, state)
-}
+    let seed = options.seed.unwrap_or_else(rand::random);
+    eprintln!("seed: {}", seed);
+    let mut rng = determinism::rng(seed);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn blinker_test() {
-        let blinker_start = Grid {
-            cells: vec![
-                vec![State::Dead, State::Alive, State::Dead],
-                vec![State::Dead, State::Alive, State::Dead],
-                vec![State::Dead, State::Alive, State::Dead],
-            ],
-        };
+    // A window opens anyway on a random soup, saying what went wrong.
+    let mut problem = None;
+    let mut on_plane = None;
+    let grid = match &replay {
+        Some(replay) => replay.initial_grid(),
+        None if starts_on_plane(&options) => match initial_plane(&options) {
+            Ok((plane, camera, rule)) => {
+                let (width, height) = options.dimensions();
+                options.rule = Some(rule);
+                let grid = plane.view(camera.0, camera.1, width, height);
+                on_plane = Some((plane, camera));
+                grid
+            }
+            Err(message) => {
+                let (width, height) = options.dimensions();
+                problem = Some(message);
+                options.soup.generate(width, height, &mut rng)
+            }
+        },
+        None => match initial_grid(&options, &mut rng) {
+            Ok((grid, rule)) => {
+                options.rule = Some(rule);
+                grid
+            }
+            Err(message) if options.stream || options.terminal => {
+                eprintln!("{}", message);
+                process::exit(1);
+            }
+            Err(message) => {
+                let (width, height) = options.dimensions();
+                problem = Some(message);
+                options.soup.generate(width, height, &mut rng)
+            }
+        },
+    };
+    drop(load);
+    log::info!(
+        "starting on a {}x{} board with {} cells alive, by {}",
+        grid.width(),
+        grid.height(),
+        on_plane
+            .as_ref()
+            .map_or_else(|| grid.population(), |(plane, _)| plane.population()),
+        options.rule.unwrap_or_default()
+    );
 
-        let blinker_end = Grid {
-            cells: vec![
-                vec![State::Dead, State::Dead, State::Dead],
-                vec![State::Alive, State::Alive, State::Alive],
-                vec![State::Dead, State::Dead, State::Dead],
-            ],
-        };
+    let mut stats_csv = match &options.stats_csv {
+        Some(path) => match StatsCsv::append(path) {
+            Ok(stats_csv) => Some(stats_csv),
+            Err(e) => {
+                eprintln!("could not open {}: {}", path, e);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    if options.stream {
+        let stdout = io::stdout();
+        let mut out = io::BufWriter::new(stdout.lock());
+        let interrupted = interrupt_flag();
+        stream::run(&mut out, grid, &options, stats_csv.as_mut(), &interrupted)?;
+        return Ok(());
+    }
+    if options.terminal {
+        terminal::run(&mut io::stdout(), grid, &options, &interrupt_flag())?;
+        return Ok(());
+    }
+
+    if let Some(path) = options
+        .config
+        .as_ref()
+        .filter(|path| !Path::new(path).exists())
+    {
+        eprintln!("could not find the config file {}", path);
+        process::exit(1);
+    }
+    let config_path = options
+        .config
+        .clone()
+        .map(PathBuf::from)
+        .or_else(config::path);
+    let config = match config_path.as_ref().map(Config::load) {
+        Some(Ok(config)) => config,
+        Some(Err(message)) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+        None => Config::default(),
+    };
+
+    let (window_width, window_height) = window_size(&grid, options.cell_size as f32);
+    let cb = ggez::ContextBuilder::new("conways game of life", "huw")
+        .window_setup(WindowSetup::default().title(TITLE))
+        .window_mode(
+            WindowMode::default()
+                .dimensions(window_width, window_height)
+                .resizable(options.fill_window)
+                .fullscreen_type(if options.kiosk {
+                    FullscreenType::Desktop
+                } else {
+                    FullscreenType::Windowed
+                }),
+        );
+    let (ctx, event_loop) = &mut match open_window(cb) {
+        Ok(built) => built,
+        Err(message) => {
+            eprintln!("could not open a window: {}", message);
+            // The terminal only plays a bounded board, so rather than play
+            // something else, say what went wrong.
+            if let Some(option) = options.needs_window() {
+                eprintln!(
+                    "{} needs the window, so it can't be played in the terminal",
+                    option
+                );
+                process::exit(1);
+            }
+            if let Some(problem) = problem {
+                eprintln!("{}", problem);
+                process::exit(1);
+            }
+            if !terminal::offer() {
+                process::exit(1);
+            }
+            terminal::run(&mut io::stdout(), grid, &options, &interrupt_flag())?;
+            return Ok(());
+        }
+    };
+    let state = &mut MainState::new(grid.clone(), &options, stats_csv, rng)?;
+    state.player = replay.as_ref().map(Player::new);
+    if let Some((plane, camera)) = on_plane {
+        state.start_on(plane, camera);
+    }
+    if let Some(message) = problem {
+        state.report(message);
+    }
+    if let Err(message) = state.apply_config(&config) {
+        eprintln!("{}: {}", config_path.unwrap_or_default().display(), message);
+        process::exit(1);
+    }
+    // `--theme` wins over the config file, until the file is changed.
+    if let Some(theme) = options.theme {
+        state.theme = theme;
+    }
+    state.config_watcher = config_path.map(|path| config::Watcher::new(path, CONFIG_CHECK));
+    if let Some(address) = &options.serve {
+        match stream::serve(address) {
+            Ok(server) => state.server = Some(server),
+            Err(e) => {
+                eprintln!("could not listen on {}: {}", address, e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(address) = &options.host {
+        match Host::bind(address) {
+            Ok(host) => state.host = Some(host),
+            Err(e) => {
+                eprintln!("could not listen on {}: {}", address, e);
+                process::exit(1);
+            }
+        }
+        eprintln!("sharing the board on {}, for others to --join", address);
+    }
+    if let Some(address) = &options.join {
+        match Guest::connect(address) {
+            Ok(guest) => state.guest = Some(guest),
+            Err(e) => {
+                eprintln!("could not join {}: {}", address, e);
+                process::exit(1);
+            }
+        }
+        // Whether it runs is up to the host.
+        state.paused = false;
+    }
+    if let Some(channel) = &options.chat {
+        match Chat::connect(&options.chat_server, channel) {
+            Ok(chat) => state.chat = Some(chat),
+            Err(e) => {
+                eprintln!(
+                    "could not join {} on {}: {}",
+                    channel, options.chat_server, e
+                );
+                process::exit(1);
+            }
+        }
+        eprintln!("reading edits from the chat of {}", channel);
+    }
+    if options.control {
+        let path = options.control_path();
+        match ipc::Listener::bind(&path) {
+            Ok(control) => state.control = Some(control),
+            Err(e) => {
+                eprintln!("could not listen on {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+        eprintln!("taking commands on {}", path.display());
+    }
+    match Ticks::new(ctx) {
+        Ok(ticks) => state.ticks = Some(ticks),
+        Err(e) => eprintln!("could not make the sounds of births and deaths: {}", e),
+    }
+    if options.kiosk {
+        mouse::set_cursor_hidden(ctx, true);
+        // Fill the screen now rather than waiting on the window system to
+        // say it has been resized.
+        let (width, height) = drawable_size(ctx);
+        event::EventHandler::resize_event(state, ctx, width, height);
+    }
+    if let Some(path) = &options.video {
+        match Video::start(path, grid.width(), grid.height(), state.frame_style) {
+            Ok(video) => {
+                state.video = Some(video);
+                state.add_video_frame();
+            }
+            Err(e) => eprintln!("could not encode {}: {}", path, e),
+        }
+    }
+    state.totals_path = achievements::path();
+    if let Some(path) = &state.totals_path {
+        match Stats::load(path) {
+            Ok(totals) => state.totals = totals,
+            Err(message) => {
+                eprintln!("{}", message);
+                // Saving over stats that couldn't be read would lose them.
+                state.totals_path = None;
+            }
+        }
+    }
+    let session_path = session::path();
+    if !options.has_start() && options.record.is_none() && !options.kiosk {
+        state.saved_session = session::saved_path().and_then(|path| match Session::load(path) {
+            Ok(session) => Some(session),
+            Err(message) => {
+                eprintln!("{}", message);
+                None
+            }
+        });
+    }
+    if options.record.is_some() {
+        let mut replay = Replay::new(
+            &grid,
+            seed,
+            options.auto_restart,
+            options.soup,
+            options.history_mb,
+        );
+        replay.infinite = options.infinite;
+        replay.auto_expand = options.auto_expand;
+        replay.settle_period = options.settle_period;
+        replay.topology = options.topology;
+        replay.rule = options.rule.unwrap_or_default();
+        // Replays start running, so one that began paused starts by pausing.
+        if state.paused {
+            replay.record(0, Action::TogglePause);
+        }
+        state.recording = Some(replay);
+    }
+    event::run(ctx, event_loop, state)?;
 
-        assert_eq!(blinker_start.next_generation(), blinker_end);
+    if state.animation.is_some() {
+        state.toggle_recording();
+    }
+    state.finish_video();
+    state.save_totals();
+    // A screensaver's boards aren't worth keeping over the last real session.
+    if let Some(path) = session_path
+        .as_ref()
+        .filter(|_| state.game.is_none() && !state.kiosk)
+    {
+        if let Err(e) = state.session().save(path) {
+            eprintln!("could not save the session to {}: {}", path.display(), e);
+        }
+    }
+    if let (Some(path), Some(recording)) = (&options.record, &state.recording) {
+        match recording.save(path) {
+            Ok(()) => println!("saved the replay to {}", path),
+            Err(e) => eprintln!("could not save the replay to {}: {}", path, e),
+        }
     }
+    Ok(())
 }
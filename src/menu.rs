@@ -0,0 +1,179 @@
+//! The settings menu `Esc` opens over the board, for the options that
+//! don't suit a key of their own.
+
+use crate::i18n::Language;
+use conways_game_of_life::theme::Theme;
+
+/// A line of the menu that can be chosen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Item {
+    /// Typed in, as the rule of a pattern file would be.
+    Rule,
+    /// Typed in as `N` or `WIDTHxHEIGHT`.
+    Size,
+    /// Stepped through the named topologies.
+    Topology,
+    /// Switched between dark and light.
+    Theme,
+    Resume,
+    Quit,
+}
+
+const ITEMS: [Item; 6] = [
+    Item::Rule,
+    Item::Size,
+    Item::Topology,
+    Item::Theme,
+    Item::Resume,
+    Item::Quit,
+];
+
+/// The current values of the settings the menu shows.
+pub struct Settings {
+    pub rule: String,
+    pub width: usize,
+    pub height: usize,
+    pub topology: String,
+    pub theme: Theme,
+}
+
+/// Which line of the menu is chosen, and what is being typed into it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Menu {
+    selected: usize,
+    /// The text typed so far into the chosen line.
+    pub editing: Option<String>,
+    /// What became of the last change, good or bad.
+    pub message: Option<String>,
+}
+
+impl Menu {
+    pub fn new() -> Menu {
+        Menu::default()
+    }
+
+    pub fn selected(&self) -> Item {
+        ITEMS[self.selected]
+    }
+
+    /// Chooses the line above, or wraps round to the bottom.
+    pub fn up(&mut self) {
+        self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len();
+    }
+
+    /// Chooses the line below, or wraps round to the top.
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1) % ITEMS.len();
+    }
+
+    /// The text of the menu, one line per item with the chosen one marked,
+    /// then any message and how to use it.
+    pub fn lines(&self, settings: &Settings, language: Language) -> Vec<String> {
+        let tr = |text| language.tr(text);
+        let mut lines = vec![tr("Settings").to_string(), String::new()];
+        for (index, item) in ITEMS.iter().enumerate() {
+            let marker = if index == self.selected { "> " } else { "  " };
+            let editing = self.editing.as_ref().filter(|_| index == self.selected);
+            let line = match (item, editing) {
+                (Item::Rule, Some(text)) | (Item::Size, Some(text)) => {
+                    let label = if *item == Item::Rule { "Rule" } else { "Size" };
+                    format!("{}: {}_", tr(label), text)
+                }
+                (Item::Rule, None) => format!("{}: {}", tr("Rule"), settings.rule),
+                (Item::Size, None) => {
+                    format!("{}: {}x{}", tr("Size"), settings.width, settings.height)
+                }
+                (Item::Topology, _) => format!("{}: {}", tr("Topology"), settings.topology),
+                (Item::Theme, _) => format!("{}: {}", tr("Theme"), tr(settings.theme.name())),
+                (Item::Resume, _) => tr("Resume").to_string(),
+                (Item::Quit, _) => tr("Quit").to_string(),
+            };
+            lines.push(format!("{}{}", marker, line));
+        }
+        lines.push(String::new());
+        if let Some(message) = &self.message {
+            lines.push(message.clone());
+        }
+        lines.push(if self.editing.is_some() {
+            tr("Return sets it, Esc cancels").to_string()
+        } else {
+            tr("Up and Down choose, Return changes, Esc goes back to the board").to_string()
+        });
+        lines
+    }
+}
+
+/// Reads a board size typed into the menu, either one number for a square
+/// board or a width and height like `80x40`.
+pub fn parse_size(text: &str, language: Language) -> Result<(usize, usize), String> {
+    let invalid = || language.fill("'{}' is not a size like 50 or 80x40", &[&text]);
+    let text = text.trim();
+    let (width, height) = match text.split_once(['x', 'X']) {
+        Some((width, height)) => (width.trim(), height.trim()),
+        None => (text, text),
+    };
+    let width = width.parse().map_err(|_| invalid())?;
+    let height = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(language
+            .tr("the board needs at least one cell each way")
+            .to_string());
+    }
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigation_test() {
+        let mut menu = Menu::new();
+        assert_eq!(menu.selected(), Item::Rule);
+
+        menu.up();
+        assert_eq!(menu.selected(), Item::Quit);
+        menu.down();
+        menu.down();
+        assert_eq!(menu.selected(), Item::Size);
+    }
+
+    #[test]
+    fn lines_test() {
+        let settings = Settings {
+            rule: "B3/S23".to_string(),
+            width: 50,
+            height: 40,
+            topology: "torus".to_string(),
+            theme: Theme::Light,
+        };
+        let mut menu = Menu::new();
+        menu.down();
+        menu.editing = Some("8".to_string());
+
+        let lines = menu.lines(&settings, Language::English);
+
+        assert_eq!(lines[2], "  Rule: B3/S23");
+        assert_eq!(lines[3], "> Size: 8_");
+        assert_eq!(lines[4], "  Topology: torus");
+        assert_eq!(lines[5], "  Theme: light");
+        assert_eq!(lines.last().unwrap(), "Return sets it, Esc cancels");
+        let lines = menu.lines(&settings, Language::Spanish);
+        assert_eq!(lines[4], "  Topología: torus");
+        assert_eq!(lines[5], "  Tema: claro");
+    }
+
+    #[test]
+    fn parse_size_test() {
+        let parse = |text| parse_size(text, Language::English);
+
+        assert_eq!(parse("80x40"), Ok((80, 40)));
+        assert_eq!(parse(" 30 "), Ok((30, 30)));
+        assert_eq!(parse("12 X 7"), Ok((12, 7)));
+        assert!(parse("0x5").is_err());
+        assert_eq!(
+            parse_size("big", Language::Spanish),
+            Err("'big' no es un tamaño como 50 u 80x40".to_string())
+        );
+    }
+}
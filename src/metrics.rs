@@ -0,0 +1,262 @@
+//! Metrics about a run in the Prometheus text format, for keeping an eye
+//! on long runs alongside other services.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// How far back the speed of a run is measured over.
+pub const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Metrics {
+    pub generation: u64,
+    pub population: usize,
+    pub peak_population: usize,
+    /// How many cells the board has, alive or dead.
+    pub cells: usize,
+    pub births: usize,
+    pub deaths: usize,
+    pub generations_per_second: f64,
+    pub paused: bool,
+    pub memory: Option<Memory>,
+}
+
+impl Metrics {
+    /// The metrics as a Prometheus text exposition.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+            let _ = write!(
+                text,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n",
+                name = name,
+                kind = kind,
+                help = help,
+                value = value
+            );
+        };
+        metric(
+            "life_generation",
+            "gauge",
+            "The generation the board is at.",
+            self.generation as f64,
+        );
+        metric(
+            "life_population",
+            "gauge",
+            "How many cells are alive.",
+            self.population as f64,
+        );
+        metric(
+            "life_peak_population",
+            "gauge",
+            "The most cells alive at once during the run.",
+            self.peak_population as f64,
+        );
+        metric(
+            "life_board_cells",
+            "gauge",
+            "How many cells the board has.",
+            self.cells as f64,
+        );
+        metric(
+            "life_births_total",
+            "counter",
+            "Cells born during the run.",
+            self.births as f64,
+        );
+        metric(
+            "life_deaths_total",
+            "counter",
+            "Cells died during the run.",
+            self.deaths as f64,
+        );
+        metric(
+            "life_generations_per_second",
+            "gauge",
+            "How fast the board has been running lately.",
+            self.generations_per_second,
+        );
+        metric(
+            "life_paused",
+            "gauge",
+            "1 while the board is paused, otherwise 0.",
+            if self.paused { 1.0 } else { 0.0 },
+        );
+        if let Some(memory) = self.memory {
+            metric(
+                "process_resident_memory_bytes",
+                "gauge",
+                "Resident memory size in bytes.",
+                memory.resident as f64,
+            );
+            metric(
+                "process_resident_memory_peak_bytes",
+                "gauge",
+                "The largest the resident memory has been, in bytes.",
+                memory.peak_resident as f64,
+            );
+            metric(
+                "process_virtual_memory_bytes",
+                "gauge",
+                "Virtual memory size in bytes.",
+                memory.virtual_size as f64,
+            );
+        }
+        text
+    }
+}
+
+/// How much memory the process is using, in bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Memory {
+    pub resident: u64,
+    pub peak_resident: u64,
+    pub virtual_size: u64,
+}
+
+impl Memory {
+    /// The memory in use now, where the platform says, which so far is
+    /// only Linux.
+    pub fn current() -> Option<Memory> {
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| Memory::parse(&status))
+    }
+
+    /// The memory from the text of `/proc/self/status`.
+    fn parse(status: &str) -> Option<Memory> {
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .and_then(|value| value.trim().strip_suffix("kB"))
+                .and_then(|kilobytes| kilobytes.trim().parse::<u64>().ok())
+                .map(|kilobytes| kilobytes * 1024)
+        };
+        Some(Memory {
+            resident: field("VmRSS")?,
+            peak_resident: field("VmHWM")?,
+            virtual_size: field("VmSize")?,
+        })
+    }
+}
+
+/// How fast generations have gone by over the last `window`.
+#[derive(Clone, Debug)]
+pub struct Rate {
+    samples: VecDeque<(Instant, u64)>,
+    window: Duration,
+}
+
+impl Rate {
+    pub fn new(window: Duration) -> Rate {
+        Rate {
+            samples: VecDeque::new(),
+            window,
+        }
+    }
+
+    /// Notes that the board was at `generation` at `now`.
+    pub fn record(&mut self, now: Instant, generation: u64) {
+        self.samples.push_back((now, generation));
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(time, _)| now.duration_since(*time) >= self.window)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Generations a second, or 0 until there are two samples to go on.
+    pub fn per_second(&self) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((first_time, first)), Some((last_time, last))) if last_time > first_time => {
+                last.saturating_sub(*first) as f64
+                    / last_time.duration_since(*first_time).as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prometheus_test() {
+        let metrics = Metrics {
+            generation: 120,
+            population: 48,
+            cells: 400,
+            births: 300,
+            generations_per_second: 12.5,
+            paused: true,
+            ..Metrics::default()
+        };
+        let text = metrics.to_prometheus();
+
+        assert!(text.starts_with(
+            "# HELP life_generation The generation the board is at.\n\
+             # TYPE life_generation gauge\n\
+             life_generation 120\n"
+        ));
+        for line in &[
+            "life_population 48\n",
+            "life_board_cells 400\n",
+            "# TYPE life_births_total counter\nlife_births_total 300\n",
+            "life_generations_per_second 12.5\n",
+            "life_paused 1\n",
+        ] {
+            assert!(text.contains(line), "{}", line);
+        }
+        assert!(!text.contains("process_"));
+
+        let with_memory = Metrics {
+            memory: Some(Memory {
+                resident: 2048,
+                peak_resident: 4096,
+                virtual_size: 8192,
+            }),
+            ..metrics
+        };
+        assert!(with_memory
+            .to_prometheus()
+            .contains("process_resident_memory_bytes 2048\n"));
+    }
+
+    #[test]
+    fn memory_test() {
+        let status = "Name:\tlife\nVmPeak:\t  9000 kB\nVmSize:\t  8000 kB\nVmHWM:\t   300 kB\nVmRSS:\t   200 kB\n";
+        assert_eq!(
+            Memory::parse(status),
+            Some(Memory {
+                resident: 200 * 1024,
+                peak_resident: 300 * 1024,
+                virtual_size: 8000 * 1024,
+            })
+        );
+        assert_eq!(Memory::parse("Name:\tlife\n"), None);
+    }
+
+    #[test]
+    fn rate_test() {
+        let start = Instant::now();
+        let mut rate = Rate::new(Duration::from_secs(5));
+        assert_eq!(rate.per_second(), 0.0);
+        rate.record(start, 0);
+        assert_eq!(rate.per_second(), 0.0);
+
+        rate.record(start + Duration::from_secs(2), 20);
+        assert_eq!(rate.per_second(), 10.0);
+        // Paused for a while, the speed falls to nothing.
+        rate.record(start + Duration::from_secs(8), 20);
+        rate.record(start + Duration::from_secs(14), 20);
+        assert_eq!(rate.per_second(), 0.0);
+        rate.record(start + Duration::from_secs(16), 60);
+        assert_eq!(rate.per_second(), 5.0);
+    }
+}
@@ -0,0 +1,192 @@
+//! Brief effects where cells are born and die: a pop that spreads and fades
+//! round each birth, and sparks that fly off from each death. They are
+//! spawned from the difference between one generation and the next, and
+//! measured in cells so they can be drawn at any size.
+
+use crate::grid::{Grid, State};
+use rand::Rng;
+use std::f32::consts::PI;
+
+/// How long a pop lasts, in seconds.
+const POP_LIFE: f32 = 0.3;
+
+/// How long a spark lasts, in seconds.
+const SPARK_LIFE: f32 = 0.6;
+
+/// How many sparks fly off from each cell that dies.
+const SPARKS_PER_DEATH: usize = 3;
+
+/// The fastest a spark flies, in cells a second.
+const SPARK_SPEED: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// A ring spreading out from a cell being born.
+    Pop,
+    /// A speck flying off from a cell dying.
+    Spark,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub kind: Kind,
+    /// Where it is, in rows and columns from the top left corner of the
+    /// board, with a cell's centre half a cell in from its corner.
+    pub row: f32,
+    pub col: f32,
+    /// How fast it is moving, in cells a second.
+    velocity: (f32, f32),
+    /// How long it has lasted, in seconds.
+    age: f32,
+}
+
+impl Particle {
+    /// How much of its life is left, from 1 when it is spawned down to 0 as
+    /// it disappears.
+    pub fn life(&self) -> f32 {
+        let lifetime = match self.kind {
+            Kind::Pop => POP_LIFE,
+            Kind::Spark => SPARK_LIFE,
+        };
+        (1.0 - self.age / lifetime).max(0.0)
+    }
+}
+
+/// The particles on screen, up to a limit so a soup exploding all at once
+/// can't slow drawing to a crawl.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Particles {
+    particles: Vec<Particle>,
+    limit: usize,
+}
+
+impl Particles {
+    pub fn new(limit: usize) -> Particles {
+        Particles {
+            particles: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Spawns a pop for every cell born going from `before` to `after`, and
+    /// sparks flying off in random directions from every cell that died.
+    pub fn spawn<R: Rng + ?Sized>(&mut self, before: &Grid, after: &Grid, rng: &mut R) {
+        let rows = before.cells.iter().zip(&after.cells).enumerate();
+        for (row, (before_row, after_row)) in rows {
+            for (col, (before, after)) in before_row.iter().zip(after_row).enumerate() {
+                let (row, col) = (row as f32 + 0.5, col as f32 + 0.5);
+                match (before, after) {
+                    (State::Dead, State::Alive) => self.push(Kind::Pop, row, col, (0.0, 0.0)),
+                    (State::Alive, State::Dead) => {
+                        for _ in 0..SPARKS_PER_DEATH {
+                            let angle = rng.gen_range(0.0, 2.0 * PI);
+                            let speed = rng.gen_range(SPARK_SPEED / 3.0, SPARK_SPEED);
+                            let velocity = (angle.sin() * speed, angle.cos() * speed);
+                            self.push(Kind::Spark, row, col, velocity);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, kind: Kind, row: f32, col: f32, velocity: (f32, f32)) {
+        if self.particles.len() < self.limit {
+            self.particles.push(Particle {
+                kind,
+                row,
+                col,
+                velocity,
+                age: 0.0,
+            });
+        }
+    }
+
+    /// Moves every particle on by `seconds`, dropping those that have
+    /// faded away.
+    pub fn update(&mut self, seconds: f32) {
+        for particle in &mut self.particles {
+            particle.row += particle.velocity.0 * seconds;
+            particle.col += particle.velocity.1 * seconds;
+            particle.age += seconds;
+        }
+        self.particles.retain(|particle| particle.life() > 0.0);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn spawn_test() {
+        let blinker = Grid::seed(5, vec![(2, 1), (2, 2), (2, 3)]);
+        let mut particles = Particles::new(100);
+
+        particles.spawn(
+            &blinker,
+            &blinker.next_generation(),
+            &mut StdRng::seed_from_u64(1),
+        );
+
+        let pops: Vec<_> = particles
+            .iter()
+            .filter(|particle| particle.kind == Kind::Pop)
+            .map(|particle| (particle.row, particle.col))
+            .collect();
+        assert_eq!(pops, vec![(1.5, 2.5), (3.5, 2.5)]);
+        assert_eq!(particles.len(), 2 + 2 * SPARKS_PER_DEATH);
+        assert!(particles.iter().all(|particle| particle.life() == 1.0));
+    }
+
+    #[test]
+    fn update_test() {
+        let before = Grid::seed(3, vec![(1, 1)]);
+        let after = Grid::seed(3, vec![]);
+        let mut particles = Particles::new(100);
+        particles.spawn(&before, &after, &mut StdRng::seed_from_u64(2));
+
+        particles.update(SPARK_LIFE / 2.0);
+
+        assert_eq!(particles.len(), SPARKS_PER_DEATH);
+        for particle in particles.iter() {
+            assert!((particle.life() - 0.5).abs() < 1e-6);
+            let distance = ((particle.row - 1.5).powi(2) + (particle.col - 1.5).powi(2)).sqrt();
+            assert!(distance > 0.0 && distance <= SPARK_SPEED * SPARK_LIFE / 2.0 + 1e-6);
+        }
+        particles.update(SPARK_LIFE);
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn limit_test() {
+        let full = Grid::seed(4, vec![(0, 0), (0, 1), (1, 0), (3, 3)]);
+        let mut particles = Particles::new(5);
+
+        particles.spawn(&Grid::seed(4, vec![]), &full, &mut StdRng::seed_from_u64(3));
+        particles.spawn(&full, &Grid::seed(4, vec![]), &mut StdRng::seed_from_u64(3));
+
+        assert_eq!(particles.len(), 5);
+        particles.clear();
+        assert!(particles.is_empty());
+    }
+}
@@ -0,0 +1,445 @@
+use crate::grid::{Grid, State};
+use crate::rule::Rule;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+/// Width and height of a chunk in cells, one bit per cell in a `u64` row.
+const CHUNK: usize = 64;
+
+/// The position of a chunk, in chunks from the one holding cell `(0, 0)`.
+type Key = (i64, i64);
+
+/// The first and last rows and columns of chunks, those holding rows and
+/// columns `i64::MIN` and `i64::MAX`.
+const FIRST_KEY: i64 = i64::MIN / CHUNK as i64;
+const LAST_KEY: i64 = i64::MAX / CHUNK as i64;
+
+type Chunk = [u64; CHUNK];
+
+/// An unbounded board, stored as square chunks of cells that are only
+/// allocated while something lives in them. Rows and columns can be any
+/// `i64`, negative included, so patterns never meet an edge in practice;
+/// past `i64::MIN` and `i64::MAX` every cell is dead.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Plane {
+    chunks: HashMap<Key, Chunk>,
+}
+
+/// The chunk holding a cell and the cell's row and column within it.
+fn locate(row: i64, col: i64) -> (Key, usize, usize) {
+    let size = CHUNK as i64;
+    (
+        (row.div_euclid(size), col.div_euclid(size)),
+        row.rem_euclid(size) as usize,
+        col.rem_euclid(size) as usize,
+    )
+}
+
+/// The row or column `by` cells down or across from `origin`, or `None` if
+/// that is past the edge of the plane.
+pub fn offset(origin: i64, by: usize) -> Option<i64> {
+    i64::try_from(by).ok().and_then(|by| origin.checked_add(by))
+}
+
+impl Plane {
+    pub fn new() -> Plane {
+        Plane::default()
+    }
+
+    /// A plane holding `grid` with its top left corner at `top` and `left`,
+    /// without any of it that would be past the edge.
+    pub fn from_grid(grid: &Grid, top: i64, left: i64) -> Plane {
+        let mut plane = Plane::new();
+        for (row, col) in grid.living_cells() {
+            if let (Some(row), Some(col)) = (offset(top, row), offset(left, col)) {
+                plane.set(row, col, true);
+            }
+        }
+        plane
+    }
+
+    /// A plane with `cells` alive and no others.
+    pub fn from_cells(cells: &[(i64, i64)]) -> Plane {
+        let mut plane = Plane::new();
+        for &(row, col) in cells {
+            plane.set(row, col, true);
+        }
+        plane
+    }
+
+    pub fn is_alive(&self, row: i64, col: i64) -> bool {
+        let (key, row, col) = locate(row, col);
+        self.chunks
+            .get(&key)
+            .is_some_and(|chunk| chunk[row] & (1 << col) != 0)
+    }
+
+    /// How many of the eight cells round the one at `row` and `col` are
+    /// alive.
+    pub fn live_neighbours(&self, row: i64, col: i64) -> usize {
+        let mut count = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbour = row.checked_add(dy).zip(col.checked_add(dx));
+                if (dy, dx) != (0, 0) && neighbour.is_some_and(|(row, col)| self.is_alive(row, col))
+                {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    pub fn set(&mut self, row: i64, col: i64, alive: bool) {
+        let (key, row, col) = locate(row, col);
+        if alive {
+            self.chunks.entry(key).or_insert([0; CHUNK])[row] |= 1 << col;
+        } else if let Some(chunk) = self.chunks.get_mut(&key) {
+            chunk[row] &= !(1 << col);
+            if chunk.iter().all(|bits| *bits == 0) {
+                self.chunks.remove(&key);
+            }
+        }
+    }
+
+    pub fn toggle(&mut self, row: i64, col: i64) {
+        let alive = self.is_alive(row, col);
+        self.set(row, col, !alive);
+    }
+
+    pub fn population(&self) -> usize {
+        self.chunks
+            .values()
+            .flat_map(|chunk| chunk.iter())
+            .map(|bits| bits.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// How many chunks are allocated.
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// The `(row, column)` of every living cell, in order.
+    pub fn living_cells(&self) -> Vec<(i64, i64)> {
+        let size = CHUNK as i64;
+        let mut living_cells = Vec::with_capacity(self.population());
+        for (&(chunk_row, chunk_col), chunk) in &self.chunks {
+            for (row, bits) in chunk.iter().enumerate() {
+                for col in 0..CHUNK {
+                    if bits & (1 << col) != 0 {
+                        living_cells
+                            .push((chunk_row * size + row as i64, chunk_col * size + col as i64));
+                    }
+                }
+            }
+        }
+        living_cells.sort_unstable();
+        living_cells
+    }
+
+    /// The cells alive in just one of `self` and `other`, such as those that
+    /// were born or died between two generations.
+    pub fn difference(&self, other: &Plane) -> Plane {
+        let mut chunks: HashMap<Key, Chunk> = HashMap::new();
+        for (key, chunk) in self.chunks.iter().chain(&other.chunks) {
+            let difference = chunks.entry(*key).or_insert([0; CHUNK]);
+            for (bits, other_bits) in difference.iter_mut().zip(chunk) {
+                *bits ^= other_bits;
+            }
+        }
+        chunks.retain(|_, chunk| chunk.iter().any(|bits| *bits != 0));
+        Plane { chunks }
+    }
+
+    /// The average position of the living cells, or `None` if there are
+    /// none.
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        let cells = self.living_cells();
+        if cells.is_empty() {
+            return None;
+        }
+        let (rows, cols) = cells.iter().fold((0.0, 0.0), |(rows, cols), &(row, col)| {
+            (rows + row as f64, cols + col as f64)
+        });
+        Some((rows / cells.len() as f64, cols / cells.len() as f64))
+    }
+
+    /// The smallest rectangle holding every living cell, as its top, left,
+    /// bottom and right rows and columns inclusive.
+    pub fn bounds(&self) -> Option<(i64, i64, i64, i64)> {
+        self.living_cells()
+            .into_iter()
+            .fold(None, |bounds, (row, col)| {
+                Some(match bounds {
+                    Some((top, left, bottom, right)) => {
+                        (top.min(row), left.min(col), bottom.max(row), right.max(col))
+                    }
+                    None => (row, col, row, col),
+                })
+            })
+    }
+
+    /// The `width` by `height` window onto the plane with its top left
+    /// corner at `top` and `left`, dead where it goes past the edge.
+    pub fn view(&self, top: i64, left: i64, width: usize, height: usize) -> Grid {
+        let cells = (0..height)
+            .map(|row| {
+                (0..width)
+                    .map(|col| {
+                        let cell = offset(top, row).zip(offset(left, col));
+                        if cell.is_some_and(|(row, col)| self.is_alive(row, col)) {
+                            State::Alive
+                        } else {
+                            State::Dead
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Grid { cells }
+    }
+
+    /// Applies the rules of the game everywhere at once.
+    pub fn next_generation(&self) -> Plane {
+        self.next_generation_under(Rule::default())
+    }
+
+    /// Applies `rule` everywhere at once. As no rule brings cells to life
+    /// with no neighbours, only chunks with living cells, and the chunks
+    /// next to them, can have any next time, and none past the edge do.
+    pub fn next_generation_under(&self, rule: Rule) -> Plane {
+        let keys = FIRST_KEY..=LAST_KEY;
+        let mut candidates = HashSet::new();
+        for &(chunk_row, chunk_col) in self.chunks.keys() {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let key = (chunk_row + dy, chunk_col + dx);
+                    if keys.contains(&key.0) && keys.contains(&key.1) {
+                        candidates.insert(key);
+                    }
+                }
+            }
+        }
+
+        let mut chunks = HashMap::new();
+        for key in candidates {
+            let chunk = self.next_chunk(key, rule);
+            if chunk.iter().any(|bits| *bits != 0) {
+                chunks.insert(key, chunk);
+            }
+        }
+        Plane { chunks }
+    }
+
+    fn next_chunk(&self, (chunk_row, chunk_col): Key, rule: Rule) -> Chunk {
+        // The chunk and its eight neighbours, row by row from the top left.
+        let mut area = [None; 9];
+        for (i, neighbour) in area.iter_mut().enumerate() {
+            let key = (chunk_row + i as i64 / 3 - 1, chunk_col + i as i64 % 3 - 1);
+            *neighbour = self.chunks.get(&key);
+        }
+        // A row of the area, counted from the chunk's top and up to one row
+        // outside it, as the cells to the west of each cell in the row, the
+        // cells themselves and the cells to their east.
+        let row_at = |row: isize| -> (u64, u64, u64) {
+            let (area_row, row) = match row {
+                -1 => (0, CHUNK - 1),
+                r if r == CHUNK as isize => (2, 0),
+                r => (1, r as usize),
+            };
+            let bits =
+                |area_col: usize| area[area_row * 3 + area_col].map_or(0, |chunk| chunk[row]);
+            let (left, middle, right) = (bits(0), bits(1), bits(2));
+            (
+                middle << 1 | left >> (CHUNK - 1),
+                middle,
+                middle >> 1 | right << (CHUNK - 1),
+            )
+        };
+
+        let mut next = [0; CHUNK];
+        for (row, bits) in next.iter_mut().enumerate() {
+            let row = row as isize;
+            let (above_west, above, above_east) = row_at(row - 1);
+            let (west, alive, east) = row_at(row);
+            let (below_west, below, below_east) = row_at(row + 1);
+            let neighbours = [
+                above_west, above, above_east, west, east, below_west, below, below_east,
+            ];
+            *bits = if rule.is_conway() {
+                conway(neighbours, alive)
+            } else {
+                by_rule(neighbours, alive, rule)
+            };
+        }
+        next
+    }
+}
+
+/// The next state of a row of cells by Conway's rule, given the rows of
+/// their eight neighbours. Their neighbours are counted a bit of each word
+/// for each cell: the count's ones and twos, and whether it has reached
+/// four.
+fn conway(neighbours: [u64; 8], alive: u64) -> u64 {
+    let (mut ones, mut twos, mut fours) = (0u64, 0u64, 0u64);
+    for neighbours in neighbours {
+        let carry = ones & neighbours;
+        ones ^= neighbours;
+        fours |= twos & carry;
+        twos ^= carry;
+    }
+    // Three neighbours, or two for a living cell.
+    !fours & twos & (ones | alive)
+}
+
+/// The next state of a row of cells by any rule, given the rows of their
+/// eight neighbours. The counts are added up in full, four bits to a cell,
+/// then each count the rule cares about is picked out in turn.
+fn by_rule(neighbours: [u64; 8], alive: u64, rule: Rule) -> u64 {
+    let mut count = [0u64; 4];
+    for neighbours in neighbours {
+        let mut carry = neighbours;
+        for bit in count.iter_mut() {
+            let next = *bit & carry;
+            *bit ^= carry;
+            carry = next;
+        }
+    }
+    let mut next = 0;
+    for n in 0..=8 {
+        let (born, survives) = (rule.born(n), rule.survives(n));
+        if !born && !survives {
+            continue;
+        }
+        let with_n = count.iter().enumerate().fold(!0, |cells, (bit, &ones)| {
+            cells & if n & 1 << bit != 0 { ones } else { !ones }
+        });
+        next |= with_n & (if born { !alive } else { 0 } | if survives { alive } else { 0 });
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blinker_across_chunks_test() {
+        // Straddles the corner where four chunks meet.
+        let mut plane = Plane::new();
+        for col in -1..=1 {
+            plane.set(-1, col, true);
+        }
+
+        let next = plane.next_generation();
+
+        assert_eq!(next.living_cells(), vec![(-2, 0), (-1, 0), (0, 0)]);
+        assert_eq!(next.next_generation(), plane);
+        assert_eq!(plane.live_neighbours(-1, 0), 2);
+        assert_eq!(plane.live_neighbours(0, 0), 3);
+    }
+
+    #[test]
+    fn glider_travels_test() {
+        let glider = Grid::seed(3, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let mut plane = Plane::from_grid(&glider, 60, 60);
+
+        for _ in 0..4 * 100 {
+            plane = plane.next_generation();
+        }
+
+        assert_eq!(plane.population(), 5);
+        assert_eq!(plane.bounds(), Some((160, 160, 162, 162)));
+        assert_eq!(plane.view(160, 160, 3, 3), glider);
+        assert_eq!(plane.chunks(), 1);
+    }
+
+    #[test]
+    fn difference_test() {
+        let mut blinker = Plane::new();
+        for col in 63..=65 {
+            blinker.set(10, col, true);
+        }
+
+        let changes = blinker.difference(&blinker.next_generation());
+
+        assert_eq!(
+            changes.living_cells(),
+            vec![(9, 64), (10, 63), (10, 65), (11, 64)]
+        );
+        assert_eq!(changes.centroid(), Some((10.0, 64.0)));
+        assert!(blinker.difference(&blinker).is_empty());
+        assert_eq!(Plane::new().centroid(), None);
+    }
+
+    #[test]
+    fn edit_test() {
+        let mut plane = Plane::new();
+        plane.toggle(-100, 5);
+        assert!(plane.is_alive(-100, 5));
+        assert_eq!(plane.view(-101, 4, 3, 3).living_cells(), vec![(1, 1)]);
+
+        plane.toggle(-100, 5);
+
+        assert!(plane.is_empty());
+        assert_eq!(plane.bounds(), None);
+    }
+
+    #[test]
+    fn edge_test() {
+        // A blinker along the bottom edge has nowhere to turn to below it.
+        let mut blinker = Plane::new();
+        for col in i64::MAX - 2..=i64::MAX {
+            blinker.set(i64::MAX, col, true);
+        }
+        assert_eq!(blinker.live_neighbours(i64::MAX, i64::MAX), 1);
+        assert_eq!(
+            blinker.next_generation().living_cells(),
+            vec![(i64::MAX - 1, i64::MAX - 1), (i64::MAX, i64::MAX - 1)]
+        );
+        assert_eq!(
+            blinker.view(i64::MAX, i64::MAX - 2, 4, 2).living_cells(),
+            vec![(0, 0), (0, 1), (0, 2)]
+        );
+
+        let mut blinker = Plane::new();
+        for row in [i64::MIN, i64::MIN + 1, i64::MIN + 2] {
+            blinker.set(row, i64::MIN, true);
+        }
+        assert_eq!(
+            blinker.next_generation().living_cells(),
+            vec![(i64::MIN + 1, i64::MIN), (i64::MIN + 1, i64::MIN + 1)]
+        );
+
+        let grid = Grid::seed(3, vec![(0, 0), (2, 2)]);
+        let plane = Plane::from_grid(&grid, i64::MAX - 1, 0);
+        assert_eq!(plane.living_cells(), vec![(i64::MAX - 1, 0)]);
+        assert_eq!(offset(i64::MAX, 1), None);
+        assert_eq!(offset(-3, 3), Some(0));
+    }
+
+    #[test]
+    fn rule_test() {
+        // A soup in the middle of a board big enough that nothing reaches
+        // its edges, run by HighLife on the board and on the plane.
+        use rand::{rngs::StdRng, SeedableRng};
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        let soup = Grid::random_grid(12, 0.5, &mut StdRng::seed_from_u64(3));
+        let mut grid = Grid::seed(80, vec![]);
+        for (row, col) in soup.living_cells() {
+            grid.cells[row + 34][col + 34] = State::Alive;
+        }
+        let mut plane = Plane::from_grid(&grid, -40, -40);
+        for _ in 0..20 {
+            grid = grid.next_generation_under(Default::default(), highlife);
+            plane = plane.next_generation_under(highlife);
+            assert_eq!(plane.view(-40, -40, 80, 80), grid);
+        }
+        assert_eq!(plane.population(), grid.living_cells().len());
+    }
+}
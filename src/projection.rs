@@ -0,0 +1,225 @@
+//! Ways of showing the board in the window other than as a flat grid. They
+//! only change how the cells are drawn, never how the board runs.
+
+use crate::grid::{Grid, State};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// How far the top of the sphere is tipped towards the viewer, in radians,
+/// so the northern rows can be seen as well as the equator.
+const TILT: f32 = 0.35;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum View {
+    /// The grid as it is, one square per cell.
+    #[default]
+    Flat,
+    /// The grid wrapped round a spinning globe, the columns running round
+    /// the equator and the rows from pole to pole, so patterns crossing the
+    /// left and right edges of a torus or cylinder go straight on.
+    Sphere,
+    /// The living cells as little cubes standing on the board, seen from
+    /// above one corner.
+    Isometric,
+}
+
+impl View {
+    /// The view `V` switches to from this one.
+    pub fn next(self) -> View {
+        match self {
+            View::Flat => View::Sphere,
+            View::Sphere => View::Isometric,
+            View::Isometric => View::Flat,
+        }
+    }
+
+    /// The view's name, as `--view` takes it.
+    pub fn name(self) -> &'static str {
+        match self {
+            View::Flat => "flat",
+            View::Sphere => "sphere",
+            View::Isometric => "isometric",
+        }
+    }
+}
+
+impl FromStr for View {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<View, String> {
+        match s {
+            "flat" => Ok(View::Flat),
+            "sphere" => Ok(View::Sphere),
+            "isometric" => Ok(View::Isometric),
+            _ => Err(format!(
+                "unknown view '{}', use flat, sphere or isometric",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for View {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl From<View> for String {
+    fn from(view: View) -> String {
+        view.to_string()
+    }
+}
+
+impl TryFrom<String> for View {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<View, String> {
+        s.parse()
+    }
+}
+
+/// Where the middle of a cell of a `width` by `height` grid appears on a
+/// sphere of radius 1 that has spun `rotation` radians about its axis, as
+/// the distances right and down from the middle of the sphere and how
+/// squarely the cell faces the viewer, from 0 at the rim to 1 head on.
+/// Cells on the far side are `None`.
+pub fn on_sphere(
+    width: usize,
+    height: usize,
+    row: usize,
+    col: usize,
+    rotation: f32,
+) -> Option<(f32, f32, f32)> {
+    let latitude = PI * ((row as f32 + 0.5) / height as f32 - 0.5);
+    let longitude = 2.0 * PI * (col as f32 + 0.5) / width as f32 + rotation;
+    let x = latitude.cos() * longitude.sin();
+    let y = latitude.sin();
+    let z = latitude.cos() * longitude.cos();
+
+    let facing = z * TILT.cos() - y * TILT.sin();
+    if facing <= 0.0 {
+        return None;
+    }
+    Some((x, y * TILT.cos() + z * TILT.sin(), facing))
+}
+
+/// Where the top corner of a cell's square lands in the isometric view,
+/// right and down from the top corner of the board, with each square two
+/// units wide and one high.
+pub fn isometric(row: usize, col: usize) -> (f32, f32) {
+    (col as f32 - row as f32, (col + row) as f32 / 2.0)
+}
+
+/// How many generations each cell has been alive for in a row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ages {
+    generation: u64,
+    ages: Vec<Vec<u32>>,
+}
+
+impl Ages {
+    /// Starts counting at `generation`, with every living cell just born.
+    pub fn new(generation: u64, grid: &Grid) -> Ages {
+        let mut ages = Ages {
+            generation,
+            ages: vec![vec![0; grid.width()]; grid.height()],
+        };
+        ages.count(grid);
+        ages
+    }
+
+    /// Brings the ages up to `grid` at `generation`. Only the generation
+    /// straight after the last one can carry the count on; after any other
+    /// jump, or a change of size, every cell starts again.
+    pub fn update(&mut self, generation: u64, grid: &Grid) {
+        let same_size = self.ages.len() == grid.height()
+            && self.ages.first().map_or(0, Vec::len) == grid.width();
+        if !same_size || (generation != self.generation && generation != self.generation + 1) {
+            *self = Ages::new(generation, grid);
+        } else if generation == self.generation + 1 {
+            self.generation = generation;
+            self.count(grid);
+        }
+    }
+
+    fn count(&mut self, grid: &Grid) {
+        for (ages, cells) in self.ages.iter_mut().zip(&grid.cells) {
+            for (age, cell) in ages.iter_mut().zip(cells) {
+                *age = match cell {
+                    State::Alive => *age + 1,
+                    State::Dead => 0,
+                };
+            }
+        }
+    }
+
+    /// How many generations the cell has been alive for, counting the one
+    /// it was last updated to, or 0 if it is dead.
+    pub fn age(&self, row: usize, col: usize) -> u32 {
+        self.ages
+            .get(row)
+            .and_then(|ages| ages.get(col))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn on_sphere_test() {
+        // Half a cell back brings column 0 of the middle row to the front.
+        let rotation = -PI / 10.0;
+        let (x, y, facing) = on_sphere(10, 9, 4, 0, rotation).unwrap();
+        assert!(x.abs() < 1e-5);
+        assert!((y - TILT.sin()).abs() < 1e-5);
+        assert!((facing - TILT.cos()).abs() < 1e-5);
+
+        assert_eq!(on_sphere(10, 9, 4, 5, rotation), None);
+        let (x, _, _) = on_sphere(10, 9, 4, 2, rotation).unwrap();
+        assert!(x > 0.0);
+        let (_, y, _) = on_sphere(10, 9, 0, 0, rotation).unwrap();
+        assert!(y < 0.0);
+    }
+
+    #[test]
+    fn isometric_test() {
+        assert_eq!(isometric(0, 0), (0.0, 0.0));
+        assert_eq!(isometric(0, 1), (1.0, 0.5));
+        assert_eq!(isometric(1, 0), (-1.0, 0.5));
+        assert_eq!(isometric(2, 2), (0.0, 2.0));
+    }
+
+    #[test]
+    fn ages_test() {
+        let blinker = Grid::seed(3, vec![(1, 0), (1, 1), (1, 2)]);
+        let mut ages = Ages::new(0, &blinker);
+
+        ages.update(1, &blinker.next_generation());
+        ages.update(2, &blinker);
+
+        assert_eq!(ages.age(1, 1), 3);
+        assert_eq!(ages.age(1, 0), 1);
+        assert_eq!(ages.age(0, 1), 0);
+        ages.update(7, &blinker);
+        assert_eq!(ages.age(1, 1), 1);
+        assert_eq!(ages.age(5, 5), 0);
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("sphere".parse(), Ok(View::Sphere));
+        assert_eq!(View::Sphere.to_string(), "sphere");
+        assert_eq!(View::Sphere.next(), View::Isometric);
+        assert_eq!(View::Isometric.next(), View::Flat);
+        assert_eq!("isometric".parse(), Ok(View::Isometric));
+        assert!("cube".parse::<View>().is_err());
+    }
+}
@@ -0,0 +1,318 @@
+//! Python bindings to the engine, built with the `python` feature, so
+//! experiments can be scripted in notebooks while the generations are run
+//! in Rust. Built with maturin it is imported as `conways_game_of_life`:
+//!
+//! ```python
+//! import conways_game_of_life as life
+//!
+//! grid = life.Grid.from_rle("bo$2bo$3o!", 20, 20, topology="torus")
+//! grid.step(80)
+//! print(grid.population, grid.living_cells())
+//! ```
+
+use crate::grid::{self, Grid, State, RULE};
+use crate::json;
+use crate::rle;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+
+fn value_error(message: String) -> PyErr {
+    PyValueError::new_err(message)
+}
+
+fn topology(name: Option<&str>) -> PyResult<Topology> {
+    name.map_or(Ok(Topology::default()), |name| {
+        name.parse().map_err(value_error)
+    })
+}
+
+fn rule(name: Option<&str>) -> PyResult<Option<Rule>> {
+    name.map(|name| grid::check_rule(name).map_err(value_error))
+        .transpose()
+}
+
+/// A board, how its edges are joined and the rule it runs by.
+#[pyclass(name = "Grid")]
+#[derive(Clone)]
+pub struct PyGrid {
+    grid: Grid,
+    topology: Topology,
+    rule: Rule,
+}
+
+impl PyGrid {
+    fn check(&self, row: usize, col: usize) -> PyResult<()> {
+        if row < self.grid.height() && col < self.grid.width() {
+            Ok(())
+        } else {
+            Err(PyIndexError::new_err(format!(
+                "{},{} is off the {}x{} grid",
+                row,
+                col,
+                self.grid.width(),
+                self.grid.height()
+            )))
+        }
+    }
+}
+
+#[pymethods]
+impl PyGrid {
+    /// An empty board `width` by `height`, with its edges joined as
+    /// `topology` says, like `"torus"`, or not at all, run by `rule`, like
+    /// `"B36/S23"`, or Conway's.
+    #[new]
+    #[pyo3(signature = (width, height, topology = None, rule = None))]
+    fn new(
+        width: usize,
+        height: usize,
+        topology: Option<&str>,
+        rule: Option<&str>,
+    ) -> PyResult<PyGrid> {
+        Ok(PyGrid {
+            grid: Grid::seed_rectangle(width, height, vec![]),
+            topology: self::topology(topology)?,
+            rule: self::rule(rule)?.unwrap_or_default(),
+        })
+    }
+
+    /// The RLE pattern `text` in the middle of a board at least `width` by
+    /// `height`, or the pattern's own size if they aren't given, run by
+    /// `rule` or else the rule in the pattern's header.
+    #[staticmethod]
+    #[pyo3(signature = (text, width = 0, height = 0, topology = None, rule = None))]
+    fn from_rle(
+        text: &str,
+        width: usize,
+        height: usize,
+        topology: Option<&str>,
+        rule: Option<&str>,
+    ) -> PyResult<PyGrid> {
+        let pattern = rle::parse(text).map_err(value_error)?;
+        Ok(PyGrid {
+            grid: pattern.fitted(width.max(1), height.max(1), 0),
+            topology: self::topology(topology)?,
+            rule: self::rule(rule)?.unwrap_or(pattern.rule),
+        })
+    }
+
+    /// The board of a JSON grid, as `to_json` writes.
+    #[staticmethod]
+    #[pyo3(signature = (text, topology = None))]
+    fn from_json(text: &str, topology: Option<&str>) -> PyResult<PyGrid> {
+        let (grid, rule) = json::from_json_with_rule(text).map_err(value_error)?;
+        Ok(PyGrid {
+            grid,
+            topology: self::topology(topology)?,
+            rule,
+        })
+    }
+
+    /// The board of a share string, as `to_share_string` writes.
+    #[staticmethod]
+    #[pyo3(signature = (text, topology = None))]
+    fn from_share_string(text: &str, topology: Option<&str>) -> PyResult<PyGrid> {
+        let (grid, rule) = Grid::from_share_string_with_rule(text).map_err(value_error)?;
+        Ok(PyGrid {
+            grid,
+            topology: self::topology(topology)?,
+            rule,
+        })
+    }
+
+    fn to_json(&self) -> String {
+        json::to_json_under(&self.grid, None, self.rule)
+    }
+
+    fn to_share_string(&self) -> String {
+        self.grid.to_share_string_under(self.rule)
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.grid.width()
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.grid.height()
+    }
+
+    #[getter]
+    fn population(&self) -> usize {
+        self.grid.population()
+    }
+
+    #[getter]
+    fn topology(&self) -> String {
+        self.topology.to_string()
+    }
+
+    #[getter]
+    fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    fn get(&self, row: usize, col: usize) -> PyResult<bool> {
+        self.check(row, col)?;
+        Ok(self.grid.cells[row][col] == State::Alive)
+    }
+
+    #[pyo3(signature = (row, col, alive = true))]
+    fn set(&mut self, row: usize, col: usize, alive: bool) -> PyResult<()> {
+        self.check(row, col)?;
+        self.grid.cells[row][col] = if alive { State::Alive } else { State::Dead };
+        Ok(())
+    }
+
+    /// The row and column of every living cell.
+    fn living_cells(&self) -> Vec<(usize, usize)> {
+        self.grid.living_cells()
+    }
+
+    /// Runs `generations` generations, 1 if not given.
+    #[pyo3(signature = (generations = 1))]
+    fn step(&mut self, py: Python, generations: u64) {
+        let (topology, rule) = (self.topology, self.rule);
+        let mut grid = self.grid.clone();
+        // Let other Python threads run while the board does.
+        py.allow_threads(|| {
+            for _ in 0..generations {
+                grid = grid.next_generation_under(topology, rule);
+            }
+        });
+        self.grid = grid;
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Grid({}x{}, population {}, {}, {})",
+            self.grid.width(),
+            self.grid.height(),
+            self.grid.population(),
+            self.topology,
+            self.rule
+        )
+    }
+}
+
+/// Fails if `rule` is not one the engine can run.
+#[pyfunction]
+fn check_rule(rule: &str) -> PyResult<()> {
+    grid::check_rule(rule).map(drop).map_err(value_error)
+}
+
+#[pymodule]
+fn conways_game_of_life(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGrid>()?;
+    module.add_function(wrap_pyfunction!(check_rule, module)?)?;
+    module.add("RULE", RULE)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_python<F: FnOnce(Python)>(test: F) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(test);
+    }
+
+    #[test]
+    fn from_rle_test() {
+        with_python(|py| {
+            let mut glider = PyGrid::from_rle("bo$2bo$3o!", 8, 8, Some("torus"), None).unwrap();
+            assert_eq!((glider.width(), glider.height()), (8, 8));
+            assert_eq!(glider.population(), 5);
+            assert_eq!(glider.topology(), "torus");
+            assert_eq!(glider.rule(), RULE);
+
+            let start = glider.living_cells();
+            // On an 8x8 torus a glider is back where it started after 32
+            // generations.
+            glider.step(py, 32);
+            assert_eq!(glider.living_cells(), start);
+            glider.step(py, 1);
+            assert_ne!(glider.living_cells(), start);
+            assert_eq!(glider.population(), 5);
+
+            let header = "x = 3, y = 1, rule = B36/S23\n3o!";
+            let highlife = PyGrid::from_rle(header, 0, 0, None, None).unwrap();
+            assert_eq!(highlife.rule(), "B36/S23");
+            let seeds = PyGrid::from_rle(header, 0, 0, None, Some("B2/S")).unwrap();
+            assert_eq!(seeds.rule(), "B2/S");
+            assert_eq!((highlife.width(), highlife.height()), (3, 1));
+        });
+    }
+
+    #[test]
+    fn get_and_set_test() {
+        with_python(|py| {
+            let mut grid = PyGrid::new(4, 3, None, None).unwrap();
+            assert!(!grid.get(2, 3).unwrap());
+            grid.set(2, 3, true).unwrap();
+            assert!(grid.get(2, 3).unwrap());
+            grid.set(2, 3, false).unwrap();
+            assert_eq!(grid.population(), 0);
+
+            for (row, col) in [(3, 0), (0, 4), (usize::MAX, 0)] {
+                assert!(grid
+                    .get(row, col)
+                    .unwrap_err()
+                    .is_instance_of::<PyIndexError>(py));
+                assert!(grid
+                    .set(row, col, true)
+                    .unwrap_err()
+                    .is_instance_of::<PyIndexError>(py));
+            }
+        });
+    }
+
+    #[test]
+    fn bad_arguments_test() {
+        with_python(|py| {
+            let value_error = |result: PyResult<PyGrid>| match result {
+                Err(e) => e.is_instance_of::<PyValueError>(py),
+                Ok(_) => false,
+            };
+            assert!(value_error(PyGrid::new(4, 4, Some("doughnut"), None)));
+            assert!(value_error(PyGrid::new(4, 4, None, Some("B0/S"))));
+            assert!(value_error(PyGrid::from_rle(
+                "3o!",
+                0,
+                0,
+                Some("doughnut"),
+                None
+            )));
+            assert!(value_error(PyGrid::from_rle("3q!", 0, 0, None, None)));
+            assert!(value_error(PyGrid::from_share_string("not base64!", None)));
+            assert!(check_rule("B3/S23").is_ok());
+            assert!(check_rule("life")
+                .unwrap_err()
+                .is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn share_string_test() {
+        with_python(|_| {
+            let mut grid = PyGrid::new(6, 5, Some("klein"), Some("B36/S23")).unwrap();
+            grid.set(1, 2, true).unwrap();
+            grid.set(4, 5, true).unwrap();
+
+            let text = grid.to_share_string();
+            let back = PyGrid::from_share_string(&text, Some("klein")).unwrap();
+            assert_eq!(back.living_cells(), vec![(1, 2), (4, 5)]);
+            assert_eq!((back.width(), back.height()), (6, 5));
+            assert_eq!(back.rule(), "B36/S23");
+            assert_eq!(back.__repr__(), grid.__repr__());
+
+            let json = PyGrid::from_json(&grid.to_json(), None).unwrap();
+            assert_eq!(json.living_cells(), grid.living_cells());
+            assert_eq!(json.rule(), "B36/S23");
+        });
+    }
+}
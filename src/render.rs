@@ -0,0 +1,160 @@
+use crate::grid::{Grid, State};
+use image::{ImageResult, Rgb, RgbImage};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Pixels per cell in saved images by default, the same as on screen.
+pub const DEFAULT_SCALE: u32 = 10;
+
+/// The colours living and dead cells are drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub alive: (u8, u8, u8),
+    pub dead: (u8, u8, u8),
+}
+
+impl Default for Palette {
+    /// The colours of the game window, blue cells on grey.
+    fn default() -> Palette {
+        Palette {
+            alive: (51, 153, 255),
+            dead: (77, 77, 77),
+        }
+    }
+}
+
+/// Draws the board as an image with each cell `scale` pixels square.
+pub fn render(grid: &Grid, palette: Palette, scale: u32) -> RgbImage {
+    let width = grid.width() as u32 * scale;
+    let height = grid.height() as u32 * scale;
+    RgbImage::from_fn(width, height, |x, y| {
+        let (r, g, b) = match grid.cells[(y / scale) as usize][(x / scale) as usize] {
+            State::Alive => palette.alive,
+            State::Dead => palette.dead,
+        };
+        Rgb([r, g, b])
+    })
+}
+
+pub fn save_png<P: AsRef<Path>>(
+    grid: &Grid,
+    palette: Palette,
+    scale: u32,
+    path: P,
+) -> ImageResult<()> {
+    render(grid, palette, scale).save(path)
+}
+
+/// Saves the board in `dir` as a PNG named after its generation, such as
+/// `frame_000123.png`, so that a directory of them sorts into order for
+/// video and timelapse tools.
+pub fn save_numbered_png(
+    dir: &Path,
+    generation: u64,
+    grid: &Grid,
+    palette: Palette,
+    scale: u32,
+) -> ImageResult<PathBuf> {
+    let path = dir.join(format!("frame_{:06}.png", generation));
+    save_png(grid, palette, scale, &path)?;
+    Ok(path)
+}
+
+/// Draws the board as an SVG with each cell one unit square, so it can be
+/// scaled to any size. Runs of living cells along a row become a single
+/// rectangle to keep the file small.
+pub fn to_svg(grid: &Grid, palette: Palette) -> String {
+    let (width, height) = (grid.width(), grid.height());
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+        width, height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width,
+        height,
+        hex(palette.dead)
+    );
+    let _ = writeln!(svg, r#"<g fill="{}">"#, hex(palette.alive));
+    for (row_idx, row) in grid.cells.iter().enumerate() {
+        let mut col = 0;
+        while col < width {
+            if row[col] == State::Dead {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < width && row[col] == State::Alive {
+                col += 1;
+            }
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="1"/>"#,
+                start,
+                row_idx,
+                col - start
+            );
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+pub fn save_svg<P: AsRef<Path>>(grid: &Grid, palette: Palette, path: P) -> io::Result<()> {
+    fs::write(path, to_svg(grid, palette))
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let grid = Grid::seed_rectangle(3, 2, vec![(1, 2)]);
+
+        let image = render(&grid, Palette::default(), 4);
+
+        assert_eq!(image.dimensions(), (12, 8));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([77, 77, 77]));
+        assert_eq!(image.get_pixel(8, 4), &Rgb([51, 153, 255]));
+        assert_eq!(image.get_pixel(11, 7), &Rgb([51, 153, 255]));
+    }
+
+    #[test]
+    fn numbered_png_test() {
+        let dir = std::env::temp_dir();
+        let grid = Grid::seed(2, vec![(0, 0)]);
+
+        let path = save_numbered_png(&dir, 123, &grid, Palette::default(), 1).unwrap();
+
+        assert_eq!(path, dir.join("frame_000123.png"));
+        assert_eq!(
+            image::open(&path).unwrap().to_rgb8(),
+            render(&grid, Palette::default(), 1)
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn svg_test() {
+        let grid = Grid::seed_rectangle(4, 2, vec![(0, 0), (1, 1), (1, 2)]);
+
+        let svg = to_svg(&grid, Palette::default());
+
+        assert!(svg.contains(r#"viewBox="0 0 4 2""#));
+        assert!(svg.contains(r##"<rect width="4" height="2" fill="#4d4d4d"/>"##));
+        assert!(svg.contains(r##"<g fill="#3399ff">"##));
+        assert!(svg.contains(r#"<rect x="0" y="0" width="1" height="1"/>"#));
+        assert!(svg.contains(r#"<rect x="1" y="1" width="2" height="1"/>"#));
+        assert_eq!(svg.matches("<rect").count(), 3);
+    }
+}
@@ -0,0 +1,365 @@
+//! Recordings of a session that can be played back exactly. A replay holds
+//! the starting board and the settings that affect how it runs, then every
+//! edit and control the user made along with the generation it was made at:
+//!
+//! ```json
+//! {"seed": 7, "auto_restart": null, "density": 0.5, "symmetry": "none",
+//!  "noise": null, "history_mb": 64, "infinite": false,
+//!  "initial": {"width": 3, "height": 3, "rule": "B3/S23", "cells": [[1, 1]]},
+//!  "events": [{"generation": 4, "action": {"toggle_cell": {"row": 0, "col": 2}}},
+//!             {"generation": 9, "action": "toggle_pause"}]}
+//! ```
+//!
+//! Everything else, including the soups started by `--auto-restart`, follows
+//! from the seed, so playing the events back in order reproduces the session.
+
+use crate::compression;
+use crate::grid::{check_density, Grid};
+use crate::json::JsonGrid;
+use crate::rule::Rule;
+use crate::soup::Soup;
+use crate::stability::DEFAULT_LONGEST_PERIOD;
+use crate::topology::Topology;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+
+/// Something the user did that changes how the session plays out.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    ToggleCell {
+        row: usize,
+        col: usize,
+    },
+    TogglePause,
+    /// Steps forward one generation while paused.
+    Step,
+    /// Jumps to a stored generation, by stepping back or on the timeline.
+    JumpTo {
+        generation: u64,
+    },
+    /// Switches to another branch of the timeline.
+    SwitchBranch {
+        branch: usize,
+    },
+    ToggleGraph,
+    ToggleCensus,
+    ToggleHeatmap,
+    ToggleClusters,
+    /// Switches between drawing the board flat and wrapped round a sphere.
+    NextView,
+    /// Sets the chance of each cell of new random soups being alive.
+    SetDensity {
+        density: f64,
+    },
+    /// Replaces the board with a random soup.
+    NewSoup,
+    /// Replaces the board with one of the classic starting patterns.
+    PlaceStarter {
+        index: usize,
+    },
+    /// Kills every cell and pauses, ready for drawing.
+    Clear,
+    /// Starts from an entry of the seed gallery.
+    ShowGalleryEntry {
+        index: usize,
+    },
+    /// Joins the edges of the board another way from now on.
+    SetTopology {
+        topology: Topology,
+    },
+    /// Runs the board by another rule from now on.
+    SetRule {
+        rule: Rule,
+    },
+    /// Grows or shrinks the board about its centre, keeping what is on it.
+    Resize {
+        width: usize,
+        height: usize,
+    },
+    /// Starts or stops the view of an `--infinite` plane following where
+    /// cells are changing.
+    ToggleFollow,
+    /// Zooms the view of an `--infinite` plane in or out to frame
+    /// everything alive.
+    ZoomToFit,
+    /// Moves the view of an `--infinite` plane so that its top left corner
+    /// is at `row` and `col`.
+    MoveCamera {
+        row: i64,
+        col: i64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Event {
+    /// The generation on screen when the action was taken.
+    pub generation: u64,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub auto_restart: Option<u64>,
+    /// How random soups were made, replays from before they could be
+    /// adjusted having used the defaults.
+    #[serde(flatten)]
+    pub soup: Soup,
+    pub history_mb: u64,
+    /// Whether the board was an `--infinite` plane, seen through a view
+    /// the size of `initial`.
+    #[serde(default)]
+    pub infinite: bool,
+    /// How the edges of the board were joined, replays from before they
+    /// could be having had none.
+    #[serde(default)]
+    pub topology: Topology,
+    /// The rule the board started out running by, replays from before
+    /// there were others having used Conway's.
+    #[serde(default)]
+    pub rule: Rule,
+    /// The `--auto-expand` margin the board grew by, if it did.
+    #[serde(default)]
+    pub auto_expand: Option<usize>,
+    /// The longest period of cycles `--auto-restart` took the board to
+    /// have settled in.
+    #[serde(default = "default_settle_period")]
+    pub settle_period: u64,
+    initial: JsonGrid,
+    pub events: Vec<Event>,
+}
+
+fn default_settle_period() -> u64 {
+    DEFAULT_LONGEST_PERIOD
+}
+
+impl Replay {
+    pub fn new(
+        grid: &Grid,
+        seed: u64,
+        auto_restart: Option<u64>,
+        soup: Soup,
+        history_mb: u64,
+    ) -> Replay {
+        Replay {
+            seed,
+            auto_restart,
+            soup,
+            history_mb,
+            infinite: false,
+            topology: Topology::default(),
+            rule: Rule::default(),
+            auto_expand: None,
+            settle_period: DEFAULT_LONGEST_PERIOD,
+            initial: JsonGrid::new(grid, None),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, generation: u64, action: Action) {
+        self.events.push(Event { generation, action });
+    }
+
+    /// The board the session started from.
+    pub fn initial_grid(&self) -> Grid {
+        self.initial
+            .to_grid()
+            .expect("replays are checked when they are read")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a replay always serializes")
+    }
+
+    pub fn from_json(text: &str) -> Result<Replay, String> {
+        let replay: Replay =
+            serde_json::from_str(text).map_err(|e| format!("invalid replay: {}", e))?;
+        let grid = replay.initial.to_grid()?;
+        replay.soup.check()?;
+
+        // Edits can be anywhere on the biggest the board has been resized to.
+        // How far it grew by itself with `--auto-expand` can only be found by
+        // playing it, so those edits are checked as they are played.
+        let (mut width, mut height) = (grid.width(), grid.height());
+        if replay.auto_expand.is_some() {
+            width = usize::MAX;
+            height = usize::MAX;
+        }
+        for event in &replay.events {
+            match event.action {
+                Action::SetDensity { density } => check_density(density)?,
+                Action::Resize { width: 0, .. } | Action::Resize { height: 0, .. } => {
+                    return Err(format!(
+                        "the board is resized to nothing at generation {}",
+                        event.generation
+                    ));
+                }
+                Action::Resize {
+                    width: new_width,
+                    height: new_height,
+                } => {
+                    width = width.max(new_width);
+                    height = height.max(new_height);
+                }
+                Action::ToggleCell { row, col } if row >= height || col >= width => {
+                    return Err(format!(
+                        "the edit at generation {} is outside the {}x{} grid",
+                        event.generation, width, height
+                    ));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(replay)
+    }
+
+    /// Writes the replay to `path`, compressed if it ends in `.gz`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        compression::write(path.as_ref(), &self.to_json())
+    }
+}
+
+/// Hands back the events of a replay as the session being played reaches
+/// them.
+pub struct Player {
+    events: VecDeque<Event>,
+}
+
+impl Player {
+    pub fn new(replay: &Replay) -> Player {
+        Player {
+            events: replay.events.iter().copied().collect(),
+        }
+    }
+
+    /// The next action to take at `generation`, if it is due. While paused
+    /// the generation can only change through the recorded actions
+    /// themselves, so the next one is always due.
+    pub fn next(&mut self, generation: u64, paused: bool) -> Option<Action> {
+        let due = self
+            .events
+            .front()
+            .is_some_and(|event| paused || event.generation == generation);
+        if due {
+            self.events.pop_front().map(|event| event.action)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let mut replay = Replay::new(&Grid::seed(3, vec![(1, 1)]), 7, None, Soup::default(), 64);
+        replay.record(4, Action::ToggleCell { row: 0, col: 2 });
+        replay.record(9, Action::TogglePause);
+        replay.record(12, Action::SetDensity { density: 0.15 });
+        replay.record(15, Action::MoveCamera { row: -40, col: 8 });
+        replay.record(
+            16,
+            Action::SetTopology {
+                topology: "wrap+2,twist".parse().unwrap(),
+            },
+        );
+        replay.record(
+            17,
+            Action::SetRule {
+                rule: "B36/S23".parse().unwrap(),
+            },
+        );
+        replay.infinite = true;
+        replay.topology = "klein".parse().unwrap();
+        replay.rule = "B2/S".parse().unwrap();
+
+        let text = replay.to_json();
+
+        assert!(text.contains(r#"{"generation":9,"action":"toggle_pause"}"#));
+        assert!(
+            text.contains(r#""action":{"set_rule":{"rule":"B36/S23"}}"#),
+            "{}",
+            text
+        );
+        assert_eq!(Replay::from_json(&text), Ok(replay.clone()));
+        assert_eq!(replay.initial_grid(), Grid::seed(3, vec![(1, 1)]));
+    }
+
+    #[test]
+    fn bad_replay_test() {
+        let mut replay = Replay::new(&Grid::seed(3, vec![]), 7, None, Soup::default(), 64);
+        replay.record(0, Action::ToggleCell { row: 3, col: 0 });
+
+        assert!(Replay::from_json(&replay.to_json()).is_err());
+        assert!(Replay::from_json("{}").is_err());
+        let mut replay = Replay::new(&Grid::seed(3, vec![]), 7, None, Soup::default(), 64);
+        replay.record(0, Action::SetDensity { density: 1.5 });
+        assert!(Replay::from_json(&replay.to_json()).is_err());
+        let mut replay = Replay::new(&Grid::seed(3, vec![]), 7, None, Soup::default(), 64);
+        replay.record(
+            1,
+            Action::Resize {
+                width: 0,
+                height: 5,
+            },
+        );
+        assert!(Replay::from_json(&replay.to_json()).is_err());
+    }
+
+    #[test]
+    fn resize_test() {
+        let mut replay = Replay::new(&Grid::seed(3, vec![]), 7, None, Soup::default(), 64);
+        replay.record(
+            2,
+            Action::Resize {
+                width: 13,
+                height: 8,
+            },
+        );
+        replay.record(3, Action::ToggleCell { row: 7, col: 12 });
+        assert!(Replay::from_json(&replay.to_json()).is_ok());
+
+        replay.record(4, Action::ToggleCell { row: 8, col: 0 });
+        assert_eq!(
+            Replay::from_json(&replay.to_json()),
+            Err("the edit at generation 4 is outside the 13x8 grid".to_string())
+        );
+    }
+
+    #[test]
+    fn default_soup_test() {
+        let text = r#"{"seed": 7, "auto_restart": null, "history_mb": 64,
+            "initial": {"width": 3, "height": 3, "rule": "B3/S23", "cells": []},
+            "events": []}"#;
+
+        let replay = Replay::from_json(text).unwrap();
+        assert_eq!(replay.soup, Soup::default());
+        assert!(!replay.infinite);
+        assert!(replay.topology.is_bounded());
+        assert!(replay.rule.is_conway());
+    }
+
+    #[test]
+    fn player_test() {
+        let mut replay = Replay::new(&Grid::seed(3, vec![]), 7, None, Soup::default(), 64);
+        replay.record(2, Action::TogglePause);
+        replay.record(2, Action::Step);
+        let mut player = Player::new(&replay);
+
+        assert_eq!(player.next(1, false), None);
+        assert_eq!(player.next(2, false), Some(Action::TogglePause));
+        assert_eq!(player.next(5, true), Some(Action::Step));
+        assert!(player.is_finished());
+        assert_eq!(player.next(3, true), None);
+    }
+}
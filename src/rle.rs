@@ -0,0 +1,648 @@
+use crate::grid::{check_rule, Grid, State};
+use crate::plane::{offset, Plane};
+use crate::rule::Rule;
+use std::io::BufRead;
+use std::str::FromStr;
+
+/// A pattern read from a run length encoded (RLE) file, with living cells
+/// given as `(row, column)` positions relative to its top left corner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub living_cells: Vec<(usize, usize)>,
+    /// The rule the pattern runs by, from its header, or Conway's if it
+    /// doesn't say.
+    pub rule: Rule,
+}
+
+/// A way of turning or mirroring a pattern before it is placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform {
+    /// A quarter turn clockwise.
+    Rotate90,
+    Rotate180,
+    /// A quarter turn anticlockwise.
+    Rotate270,
+    /// Mirrors left and right.
+    FlipX,
+    /// Mirrors top and bottom.
+    FlipY,
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Transform, String> {
+        match s {
+            "rot90" => Ok(Transform::Rotate90),
+            "rot180" => Ok(Transform::Rotate180),
+            "rot270" => Ok(Transform::Rotate270),
+            "flipx" => Ok(Transform::FlipX),
+            "flipy" => Ok(Transform::FlipY),
+            _ => Err(format!(
+                "unknown transform '{}', use rot90, rot180, rot270, flipx or flipy",
+                s
+            )),
+        }
+    }
+}
+
+impl Pattern {
+    /// A board at least `width` by `height`, grown if need be to leave
+    /// `margin` dead cells all round the pattern, with the pattern in the
+    /// middle.
+    pub fn fitted(&self, width: usize, height: usize, margin: usize) -> Grid {
+        let width = width.max(self.width + 2 * margin);
+        let height = height.max(self.height + 2 * margin);
+        self.centred(width, height)
+            .expect("the board was made big enough")
+    }
+
+    /// The living cells of `grid` as a pattern the same size.
+    pub fn from_grid(grid: &Grid) -> Pattern {
+        Pattern {
+            width: grid.width(),
+            height: grid.height(),
+            living_cells: grid.living_cells(),
+            rule: Rule::default(),
+        }
+    }
+
+    pub fn transformed(&self, transform: Transform) -> Pattern {
+        let (width, height) = (self.width, self.height);
+        let map = |&(row, col): &(usize, usize)| match transform {
+            Transform::Rotate90 => (col, height - 1 - row),
+            Transform::Rotate180 => (height - 1 - row, width - 1 - col),
+            Transform::Rotate270 => (width - 1 - col, row),
+            Transform::FlipX => (row, width - 1 - col),
+            Transform::FlipY => (height - 1 - row, col),
+        };
+        let (width, height) = match transform {
+            Transform::Rotate90 | Transform::Rotate270 => (height, width),
+            _ => (width, height),
+        };
+        let mut living_cells: Vec<(usize, usize)> = self.living_cells.iter().map(map).collect();
+        living_cells.sort_unstable();
+        Pattern {
+            width,
+            height,
+            living_cells,
+            rule: self.rule,
+        }
+    }
+
+    /// Brings the pattern's living cells to life on `grid` with its top left
+    /// corner at `row` and `col`, if it fits there.
+    pub fn place(&self, grid: &mut Grid, row: usize, col: usize) -> Result<(), String> {
+        let fits = row
+            .checked_add(self.height)
+            .is_some_and(|bottom| bottom <= grid.height())
+            && col
+                .checked_add(self.width)
+                .is_some_and(|right| right <= grid.width());
+        if !fits {
+            return Err(format!(
+                "the {}x{} pattern does not fit at {},{} on a {}x{} grid",
+                self.width,
+                self.height,
+                row,
+                col,
+                grid.width(),
+                grid.height()
+            ));
+        }
+        for (cell_row, cell_col) in &self.living_cells {
+            grid.cells[row + cell_row][col + cell_col] = State::Alive;
+        }
+        Ok(())
+    }
+
+    /// A `width` by `height` board with the pattern in the middle, or `None`
+    /// if it doesn't fit.
+    pub fn centred(&self, width: usize, height: usize) -> Option<Grid> {
+        if self.width > width || self.height > height {
+            return None;
+        }
+        let top = (height - self.height) / 2;
+        let left = (width - self.width) / 2;
+        let living_cells = self
+            .living_cells
+            .iter()
+            .map(|(row, col)| (row + top, col + left))
+            .collect();
+        Some(Grid::seed_rectangle(width, height, living_cells))
+    }
+
+    /// The pattern in RLE, with a header giving its size and rule and the
+    /// body wrapped at 70 characters, as most Life programs write it.
+    pub fn to_rle(&self) -> String {
+        let mut cells = self.living_cells.clone();
+        cells.sort_unstable();
+        let mut tags: Vec<(usize, char)> = Vec::new();
+        let mut push = |run: usize, tag: char| {
+            if run == 0 {
+                return;
+            }
+            match tags.last_mut() {
+                Some((count, last)) if *last == tag => *count += run,
+                _ => tags.push((run, tag)),
+            }
+        };
+        let (mut row, mut col) = (0, 0);
+        for (cell_row, cell_col) in cells {
+            if cell_row > row {
+                push(cell_row - row, '$');
+                row = cell_row;
+                col = 0;
+            }
+            push(cell_col - col, 'b');
+            push(1, 'o');
+            col = cell_col + 1;
+        }
+
+        let mut rle = format!(
+            "x = {}, y = {}, rule = {}\n",
+            self.width, self.height, self.rule
+        );
+        let mut line = String::new();
+        let words = tags.into_iter().map(|(run, tag)| match run {
+            1 => tag.to_string(),
+            run => format!("{}{}", run, tag),
+        });
+        for word in words.chain(std::iter::once("!".to_string())) {
+            if line.len() + word.len() > 70 {
+                rle.push_str(&line);
+                rle.push('\n');
+                line.clear();
+            }
+            line.push_str(&word);
+        }
+        rle.push_str(&line);
+        rle.push('\n');
+        rle
+    }
+}
+
+/// Parses the RLE format used by most Life pattern collections: `#` comment
+/// lines, an optional `x = .., y = .., rule = ..` header and a body of
+/// `b` (dead), `o` (alive) and `$` (end of row) tags terminated by `!`.
+/// Errors say the line and column, counted from 1, they were found at.
+pub fn parse(text: &str) -> Result<Pattern, String> {
+    let mut living_cells = Vec::new();
+    let (width, height, rule) = read(text.as_bytes(), |row, col, run| {
+        living_cells.extend((col..col + run).map(|col| (row, col)))
+    })?;
+    Ok(Pattern {
+        width,
+        height,
+        living_cells,
+        rule,
+    })
+}
+
+/// Reads an RLE pattern from `input` as `parse` does, but without holding
+/// its text or its cells, so patterns of any size can be read onto a plane:
+/// each run of living cells is handed to `run` as its row, first column and
+/// length as soon as it is read. Gives the pattern's width, height and
+/// rule.
+pub fn read<R: BufRead, F: FnMut(usize, usize, usize)>(
+    mut input: R,
+    run: F,
+) -> Result<(usize, usize, Rule), String> {
+    let mut reader = Reader {
+        run,
+        header: None,
+        any_alive: false,
+        line: Line::Start,
+        number: 1,
+        col: 0,
+        row: 0,
+        body_col: 0,
+        width: 0,
+        count: String::new(),
+        count_at: (0, 0),
+    };
+    'input: loop {
+        let bytes = input
+            .fill_buf()
+            .map_err(|e| format!("could not read the pattern: {}", e))?;
+        if bytes.is_empty() {
+            break;
+        }
+        let length = bytes.len();
+        for &byte in bytes {
+            if reader.next(byte)? {
+                break 'input;
+            }
+        }
+        input.consume(length);
+    }
+    reader.finish()
+}
+
+/// Reads an RLE pattern from `input` straight onto a plane, with its top
+/// left corner at the origin, giving the plane and the pattern's width,
+/// height and rule. Only the plane's chunks of living cells are held.
+pub fn read_plane<R: BufRead>(input: R) -> Result<(Plane, (usize, usize, Rule)), String> {
+    let mut plane = Plane::new();
+    let shape = read(input, |row, col, run| {
+        for col in col..col + run {
+            if let (Some(row), Some(col)) = (offset(0, row), offset(0, col)) {
+                plane.set(row, col, true);
+            }
+        }
+    })?;
+    Ok((plane, shape))
+}
+
+/// What the line being read is, as far as it has been read.
+enum Line {
+    /// Only whitespace so far.
+    Start,
+    Comment,
+    /// The header, as far as it has been read.
+    Header(Vec<u8>),
+    Body,
+    /// A character that can't be in the body, as far as its bytes have
+    /// been read, and the column it is at.
+    Unexpected(Vec<u8>, usize),
+}
+
+/// The state of an RLE pattern being read byte by byte.
+struct Reader<F> {
+    run: F,
+    header: Option<((usize, usize, Rule), usize)>,
+    any_alive: bool,
+    line: Line,
+    /// The number of the line being read and how many characters of it
+    /// have been, counted from 1.
+    number: usize,
+    col: usize,
+    /// Where the next tag of the body goes.
+    row: usize,
+    body_col: usize,
+    width: usize,
+    /// The run count being read, and where it started.
+    count: String,
+    count_at: (usize, usize),
+}
+
+impl<F: FnMut(usize, usize, usize)> Reader<F> {
+    /// Reads the next byte, saying whether the pattern has ended.
+    fn next(&mut self, byte: u8) -> Result<bool, String> {
+        if let Line::Unexpected(bytes, col) = &mut self.line {
+            // The rest of the bytes of a character take up no more columns.
+            if byte & 0xc0 == 0x80 && bytes.len() < 4 {
+                bytes.push(byte);
+                return Ok(false);
+            }
+            let c = String::from_utf8_lossy(bytes);
+            let message = format!("unexpected character '{}' in RLE body", c);
+            return Err(at(message, (self.number, *col)));
+        }
+        if byte == b'\n' {
+            if let Line::Header(bytes) = &self.line {
+                self.header = Some((self.parse_header(bytes)?, self.number));
+            }
+            self.line = Line::Start;
+            self.number += 1;
+            self.col = 0;
+            return Ok(false);
+        }
+        if byte & 0xc0 != 0x80 {
+            self.col += 1;
+        }
+        match &mut self.line {
+            Line::Comment => return Ok(false),
+            Line::Header(bytes) => {
+                bytes.push(byte);
+                return Ok(false);
+            }
+            Line::Start if byte.is_ascii_whitespace() => return Ok(false),
+            Line::Start if byte == b'#' => {
+                self.line = Line::Comment;
+                return Ok(false);
+            }
+            Line::Start if byte == b'x' && self.header.is_none() && !self.any_alive => {
+                // The header keeps its indent, to count columns from the
+                // start of the line.
+                let mut bytes = vec![b' '; self.col - 1];
+                bytes.push(byte);
+                self.line = Line::Header(bytes);
+                return Ok(false);
+            }
+            Line::Start => self.line = Line::Body,
+            _ => {}
+        }
+        self.tag(byte)
+    }
+
+    /// Reads the next byte of the body, saying whether the pattern has
+    /// ended.
+    fn tag(&mut self, byte: u8) -> Result<bool, String> {
+        match byte {
+            b'0'..=b'9' => {
+                if self.count.is_empty() {
+                    self.count_at = (self.number, self.col);
+                }
+                self.count.push(byte as char)
+            }
+            b'b' | b'o' | b'$' => {
+                let (run, position) = if self.count.is_empty() {
+                    (1, (self.number, self.col))
+                } else {
+                    let run = self.count.parse().map_err(|_| {
+                        at(format!("bad run count '{}'", self.count), self.count_at)
+                    })?;
+                    (run, self.count_at)
+                };
+                self.count.clear();
+                let too_far = || {
+                    let message = format!("run of {} goes past the largest possible board", run);
+                    at(message, position)
+                };
+                match byte {
+                    b'$' => {
+                        // The last row is kept free, so the height always
+                        // fits too.
+                        self.row = self
+                            .row
+                            .checked_add(run)
+                            .filter(|row| *row < usize::MAX)
+                            .ok_or_else(too_far)?;
+                        self.body_col = 0;
+                    }
+                    _ => {
+                        let end = self.body_col.checked_add(run).ok_or_else(too_far)?;
+                        if byte == b'o' {
+                            (self.run)(self.row, self.body_col, run);
+                            self.any_alive |= run > 0;
+                        }
+                        self.body_col = end;
+                    }
+                }
+                self.width = self.width.max(self.body_col);
+            }
+            b'!' => return Ok(true),
+            byte if byte.is_ascii_whitespace() => {}
+            byte if byte.is_ascii() => {
+                let message = format!("unexpected character '{}' in RLE body", byte as char);
+                return Err(at(message, (self.number, self.col)));
+            }
+            byte => self.line = Line::Unexpected(vec![byte], self.col),
+        }
+        Ok(false)
+    }
+
+    fn parse_header(&self, bytes: &[u8]) -> Result<(usize, usize, Rule), String> {
+        let line = std::str::from_utf8(bytes)
+            .map_err(|_| format!("the RLE header at line {} isn't UTF-8", self.number))?;
+        parse_header(line, self.number)
+    }
+
+    /// The width, height and rule of the pattern once it has all been read.
+    fn finish(mut self) -> Result<(usize, usize, Rule), String> {
+        match &self.line {
+            Line::Header(bytes) => {
+                self.header = Some((self.parse_header(bytes)?, self.number));
+            }
+            Line::Unexpected(..) => {
+                self.next(b'\n')?;
+            }
+            _ => {}
+        }
+        let height = if self.body_col > 0 {
+            self.row + 1
+        } else {
+            self.row
+        };
+        match self.header {
+            Some(((x, y, rule), number)) => {
+                if self.width > x || height > y {
+                    return Err(format!(
+                        "pattern body is larger than the {}x{} given in its header at line {}",
+                        x, y, number
+                    ));
+                }
+                Ok((x, y, rule))
+            }
+            None => Ok((self.width, height, Rule::default())),
+        }
+    }
+}
+
+/// `message` as found at `(line, col)`.
+fn at(message: String, (line, col): (usize, usize)) -> String {
+    format!("{} at line {}, col {}", message, line, col)
+}
+
+/// The header on line `number`.
+fn parse_header(line: &str, number: usize) -> Result<(usize, usize, Rule), String> {
+    let mut x = None;
+    let mut y = None;
+    let mut rule = Rule::default();
+
+    let mut start = 0;
+    for field in line.split(',') {
+        let col = start + field.chars().count() - field.trim_start().chars().count() + 1;
+        start += field.chars().count() + 1;
+        let mut parts = field.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts
+            .next()
+            .ok_or_else(|| {
+                at(
+                    format!("bad RLE header field '{}'", field.trim()),
+                    (number, col),
+                )
+            })?
+            .trim();
+        let parsed = match key {
+            "x" => parse_dimension(value).map(|value| x = Some(value)),
+            "y" => parse_dimension(value).map(|value| y = Some(value)),
+            "rule" => check_rule(value).map(|value| rule = value),
+            _ => Err(format!("unknown RLE header field '{}'", key)),
+        };
+        parsed.map_err(|message| at(message, (number, col)))?;
+    }
+
+    match (x, y) {
+        (Some(x), Some(y)) => Ok((x, y, rule)),
+        _ => Err(format!("RLE header needs both x and y at line {}", number)),
+    }
+}
+
+fn parse_dimension(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("bad RLE header dimension '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_rle_test() {
+        let glider = parse("bo$2bo$3o!").unwrap();
+        assert_eq!(glider.to_rle(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+        let gap = parse("x = 4, y = 4, rule = B36/S23\n2o2$3bo!").unwrap();
+        assert_eq!(gap.to_rle(), "x = 4, y = 4, rule = B36/S23\n2o2$3bo!\n");
+        assert_eq!(parse(&gap.to_rle()), Ok(gap));
+
+        let long = Pattern {
+            width: 200,
+            height: 1,
+            living_cells: (0..100).map(|col| (0, 2 * col)).collect(),
+            rule: Rule::default(),
+        };
+        let rle = long.to_rle();
+        assert!(rle.lines().all(|line| line.len() <= 70), "{}", rle);
+        assert_eq!(parse(&rle).unwrap().living_cells, long.living_cells);
+    }
+
+    #[test]
+    fn centred_test() {
+        let pattern = parse("3o!").unwrap();
+
+        assert_eq!(
+            pattern.centred(5, 3),
+            Some(Grid::seed_rectangle(5, 3, vec![(1, 1), (1, 2), (1, 3)]))
+        );
+        assert_eq!(pattern.centred(2, 3), None);
+        assert_eq!(pattern.fitted(2, 3, 1), pattern.centred(5, 3).unwrap());
+        assert_eq!(pattern.fitted(9, 9, 1), pattern.centred(9, 9).unwrap());
+    }
+
+    #[test]
+    fn transform_test() {
+        // An L tromino: o. / oo
+        let pattern = parse("o$2o!").unwrap();
+        let cells = |transform| pattern.transformed(transform).living_cells;
+
+        assert_eq!(cells(Transform::Rotate90), vec![(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(cells(Transform::Rotate180), vec![(0, 0), (0, 1), (1, 1)]);
+        assert_eq!(cells(Transform::Rotate270), vec![(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(cells(Transform::FlipX), vec![(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(cells(Transform::FlipY), vec![(0, 0), (0, 1), (1, 0)]);
+        let wide = parse("3o!").unwrap().transformed(Transform::Rotate90);
+        assert_eq!((wide.width, wide.height), (1, 3));
+        assert_eq!("flipx".parse(), Ok(Transform::FlipX));
+    }
+
+    #[test]
+    fn place_test() {
+        let mut grid = Grid::seed(4, vec![(0, 0)]);
+        let blinker = parse("3o!").unwrap();
+
+        blinker.place(&mut grid, 3, 1).unwrap();
+
+        assert_eq!(grid.living_cells(), vec![(0, 0), (3, 1), (3, 2), (3, 3)]);
+        assert!(blinker.place(&mut grid, 0, 2).is_err());
+        assert!(blinker.place(&mut grid, usize::MAX, 0).is_err());
+        assert!(blinker.place(&mut grid, 0, usize::MAX).is_err());
+        assert_eq!(Pattern::from_grid(&grid).living_cells.len(), 4);
+    }
+
+    #[test]
+    fn glider_test() {
+        let text = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+
+        let pattern = parse(text).unwrap();
+
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(
+            pattern.living_cells,
+            vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]
+        );
+        assert!(pattern.rule.is_conway());
+        let highlife = parse("x = 3, y = 1, rule = B36/S23\n3o!").unwrap();
+        assert_eq!(highlife.rule.to_string(), "B36/S23");
+        assert_eq!(
+            highlife.transformed(Transform::Rotate90).rule,
+            highlife.rule
+        );
+    }
+
+    #[test]
+    fn no_header_test() {
+        let pattern = parse("2o$\n2o!").unwrap();
+
+        assert_eq!((pattern.width, pattern.height), (2, 2));
+        assert_eq!(pattern.living_cells.len(), 4);
+    }
+
+    #[test]
+    fn bad_input_test() {
+        assert!(parse("x = 3, y = 3\nbqb!").is_err());
+        assert!(parse("x = 1, y = 1\n3o!").is_err());
+        assert!(parse("x = 3, y = 3, rule = B0/S23\n3o!").is_err());
+    }
+
+    #[test]
+    fn read_test() {
+        let text = "#C A glider\r\nx = 5, y = 4, rule = B36/S23\r\n\nbo$2bo$\n3o!\nignored";
+        // However the text comes in pieces, it reads the same.
+        for capacity in 1..8 {
+            let mut cells = Vec::new();
+            let input = std::io::BufReader::with_capacity(capacity, text.as_bytes());
+            let shape = read(input, |row, col, run| cells.push((row, col, run))).unwrap();
+            assert_eq!(shape, (5, 4, "B36/S23".parse().unwrap()));
+            assert_eq!(cells, vec![(0, 1, 1), (1, 2, 1), (2, 0, 3)]);
+            let input = std::io::BufReader::with_capacity(capacity, "3o\n2b€o!".as_bytes());
+            assert_eq!(
+                read(input, |_, _, _| ()),
+                Err("unexpected character '€' in RLE body at line 2, col 3".to_string())
+            );
+        }
+        assert_eq!(parse("2o€").unwrap_err(), parse("2o€!").unwrap_err());
+        assert_eq!(parse("x = 2, y = 1").map(|pattern| pattern.width), Ok(2));
+
+        let (plane, shape) = read_plane("x = 3, y = 3\n3o$2bo$bo!".as_bytes()).unwrap();
+        assert_eq!(shape.0, 3);
+        assert_eq!(
+            plane.living_cells(),
+            vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn error_position_test() {
+        assert_eq!(
+            parse("#N Glider\nx = 3, y = 3\nbob$\n  2bo$3q!"),
+            Err("unexpected character 'q' in RLE body at line 4, col 8".to_string())
+        );
+        assert_eq!(
+            parse("x = 3, y = three\n3o!"),
+            Err("bad RLE header dimension 'three' at line 1, col 8".to_string())
+        );
+        assert_eq!(
+            parse("x = 3, y = 1, colour = red\n3o!"),
+            Err("unknown RLE header field 'colour' at line 1, col 15".to_string())
+        );
+        assert_eq!(
+            parse("o$\nb99999999999999999999999o!"),
+            Err("bad run count '99999999999999999999999' at line 2, col 2".to_string())
+        );
+        assert_eq!(
+            parse("18446744073709551615b2o!"),
+            Err("run of 2 goes past the largest possible board at line 1, col 22".to_string())
+        );
+        assert_eq!(
+            parse("18446744073709551615$18446744073709551615$o!"),
+            Err(
+                "run of 18446744073709551615 goes past the largest possible board at line 1, \
+                 col 1"
+                    .to_string()
+            )
+        );
+        assert!(parse("9223372036854775808$9223372036854775808$o!").is_err());
+        assert!(parse("18446744073709551615bo!").is_err());
+        assert_eq!(
+            parse("\nx = 1, y = 1\n3o!"),
+            Err("pattern body is larger than the 1x1 given in its header at line 2".to_string())
+        );
+    }
+}
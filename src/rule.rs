@@ -0,0 +1,204 @@
+//! Life-like rules, which say how many of its eight neighbours a dead cell
+//! needs to be born and a living one to survive. They are written in B/S
+//! notation, like `B3/S23` for Conway's Life or `B36/S23` for HighLife, and
+//! the older S/B notation `23/3` is read as well.
+
+use crate::grid::State;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Rule {
+    /// Bit `n` is set if a dead cell with `n` living neighbours is born.
+    birth: u16,
+    /// Bit `n` is set if a living cell with `n` living neighbours survives.
+    survival: u16,
+}
+
+impl Default for Rule {
+    /// Conway's Life, `B3/S23`.
+    fn default() -> Rule {
+        Rule {
+            birth: 1 << 3,
+            survival: 1 << 2 | 1 << 3,
+        }
+    }
+}
+
+impl Rule {
+    pub fn is_conway(self) -> bool {
+        self == Rule::default()
+    }
+
+    /// A rule drawn at random from all those that can be run, every one
+    /// that doesn't bring cells to life with no neighbours as likely.
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Rule {
+        Rule {
+            birth: rng.gen_range(0, 1 << 9) & !1,
+            survival: rng.gen_range(0, 1 << 9),
+        }
+    }
+
+    /// Whether a dead cell with `live_neighbours` of its eight alive is born.
+    pub fn born(self, live_neighbours: usize) -> bool {
+        self.birth & 1 << live_neighbours != 0
+    }
+
+    /// Whether a living cell with `live_neighbours` of its eight alive
+    /// survives.
+    pub fn survives(self, live_neighbours: usize) -> bool {
+        self.survival & 1 << live_neighbours != 0
+    }
+
+    /// What a cell in `state` becomes with `live_neighbours` of its eight
+    /// alive.
+    pub fn next_state(self, state: &State, live_neighbours: usize) -> State {
+        let alive = match state {
+            State::Alive => self.survives(live_neighbours),
+            State::Dead => self.born(live_neighbours),
+        };
+        if alive {
+            State::Alive
+        } else {
+            State::Dead
+        }
+    }
+}
+
+/// The neighbour counts a string of digits such as `23` stands for.
+fn counts(digits: &str, rule: &str) -> Result<u16, String> {
+    digits.chars().try_fold(0, |counts, digit| match digit {
+        '0'..='8' => Ok(counts | 1 << (digit as u16 - '0' as u16)),
+        _ => Err(format!(
+            "bad rule '{}', use B/S notation such as B3/S23",
+            rule
+        )),
+    })
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// Reads `B3/S23`, in either case and with or without the slash, or
+    /// `23/3`. Rules that bring cells to life with no neighbours at all are
+    /// refused, as they would fill the whole of an unbounded plane at once.
+    fn from_str(s: &str) -> Result<Rule, String> {
+        let rule = s.trim().to_ascii_uppercase();
+        let bad = || format!("bad rule '{}', use B/S notation such as B3/S23", s.trim());
+        let (birth, survival) = match rule.strip_prefix('B') {
+            Some(rest) => {
+                let (birth, survival) = rest.split_once('S').ok_or_else(bad)?;
+                (birth.strip_suffix('/').unwrap_or(birth), survival)
+            }
+            None => match rule.split_once('/') {
+                Some((survival, birth)) => (birth, survival),
+                None => return Err(bad()),
+            },
+        };
+        let rule = Rule {
+            birth: counts(birth, s.trim())?,
+            survival: counts(survival, s.trim())?,
+        };
+        if rule.born(0) {
+            return Err(format!(
+                "the rule {} brings cells to life with no neighbours, which can't be run",
+                rule
+            ));
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |counts: u16| -> String {
+            (0..=8)
+                .filter(|n| counts & 1 << n != 0)
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survival))
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> String {
+        rule.to_string()
+    }
+}
+
+impl TryFrom<String> for Rule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Rule, String> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let conway = Rule::default();
+        assert_eq!("B3/S23".parse(), Ok(conway));
+        assert_eq!("b3s23".parse(), Ok(conway));
+        assert_eq!("23/3".parse(), Ok(conway));
+        assert!(conway.is_conway());
+
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(highlife.to_string(), "B36/S23");
+        assert!(highlife.born(6) && !highlife.survives(6));
+        assert!(!highlife.is_conway());
+        assert_eq!("B2/S".parse::<Rule>().unwrap().to_string(), "B2/S");
+        assert_eq!("/2".parse::<Rule>().unwrap().to_string(), "B2/S");
+
+        assert!("B9/S23".parse::<Rule>().is_err());
+        assert!("B3".parse::<Rule>().is_err());
+        assert!("life".parse::<Rule>().is_err());
+        assert!("B03/S23".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn next_state_test() {
+        let seeds: Rule = "B2/S".parse().unwrap();
+        assert_eq!(seeds.next_state(&State::Dead, 2), State::Alive);
+        assert_eq!(seeds.next_state(&State::Alive, 2), State::Dead);
+        let conway = Rule::default();
+        assert_eq!(conway.next_state(&State::Alive, 2), State::Alive);
+        assert_eq!(conway.next_state(&State::Dead, 2), State::Dead);
+        assert_eq!(conway.next_state(&State::Dead, 3), State::Alive);
+    }
+
+    #[test]
+    fn random_test() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let rules: Vec<Rule> = (0..200).map(|_| Rule::random(&mut rng)).collect();
+        assert!(rules.iter().all(|rule| !rule.born(0)));
+        assert!(rules
+            .iter()
+            .all(|rule| rule.to_string().parse() == Ok(*rule)));
+        assert!(rules.iter().any(|rule| rule.born(8)));
+        assert!(rules.iter().any(|rule| rule.survives(0)));
+        assert!(rules.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn serde_test() {
+        let rule: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(serde_json::to_string(&rule).unwrap(), r#""B36/S23""#);
+        assert_eq!(
+            serde_json::from_str::<Rule>(r#""b36/s23""#).ok(),
+            Some(rule)
+        );
+        assert!(serde_json::from_str::<Rule>(r#""B0/S""#).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! A search through random soups for what they settle into, a small
+//! apgsearch built on this engine. Many soups are run at once, each on an
+//! unbounded plane so nothing meets an edge, until its population repeats,
+//! then a census is taken of what is left, naming each object by its
+//! apgcode as well, and added to the tally.
+//!
+//! Each soup is drawn from its own generator, seeded from the search's seed
+//! and the soup's number, so a search comes out the same however many
+//! threads run it.
+
+use crate::census::Census;
+use crate::determinism;
+use crate::grid::{check_density, Grid, DEFAULT_DENSITY};
+use crate::plane::Plane;
+use crate::rule::Rule;
+use crate::soup::Soup;
+use crate::stability::{Stability, DEFAULT_LONGEST_PERIOD};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The generations a soup is run for at most, after which it is counted as
+/// unsettled.
+pub const MOST_GENERATIONS: u64 = 20_000;
+
+/// How often progress is reported.
+const PROGRESS_EVERY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Search {
+    pub soups: u64,
+    /// The width and height of each soup.
+    pub size: usize,
+    pub density: f64,
+    pub seed: u64,
+    /// How many threads to search with, 0 for one per core.
+    pub threads: usize,
+    /// The rule the soups run by. The census only knows the objects of
+    /// Conway's rule by name, so under others most are counted as other.
+    pub rule: Rule,
+    /// The longest period of population a soup is taken to have settled
+    /// into.
+    pub longest_period: u64,
+}
+
+impl Default for Search {
+    fn default() -> Search {
+        Search {
+            soups: 1000,
+            size: 16,
+            density: DEFAULT_DENSITY,
+            seed: 0,
+            threads: 0,
+            rule: Rule::default(),
+            longest_period: DEFAULT_LONGEST_PERIOD,
+        }
+    }
+}
+
+impl Search {
+    /// Whether the search can be run.
+    pub fn check(&self) -> Result<(), String> {
+        if self.soups == 0 {
+            return Err("--soups must be at least 1".to_string());
+        }
+        if self.size == 0 {
+            return Err("--size must be at least 1".to_string());
+        }
+        if self.longest_period == 0 {
+            return Err("--settle-period must be at least 1".to_string());
+        }
+        check_density(self.density)?;
+        Ok(())
+    }
+
+    /// The soup numbered `index`.
+    pub fn soup(&self, index: u64) -> Grid {
+        let soup = Soup {
+            density: self.density,
+            ..Soup::default()
+        };
+        soup.generate(
+            self.size,
+            self.size,
+            &mut determinism::rng_for(self.seed, index),
+        )
+    }
+
+    /// Runs every soup, telling `progress` how many are done about once a
+    /// second.
+    pub fn run<F: FnMut(u64)>(&self, progress: F) -> Results {
+        self.run_with(progress, |results: &mut Results, _, settled| {
+            results.record(settled)
+        })
+        .into_iter()
+        .fold(Results::default(), Results::merge)
+    }
+
+    /// Runs every soup, telling `progress` how many are done about once a
+    /// second, and has each thread `record` what its soups became, by
+    /// number, in a tally of its own. The tallies are given in no order.
+    pub fn run_with<T, P, R>(&self, mut progress: P, record: R) -> Vec<T>
+    where
+        T: Default + Send,
+        P: FnMut(u64),
+        R: Fn(&mut T, u64, &Settled) + Sync,
+    {
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        };
+        let next = AtomicU64::new(0);
+        let done = AtomicU64::new(0);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut tally = T::default();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            if index >= self.soups {
+                                return tally;
+                            }
+                            let settled = settle(&self.soup(index), self.rule, self.longest_period);
+                            record(&mut tally, index, &settled);
+                            done.fetch_add(1, Ordering::Relaxed);
+                        }
+                    })
+                })
+                .collect();
+            let mut reported = Instant::now();
+            while !workers.iter().all(|worker| worker.is_finished()) {
+                thread::sleep(Duration::from_millis(20));
+                if reported.elapsed() >= PROGRESS_EVERY {
+                    progress(done.load(Ordering::Relaxed));
+                    reported = Instant::now();
+                }
+            }
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("search threads don't panic"))
+                .collect()
+        })
+    }
+}
+
+/// What a soup became.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settled {
+    /// The generation its population started repeating, or none if it
+    /// hadn't by `MOST_GENERATIONS`.
+    pub generation: Option<u64>,
+    /// How many cells were alive when it was found to have settled.
+    pub population: usize,
+    pub census: Census,
+}
+
+/// Runs `soup` by `rule` on an unbounded plane until it settles, into
+/// cycles with periods up to `longest_period`, or for `MOST_GENERATIONS` if
+/// it doesn't.
+pub fn settle(soup: &Grid, rule: Rule, longest_period: u64) -> Settled {
+    let mut plane = Plane::from_grid(soup, 0, 0);
+    let mut stability = Stability::new(longest_period);
+    stability.observe(plane.population(), || Census::of_plane(&plane));
+    for _ in 1..=MOST_GENERATIONS {
+        plane = plane.next_generation_under(rule);
+        let settled = stability.observe(plane.population(), || Census::of_plane(&plane));
+        if settled.is_some() {
+            return Settled {
+                generation: settled,
+                population: plane.population(),
+                census: Census::of_plane_under(&plane, rule),
+            };
+        }
+    }
+    Settled {
+        generation: None,
+        population: plane.population(),
+        census: Census::of_plane_under(&plane, rule),
+    }
+}
+
+/// The tally of a search.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Results {
+    pub soups: u64,
+    pub unsettled: u64,
+    /// The generations the soups that settled took between them.
+    pub generations: u64,
+    pub counts: BTreeMap<&'static str, u64>,
+    /// Objects the census doesn't know.
+    pub other: u64,
+    /// Every object found that repeats on its own, known or not, by
+    /// apgcode.
+    pub codes: BTreeMap<String, u64>,
+}
+
+impl Results {
+    pub fn record(&mut self, settled: &Settled) {
+        self.soups += 1;
+        match settled.generation {
+            Some(generation) => self.generations += generation,
+            None => self.unsettled += 1,
+        }
+        for (name, count) in settled.census.counts() {
+            *self.counts.entry(name).or_insert(0) += count as u64;
+        }
+        self.other += settled.census.other as u64;
+        for (code, count) in settled.census.apgcodes() {
+            *self.codes.entry(code.to_string()).or_insert(0) += count as u64;
+        }
+    }
+
+    pub fn merge(mut self, other: Results) -> Results {
+        self.soups += other.soups;
+        self.unsettled += other.unsettled;
+        self.generations += other.generations;
+        for (name, count) in other.counts {
+            *self.counts.entry(name).or_insert(0) += count;
+        }
+        self.other += other.other;
+        for (code, count) in other.codes {
+            *self.codes.entry(code).or_insert(0) += count;
+        }
+        self
+    }
+}
+
+impl fmt::Display for Results {
+    /// A summary, then a table of each kind of object found, most common
+    /// first, with how many there were to a soup, and the same by apgcode.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let settled = self.soups - self.unsettled;
+        write!(f, "{} soups, {} settled", self.soups, settled)?;
+        if settled > 0 {
+            write!(
+                f,
+                " after {:.0} generations on average",
+                self.generations as f64 / settled as f64
+            )?;
+        }
+        if self.unsettled > 0 {
+            write!(
+                f,
+                ", {} still going after {}",
+                self.unsettled, MOST_GENERATIONS
+            )?;
+        }
+        writeln!(f)?;
+        let mut counts: Vec<(&str, u64)> = self.counts.iter().map(|(n, c)| (*n, *c)).collect();
+        counts.sort_by_key(|&(name, count)| (Reverse(count), name));
+        if self.other > 0 {
+            counts.push(("other", self.other));
+        }
+        if counts.is_empty() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(f, "{:<24}{:>12}{:>12}", "object", "count", "per soup")?;
+        for (name, count) in counts {
+            writeln!(
+                f,
+                "{:<24}{:>12}{:>12.4}",
+                name,
+                count,
+                count as f64 / self.soups.max(1) as f64
+            )?;
+        }
+        let mut codes: Vec<(&str, u64)> =
+            self.codes.iter().map(|(c, n)| (c.as_str(), *n)).collect();
+        codes.sort_by_key(|&(code, count)| (Reverse(count), code));
+        if codes.is_empty() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(f, "{:<24}{:>12}{:>12}", "apgcode", "count", "per soup")?;
+        for (code, count) in codes {
+            writeln!(
+                f,
+                "{:<24}{:>12}{:>12.4}",
+                code,
+                count,
+                count as f64 / self.soups.max(1) as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle;
+
+    fn grid(text: &str) -> Grid {
+        rle::parse(text).unwrap().fitted(1, 1, 2)
+    }
+
+    #[test]
+    fn settle_test() {
+        let blinker = settle(&grid("3o!"), Rule::default(), DEFAULT_LONGEST_PERIOD);
+        assert_eq!(blinker.generation, Some(0));
+        assert_eq!(blinker.census.to_string(), "1 blinker");
+        assert_eq!(
+            blinker.census.apgcodes().collect::<Vec<_>>(),
+            vec![("xp2_7", 1)]
+        );
+        assert_eq!(blinker.population, 3);
+
+        // A glider's population doesn't change as it flies off.
+        let glider = settle(&grid("bo$2bo$3o!"), Rule::default(), DEFAULT_LONGEST_PERIOD);
+        assert_eq!(glider.census.to_string(), "1 glider");
+
+        let dies = settle(&grid("o$bo!"), Rule::default(), DEFAULT_LONGEST_PERIOD);
+        assert_eq!(dies.generation, Some(1));
+        assert_eq!(dies.census.to_string(), "no objects");
+
+        // Diehard vanishes after 130 generations.
+        let diehard = settle(
+            &grid("6bo$2o$bo3b3o!"),
+            Rule::default(),
+            DEFAULT_LONGEST_PERIOD,
+        );
+        assert_eq!(diehard.generation, Some(130));
+
+        // Under Seeds every cell dies each generation, so the blinker is
+        // gone at once.
+        let seeds = "B2/S".parse().unwrap();
+        let blinker = settle(&grid("3o!"), seeds, DEFAULT_LONGEST_PERIOD);
+        assert_ne!(blinker.census.to_string(), "1 blinker");
+    }
+
+    #[test]
+    fn run_test() {
+        let search = Search {
+            soups: 4,
+            size: 6,
+            seed: 7,
+            threads: 2,
+            ..Search::default()
+        };
+        let results = search.run(|_| ());
+        assert_eq!(results.soups, 4);
+        let numbers: Vec<Vec<u64>> = search.run_with(
+            |_| (),
+            |numbers: &mut Vec<u64>, index, _| numbers.push(index),
+        );
+        let mut numbers: Vec<u64> = numbers.into_iter().flatten().collect();
+        numbers.sort_unstable();
+        assert_eq!(numbers, vec![0, 1, 2, 3]);
+        assert_eq!(results.unsettled, 0);
+        assert!(results.counts.values().sum::<u64>() + results.other > 0);
+        // The same soups come out whatever the number of threads.
+        let alone = Search {
+            threads: 1,
+            ..search.clone()
+        };
+        assert_eq!(alone.run(|_| ()), results);
+        assert_ne!(search.soup(0), search.soup(1));
+
+        let text = results.to_string();
+        assert!(text.starts_with("4 soups, 4 settled after "), "{}", text);
+        assert!(text.contains("per soup"), "{}", text);
+        assert!(text.contains("\napgcode "), "{}", text);
+        assert!(!results.codes.is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use conways_game_of_life::checksum;
+use conways_game_of_life::compression;
+use conways_game_of_life::json::JsonGrid;
+use conways_game_of_life::plane::Plane;
+use conways_game_of_life::projection::View;
+use conways_game_of_life::rule::Rule;
+use conways_game_of_life::soup::Soup;
+use conways_game_of_life::theme::Theme;
+use conways_game_of_life::topology::Topology;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The version of the format sessions are saved in. It goes up by one
+/// whenever the format changes, with a migration to bring sessions saved
+/// in the last version up to date.
+pub const VERSION: u64 = 2;
+
+/// The migrations of sessions from each version to the next, the first
+/// from version 1 to 2.
+const MIGRATIONS: [fn(&mut Map<String, Value>); VERSION as usize - 1] = [from_unversioned];
+
+/// The board and view settings as they were when the window was closed,
+/// offered back the next time the game starts. Sessions saved by older
+/// versions are migrated to this one as they are loaded.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Session {
+    /// The version of the format the session is in.
+    pub version: u64,
+    /// The board, with the generation it had reached. With `--infinite`
+    /// this is only the part of the plane in view.
+    pub grid: JsonGrid,
+    pub paused: bool,
+    pub show_graph: bool,
+    pub show_census: bool,
+    pub show_clusters: bool,
+    pub show_heatmap: bool,
+    /// The living cells of the whole plane, with `--infinite`.
+    pub plane: Option<Vec<(i64, i64)>>,
+    /// The cell of the plane at the top left of the view.
+    pub camera: (i64, i64),
+    pub topology: Topology,
+    pub rule: Rule,
+    pub view: View,
+    pub theme: Theme,
+    /// Generations a second.
+    pub speed: Option<u32>,
+    pub soup: Soup,
+}
+
+impl Session {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Session, String> {
+        let path = path.as_ref();
+        let text = compression::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Session::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Reads a session saved in this version of the format or any older
+    /// one, checking its checksum if it has one and migrating it up to this
+    /// one.
+    pub fn from_json(text: &str) -> Result<Session, String> {
+        let mut value: Value =
+            serde_json::from_str(text).map_err(|e| format!("invalid session: {}", e))?;
+        let fields = value
+            .as_object_mut()
+            .ok_or_else(|| "invalid session: it isn't a JSON object".to_string())?;
+        checksum::verify(fields)?;
+        let version = match fields.get("version") {
+            None => 1,
+            Some(version) => version
+                .as_u64()
+                .filter(|&version| version >= 1)
+                .ok_or_else(|| format!("invalid session version {}", version))?,
+        };
+        if version > VERSION {
+            return Err(format!(
+                "the session is in version {} of the format, from a newer release; this one reads up to {}",
+                version, VERSION
+            ));
+        }
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            migrate(fields);
+        }
+        fields.insert("version".to_string(), json!(VERSION));
+        let session: Session =
+            serde_json::from_value(value).map_err(|e| format!("invalid session: {}", e))?;
+        session.grid.to_grid()?;
+        if session.speed == Some(0) {
+            return Err("the speed must be at least 1".to_string());
+        }
+        Ok(session)
+    }
+
+    /// The whole plane, with `--infinite`.
+    pub fn plane(&self) -> Option<Plane> {
+        self.plane.as_ref().map(|cells| {
+            let mut plane = Plane::default();
+            for &(row, col) in cells {
+                plane.set(row, col, true);
+            }
+            plane
+        })
+    }
+
+    /// Writes the session to `path`, sealed with its checksum and compressed
+    /// if it ends in `.gz`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        compression::write(path, &checksum::to_sealed_json(self))
+    }
+}
+
+/// Version 1 sessions, from before there were versions, have the board and
+/// the panels shown, then only the settings that had been added by the
+/// release that saved them. The rest take their defaults.
+fn from_unversioned(fields: &mut Map<String, Value>) {
+    let defaults = [
+        ("plane", Value::Null),
+        ("camera", json!([0, 0])),
+        ("topology", json!(Topology::default())),
+        ("rule", json!(Rule::default())),
+        ("view", json!(View::default())),
+        ("theme", json!(Theme::default())),
+        ("speed", Value::Null),
+        ("soup", json!(Soup::default())),
+    ];
+    for (name, value) in defaults {
+        fields.entry(name).or_insert(value);
+    }
+}
+
+/// Where the last session is kept, compressed, in the platform's data
+/// directory, such as `~/.local/share/conways_game_of_life/session.json.gz`
+/// on Linux.
+pub fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("conways_game_of_life").join("session.json.gz"))
+}
+
+/// Where the last session saved is, if there is one: at `path`, or where
+/// releases from before sessions were compressed kept it.
+pub fn saved_path() -> Option<PathBuf> {
+    let path = path()?;
+    let older = path.with_extension("");
+    vec![path, older].into_iter().find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conways_game_of_life::grid::Grid;
+
+    #[test]
+    fn round_trip_test() {
+        let path = std::env::temp_dir()
+            .join(format!("session_test_{}", std::process::id()))
+            .join("session.json.gz");
+        let session = Session {
+            version: VERSION,
+            grid: JsonGrid::new(&Grid::seed(4, vec![(1, 2)]), Some(42)),
+            paused: true,
+            show_graph: false,
+            show_census: true,
+            show_clusters: false,
+            show_heatmap: false,
+            plane: Some(vec![(-70, 3), (1, 2), (5, 200)]),
+            camera: (-4, 1),
+            topology: "klein".parse().unwrap(),
+            rule: "B36/S23".parse().unwrap(),
+            view: View::Isometric,
+            theme: Theme::Light,
+            speed: Some(12),
+            soup: Soup {
+                density: 0.3,
+                ..Soup::default()
+            },
+        };
+
+        session.save(&path).unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(&[0x1f, 0x8b]));
+
+        let loaded = Session::load(&path).unwrap();
+        assert_eq!(loaded, session);
+        assert_eq!(
+            loaded.plane().unwrap().living_cells().len(),
+            3,
+            "{:?}",
+            loaded.plane()
+        );
+
+        // A session mangled on disk is refused rather than loaded wrongly.
+        let text = compression::read_to_string(&path).unwrap();
+        assert!(text.contains(r#""checksum":""#), "{}", text);
+        let mangled = text.replace("[-70,3]", "[-71,3]");
+        assert!(Session::from_json(&mangled)
+            .unwrap_err()
+            .contains("corrupted"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(Session::load(&path).is_err());
+    }
+
+    #[test]
+    fn older_session_test() {
+        let path = std::env::temp_dir()
+            .join(format!("older_session_test_{}", std::process::id()))
+            .join("session.json");
+        let grid = JsonGrid::new(&Grid::seed(4, vec![(1, 2)]), Some(3));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            format!(
+                r#"{{"grid": {}, "paused": false, "show_graph": false, "show_census": false, "show_clusters": false, "show_heatmap": true}}"#,
+                serde_json::to_string(&grid).unwrap()
+            ),
+        )
+        .unwrap();
+
+        let session = Session::load(&path).unwrap();
+
+        assert_eq!(session.grid, grid);
+        assert_eq!(session.plane, None);
+        assert_eq!(session.camera, (0, 0));
+        assert_eq!(session.view, View::Flat);
+        assert!(session.rule.is_conway());
+        assert_eq!(session.speed, None);
+        assert_eq!(session.version, VERSION);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(Session::load(&path).is_err());
+    }
+
+    #[test]
+    fn version_test() {
+        let grid = serde_json::to_string(&JsonGrid::new(&Grid::seed(2, vec![]), None)).unwrap();
+        let panels = r#""paused": true, "show_graph": false, "show_census": false, "show_clusters": false, "show_heatmap": false"#;
+        let older = format!(r#"{{"grid": {}, {}, "rule": "B36/S23"}}"#, grid, panels);
+        let session = Session::from_json(&older).unwrap();
+        assert_eq!(session.rule.to_string(), "B36/S23");
+        assert_eq!(session.topology, Topology::default());
+        assert_eq!(
+            Session::from_json(&serde_json::to_string(&session).unwrap()),
+            Ok(session)
+        );
+
+        // A session of this version has every setting.
+        let current = format!(
+            r#"{{"version": {}, "grid": {}, {}}}"#,
+            VERSION, grid, panels
+        );
+        assert!(Session::from_json(&current).is_err());
+
+        let newer = format!(
+            r#"{{"version": {}, "grid": {}, {}}}"#,
+            VERSION + 1,
+            grid,
+            panels
+        );
+        let error = Session::from_json(&newer).unwrap_err();
+        assert!(error.contains("newer release"), "{}", error);
+        let bad = format!(r#"{{"version": 0, "grid": {}, {}}}"#, grid, panels);
+        assert!(Session::from_json(&bad).is_err());
+        assert!(Session::from_json("[]").is_err());
+    }
+}
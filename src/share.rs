@@ -0,0 +1,225 @@
+use crate::grid::{check_rule, Grid, State, RULE};
+use crate::rule::Rule;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// The URL safe base64 alphabet, so a share string can go in a link as is.
+pub const URL_SAFE_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// The standard base64 alphabet, for everything else.
+pub const STANDARD_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The most a share string may decompress to, so a malicious one can't
+/// exhaust memory. That is room for a board of over 10000x10000 cells.
+const MAX_DECODED_BYTES: u64 = 16 * 1024 * 1024;
+
+impl Grid {
+    /// Encodes the board and rule as a short string for pasting into chat or
+    /// a URL. The header `rule:WIDTHxHEIGHT:` is followed by one bit per
+    /// cell, row by row, and the whole is compressed with deflate and written
+    /// in URL safe base64.
+    pub fn to_share_string(&self) -> String {
+        self.to_share_string_under(Rule::default())
+    }
+
+    /// Encodes the board as a share string for running by `rule`.
+    pub fn to_share_string_under(&self, rule: Rule) -> String {
+        let (width, height) = (self.width(), self.height());
+        let mut bytes = format!("{}:{}x{}:", rule, width, height).into_bytes();
+        let mut bits = vec![0u8; (width * height).div_ceil(8)];
+        for (row, col) in self.living_cells() {
+            let index = row * width + col;
+            bits[index / 8] |= 1 << (index % 8);
+        }
+        bytes.extend(bits);
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        // Writing to a Vec can't fail.
+        let _ = encoder.write_all(&bytes);
+        encode_base64(&encoder.finish().unwrap_or_default(), URL_SAFE_ALPHABET)
+    }
+
+    /// Reads a board back from [`Grid::to_share_string`], if it runs by
+    /// Conway's rule.
+    pub fn from_share_string(text: &str) -> Result<Grid, String> {
+        let (grid, rule) = Grid::from_share_string_with_rule(text)?;
+        if !rule.is_conway() {
+            return Err(format!(
+                "unsupported rule '{}', only {} is supported here",
+                rule, RULE
+            ));
+        }
+        Ok(grid)
+    }
+
+    /// Reads a board back from [`Grid::to_share_string_under`], with the
+    /// rule it runs by.
+    pub fn from_share_string_with_rule(text: &str) -> Result<(Grid, Rule), String> {
+        let compressed = decode_base64(text.trim())?;
+        let mut bytes = Vec::new();
+        DeflateDecoder::new(compressed.as_slice())
+            .take(MAX_DECODED_BYTES)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("share string is corrupt: {}", e))?;
+
+        let mut fields = bytes.splitn(3, |byte| *byte == b':');
+        let rule = fields
+            .next()
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default();
+        let rule = check_rule(&rule)?;
+        let size = fields
+            .next()
+            .map(String::from_utf8_lossy)
+            .ok_or("share string has no board size")?;
+        let (width, height) = match size.split_once('x') {
+            Some((width, height)) => (
+                width
+                    .parse::<usize>()
+                    .map_err(|_| "share string has a bad width")?,
+                height
+                    .parse::<usize>()
+                    .map_err(|_| "share string has a bad height")?,
+            ),
+            None => return Err("share string has no board size".to_string()),
+        };
+        if width == 0 || height == 0 {
+            return Err("share string has an empty board".to_string());
+        }
+        let bits = fields.next().unwrap_or_default();
+        if width.checked_mul(height).map(|cells| cells.div_ceil(8)) != Some(bits.len()) {
+            return Err(format!(
+                "share string has the wrong number of cells for {}x{}",
+                width, height
+            ));
+        }
+
+        let cells = (0..height)
+            .map(|row| {
+                (0..width)
+                    .map(|col| {
+                        let index = row * width + col;
+                        if bits[index / 8] & (1 << (index % 8)) != 0 {
+                            State::Alive
+                        } else {
+                            State::Dead
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok((Grid { cells }, rule))
+    }
+}
+
+/// `bytes` in base64 written with `alphabet`, without the padding, which
+/// the length makes redundant.
+pub fn encode_base64(bytes: &[u8], alphabet: &[u8; 64]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            text.push(alphabet[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    text
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut group = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = URL_SAFE_ALPHABET
+            .iter()
+            .position(|letter| *letter == c)
+            .ok_or_else(|| format!("unexpected character '{}' in share string", c as char))?;
+        group = group << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let glider = Grid::seed_rectangle(7, 5, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+
+        let text = glider.to_share_string();
+
+        assert!(text
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+        assert_eq!(Grid::from_share_string(&text), Ok(glider));
+    }
+
+    #[test]
+    fn short_test() {
+        let mut grid = Grid::seed(200, vec![(100, 100), (100, 101), (100, 102)]);
+        grid.toggle(10, 10);
+
+        assert!(grid.to_share_string().len() < 100);
+    }
+
+    #[test]
+    fn base64_test() {
+        for length in 0..6 {
+            let bytes: Vec<u8> = (0..length).map(|i| 250 - i as u8 * 37).collect();
+            assert_eq!(
+                decode_base64(&encode_base64(&bytes, URL_SAFE_ALPHABET)),
+                Ok(bytes)
+            );
+        }
+        assert_eq!(encode_base64(b"Life", URL_SAFE_ALPHABET), "TGlmZQ");
+        assert_eq!(encode_base64(&[0xfb, 0xff], URL_SAFE_ALPHABET), "-_8");
+        assert_eq!(encode_base64(&[0xfb, 0xff], STANDARD_ALPHABET), "+/8");
+    }
+
+    #[test]
+    fn bad_share_string_test() {
+        assert!(Grid::from_share_string("not base64!").is_err());
+        assert!(Grid::from_share_string("TGlmZQ").is_err());
+        let wrong_rule = encode_base64(
+            &{
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(b"B36/S23:1x1:\x01").unwrap();
+                encoder.finish().unwrap()
+            },
+            URL_SAFE_ALPHABET,
+        );
+        assert!(Grid::from_share_string(&wrong_rule)
+            .unwrap_err()
+            .contains("unsupported rule"));
+    }
+
+    #[test]
+    fn rule_test() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        let blinker = Grid::seed(3, vec![(1, 0), (1, 1), (1, 2)]);
+
+        let text = blinker.to_share_string_under(highlife);
+
+        assert_eq!(
+            Grid::from_share_string_with_rule(&text),
+            Ok((blinker.clone(), highlife))
+        );
+        assert!(Grid::from_share_string(&text).is_err());
+        assert_eq!(
+            Grid::from_share_string_with_rule(&blinker.to_share_string()),
+            Ok((blinker, Rule::default()))
+        );
+    }
+}
@@ -0,0 +1,581 @@
+//! One board shared by several people over the network. A host runs the
+//! board as usual and others join it: everyone sees the same generations
+//! and edits, and where everyone else's cursor is, each in its own colour.
+//!
+//! The host's board is the only one. Guests send it the edits they make, as
+//! the state they want a cell to be rather than a toggle, and it sends back
+//! every change to the board, whoever made it. So two people clicking the
+//! same cell at once agree on it, and whichever edit reached the host last
+//! is the one that stands.
+//!
+//! Messages are JSON, one to a line, like:
+//!
+//! ```json
+//! {"type": "set", "row": 3, "col": 4, "alive": true}
+//! {"type": "cells", "generation": 12, "alive": [[3, 4]], "dead": [[2, 4]]}
+//! ```
+
+use crate::grid::{Grid, State};
+use crate::socket::Socket;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long connecting to a host can take.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The longest line expected, past which the connection is given up as
+/// broken.
+const MAX_LINE: usize = 1 << 24;
+
+/// The user the host is.
+pub const HOST: usize = 0;
+
+/// The `host:port` from `spec`, checking it has a port.
+pub fn address(spec: &str) -> Result<String, String> {
+    match spec.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+            Ok(spec.to_string())
+        }
+        _ => Err(format!("'{}' is not an address like 0.0.0.0:7000", spec)),
+    }
+}
+
+/// The colours of users' cursors, taken in turn.
+const CURSOR_COLOURS: [(u8, u8, u8); 6] = [
+    (255, 200, 40),
+    (60, 200, 255),
+    (255, 90, 160),
+    (120, 230, 90),
+    (190, 120, 255),
+    (255, 140, 60),
+];
+
+/// The colour of `user`'s cursor.
+pub fn colour(user: usize) -> (u8, u8, u8) {
+    CURSOR_COLOURS[user % CURSOR_COLOURS.len()]
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// Tells a guest which user it is.
+    Welcome {
+        user: usize,
+    },
+    /// All of the board, as a share string.
+    Board {
+        generation: u64,
+        board: String,
+    },
+    /// The cells that changed since the board last sent.
+    Cells {
+        generation: u64,
+        alive: Vec<(usize, usize)>,
+        dead: Vec<(usize, usize)>,
+    },
+    /// Where a user's cursor is, or that it is off the board.
+    Cursor {
+        #[serde(default)]
+        user: usize,
+        cell: Option<(usize, usize)>,
+    },
+    Left {
+        user: usize,
+    },
+    /// A guest asking for a cell to be made alive or dead.
+    Set {
+        row: usize,
+        col: usize,
+        alive: bool,
+    },
+}
+
+impl Message {
+    pub fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).expect("messages always serialize");
+        line.push('\n');
+        line
+    }
+
+    pub fn parse(line: &str) -> Result<Message, String> {
+        serde_json::from_str(line).map_err(|e| format!("not a message: {}", e))
+    }
+}
+
+/// The message that takes `before` to `after`, or none if they are the same.
+pub fn changes(generation: u64, before: &(u64, Grid), after: &Grid) -> Option<Message> {
+    let (before_generation, before) = before;
+    if (before.width(), before.height()) != (after.width(), after.height()) {
+        return Some(Message::Board {
+            generation,
+            board: after.to_share_string(),
+        });
+    }
+    let (mut alive, mut dead) = (Vec::new(), Vec::new());
+    for (row, (old, new)) in before.cells.iter().zip(&after.cells).enumerate() {
+        for (col, (old, new)) in old.iter().zip(new).enumerate() {
+            match (old, new) {
+                (State::Dead, State::Alive) => alive.push((row, col)),
+                (State::Alive, State::Dead) => dead.push((row, col)),
+                _ => (),
+            }
+        }
+    }
+    if alive.is_empty() && dead.is_empty() && *before_generation == generation {
+        return None;
+    }
+    Some(Message::Cells {
+        generation,
+        alive,
+        dead,
+    })
+}
+
+/// One end of the connection between a host and a guest. Messages that
+/// can't be sent at once wait for the next, so a slow connection doesn't
+/// hold up the others, until so many have piled up that it is dropped.
+struct Connection {
+    socket: Socket,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            socket: Socket::new(stream)?,
+        })
+    }
+
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.socket.send(message.to_line().as_bytes())
+    }
+
+    /// Sends what is still waiting to be, and gives the messages received
+    /// since last asked. An error means the connection is lost.
+    fn receive(&mut self) -> io::Result<Vec<Message>> {
+        self.socket.flush()?;
+        self.socket
+            .lines(MAX_LINE)?
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                Message::parse(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+            .collect()
+    }
+}
+
+struct Peer {
+    user: usize,
+    connection: Connection,
+}
+
+/// The end of a shared board that runs it.
+pub struct Host {
+    listener: TcpListener,
+    guests: Vec<Peer>,
+    next_user: usize,
+    cursors: BTreeMap<usize, (usize, usize)>,
+    /// The generation and board last sent.
+    sent: Option<(u64, Grid)>,
+}
+
+impl Host {
+    /// Listens for guests on `address`, a `host:port`.
+    pub fn bind(address: &str) -> io::Result<Host> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Host {
+            listener,
+            guests: Vec::new(),
+            next_user: HOST + 1,
+            cursors: BTreeMap::new(),
+            sent: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn guests(&self) -> usize {
+        self.guests.len()
+    }
+
+    /// Where everyone's cursor is, by user.
+    pub fn cursors(&self) -> &BTreeMap<usize, (usize, usize)> {
+        &self.cursors
+    }
+
+    /// Lets in anyone who has joined since last asked, sending them the
+    /// board last published, and gives the edits guests have asked for, in
+    /// the order they arrived, with who asked.
+    pub fn poll(&mut self) -> Vec<(usize, (usize, usize), bool)> {
+        while let Ok((stream, _)) = self.listener.accept() {
+            let user = self.next_user;
+            let mut welcome = vec![Message::Welcome { user }];
+            // Otherwise they get it with everyone else when it is published.
+            if let Some((generation, grid)) = &self.sent {
+                welcome.push(Message::Board {
+                    generation: *generation,
+                    board: grid.to_share_string(),
+                });
+            }
+            welcome.extend(self.cursors.iter().map(|(&user, &cell)| Message::Cursor {
+                user,
+                cell: Some(cell),
+            }));
+            let joined = Connection::new(stream).and_then(|mut connection| {
+                for message in &welcome {
+                    connection.send(message)?;
+                }
+                Ok(connection)
+            });
+            match joined {
+                Ok(connection) => {
+                    self.next_user += 1;
+                    self.guests.push(Peer { user, connection });
+                }
+                Err(e) => eprintln!("someone could not join: {}", e),
+            }
+        }
+        let mut edits = Vec::new();
+        let mut relayed = Vec::new();
+        let mut left = Vec::new();
+        for guest in &mut self.guests {
+            match guest.connection.receive() {
+                Ok(messages) => {
+                    for message in messages {
+                        match message {
+                            Message::Set { row, col, alive } => {
+                                edits.push((guest.user, (row, col), alive))
+                            }
+                            Message::Cursor { cell, .. } => relayed.push(Message::Cursor {
+                                user: guest.user,
+                                cell,
+                            }),
+                            _ => (),
+                        }
+                    }
+                }
+                Err(_) => left.push(guest.user),
+            }
+        }
+        self.guests.retain(|guest| !left.contains(&guest.user));
+        relayed.extend(left.into_iter().map(|user| Message::Left { user }));
+        for message in relayed {
+            self.broadcast(&message);
+        }
+        edits
+    }
+
+    /// Moves the host's own cursor, telling the guests if it has moved.
+    pub fn move_cursor(&mut self, cell: Option<(usize, usize)>) {
+        if self.cursors.get(&HOST).copied() != cell {
+            self.broadcast(&Message::Cursor { user: HOST, cell });
+        }
+    }
+
+    /// Sends the guests what has changed on `grid` since it was last sent.
+    pub fn publish(&mut self, generation: u64, grid: &Grid) {
+        let message = match &self.sent {
+            Some(sent) => changes(generation, sent, grid),
+            None => Some(Message::Board {
+                generation,
+                board: grid.to_share_string(),
+            }),
+        };
+        if let Some(message) = message {
+            self.broadcast(&message);
+            self.sent = Some((generation, grid.clone()));
+        }
+    }
+
+    /// Sends `message` to every guest but the one it is about, keeping track
+    /// of cursors and dropping any guest that can't be sent to.
+    fn broadcast(&mut self, message: &Message) {
+        let about = match *message {
+            Message::Cursor { user, cell } => {
+                match cell {
+                    Some(cell) => self.cursors.insert(user, cell),
+                    None => self.cursors.remove(&user),
+                };
+                Some(user)
+            }
+            Message::Left { user } => {
+                self.cursors.remove(&user);
+                None
+            }
+            _ => None,
+        };
+        let mut dropped = Vec::new();
+        for guest in &mut self.guests {
+            if Some(guest.user) != about && guest.connection.send(message).is_err() {
+                dropped.push(guest.user);
+            }
+        }
+        if !dropped.is_empty() {
+            self.guests.retain(|guest| !dropped.contains(&guest.user));
+            for user in dropped {
+                self.broadcast(&Message::Left { user });
+            }
+        }
+    }
+}
+
+/// The end of a shared board that has joined a host.
+pub struct Guest {
+    connection: Connection,
+    user: Option<usize>,
+    cursors: BTreeMap<usize, (usize, usize)>,
+    /// The board as the host last said.
+    board: Option<(u64, Grid)>,
+}
+
+impl Guest {
+    /// Joins the host at `address`, a `host:port`.
+    pub fn connect(address: &str) -> io::Result<Guest> {
+        let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{} not found", address))
+        })?;
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        Ok(Guest {
+            connection: Connection::new(stream)?,
+            user: None,
+            cursors: BTreeMap::new(),
+            board: None,
+        })
+    }
+
+    /// The user the host says this is, once it has.
+    pub fn user(&self) -> Option<usize> {
+        self.user
+    }
+
+    /// Where everyone else's cursor is, by user.
+    pub fn cursors(&self) -> &BTreeMap<usize, (usize, usize)> {
+        &self.cursors
+    }
+
+    /// Each board the host has sent since last asked, in order, with its
+    /// generation. An error means the host is gone.
+    pub fn poll(&mut self) -> io::Result<Vec<(u64, Grid)>> {
+        let mut boards = Vec::new();
+        for message in self.connection.receive()? {
+            match message {
+                Message::Welcome { user } => self.user = Some(user),
+                Message::Board { generation, board } => {
+                    let grid = Grid::from_share_string(&board)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    self.board = Some((generation, grid));
+                }
+                Message::Cells {
+                    generation,
+                    alive,
+                    dead,
+                } => {
+                    let (board_generation, grid) = self.board.as_mut().ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "changes before a board")
+                    })?;
+                    let (width, height) = (grid.width(), grid.height());
+                    for (cells, state) in [(alive, State::Alive), (dead, State::Dead)] {
+                        for (row, col) in cells {
+                            if row >= height || col >= width {
+                                return Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    "a change off the board",
+                                ));
+                            }
+                            grid.cells[row][col] = state.clone();
+                        }
+                    }
+                    *board_generation = generation;
+                }
+                Message::Cursor { user, cell } => {
+                    match cell {
+                        Some(cell) => self.cursors.insert(user, cell),
+                        None => self.cursors.remove(&user),
+                    };
+                    continue;
+                }
+                Message::Left { user } => {
+                    self.cursors.remove(&user);
+                    continue;
+                }
+                Message::Set { .. } => continue,
+            }
+            if let Some(board) = &self.board {
+                boards.push(board.clone());
+            }
+        }
+        Ok(boards)
+    }
+
+    /// Asks the host to make the cell at `row` and `col` alive or dead.
+    pub fn set(&mut self, row: usize, col: usize, alive: bool) -> io::Result<()> {
+        self.connection.send(&Message::Set { row, col, alive })
+    }
+
+    pub fn move_cursor(&mut self, cell: Option<(usize, usize)>) -> io::Result<()> {
+        self.connection.send(&Message::Cursor { user: 0, cell })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll_until<T, F: FnMut() -> Vec<T>>(mut poll: F) -> Vec<T> {
+        for _ in 0..100 {
+            let got = poll();
+            if !got.is_empty() {
+                return got;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Vec::new()
+    }
+
+    #[test]
+    fn address_test() {
+        assert_eq!(address("0.0.0.0:7000"), Ok("0.0.0.0:7000".to_string()));
+        assert_eq!(
+            address("life.local:7000"),
+            Ok("life.local:7000".to_string())
+        );
+        assert!(address("0.0.0.0").is_err());
+        assert!(address(":7000").is_err());
+        assert!(address("0.0.0.0:port").is_err());
+    }
+
+    #[test]
+    fn message_test() {
+        let set = Message::Set {
+            row: 3,
+            col: 4,
+            alive: true,
+        };
+        assert_eq!(
+            set.to_line(),
+            "{\"type\":\"set\",\"row\":3,\"col\":4,\"alive\":true}\n"
+        );
+        assert_eq!(Message::parse(&set.to_line()), Ok(set));
+        assert_eq!(
+            Message::parse("{\"type\": \"cursor\", \"cell\": null}"),
+            Ok(Message::Cursor {
+                user: 0,
+                cell: None
+            })
+        );
+        assert!(Message::parse("{\"type\": \"launch\"}").is_err());
+    }
+
+    #[test]
+    fn changes_test() {
+        let blinker = Grid::seed_rectangle(3, 3, vec![(1, 0), (1, 1), (1, 2)]);
+        let next = blinker.next_generation();
+        assert_eq!(
+            changes(1, &(0, blinker.clone()), &next),
+            Some(Message::Cells {
+                generation: 1,
+                alive: vec![(0, 1), (2, 1)],
+                dead: vec![(1, 0), (1, 2)],
+            })
+        );
+        assert_eq!(changes(0, &(0, blinker.clone()), &blinker), None);
+        assert!(matches!(
+            changes(0, &(0, blinker), &Grid::seed_rectangle(4, 4, vec![])),
+            Some(Message::Board { .. })
+        ));
+    }
+
+    #[test]
+    fn session_test() {
+        let mut grid = Grid::seed_rectangle(5, 5, vec![(2, 1), (2, 2), (2, 3)]);
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let address = host.local_addr().unwrap().to_string();
+        host.publish(0, &grid);
+        host.move_cursor(Some((0, 0)));
+
+        let mut ann = Guest::connect(&address).unwrap();
+        let mut bob = Guest::connect(&address).unwrap();
+        assert!(host.poll().is_empty());
+        assert_eq!(host.guests(), 2);
+        assert_eq!(poll_until(|| ann.poll().unwrap()), vec![(0, grid.clone())]);
+        assert_eq!(ann.user(), Some(1));
+        assert_eq!(ann.cursors().get(&HOST), Some(&(0, 0)));
+        poll_until(|| bob.poll().unwrap());
+        assert_eq!(bob.user(), Some(2));
+
+        // Both make the same cell alive at once, and it ends up alive.
+        ann.set(0, 4, true).unwrap();
+        bob.set(0, 4, true).unwrap();
+        ann.move_cursor(Some((0, 4))).unwrap();
+        let mut edits = Vec::new();
+        for _ in 0..100 {
+            edits.extend(host.poll());
+            if edits.len() == 2 && host.cursors().contains_key(&1) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        edits.sort();
+        assert_eq!(edits, vec![(1, (0, 4), true), (2, (0, 4), true)]);
+        assert_eq!(host.cursors().get(&1), Some(&(0, 4)));
+        grid.cells[0][4] = State::Alive;
+        host.publish(0, &grid);
+        grid = grid.next_generation();
+        host.publish(1, &grid);
+
+        let mut boards = Vec::new();
+        for _ in 0..100 {
+            boards.extend(bob.poll().unwrap());
+            if boards.len() == 2 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(boards.last(), Some(&(1, grid.clone())));
+        assert_eq!(boards[0].1.cells[0][4], State::Alive);
+        assert_eq!(bob.cursors().get(&1), Some(&(0, 4)));
+
+        drop(ann);
+        for _ in 0..100 {
+            host.poll();
+            if host.guests() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(host.guests(), 1);
+        assert!(!host.cursors().contains_key(&1));
+    }
+
+    #[test]
+    fn slow_guest_test() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        // A guest that never reads what it is sent.
+        let _guest = TcpStream::connect(host.local_addr().unwrap()).unwrap();
+        host.poll();
+        assert_eq!(host.guests(), 1);
+
+        let stripes = |offset: usize| {
+            let cells = (0..200)
+                .filter(|row| row % 2 == offset)
+                .flat_map(|row| (0..200).map(move |col| (row, col)))
+                .collect();
+            Grid::seed(200, cells)
+        };
+        // Every cell changes each generation, several megabytes in all, much
+        // more than the connection holds, and none of it waits on the guest.
+        let start = std::time::Instant::now();
+        for generation in 0..20 {
+            host.publish(generation, &stripes(generation as usize % 2));
+        }
+        assert!(start.elapsed() < TIMEOUT, "{:?}", start.elapsed());
+        assert_eq!(host.guests(), 1);
+    }
+}
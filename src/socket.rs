@@ -0,0 +1,175 @@
+//! A TCP connection that is never waited on, for the servers and clients
+//! that run alongside the game loop. What arrives is kept until a whole
+//! line or request has come, and what is sent is kept until the other end
+//! takes it, so a slow or silent connection can't hold the game up.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+/// The most that can be waiting to be sent, past which the other end is
+/// taken to be too slow to keep up.
+const MAX_BACKLOG: usize = 1 << 26;
+
+pub struct Socket {
+    stream: TcpStream,
+    /// What has been read but not yet taken.
+    incoming: Vec<u8>,
+    /// What has been sent but not yet written.
+    outgoing: Vec<u8>,
+}
+
+impl Socket {
+    pub fn new(stream: TcpStream) -> io::Result<Socket> {
+        stream.set_nonblocking(true)?;
+        Ok(Socket {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    /// Reads some of what has arrived, if anything has, giving whether it
+    /// had. An error means the connection is lost.
+    fn read(&mut self) -> io::Result<bool> {
+        let mut buffer = [0; 4096];
+        match self.stream.read(&mut buffer) {
+            Ok(0) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the connection was closed",
+            )),
+            Ok(read) => {
+                self.incoming.extend_from_slice(&buffer[..read]);
+                Ok(true)
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The whole lines that have arrived, without their line endings. An
+    /// error means the connection is lost, or that a line longer than
+    /// `longest` bytes is coming.
+    pub fn lines(&mut self, longest: usize) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        loop {
+            let more = self.read()?;
+            while let Some(end) = self.incoming.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.incoming.drain(..=end).collect();
+                lines.push(String::from_utf8_lossy(&line).trim_end().to_string());
+            }
+            if self.incoming.len() > longest {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "too long a line",
+                ));
+            }
+            if !more {
+                return Ok(lines);
+            }
+        }
+    }
+
+    /// The head of an HTTP request, up to the blank line that ends it, once
+    /// all of it has arrived. An error means the connection is lost, or
+    /// that the head is longer than `longest` bytes.
+    pub fn head(&mut self, longest: usize) -> io::Result<Option<String>> {
+        loop {
+            let more = self.read()?;
+            if let Some(end) = self.incoming.windows(4).position(|end| end == b"\r\n\r\n") {
+                let head: Vec<u8> = self.incoming.drain(..end + 4).collect();
+                return Ok(Some(String::from_utf8_lossy(&head).into_owned()));
+            }
+            if self.incoming.len() > longest {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "too long a request",
+                ));
+            }
+            if !more {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Sends `bytes` after anything still waiting, writing as much as the
+    /// other end will take now and keeping the rest for [`Socket::flush`].
+    pub fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.outgoing.extend_from_slice(bytes);
+        self.flush()
+    }
+
+    /// Writes as much of what is waiting as the other end will take. An
+    /// error means the connection is lost, or is too far behind.
+    pub fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        if self.outgoing.len() > MAX_BACKLOG {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the connection can't keep up",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether everything sent has been written.
+    pub fn is_flushed(&self) -> bool {
+        self.outgoing.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn socket_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut socket = Socket::new(listener.accept().unwrap().0).unwrap();
+
+        client.write_all(b"one\r\ntwo\nthr").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(socket.lines(100).unwrap(), vec!["one", "two"]);
+        assert!(socket.lines(2).is_err(), "thr is more than 2 bytes");
+        client.write_all(b"ee\r\n\r\nrest").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(socket.head(100).unwrap(), Some("three\r\n\r\n".to_string()));
+        assert_eq!(socket.head(100).unwrap(), None);
+        assert!(socket.head(3).is_err(), "rest is more than 3 bytes");
+
+        socket.send(b"hello").unwrap();
+        assert!(socket.is_flushed());
+        let mut received = [0; 5];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"hello");
+
+        drop(client);
+        thread::sleep(Duration::from_millis(50));
+        assert!(socket.lines(100).is_err());
+    }
+
+    #[test]
+    fn backlog_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut socket = Socket::new(listener.accept().unwrap().0).unwrap();
+        // The client never reads, so what is sent piles up without
+        // blocking until there is too much of it.
+        let chunk = vec![0; 1 << 20];
+        let sent = (0..100).position(|_| socket.send(&chunk).is_err());
+        assert!(sent.is_some());
+        assert!(!socket.is_flushed());
+    }
+}
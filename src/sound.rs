@@ -0,0 +1,181 @@
+//! Sounds made from the board as it runs. They are synthesized as WAV data
+//! rather than loaded from files, so there is nothing to ship alongside the
+//! game.
+
+use std::f32::consts::PI;
+
+/// Samples a second of the synthesized sounds.
+pub const SAMPLE_RATE: u32 = 22050;
+
+/// How many births or deaths in a generation tick at full volume.
+const LOUDEST: f32 = 400.0;
+
+/// A mono, 16-bit WAV file of a sine tone at `frequency` hertz lasting
+/// `seconds`, fading out towards the end so it stops without a click.
+pub fn tone(frequency: f32, seconds: f32) -> Vec<u8> {
+    let samples = (SAMPLE_RATE as f32 * seconds).round() as usize;
+    wav((0..samples).map(|i| {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let fade = 1.0 - i as f32 / samples as f32;
+        (2.0 * PI * frequency * t).sin() * fade
+    }))
+}
+
+/// How long the built in ambient loop lasts, in seconds.
+const AMBIENT_LENGTH: u32 = 8;
+
+/// The notes of the ambient loop's chord, in hertz, an A major chord over
+/// a low A. Each is a whole number of cycles over the loop, so it repeats
+/// without a join being heard.
+const AMBIENT_CHORD: [f32; 4] = [110.0, 220.0, 277.5, 330.0];
+
+/// A quiet WAV loop to play behind the game: a soft chord that swells and
+/// fades, each note at its own slow pace.
+pub fn ambient_loop() -> Vec<u8> {
+    let samples = SAMPLE_RATE * AMBIENT_LENGTH;
+    wav((0..samples).map(|i| {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let sum: f32 = AMBIENT_CHORD
+            .iter()
+            .enumerate()
+            .map(|(n, frequency)| {
+                // Between one and four swells of each note over the loop.
+                let swell = (PI * (n + 1) as f32 * t / AMBIENT_LENGTH as f32).sin();
+                (2.0 * PI * frequency * t).sin() * swell * swell
+            })
+            .sum();
+        sum / AMBIENT_CHORD.len() as f32 * 0.5
+    }))
+}
+
+/// A mono, 16-bit WAV file of `samples`, each from -1 to 1.
+fn wav<I: ExactSizeIterator<Item = f32>>(samples: I) -> Vec<u8> {
+    let mut wav = wav_header(samples.len());
+    for sample in samples {
+        wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
+
+/// The 44 byte header of a mono, 16-bit WAV file of `samples` samples.
+fn wav_header(samples: usize) -> Vec<u8> {
+    let data_len = samples as u32 * 2;
+    let mut header = Vec::with_capacity(44 + data_len as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel.
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// How loud the tick for `count` births or deaths in a generation is, from
+/// silent for none to 1 for a boom of [`LOUDEST`] or more. It rises
+/// quickly at first, so a handful of changes can still be heard.
+pub fn tick_volume(count: usize) -> f32 {
+    (count as f32 / LOUDEST).sqrt().min(1.0)
+}
+
+/// The pitch a board with nothing alive is sonified at, in hertz.
+const LOWEST_PITCH: f32 = 110.0;
+
+/// How many octaves above the lowest pitch a board half full sits.
+const OCTAVES: f32 = 3.0;
+
+/// A note of the sonified population: how high and how loud it sounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Note {
+    /// In hertz.
+    pub frequency: f32,
+    /// From 0 to 1.
+    pub volume: f32,
+}
+
+impl Note {
+    /// The note for a board of `cells` cells whose population went from
+    /// `previous` to `population` since the last note. The fuller the board
+    /// the higher the pitch, with a board half full, about as full as soups
+    /// get, [`OCTAVES`] above the lowest. The faster the population is
+    /// changing the louder it is, so a steady board hums along quietly and
+    /// an explosion swells.
+    pub fn new(population: usize, previous: usize, cells: usize) -> Note {
+        let cells = cells.max(1) as f32;
+        let density = (population as f32 / cells * 2.0).min(1.0);
+        let frequency = LOWEST_PITCH * 2f32.powf(OCTAVES * density.sqrt());
+        let change = (population as f32 - previous as f32).abs() / cells;
+        let volume = 0.2 + 0.8 * (change * 100.0).sqrt().min(1.0);
+        Note { frequency, volume }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_test() {
+        let wav = tone(440.0, 0.1);
+
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        let samples = (SAMPLE_RATE / 10) as usize;
+        assert_eq!(wav.len(), 44 + samples * 2);
+        assert_eq!(
+            u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]),
+            samples as u32 * 2
+        );
+        // Silent at the start of the wave, and faded out by the end.
+        assert_eq!(&wav[44..46], &[0, 0]);
+        let last = i16::from_le_bytes([wav[wav.len() - 2], wav[wav.len() - 1]]);
+        assert!(last.abs() < 100);
+    }
+
+    #[test]
+    fn ambient_loop_test() {
+        let wav = ambient_loop();
+        let samples: Vec<i16> = wav[44..]
+            .chunks(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+
+        assert_eq!(samples.len(), (SAMPLE_RATE * AMBIENT_LENGTH) as usize);
+        // It starts and ends silent, so looping it doesn't click.
+        assert_eq!(samples[0], 0);
+        assert!(samples[samples.len() - 1].abs() < 50);
+        let loudest = samples.iter().map(|sample| sample.abs()).max().unwrap();
+        assert!(loudest > 1000 && loudest < i16::MAX / 2);
+    }
+
+    #[test]
+    fn tick_volume_test() {
+        assert_eq!(tick_volume(0), 0.0);
+        assert_eq!(tick_volume(100), 0.5);
+        assert_eq!(tick_volume(400), 1.0);
+        assert_eq!(tick_volume(10_000), 1.0);
+        assert!(tick_volume(3) > 0.05);
+    }
+
+    #[test]
+    fn note_test() {
+        let empty = Note::new(0, 0, 100);
+        assert_eq!(empty.frequency, LOWEST_PITCH);
+        assert_eq!(empty.volume, 0.2);
+
+        let half_full = Note::new(50, 50, 100);
+        assert_eq!(half_full.frequency, LOWEST_PITCH * 8.0);
+        assert_eq!(Note::new(90, 90, 100).frequency, half_full.frequency);
+
+        let growing = Note::new(30, 20, 100);
+        assert!(growing.frequency < half_full.frequency);
+        assert_eq!(growing.volume, 1.0);
+        assert!(Note::new(1001, 1000, 10_000).volume < growing.volume);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::grid::{check_density, Grid, State, DEFAULT_DENSITY};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Layers of noise added together, each with features half the size of
+/// the one before, for detail at several scales.
+const NOISE_OCTAVES: u32 = 3;
+
+/// How random soups are made.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Soup {
+    /// The chance of each cell being alive, from 0 to 1.
+    #[serde(default = "default_density")]
+    pub density: f64,
+    #[serde(default)]
+    pub symmetry: Symmetry,
+    /// The rough size in cells of the clumps of coherent noise to threshold
+    /// instead of choosing every cell independently.
+    #[serde(default)]
+    pub noise: Option<f64>,
+}
+
+impl Default for Soup {
+    fn default() -> Soup {
+        Soup {
+            density: DEFAULT_DENSITY,
+            symmetry: Symmetry::None,
+            noise: None,
+        }
+    }
+}
+
+impl Soup {
+    pub fn check(&self) -> Result<(), String> {
+        check_density(self.density)?;
+        match self.noise {
+            Some(scale) if !(scale >= 1.0 && scale.is_finite()) => {
+                Err(format!("noise scale {} is less than 1 cell", scale))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// A `width` by `height` soup drawn from `rng`, so that seeding it
+    /// reproduces the same board.
+    pub fn generate<R: Rng + ?Sized>(&self, width: usize, height: usize, rng: &mut R) -> Grid {
+        let soup = match self.noise {
+            Some(scale) => noise_soup(width, height, scale, self.density, rng),
+            None => Grid::random_rectangle(width, height, self.density, rng),
+        };
+        self.symmetry.apply(&soup)
+    }
+}
+
+fn default_density() -> f64 {
+    DEFAULT_DENSITY
+}
+
+/// A soup with the `density` share of cells that score highest in fractal
+/// value noise alive, giving organic clumps about `scale` cells across.
+fn noise_soup<R: Rng + ?Sized>(
+    width: usize,
+    height: usize,
+    scale: f64,
+    density: f64,
+    rng: &mut R,
+) -> Grid {
+    let mut field = vec![0.0; width * height];
+    for octave in 0..NOISE_OCTAVES {
+        let spacing = (scale / 2f64.powi(octave as i32)).max(1.0);
+        let amplitude = 0.5f64.powi(octave as i32);
+        let lattice_width = (width as f64 / spacing) as usize + 2;
+        let lattice_height = (height as f64 / spacing) as usize + 2;
+        let lattice: Vec<f64> = (0..lattice_width * lattice_height)
+            .map(|_| rng.gen())
+            .collect();
+        let at = |x: usize, y: usize| lattice[y * lattice_width + x];
+
+        for row in 0..height {
+            let y = row as f64 / spacing;
+            let (y0, ty) = (y as usize, smooth(y.fract()));
+            for col in 0..width {
+                let x = col as f64 / spacing;
+                let (x0, tx) = (x as usize, smooth(x.fract()));
+                let top = lerp(at(x0, y0), at(x0 + 1, y0), tx);
+                let bottom = lerp(at(x0, y0 + 1), at(x0 + 1, y0 + 1), tx);
+                field[row * width + col] += amplitude * lerp(top, bottom, ty);
+            }
+        }
+    }
+
+    // The value ranked at the density gives the threshold, so the soup has
+    // exactly the density asked for whatever the noise is like.
+    let mut ranked = field.clone();
+    ranked.sort_by(|a, b| b.total_cmp(a));
+    let alive = (density * ranked.len() as f64).round() as usize;
+    let living_cells = match alive.checked_sub(1).map(|index| ranked[index]) {
+        Some(threshold) => field
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value >= threshold)
+            .map(|(index, _)| (index / width, index % width))
+            .collect(),
+        None => Vec::new(),
+    };
+    Grid::seed_rectangle(width, height, living_cells)
+}
+
+fn smooth(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// A symmetry forced on a random soup, which tends to evolve into very
+/// different and often prettier shapes than plain noise.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// The right half mirrors the left.
+    Horizontal,
+    /// The bottom half mirrors the top.
+    Vertical,
+    /// Each quarter mirrors the top left one.
+    FourFold,
+    /// Mirrored across the diagonal from the top left corner, over the
+    /// largest square that fits the board.
+    Diagonal,
+}
+
+impl Symmetry {
+    /// Makes `grid` symmetric by copying the cells of one part of it over
+    /// their reflections.
+    pub fn apply(self, grid: &Grid) -> Grid {
+        let (width, height) = (grid.width(), grid.height());
+        let square = width.min(height);
+        let source = |row: usize, col: usize| -> (usize, usize) {
+            let mirror_col = col.min(width - 1 - col);
+            let mirror_row = row.min(height - 1 - row);
+            match self {
+                Symmetry::None => (row, col),
+                Symmetry::Horizontal => (row, mirror_col),
+                Symmetry::Vertical => (mirror_row, col),
+                Symmetry::FourFold => (mirror_row, mirror_col),
+                Symmetry::Diagonal if row < square && col < square => (row.min(col), row.max(col)),
+                Symmetry::Diagonal => (row, col),
+            }
+        };
+
+        let cells = (0..height)
+            .map(|row| {
+                (0..width)
+                    .map(|col| {
+                        let (row, col) = source(row, col);
+                        grid.cells[row][col].clone()
+                    })
+                    .collect::<Vec<State>>()
+            })
+            .collect();
+        Grid { cells }
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Symmetry, String> {
+        match s {
+            "none" => Ok(Symmetry::None),
+            "horizontal" => Ok(Symmetry::Horizontal),
+            "vertical" => Ok(Symmetry::Vertical),
+            "four-fold" => Ok(Symmetry::FourFold),
+            "diagonal" => Ok(Symmetry::Diagonal),
+            _ => Err(format!(
+                "unknown symmetry '{}', use none, horizontal, vertical, four-fold or diagonal",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn symmetry_test() {
+        let soup = Grid::random_rectangle(7, 6, 0.5, &mut StdRng::seed_from_u64(3));
+        let cell = |grid: &Grid, row: usize, col: usize| grid.cells[row][col].clone();
+
+        let horizontal = Symmetry::Horizontal.apply(&soup);
+        let vertical = Symmetry::Vertical.apply(&soup);
+        let four_fold = Symmetry::FourFold.apply(&soup);
+        let diagonal = Symmetry::Diagonal.apply(&soup);
+
+        for row in 0..6 {
+            for col in 0..7 {
+                assert_eq!(cell(&horizontal, row, col), cell(&horizontal, row, 6 - col));
+                assert_eq!(cell(&vertical, row, col), cell(&vertical, 5 - row, col));
+                assert_eq!(
+                    cell(&four_fold, row, col),
+                    cell(&four_fold, 5 - row, 6 - col)
+                );
+                assert_eq!(cell(&four_fold, row, col), cell(&four_fold, row, 6 - col));
+                if col < 6 {
+                    assert_eq!(cell(&diagonal, row, col), cell(&diagonal, col, row));
+                }
+            }
+        }
+        assert_eq!(Symmetry::None.apply(&soup), soup);
+        assert_eq!(cell(&horizontal, 2, 1), cell(&soup, 2, 1));
+    }
+
+    #[test]
+    fn noise_test() {
+        let soup = Soup {
+            density: 0.3,
+            noise: Some(8.0),
+            ..Soup::default()
+        };
+        let grid = soup.generate(40, 30, &mut StdRng::seed_from_u64(5));
+
+        assert_eq!(grid.population(), 360);
+        assert_eq!(grid, soup.generate(40, 30, &mut StdRng::seed_from_u64(5)));
+        // Clumps mean living cells mostly have living neighbours, where in
+        // plain noise of this density they mostly don't.
+        let beside = grid
+            .living_cells()
+            .iter()
+            .filter(|(row, col)| *col + 1 < 40 && grid.cells[*row][col + 1] == State::Alive)
+            .count();
+        assert!(beside * 2 > grid.population(), "{} of {}", beside, 360);
+        assert!(Soup {
+            noise: Some(0.5),
+            ..Soup::default()
+        }
+        .check()
+        .is_err());
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("four-fold".parse(), Ok(Symmetry::FourFold));
+        assert!("radial".parse::<Symmetry>().is_err());
+    }
+}
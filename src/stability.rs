@@ -0,0 +1,162 @@
+//! Deciding when a board has settled. A board that has died out has, but so
+//! has one left with oscillators changing phase or gliders flying off, which
+//! never repeats exactly on an unbounded plane. So a board is taken to have
+//! settled once its population has been repeating for long enough with any
+//! period up to the longest allowed, and a census taken every so often while
+//! it does has stopped changing. The population alone can repeat while the
+//! board is still changing, which the census catches.
+
+use crate::census::Census;
+use std::collections::VecDeque;
+
+/// The longest period of population a board is taken to have settled into
+/// by default. Most objects' periods are 1, 2, 3, 4 or 15, so 60 covers
+/// them together.
+pub const DEFAULT_LONGEST_PERIOD: u64 = 60;
+
+/// How many generations the population has to have been repeating for
+/// before a board is taken to have settled, at the least.
+const SETTLED_FOR: u64 = 120;
+
+/// How often the census is taken while the population repeats.
+const CHECK_EVERY: u64 = 30;
+
+/// Watches a board generation by generation for when it settles.
+pub struct Stability {
+    longest_period: usize,
+    /// The populations of the last `longest_period` generations, oldest
+    /// first.
+    recent: VecDeque<usize>,
+    /// For each period, how many generations in a row, up to the last, had
+    /// the population of the one that period before.
+    runs: Vec<u64>,
+    /// The generation last observed.
+    generation: Option<u64>,
+    /// The first generation of those since that had no living cells.
+    extinct_since: Option<u64>,
+    last_census: Option<Census>,
+}
+
+impl Stability {
+    /// A detector counting cycles with periods up to `longest_period` as
+    /// settled.
+    pub fn new(longest_period: u64) -> Stability {
+        let longest_period = longest_period.max(1) as usize;
+        Stability {
+            longest_period,
+            recent: VecDeque::with_capacity(longest_period + 1),
+            runs: vec![0; longest_period],
+            generation: None,
+            extinct_since: None,
+            last_census: None,
+        }
+    }
+
+    /// Records the next generation, starting from generation 0, with
+    /// `population` living cells, and gives the generation the board
+    /// settled at once it has. `census` is only taken while the population
+    /// is repeating, every `CHECK_EVERY` generations.
+    pub fn observe<F: FnOnce() -> Census>(&mut self, population: usize, census: F) -> Option<u64> {
+        let settled = self.repeating_since(population);
+        if population == 0 {
+            return settled;
+        }
+        if !self.generation.unwrap_or(0).is_multiple_of(CHECK_EVERY) {
+            return None;
+        }
+        settled.filter(|_| {
+            let census = census();
+            let same = self.last_census.as_ref() == Some(&census);
+            self.last_census = Some(census);
+            same
+        })
+    }
+
+    /// Records the next generation's population, giving the generation it
+    /// started repeating from if it has been for long enough with a period
+    /// up to the longest, or the generation it died out at.
+    fn repeating_since(&mut self, population: usize) -> Option<u64> {
+        let generation = self.generation.map_or(0, |generation| generation + 1);
+        self.generation = Some(generation);
+        for period in 1..=self.longest_period {
+            let repeats = self.recent.len() >= period
+                && self.recent[self.recent.len() - period] == population;
+            self.runs[period - 1] = if repeats {
+                self.runs[period - 1] + 1
+            } else {
+                0
+            };
+        }
+        if self.recent.len() == self.longest_period {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(population);
+        self.extinct_since = match population {
+            0 => self.extinct_since.or(Some(generation)),
+            _ => None,
+        };
+        if self.extinct_since.is_some() {
+            return self.extinct_since;
+        }
+        (1..=self.longest_period as u64)
+            .find(|&period| self.runs[period as usize - 1] >= (4 * period).max(SETTLED_FOR))
+            .map(|period| generation + 1 - self.runs[period as usize - 1] - period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    /// The generation `populations` are found to have started repeating
+    /// from, with the census never changing, and the one that was found at.
+    fn settled(populations: &[usize], longest_period: u64) -> Option<(u64, u64)> {
+        let mut stability = Stability::new(longest_period);
+        populations
+            .iter()
+            .enumerate()
+            .find_map(|(generation, &population)| {
+                stability
+                    .observe(population, Census::default)
+                    .map(|settled| (settled, generation as u64))
+            })
+    }
+
+    #[test]
+    fn observe_test() {
+        let mut populations = vec![9, 7, 12, 8];
+        populations.extend([3, 6].iter().cycle().take(200));
+        // The census is first taken at 150, and found the same at 180.
+        assert_eq!(settled(&populations, 60), Some((4, 180)));
+        assert_eq!(settled(&populations[..50], 60), None);
+        assert_eq!(settled(&[5; 200], 60), Some((0, 150)));
+        let growing: Vec<usize> = (1..300).collect();
+        assert_eq!(settled(&growing, 60), None);
+        assert_eq!(settled(&[4, 3, 0, 0], 60), Some((2, 2)));
+
+        // A cycle longer than the longest period isn't settled.
+        let mut populations = vec![1];
+        populations.extend((1..8).cycle().take(400));
+        assert_eq!(settled(&populations, 6), None);
+        assert_eq!(settled(&populations, 7).map(|settled| settled.0), Some(1));
+    }
+
+    #[test]
+    fn census_test() {
+        // The population repeats but the board doesn't settle until the
+        // census stops changing.
+        let mut stability = Stability::new(60);
+        let mut found = None;
+        for generation in 0..400 {
+            let block = vec![(1, 1), (1, 2), (2, 1), (2, 2)];
+            let cells = if generation < 130 { Vec::new() } else { block };
+            let census = || Census::take(&Grid::seed(4, cells));
+            if let Some(settled) = stability.observe(10, census) {
+                found = Some((settled, generation));
+                break;
+            }
+        }
+        assert_eq!(found, Some((0, 180)));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::rle::{self, Pattern};
+
+/// Classic patterns to start a demo from, in the order of the number keys
+/// that place them: name and RLE.
+const STARTERS: &[(&str, &str)] = &[
+    ("glider", "bo$2bo$3o!"),
+    ("R-pentomino", "b2o$2o$bo!"),
+    ("acorn", "bo5b$3bo3b$2o2b3o!"),
+    ("diehard", "6bob$2o6b$bo3b3o!"),
+    (
+        "Gosper glider gun",
+        "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+         2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    ),
+    (
+        "pulsar",
+        "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$\
+         o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    ),
+    ("lightweight spaceship", "bo2bo$o4b$o3bo$4o!"),
+    ("pentadecathlon", "2bo4bo2b$2ob4ob2o$2bo4bo!"),
+    ("pi-heptomino", "3o$obo$obo!"),
+];
+
+/// How many starters there are.
+pub fn len() -> usize {
+    STARTERS.len()
+}
+
+/// The name and pattern of the starter at `index`, counting from 0.
+pub fn starter(index: usize) -> Option<(&'static str, Pattern)> {
+    STARTERS.get(index).map(|(name, text)| {
+        let pattern = rle::parse(text).expect("starters are valid RLE");
+        (*name, pattern)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starters_test() {
+        let sizes: Vec<(usize, usize, usize)> = (0..len())
+            .map(|index| {
+                let (_, pattern) = starter(index).unwrap();
+                (pattern.width, pattern.height, pattern.living_cells.len())
+            })
+            .collect();
+
+        assert_eq!(sizes.len(), 9);
+        assert_eq!(sizes[2], (7, 3, 7));
+        assert_eq!(sizes[4], (36, 9, 36));
+        assert_eq!(sizes[5], (13, 13, 48));
+        assert!(starter(len()).is_none());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::grid::{Grid, State};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// The cells that came alive and died going from one generation to the
+/// next.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Changes {
+    pub births: usize,
+    pub deaths: usize,
+}
+
+impl Changes {
+    pub fn between(before: &Grid, after: &Grid) -> Changes {
+        let mut changes = Changes::default();
+        for (before_row, after_row) in before.cells.iter().zip(&after.cells) {
+            for (before_state, after_state) in before_row.iter().zip(after_row) {
+                match (before_state, after_state) {
+                    (State::Dead, State::Alive) => changes.births += 1,
+                    (State::Alive, State::Dead) => changes.deaths += 1,
+                    _ => (),
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// The Shannon entropy of the board's 2x2 blocks, which of the sixteen ways
+/// each is filled, over the four bits a block can hold at most, so from 0
+/// for an empty or uniform board to 1 for noise. Rows and columns left
+/// over at an odd edge are left out.
+pub fn block_entropy(grid: &Grid) -> f64 {
+    let mut counts = [0usize; 16];
+    for rows in grid.cells.chunks_exact(2) {
+        for col in (0..grid.width() - grid.width() % 2).step_by(2) {
+            let alive = |row: usize, col: usize| (rows[row][col] == State::Alive) as usize;
+            let block = alive(0, col)
+                | alive(0, col + 1) << 1
+                | alive(1, col) << 2
+                | alive(1, col + 1) << 3;
+            counts[block] += 1;
+        }
+    }
+    let blocks: usize = counts.iter().sum();
+    if blocks == 0 {
+        return 0.0;
+    }
+    let bits: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / blocks as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum();
+    bits / 4.0
+}
+
+/// The bytes the board takes at one bit a cell, row by row, once
+/// compressed with deflate: an estimate of how much there is to say about
+/// it, small for an empty or regular board and large for noise.
+pub fn compressed_size(grid: &Grid) -> usize {
+    let width = grid.width();
+    let mut bits = vec![0u8; (width * grid.height()).div_ceil(8)];
+    for (row, col) in grid.living_cells() {
+        let index = row * width + col;
+        bits[index / 8] |= 1 << (index % 8);
+    }
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+    // Writing to a Vec can't fail.
+    let _ = encoder.write_all(&bits);
+    encoder.finish().map_or(0, |compressed| compressed.len())
+}
+
+/// How complex one generation is, for telling a board frozen in order or
+/// boiling in chaos from one in between.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complexity {
+    /// See [`block_entropy`].
+    pub block_entropy: f64,
+    /// The share of cells that were born or died on the way to it.
+    pub change_rate: f64,
+    /// See [`compressed_size`].
+    pub compressed_size: usize,
+}
+
+impl Complexity {
+    /// Measures `grid`, reached through `changes`.
+    pub fn measure(grid: &Grid, changes: Changes) -> Complexity {
+        let cells = (grid.width() * grid.height()).max(1);
+        Complexity {
+            block_entropy: block_entropy(grid),
+            change_rate: (changes.births + changes.deaths) as f64 / cells as f64,
+            compressed_size: compressed_size(grid),
+        }
+    }
+}
+
+/// Appends one `generation,population,births,deaths` row per generation to
+/// a CSV file, followed by the generation's [`Complexity`], writing the
+/// header first if the file is new or empty.
+pub struct StatsCsv {
+    out: BufWriter<File>,
+}
+
+impl StatsCsv {
+    pub fn append<P: AsRef<Path>>(path: P) -> io::Result<StatsCsv> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut out = BufWriter::new(file);
+        if is_empty {
+            writeln!(
+                out,
+                "generation,population,births,deaths,block_entropy,change_rate,compressed_size"
+            )?;
+        }
+        Ok(StatsCsv { out })
+    }
+
+    pub fn record(&mut self, generation: u64, grid: &Grid, changes: Changes) -> io::Result<()> {
+        let complexity = Complexity::measure(grid, changes);
+        writeln!(
+            self.out,
+            "{},{},{},{},{:.6},{:.6},{}",
+            generation,
+            grid.population(),
+            changes.births,
+            changes.deaths,
+            complexity.block_entropy,
+            complexity.change_rate,
+            complexity.compressed_size
+        )
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Totals over a whole run, for reporting when it ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub generations: u64,
+    pub births: usize,
+    pub deaths: usize,
+    pub peak_population: usize,
+    pub final_population: usize,
+}
+
+impl Summary {
+    /// Adds a generation with `population` living cells, reached through
+    /// `changes`.
+    pub fn record(&mut self, population: usize, changes: Changes) {
+        self.generations += 1;
+        self.births += changes.births;
+        self.deaths += changes.deaths;
+        self.peak_population = self.peak_population.max(population);
+        self.final_population = population;
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} generations, {} births, {} deaths, peak population {}, final population {}",
+            self.generations, self.births, self.deaths, self.peak_population, self.final_population
+        )
+    }
+}
+
+/// The population of the most recent generations, oldest first, dropping
+/// the oldest count once `capacity` are held.
+pub struct PopulationHistory {
+    counts: VecDeque<usize>,
+    capacity: usize,
+}
+
+impl PopulationHistory {
+    pub fn new(capacity: usize) -> PopulationHistory {
+        PopulationHistory {
+            counts: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, population: usize) {
+        if self.counts.len() == self.capacity {
+            self.counts.pop_front();
+        }
+        self.counts.push_back(population);
+    }
+
+    /// Forgets the most recent count, for when the board is stepped back.
+    pub fn pop(&mut self) -> Option<usize> {
+        self.counts.pop_back()
+    }
+
+    pub fn counts(&self) -> impl Iterator<Item = usize> + '_ {
+        self.counts.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn max(&self) -> usize {
+        self.counts().max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn block_entropy_test() {
+        assert_eq!(block_entropy(&Grid::seed(4, vec![])), 0.0);
+        let full: Vec<(usize, usize)> = (0..4)
+            .flat_map(|row| (0..4).map(move |col| (row, col)))
+            .collect();
+        assert_eq!(block_entropy(&Grid::seed(4, full)), 0.0);
+        // Two of the four blocks empty and two each a different one of the
+        // sixteen: 1.5 bits.
+        let mixed = Grid::seed(4, vec![(0, 0), (2, 3)]);
+        assert!((block_entropy(&mixed) - 1.5 / 4.0).abs() < 1e-9);
+        // The odd row and column are left out.
+        assert_eq!(block_entropy(&Grid::seed(3, vec![(2, 2)])), 0.0);
+        assert_eq!(block_entropy(&Grid::seed(1, vec![(0, 0)])), 0.0);
+    }
+
+    #[test]
+    fn complexity_test() {
+        let empty = Grid::seed(32, vec![]);
+        let mut rng = StdRng::seed_from_u64(1);
+        let noise = Grid::seed(
+            32,
+            (0..32 * 32)
+                .filter(|_| rng.gen_bool(0.5))
+                .map(|cell| (cell / 32, cell % 32))
+                .collect(),
+        );
+        assert!(compressed_size(&empty) < compressed_size(&noise) / 4);
+        let complexity = Complexity::measure(
+            &noise,
+            Changes {
+                births: 100,
+                deaths: 28,
+            },
+        );
+        assert_eq!(complexity.change_rate, 0.125);
+        assert_eq!(complexity.block_entropy, block_entropy(&noise));
+        assert_eq!(complexity.compressed_size, compressed_size(&noise));
+        assert_eq!(
+            Complexity::measure(&empty, Changes::default()).change_rate,
+            0.0
+        );
+    }
+
+    #[test]
+    fn changes_test() {
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+
+        let changes = Changes::between(&blinker, &blinker.next_generation());
+
+        assert_eq!(
+            changes,
+            Changes {
+                births: 2,
+                deaths: 2
+            }
+        );
+    }
+
+    #[test]
+    fn csv_test() {
+        let path = std::env::temp_dir().join(format!("stats_csv_test_{}.csv", std::process::id()));
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        for generation in 0..2 {
+            let mut csv = StatsCsv::append(&path).unwrap();
+            csv.record(generation, &blinker, Changes::default())
+                .unwrap();
+            csv.flush().unwrap();
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            text,
+            "generation,population,births,deaths,block_entropy,change_rate,compressed_size\n\
+             0,3,0,0,0.000000,0.000000,4\n1,3,0,0,0.000000,0.000000,4\n"
+        );
+    }
+
+    #[test]
+    fn summary_test() {
+        let mut summary = Summary::default();
+        summary.record(3, Changes::default());
+        summary.record(
+            5,
+            Changes {
+                births: 3,
+                deaths: 1,
+            },
+        );
+        summary.record(
+            2,
+            Changes {
+                births: 0,
+                deaths: 3,
+            },
+        );
+
+        assert_eq!(
+            summary.to_string(),
+            "3 generations, 3 births, 4 deaths, peak population 5, final population 2"
+        );
+    }
+
+    #[test]
+    fn rolling_history_test() {
+        let mut history = PopulationHistory::new(3);
+        for population in &[5, 9, 2, 4] {
+            history.record(*population);
+        }
+
+        assert_eq!(history.counts().collect::<Vec<_>>(), vec![9, 2, 4]);
+        assert_eq!(history.len(), 3);
+        assert!(!history.is_empty());
+        assert_eq!(history.max(), 9);
+        assert_eq!(history.pop(), Some(4));
+        assert_eq!(history.counts().collect::<Vec<_>>(), vec![9, 2]);
+    }
+}
@@ -0,0 +1,589 @@
+use crate::cli::Options;
+use conways_game_of_life::animation::{FrameStyle, Recording};
+use conways_game_of_life::api::{Api, Reply, Request, Response};
+use conways_game_of_life::census::Census;
+use conways_game_of_life::compression;
+use conways_game_of_life::cycle::{CycleDetector, DEFAULT_WINDOW};
+use conways_game_of_life::grid::{Grid, State};
+use conways_game_of_life::heatmap::Heatmap;
+use conways_game_of_life::json;
+use conways_game_of_life::logging::Span;
+use conways_game_of_life::metrics::{Memory, Metrics, Rate, RATE_WINDOW};
+use conways_game_of_life::render;
+use conways_game_of_life::rule::Rule;
+use conways_game_of_life::stability::Stability;
+use conways_game_of_life::stats::{Changes, StatsCsv, Summary};
+use conways_game_of_life::video::Video;
+use conways_game_of_life::websocket::Server;
+use log::Level;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often requests are looked for while the board is paused.
+const PAUSED_POLL: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Raw,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "raw" => Ok(Format::Raw),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown stream format '{}', use raw or json", s)),
+        }
+    }
+}
+
+/// Writes one frame in the chosen format, a JSON frame being the grid's
+/// JSON document on a single line.
+pub fn write_frame<W: Write>(
+    out: &mut W,
+    format: Format,
+    generation: u64,
+    grid: &Grid,
+    rule: Rule,
+) -> io::Result<()> {
+    match format {
+        Format::Raw => write_raw_frame(out, generation, grid),
+        Format::Json => writeln!(out, "{}", json::to_json_under(grid, Some(generation), rule)),
+    }
+}
+
+/// Writes one raw frame: a `gen N WIDTH HEIGHT` header followed by one line
+/// per row, `1` for a living cell and `0` for a dead one.
+fn write_raw_frame<W: Write>(out: &mut W, generation: u64, grid: &Grid) -> io::Result<()> {
+    writeln!(out, "gen {} {} {}", generation, grid.width(), grid.height())?;
+    for row in &grid.cells {
+        let line: String = row
+            .iter()
+            .map(|cell| match cell {
+                State::Alive => '1',
+                State::Dead => '0',
+            })
+            .collect();
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Streams generations to `out` until `options.generations` frames have
+/// been written, or forever if there is no limit. A closed pipe on the
+/// reading end is treated as a normal way to stop. Statistics for each frame
+/// are also appended to `stats` when given.
+///
+/// The first time the board repeats, allowing for movement, the behaviour
+/// it settled into and a census of its objects are reported on stderr, and
+/// with `options.stop_on_cycle` streaming stops before the repeat is
+/// written. The same goes for the first time it settles into cycles of up
+/// to `options.settle_period`, which boards with gliders flying off do
+/// without ever repeating. With `options.heatmap_png` the activity heatmap
+/// of the streamed generations is saved at the end, as is an animation of
+/// the first `options.frames` of them with `options.animation`, and with
+/// `options.video` they are encoded to a video as they go. Every
+/// `options.every`th generation is saved as a PNG in `options.frames_dir`
+/// if given. With `options.checkpoint_every` the board is saved to
+/// `options.checkpoint_dir` every so many generations. The edges of the
+/// board are joined as `options.topology` says, and with
+/// `options.auto_expand` the board grows whenever living cells reach them.
+///
+/// Once `interrupted` is set, by Ctrl+C for instance, streaming stops as
+/// soon as the current frame is written. Everything is saved as it would be
+/// at the end of the stream, the board is written to `options.checkpoint_dir`
+/// and a summary of the run is reported on stderr.
+///
+/// With `options.serve` each generation is also sent to WebSocket clients,
+/// and with `options.api` the run takes requests over HTTP, waiting
+/// between generations while it is paused.
+pub fn run<W: Write>(
+    out: &mut W,
+    mut grid: Grid,
+    options: &Options,
+    mut stats: Option<&mut StatsCsv>,
+    interrupted: &AtomicBool,
+) -> io::Result<()> {
+    let rule = options.rule.unwrap_or_default();
+    let mut generation = 0;
+    let mut changes = Changes::default();
+    let mut summary = Summary::default();
+    let mut cycle_detector = Some(CycleDetector::new(DEFAULT_WINDOW));
+    let mut stability = Some(Stability::new(options.settle_period));
+    let mut heatmap = options
+        .heatmap_png
+        .as_ref()
+        .map(|_| Heatmap::new(grid.width(), grid.height()));
+    let mut animation = options
+        .animation
+        .as_ref()
+        .map(|_| Recording::new(options.frames));
+    let mut video = match &options.video {
+        Some(path) => Some(Video::start(
+            path,
+            grid.width(),
+            grid.height(),
+            frame_style(options),
+        )?),
+        None => None,
+    };
+    let mut server = match &options.serve {
+        Some(address) => Some(serve(address)?),
+        None => None,
+    };
+    let mut control = match &options.api {
+        Some(address) => {
+            let api = Api::bind(address)?;
+            eprintln!("taking requests on http://{}", address);
+            Some(Control::new(api, rule))
+        }
+        None => None,
+    };
+    if let Some(dir) = &options.frames_dir {
+        fs::create_dir_all(dir)?;
+    }
+    if options.checkpoint_every.is_some() {
+        fs::create_dir_all(&options.checkpoint_dir)?;
+    }
+    while options.generations.is_none_or(|limit| generation < limit) {
+        if let Some(cycle) = cycle_detector
+            .as_mut()
+            .and_then(|detector| detector.observe(generation, &grid))
+        {
+            eprintln!(
+                "generation {} repeats generation {}: {} ({})",
+                generation,
+                cycle.first_seen,
+                cycle.behaviour(grid.population()),
+                Census::take(&grid)
+            );
+            if options.stop_on_cycle {
+                break;
+            }
+            cycle_detector = None;
+        }
+        if let Some(since) = stability
+            .as_mut()
+            .and_then(|stability| stability.observe(grid.population(), || Census::take(&grid)))
+        {
+            eprintln!(
+                "generation {} has settled, in cycles of up to {}, since generation {}: {}",
+                generation,
+                options.settle_period,
+                since,
+                Census::take(&grid)
+            );
+            if options.stop_on_cycle {
+                break;
+            }
+            stability = None;
+        }
+        let render = Span::enter(
+            module_path!(),
+            Level::Debug,
+            "render",
+            format_args!("generation={} format={:?}", generation, options.format),
+        );
+        let written = write_frame(out, options.format, generation, &grid, rule);
+        drop(render);
+        match written {
+            Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => break,
+            result => result?,
+        }
+        if let Some(stats) = stats.as_mut() {
+            stats.record(generation, &grid, changes)?;
+        }
+        if let Some(server) = server.as_mut() {
+            server.publish(generation, &grid);
+        }
+        if let Some(heatmap) = heatmap.as_mut() {
+            heatmap.record(&grid);
+        }
+        if let Some(animation) = animation.as_mut() {
+            animation.capture(&grid);
+        }
+        if let Some(video) = video.as_mut() {
+            if options.frames.is_none_or(|limit| video.frames() < limit) {
+                video.add_frame(&grid)?;
+            }
+        }
+        if let Some(dir) = &options.frames_dir {
+            if generation.is_multiple_of(options.every) {
+                let style = frame_style(options);
+                render::save_numbered_png(
+                    Path::new(dir),
+                    generation,
+                    &grid,
+                    style.palette,
+                    style.scale,
+                )
+                .map_err(io::Error::other)?;
+            }
+        }
+        summary.record(grid.population(), changes);
+        if interrupted.load(Ordering::SeqCst) {
+            fs::create_dir_all(&options.checkpoint_dir)?;
+            let path =
+                save_checkpoint(Path::new(&options.checkpoint_dir), generation, &grid, rule)?;
+            eprintln!(
+                "interrupted at generation {}, saved the board to {}",
+                generation,
+                path.display()
+            );
+            eprintln!("{}", summary);
+            break;
+        }
+        if options
+            .checkpoint_every
+            .is_some_and(|every| generation > 0 && generation.is_multiple_of(every))
+        {
+            let path =
+                save_checkpoint(Path::new(&options.checkpoint_dir), generation, &grid, rule)?;
+            eprintln!("saved generation {} to {}", generation, path.display());
+        }
+        if let Some(control) = control.as_mut() {
+            control.wait(out, generation, &mut grid, &summary, interrupted)?;
+        }
+        let step = Span::enter(
+            module_path!(),
+            Level::Debug,
+            "step",
+            format_args!("generation={}", generation),
+        );
+        let next_grid = grid.next_generation_under(options.topology, rule);
+        drop(step);
+        changes = Changes::between(&grid, &next_grid);
+        grid = match options
+            .auto_expand
+            .and_then(|margin| next_grid.expanded(margin))
+        {
+            Some(expanded) => expanded,
+            None => next_grid,
+        };
+        generation += 1;
+    }
+    if let Some(stats) = stats {
+        stats.flush()?;
+    }
+    if let (Some(heatmap), Some(path)) = (heatmap, &options.heatmap_png) {
+        heatmap
+            .save_png(path, options.colormap, options.scale)
+            .map_err(io::Error::other)?;
+    }
+    if let (Some(animation), Some(path)) = (animation, &options.animation) {
+        animation
+            .save(path, options.animation_format, frame_style(options))
+            .map_err(io::Error::other)?;
+    }
+    if let Some(video) = video {
+        video.finish()?;
+    }
+    match out.flush() {
+        Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// The state of a run taking requests over HTTP.
+struct Control {
+    api: Api,
+    paused: bool,
+    /// How many more generations to run while paused.
+    steps: u64,
+    /// Where to answer the `/step` requests being run.
+    stepping: Vec<Reply>,
+    /// How fast the run is going, for `/metrics`.
+    rate: Rate,
+    /// The rule the board runs by, for `/state`.
+    rule: Rule,
+}
+
+impl Control {
+    fn new(api: Api, rule: Rule) -> Control {
+        Control {
+            api,
+            rule,
+            paused: false,
+            steps: 0,
+            stepping: Vec::new(),
+            rate: Rate::new(RATE_WINDOW),
+        }
+    }
+
+    /// Answers the requests that have come in for the board at
+    /// `generation`, with `summary` of the run so far, then waits for more
+    /// while it is paused, until it is resumed or stepped, or the run is
+    /// `interrupted`.
+    fn wait<W: Write>(
+        &mut self,
+        out: &mut W,
+        generation: u64,
+        grid: &mut Grid,
+        summary: &Summary,
+        interrupted: &AtomicBool,
+    ) -> io::Result<()> {
+        loop {
+            self.rate.record(Instant::now(), generation);
+            if self.steps == 0 {
+                for reply in self.stepping.drain(..) {
+                    reply.send(Response::status(generation, grid.population(), self.paused));
+                }
+            }
+            for (request, reply) in self.api.poll() {
+                let response = match request {
+                    Request::Pause => {
+                        self.paused = true;
+                        Response::status(generation, grid.population(), true)
+                    }
+                    Request::Resume => {
+                        self.paused = false;
+                        Response::status(generation, grid.population(), false)
+                    }
+                    Request::Step(n) => {
+                        self.steps += n;
+                        self.stepping.push(reply);
+                        continue;
+                    }
+                    Request::State => {
+                        Response::ok(json::to_json_under(grid, Some(generation), self.rule))
+                    }
+                    Request::Metrics => Response::metrics(&Metrics {
+                        generation,
+                        population: grid.population(),
+                        peak_population: summary.peak_population,
+                        cells: grid.width() * grid.height(),
+                        births: summary.births,
+                        deaths: summary.deaths,
+                        generations_per_second: self.rate.per_second(),
+                        paused: self.paused,
+                        memory: Memory::current(),
+                    }),
+                    Request::Place { pattern, at } => {
+                        let (row, col) = at.unwrap_or((
+                            grid.height().saturating_sub(pattern.height) / 2,
+                            grid.width().saturating_sub(pattern.width) / 2,
+                        ));
+                        match pattern.place(grid, row, col) {
+                            Ok(()) => Response::status(generation, grid.population(), self.paused),
+                            Err(e) => Response::error(400, &e),
+                        }
+                    }
+                };
+                reply.send(response);
+            }
+            if self.steps > 0 {
+                self.steps -= 1;
+                return Ok(());
+            }
+            if !self.paused || interrupted.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            match out.flush() {
+                Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+                result => result?,
+            }
+            thread::sleep(PAUSED_POLL);
+        }
+    }
+}
+
+/// Starts sending generations to WebSocket clients on `address`, a
+/// `host:port`.
+pub fn serve(address: &str) -> io::Result<Server> {
+    let server = Server::bind(address)?;
+    eprintln!(
+        "sending generations to WebSocket clients on ws://{}",
+        address
+    );
+    Ok(server)
+}
+
+/// How `options` say animations should be drawn.
+pub fn frame_style(options: &Options) -> FrameStyle {
+    FrameStyle {
+        scale: options.scale,
+        delay: options.frame_delay,
+        ..FrameStyle::default()
+    }
+}
+
+/// Saves the board as a compressed JSON grid named after its generation,
+/// such as `checkpoint-10000.json.gz`, so that a long run can be picked up
+/// again by starting from the file.
+fn save_checkpoint(dir: &Path, generation: u64, grid: &Grid, rule: Rule) -> io::Result<PathBuf> {
+    let path = dir.join(format!("checkpoint-{}.json.gz", generation));
+    compression::write(&path, &json::to_sealed_json(grid, Some(generation), rule))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blinker_stream_test() {
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            generations: Some(2),
+            ..Options::default()
+        };
+        let mut out = Vec::new();
+
+        run(&mut out, blinker, &options, None, &AtomicBool::new(false)).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "gen 0 3 3\n010\n010\n010\ngen 1 3 3\n000\n111\n000\n");
+    }
+
+    #[test]
+    fn auto_expand_test() {
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            generations: Some(3),
+            auto_expand: Some(1),
+            ..Options::default()
+        };
+        let mut out = Vec::new();
+
+        run(&mut out, blinker, &options, None, &AtomicBool::new(false)).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let headers: Vec<&str> = text
+            .lines()
+            .filter(|line| line.starts_with("gen"))
+            .collect();
+        assert_eq!(headers, vec!["gen 0 3 3", "gen 1 5 3", "gen 2 5 5"]);
+        assert!(text.contains("gen 1 5 3\n00000\n01110\n00000\n"));
+    }
+
+    #[test]
+    fn json_stream_test() {
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            format: Format::Json,
+            generations: Some(2),
+            ..Options::default()
+        };
+        let mut out = Vec::new();
+
+        run(&mut out, blinker, &options, None, &AtomicBool::new(false)).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with(r#"{"generation":1,"#));
+        assert!(lines[1].ends_with(r#""cells":[[1,0],[1,1],[1,2]]}"#));
+    }
+
+    #[test]
+    fn stop_on_cycle_test() {
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            stop_on_cycle: true,
+            ..Options::default()
+        };
+        let mut out = Vec::new();
+
+        run(&mut out, blinker, &options, None, &AtomicBool::new(false)).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().filter(|line| line.starts_with("gen")).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn checkpoint_test() {
+        let dir = std::env::temp_dir().join(format!("checkpoint_test_{}", std::process::id()));
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            generations: Some(5),
+            checkpoint_every: Some(2),
+            checkpoint_dir: dir.to_string_lossy().into_owned(),
+            ..Options::default()
+        };
+
+        run(
+            &mut Vec::new(),
+            blinker.clone(),
+            &options,
+            None,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+
+        let saved = compression::read_to_string(&dir.join("checkpoint-4.json.gz")).unwrap();
+        assert_eq!(json::from_json(&saved), Ok(blinker));
+        assert!(saved.contains(r#""generation":4,"#), "{}", saved);
+        assert!(saved.contains(r#""checksum":""#), "{}", saved);
+        assert!(dir.join("checkpoint-2.json.gz").exists());
+        assert!(!dir.join("checkpoint-0.json.gz").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn frames_dir_test() {
+        let dir = std::env::temp_dir().join(format!("frames_dir_test_{}", std::process::id()));
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            generations: Some(5),
+            frames_dir: Some(dir.to_string_lossy().into_owned()),
+            every: 2,
+            ..Options::default()
+        };
+
+        run(
+            &mut Vec::new(),
+            blinker,
+            &options,
+            None,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["frame_000000.png", "frame_000002.png", "frame_000004.png"]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn interrupt_test() {
+        let dir = std::env::temp_dir().join(format!("interrupt_test_{}", std::process::id()));
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            checkpoint_dir: dir.to_string_lossy().into_owned(),
+            ..Options::default()
+        };
+        let mut out = Vec::new();
+
+        run(
+            &mut out,
+            blinker.clone(),
+            &options,
+            None,
+            &AtomicBool::new(true),
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 4);
+        let saved = compression::read_to_string(&dir.join("checkpoint-0.json.gz")).unwrap();
+        assert_eq!(json::from_json(&saved), Ok(blinker));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,291 @@
+//! Experiments that sweep a grid of parameters, running a soup search for
+//! every combination of rule, density and size a manifest lists and
+//! tabulating the results side by side. A manifest is a JSON or TOML file
+//! such as:
+//!
+//! ```toml
+//! rules = ["B3/S23", "B36/S23"]
+//! densities = [0.3, 0.5]
+//! sizes = [16, 32]
+//! soups = 1000
+//! ```
+
+use crate::grid::{check_density, DEFAULT_DENSITY};
+use crate::rule::Rule;
+use crate::search::{Results, Search};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    pub rules: Vec<Rule>,
+    pub densities: Vec<f64>,
+    pub sizes: Vec<usize>,
+    /// How many soups, each from its own seed, to run of every combination.
+    pub soups: u64,
+    /// The seed the soups of every combination are drawn from.
+    pub seed: u64,
+    /// How many threads to run each combination with, 0 for one per core.
+    pub threads: usize,
+}
+
+impl Default for Manifest {
+    fn default() -> Manifest {
+        Manifest {
+            rules: vec![Rule::default()],
+            densities: vec![DEFAULT_DENSITY],
+            sizes: vec![16],
+            soups: 100,
+            seed: 0,
+            threads: 0,
+        }
+    }
+}
+
+/// The results of one combination.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub search: Search,
+    pub results: Results,
+}
+
+impl Row {
+    /// The generations the soups that settled took on average, if any did.
+    pub fn mean_generations(&self) -> Option<f64> {
+        let settled = self.results.soups - self.results.unsettled;
+        if settled == 0 {
+            return None;
+        }
+        Some(self.results.generations as f64 / settled as f64)
+    }
+
+    /// How many objects were left to a soup.
+    pub fn objects_per_soup(&self) -> f64 {
+        let objects = self.results.counts.values().sum::<u64>() + self.results.other;
+        objects as f64 / self.results.soups.max(1) as f64
+    }
+
+    /// The object left most often, if any were.
+    pub fn most_common(&self) -> Option<&'static str> {
+        self.results
+            .counts
+            .iter()
+            .max_by_key(|&(name, count)| (count, std::cmp::Reverse(*name)))
+            .map(|(name, _)| *name)
+    }
+}
+
+impl Manifest {
+    /// Reads a manifest, as TOML if `toml` and as JSON otherwise.
+    pub fn parse(text: &str, toml: bool) -> Result<Manifest, String> {
+        let manifest: Manifest = if toml {
+            toml::from_str(text).map_err(|e| format!("invalid manifest: {}", e))?
+        } else {
+            serde_json::from_str(text).map_err(|e| format!("invalid manifest: {}", e))?
+        };
+        manifest.check()?;
+        Ok(manifest)
+    }
+
+    /// Whether every combination can be run.
+    pub fn check(&self) -> Result<(), String> {
+        if self.rules.is_empty() || self.densities.is_empty() || self.sizes.is_empty() {
+            return Err("the manifest needs at least one rule, density and size".to_string());
+        }
+        for &density in &self.densities {
+            check_density(density)?;
+        }
+        if self.sizes.contains(&0) {
+            return Err("every size in the manifest must be at least 1".to_string());
+        }
+        if self.soups == 0 {
+            return Err("the manifest must run at least 1 soup".to_string());
+        }
+        Ok(())
+    }
+
+    /// Reads the manifest at `path`, which is TOML if it ends in `.toml`.
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let toml = path
+            .extension()
+            .is_some_and(|extension| extension == "toml");
+        Manifest::parse(&text, toml).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The search each combination runs, rule by rule, then density by
+    /// density, then size by size.
+    pub fn combinations(&self) -> Vec<Search> {
+        let mut searches = Vec::new();
+        for &rule in &self.rules {
+            for &density in &self.densities {
+                for &size in &self.sizes {
+                    searches.push(Search {
+                        soups: self.soups,
+                        size,
+                        density,
+                        seed: self.seed,
+                        threads: self.threads,
+                        rule,
+                        ..Search::default()
+                    });
+                }
+            }
+        }
+        searches
+    }
+
+    /// Runs every combination in turn, each with its soups spread over the
+    /// threads, telling `progress` how many combinations are done as each
+    /// finishes.
+    pub fn run<F: FnMut(usize)>(&self, mut progress: F) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for search in self.combinations() {
+            let results = search.run(|_| ());
+            rows.push(Row { search, results });
+            progress(rows.len());
+        }
+        rows
+    }
+}
+
+/// The cells of each row, then the header above them.
+fn cells(rows: &[Row]) -> (Vec<Vec<String>>, [&'static str; 8]) {
+    let header = [
+        "rule",
+        "density",
+        "size",
+        "soups",
+        "settled",
+        "mean_generations",
+        "objects_per_soup",
+        "most_common",
+    ];
+    let cells = rows
+        .iter()
+        .map(|row| {
+            vec![
+                row.search.rule.to_string(),
+                row.search.density.to_string(),
+                row.search.size.to_string(),
+                row.results.soups.to_string(),
+                (row.results.soups - row.results.unsettled).to_string(),
+                row.mean_generations()
+                    .map_or_else(String::new, |mean| format!("{:.1}", mean)),
+                format!("{:.4}", row.objects_per_soup()),
+                row.most_common().unwrap_or("").to_string(),
+            ]
+        })
+        .collect();
+    (cells, header)
+}
+
+/// The results as a table to read, a combination to a line.
+pub fn table(rows: &[Row]) -> String {
+    let (cells, header) = cells(rows);
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(column, name)| {
+            cells
+                .iter()
+                .map(|row| row[column].len())
+                .chain(std::iter::once(name.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line = |row: Vec<&str>| -> String {
+        let padded: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    let mut table = line(header.to_vec());
+    for row in &cells {
+        table.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    table
+}
+
+/// The results as CSV, with a header.
+pub fn csv(rows: &[Row]) -> String {
+    let (cells, header) = cells(rows);
+    let mut csv = format!("{}\n", header.join(","));
+    for row in cells {
+        csv.push_str(&format!("{}\n", row.join(",")));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let toml = "rules = [\"B3/S23\", \"b36s23\"]\ndensities = [0.3, 0.5]\nsoups = 10\n";
+        let manifest = Manifest::parse(toml, true).unwrap();
+        assert_eq!(manifest.rules[1].to_string(), "B36/S23");
+        assert_eq!(manifest.sizes, vec![16]);
+        assert_eq!(manifest.combinations().len(), 4);
+        assert_eq!(manifest.combinations()[1].density, 0.5);
+
+        let json = r#"{"rules": ["B3/S23"], "sizes": [8, 12], "seed": 3}"#;
+        let manifest = Manifest::parse(json, false).unwrap();
+        let sizes: Vec<usize> = manifest.combinations().iter().map(|s| s.size).collect();
+        assert_eq!(sizes, vec![8, 12]);
+        assert!(manifest
+            .combinations()
+            .iter()
+            .all(|search| search.seed == 3));
+
+        assert!(Manifest::parse(r#"{"rules": []}"#, false).is_err());
+        assert!(Manifest::parse(r#"{"densities": [1.5]}"#, false).is_err());
+        assert!(Manifest::parse(r#"{"sizes": [0]}"#, false).is_err());
+        assert!(Manifest::parse(r#"{"soups": 0}"#, false).is_err());
+        assert!(Manifest::parse(r#"{"rules": ["B0/S"]}"#, false).is_err());
+        assert!(Manifest::parse("colours = 3", true).is_err());
+    }
+
+    #[test]
+    fn run_test() {
+        let manifest = Manifest {
+            rules: vec![Rule::default(), "B36/S23".parse().unwrap()],
+            sizes: vec![6],
+            soups: 3,
+            seed: 5,
+            threads: 2,
+            ..Manifest::default()
+        };
+        let mut done = Vec::new();
+        let rows = manifest.run(|combinations| done.push(combinations));
+        assert_eq!(done, vec![1, 2]);
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.results.soups == 3));
+        assert_eq!(rows[0].results, rows[0].search.run(|_| ()));
+
+        let table = table(&rows);
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.starts_with("rule     density  size"), "{}", table);
+        assert!(
+            table.lines().nth(2).unwrap().starts_with("B36/S23"),
+            "{}",
+            table
+        );
+        let csv = csv(&rows);
+        assert!(csv.starts_with(
+            "rule,density,size,soups,settled,mean_generations,objects_per_soup,most_common\n"
+        ));
+        assert!(
+            csv.lines().nth(1).unwrap().starts_with("B3/S23,0.5,6,3,"),
+            "{}",
+            csv
+        );
+    }
+}
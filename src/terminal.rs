@@ -0,0 +1,118 @@
+use crate::cli::Options;
+use conways_game_of_life::explore;
+use conways_game_of_life::grid::Grid;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Moves the cursor to the top left and clears the terminal.
+const CLEAR: &str = "\x1b[H\x1b[2J";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+
+/// Plays `grid` in the terminal, redrawing it in half blocks every
+/// `options.frame_delay` milliseconds, until `options.generations`
+/// generations have been shown, the board dies out, or `interrupted` is
+/// set, by Ctrl+C for instance. The edges of the board are joined as
+/// `options.topology` says.
+pub fn run<W: Write>(
+    out: &mut W,
+    mut grid: Grid,
+    options: &Options,
+    interrupted: &AtomicBool,
+) -> io::Result<()> {
+    let rule = options.rule.unwrap_or_default();
+    let delay = Duration::from_millis(u64::from(options.frame_delay));
+    write!(out, "{}", HIDE_CURSOR)?;
+    let mut generation = 0;
+    let played = loop {
+        let frame = write_frame(out, generation, &grid);
+        if frame.is_err() || interrupted.load(Ordering::SeqCst) {
+            break frame;
+        }
+        if options.generations.is_some_and(|limit| generation >= limit) || grid.population() == 0 {
+            break Ok(());
+        }
+        thread::sleep(delay);
+        grid = grid.next_generation_under(options.topology, rule);
+        generation += 1;
+    };
+    write!(out, "{}", SHOW_CURSOR)?;
+    out.flush()?;
+    played
+}
+
+fn write_frame<W: Write>(out: &mut W, generation: u64, grid: &Grid) -> io::Result<()> {
+    write!(out, "{}", CLEAR)?;
+    writeln!(
+        out,
+        "generation {}, {} alive, Ctrl+C to stop",
+        generation,
+        grid.population()
+    )?;
+    write!(out, "{}", explore::preview(grid))?;
+    out.flush()
+}
+
+/// Asks on stderr whether to play in the terminal, since the window
+/// couldn't be opened. Without a terminal to ask on the answer is no, with
+/// a hint of how to run without a window.
+pub fn offer() -> bool {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        eprintln!("run with --terminal to play in the terminal, or --stream to write generations to stdout");
+        return false;
+    }
+    eprint!("play in the terminal instead? [Y/n] ");
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    accepts(&answer)
+}
+
+/// Whether `answer` to a yes or no question is yes, as it is when nothing
+/// is given.
+fn accepts(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_test() {
+        let blinker = Grid::seed(3, vec![(0, 1), (1, 1), (2, 1)]);
+        let options = Options {
+            generations: Some(2),
+            frame_delay: 0,
+            ..Options::default()
+        };
+        let mut out = Vec::new();
+
+        run(&mut out, blinker, &options, &AtomicBool::new(false)).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.matches(CLEAR).count(), 3);
+        assert!(text.contains("generation 2, 3 alive"), "{}", text);
+        assert!(text.starts_with(HIDE_CURSOR) && text.ends_with(SHOW_CURSOR));
+
+        // An empty board is shown once, since nothing will ever happen.
+        let mut out = Vec::new();
+        run(
+            &mut out,
+            Grid::seed(4, vec![]),
+            &options,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().matches(CLEAR).count(), 1);
+    }
+
+    #[test]
+    fn accepts_test() {
+        assert!(accepts("\n") && accepts("y\n") && accepts(" Yes "));
+        assert!(!accepts("n\n") && !accepts("no") && !accepts("later"));
+    }
+}
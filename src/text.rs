@@ -0,0 +1,142 @@
+use crate::grid::Grid;
+use crate::rle::Pattern;
+use crate::rule::Rule;
+
+/// Width and height of a character in cells.
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// Blank cells between characters and between lines.
+const SPACING: usize = 1;
+
+/// The rows of a character in a 5x7 bitmap font, top first, with the
+/// leftmost cell in the highest of the five bits. Letters are upper case
+/// only, lower case ones being drawn the same.
+#[rustfmt::skip]
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    let rows = match c.to_ascii_uppercase() {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        ' ' => [0; GLYPH_HEIGHT],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+        '?' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        ',' => [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        '\'' => [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// The living cells spelling out `text` as a pattern just big enough to hold
+/// it. Each line of `text` is centred under the longest.
+pub fn rasterize(text: &str) -> Result<Pattern, String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let line_width = |line: &str| {
+        let chars = line.chars().count();
+        (chars * (GLYPH_WIDTH + SPACING)).saturating_sub(SPACING)
+    };
+    let width = lines.iter().map(|line| line_width(line)).max().unwrap_or(0);
+    let height = (lines.len() * (GLYPH_HEIGHT + SPACING)).saturating_sub(SPACING);
+
+    let mut living_cells = Vec::new();
+    for (line_idx, line) in lines.iter().enumerate() {
+        let top = line_idx * (GLYPH_HEIGHT + SPACING);
+        let indent = (width - line_width(line)) / 2;
+        for (char_idx, c) in line.chars().enumerate() {
+            let rows = glyph(c).ok_or_else(|| format!("no glyph for '{}' in --text", c))?;
+            let left = indent + char_idx * (GLYPH_WIDTH + SPACING);
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        living_cells.push((top + row, left + col));
+                    }
+                }
+            }
+        }
+    }
+    Ok(Pattern {
+        width,
+        height,
+        living_cells,
+        rule: Rule::default(),
+    })
+}
+
+/// A `width` by `height` board with `text` written across its centre.
+pub fn text_grid(text: &str, width: usize, height: usize) -> Result<Grid, String> {
+    let pattern = rasterize(text)?;
+    pattern.centred(width, height).ok_or_else(|| {
+        format!(
+            "the text is {}x{} cells and does not fit a {}x{} grid, try a bigger --size",
+            pattern.width, pattern.height, width, height
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rasterize_test() {
+        let pattern = rasterize("HI").unwrap();
+        let cells = &pattern.living_cells;
+
+        assert_eq!((pattern.width, pattern.height), (11, 7));
+        // The crossbar of the H.
+        assert!((0..5).all(|col| cells.contains(&(3, col))));
+        // The stem of the I.
+        assert!((0..7).all(|row| cells.contains(&(row, 8))));
+        assert_eq!(rasterize("hi"), rasterize("HI"));
+        assert!(rasterize("H\u{e9}").is_err());
+    }
+
+    #[test]
+    fn text_grid_test() {
+        let grid = text_grid("I", 9, 9).unwrap();
+
+        assert_eq!(grid.living_cells().first(), Some(&(1, 3)));
+        assert_eq!(grid.population(), 11);
+        assert!(text_grid("HELLO", 20, 20).is_err());
+        let lines = rasterize("HELLO\nHI").unwrap();
+        assert_eq!((lines.width, lines.height), (29, 15));
+        assert!(lines.living_cells.contains(&(11, 9)));
+    }
+}
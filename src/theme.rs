@@ -0,0 +1,280 @@
+//! The colours of the game window, in a dark and a light theme. The light
+//! one is for bright rooms and projectors, where the dark one washes out.
+
+use crate::render::Palette;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Theme {
+    /// Blue cells on grey, round a dark blue border.
+    #[default]
+    Dark,
+    /// Deep blue cells on pale grey, round an off-white border, with dark
+    /// text on light panels.
+    Light,
+}
+
+impl Theme {
+    /// The theme's name, as `--theme` takes it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+
+    /// The other theme.
+    pub fn toggle(self) -> Theme {
+        match self {
+            Theme::Dark => Theme::Light,
+            Theme::Light => Theme::Dark,
+        }
+    }
+
+    /// The colours of the cells.
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Dark => Palette::default(),
+            Theme::Light => Palette {
+                alive: (0, 80, 190),
+                dead: (225, 225, 220),
+            },
+        }
+    }
+
+    /// The colour round the board.
+    pub fn background(self) -> (u8, u8, u8) {
+        match self {
+            Theme::Dark => (26, 51, 77),
+            Theme::Light => (250, 250, 245),
+        }
+    }
+
+    /// The colour of text, and of the handle on the timeline.
+    pub fn text(self) -> (u8, u8, u8) {
+        match self {
+            Theme::Dark => (255, 255, 255),
+            Theme::Light => (20, 20, 20),
+        }
+    }
+
+    /// The colour of the panels behind text drawn over the board, which is
+    /// drawn partly see-through.
+    pub fn panel(self) -> (u8, u8, u8) {
+        match self {
+            Theme::Dark => (0, 0, 0),
+            Theme::Light => (255, 255, 255),
+        }
+    }
+
+    /// The colour that picks out the population graph and bookmarks.
+    pub fn highlight(self) -> (u8, u8, u8) {
+        match self {
+            Theme::Dark => (255, 204, 0),
+            Theme::Light => (200, 80, 0),
+        }
+    }
+
+    /// The colour of the part of the timeline that has been played.
+    pub fn progress(self) -> (u8, u8, u8) {
+        match self {
+            Theme::Dark => (40, 90, 150),
+            Theme::Light => (140, 180, 230),
+        }
+    }
+
+    /// Whether the desktop prefers dark or light windows, where that can be
+    /// found out: from `GTK_THEME` or GNOME's colour scheme on Linux, the
+    /// interface style on macOS or the app theme on Windows.
+    pub fn system() -> Option<Theme> {
+        if let Ok(gtk_theme) = std::env::var("GTK_THEME") {
+            return Some(from_gtk(&gtk_theme));
+        }
+        if cfg!(target_os = "macos") {
+            // The setting is only there at all while the style is dark.
+            let style = command_output("defaults", &["read", "-g", "AppleInterfaceStyle"]);
+            Some(match style {
+                Some(style) if style.trim() == "Dark" => Theme::Dark,
+                _ => Theme::Light,
+            })
+        } else if cfg!(windows) {
+            let output = command_output(
+                "reg",
+                &[
+                    "query",
+                    r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                    "/v",
+                    "AppsUseLightTheme",
+                ],
+            )?;
+            from_windows(&output)
+        } else {
+            let output = command_output(
+                "gsettings",
+                &["get", "org.gnome.desktop.interface", "color-scheme"],
+            )?;
+            from_gnome(&output)
+        }
+    }
+}
+
+/// The colour of a chroma key, for keying the board out over other video:
+/// `green`, `blue` or `magenta`, or any colour written like `#00ff00`.
+pub fn chroma_key(spec: &str) -> Result<(u8, u8, u8), String> {
+    match spec.to_ascii_lowercase().as_str() {
+        "green" => return Ok((0, 255, 0)),
+        "blue" => return Ok((0, 0, 255)),
+        "magenta" => return Ok((255, 0, 255)),
+        _ => (),
+    }
+    let hex = spec.strip_prefix('#').unwrap_or(spec);
+    let channel = |at: usize| {
+        hex.get(at..at + 2)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+    };
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok((r, g, b)),
+        _ => Err(format!(
+            "'{}' is not a colour like green, blue, magenta or #00ff00",
+            spec
+        )),
+    }
+}
+
+/// What a command printed, if it ran and succeeded.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The theme a `GTK_THEME` name is, such as `Adwaita:dark` or
+/// `Adwaita-dark` for dark ones.
+fn from_gtk(name: &str) -> Theme {
+    let name = name.trim().to_ascii_lowercase();
+    if name.ends_with(":dark") || name.ends_with("-dark") {
+        Theme::Dark
+    } else {
+        Theme::Light
+    }
+}
+
+/// The theme GNOME's `color-scheme` setting asks for, such as
+/// `'prefer-dark'`. `'default'` leaves it to each app, so it says nothing.
+fn from_gnome(setting: &str) -> Option<Theme> {
+    match setting.trim().trim_matches('\'') {
+        "prefer-dark" => Some(Theme::Dark),
+        "prefer-light" => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+/// The theme Windows' `AppsUseLightTheme` asks for, from what `reg query`
+/// prints about it, which ends with `0x0` or `0x1`.
+fn from_windows(query: &str) -> Option<Theme> {
+    let line = query
+        .lines()
+        .find(|line| line.contains("AppsUseLightTheme"))?;
+    match line.split_whitespace().last()? {
+        "0x0" => Some(Theme::Dark),
+        "0x1" => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Theme, String> {
+        match s {
+            "dark" => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            _ => Err(format!("unknown theme '{}', use dark or light", s)),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl From<Theme> for String {
+    fn from(theme: Theme) -> String {
+        theme.to_string()
+    }
+}
+
+impl TryFrom<String> for Theme {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Theme, String> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("light".parse(), Ok(Theme::Light));
+        assert_eq!(Theme::Light.to_string(), "light");
+        assert_eq!(Theme::Dark.toggle(), Theme::Light);
+        assert_eq!(Theme::Light.toggle(), Theme::Dark);
+        assert!("sepia".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn chroma_key_test() {
+        assert_eq!(chroma_key("green"), Ok((0, 255, 0)));
+        assert_eq!(chroma_key("Magenta"), Ok((255, 0, 255)));
+        assert_eq!(chroma_key("#00B140"), Ok((0, 177, 64)));
+        assert_eq!(chroma_key("0047bb"), Ok((0, 71, 187)));
+        assert!(chroma_key("#00ff0").is_err());
+        assert!(chroma_key("#00ffzz").is_err());
+        assert!(chroma_key("chartreuse").is_err());
+    }
+
+    #[test]
+    fn system_setting_test() {
+        assert_eq!(from_gnome("'prefer-dark'\n"), Some(Theme::Dark));
+        assert_eq!(from_gnome("'prefer-light'\n"), Some(Theme::Light));
+        assert_eq!(from_gnome("'default'\n"), None);
+        assert_eq!(from_gnome(""), None);
+
+        assert_eq!(from_gtk("Adwaita:dark"), Theme::Dark);
+        assert_eq!(from_gtk("Adwaita-dark"), Theme::Dark);
+        assert_eq!(from_gtk("Arc-Dark"), Theme::Dark);
+        assert_eq!(from_gtk("Adwaita"), Theme::Light);
+        assert_eq!(from_gtk("Darkish"), Theme::Light);
+
+        let query = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize\r\n    AppsUseLightTheme    REG_DWORD    0x0\r\n";
+        assert_eq!(from_windows(query), Some(Theme::Dark));
+        assert_eq!(
+            from_windows(&query.replace("0x0", "0x1")),
+            Some(Theme::Light)
+        );
+        assert_eq!(from_windows("ERROR: not found"), None);
+    }
+
+    #[test]
+    fn contrast_test() {
+        // Text must stand out from the panels it is drawn on in both.
+        for theme in [Theme::Dark, Theme::Light] {
+            let brightness = |(r, g, b): (u8, u8, u8)| r as i32 + g as i32 + b as i32;
+            assert!((brightness(theme.text()) - brightness(theme.panel())).abs() > 500);
+            let palette = theme.palette();
+            assert_ne!(palette.alive, palette.dead);
+        }
+    }
+}
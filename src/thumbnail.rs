@@ -0,0 +1,202 @@
+use crate::compression;
+use crate::grid::Grid;
+use crate::json;
+use crate::render::Palette;
+use crate::rle;
+use image::{ImageResult, Rgb, RgbImage};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Width and height of a thumbnail in pixels by default.
+pub const DEFAULT_SIZE: u32 = 64;
+
+/// Generations a pattern is evolved for in its thumbnail. Earlier ones are
+/// drawn fainter, leaving a trail behind anything that moves.
+const BLUR_GENERATIONS: usize = 4;
+
+/// The pattern files in `dir`, RLE or JSON, sorted by name.
+pub fn pattern_files<P: AsRef<Path>>(dir: P) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_pattern = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension == "rle" || extension == "json");
+        if is_pattern && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads an RLE or JSON pattern as a board just big enough to hold it.
+pub fn load_pattern<P: AsRef<Path>>(path: P) -> Result<Grid, String> {
+    let path = path.as_ref();
+    let text = compression::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    if text.trim_start().starts_with('{') {
+        return json::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+    let pattern = rle::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Grid::seed_rectangle(
+        pattern.width.max(1),
+        pattern.height.max(1),
+        pattern.living_cells,
+    ))
+}
+
+/// Draws `pattern` evolving over a few generations as a `size` pixel square,
+/// the latest generation brightest, fitted and centred so that the whole
+/// trail shows.
+pub fn thumbnail(pattern: &Grid, size: u32, palette: Palette) -> RgbImage {
+    // Room for a spaceship to travel without wrapping round the edges.
+    let margin = BLUR_GENERATIONS + 1;
+    let width = pattern.width() + 2 * margin;
+    let height = pattern.height() + 2 * margin;
+    let living_cells = pattern
+        .living_cells()
+        .into_iter()
+        .map(|(row, col)| (row + margin, col + margin))
+        .collect();
+    let mut board = Grid::seed_rectangle(width, height, living_cells);
+
+    // How bright each cell is drawn, from 0 for never alive up to
+    // BLUR_GENERATIONS + 1 for alive in the last generation.
+    let mut weights = vec![vec![0; width]; height];
+    for weight in 1..=BLUR_GENERATIONS + 1 {
+        for (row, col) in board.living_cells() {
+            weights[row][col] = weight;
+        }
+        board = board.next_generation();
+    }
+
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for (row, weights_row) in weights.iter().enumerate() {
+        for (col, weight) in weights_row.iter().enumerate() {
+            if *weight > 0 {
+                bounds = Some(match bounds {
+                    Some((top, left, bottom, right)) => {
+                        (top.min(row), left.min(col), bottom.max(row), right.max(col))
+                    }
+                    None => (row, col, row, col),
+                });
+            }
+        }
+    }
+    let (top, left, bottom, right) = match bounds {
+        Some(bounds) => bounds,
+        None => return RgbImage::from_pixel(size, size, rgb(palette.dead)),
+    };
+
+    // Cells across the square, with a border of one cell all round.
+    let span = (bottom - top + 1).max(right - left + 1) + 2;
+    let row_offset = (span - (bottom - top + 1)) / 2;
+    let col_offset = (span - (right - left + 1)) / 2;
+    RgbImage::from_fn(size, size, |x, y| {
+        let row = (y as usize * span / size as usize + top).checked_sub(row_offset);
+        let col = (x as usize * span / size as usize + left).checked_sub(col_offset);
+        let weight = match (row, col) {
+            (Some(row), Some(col)) if row < height && col < width => weights[row][col],
+            _ => 0,
+        };
+        blend(palette, weight as f32 / (BLUR_GENERATIONS + 1) as f32)
+    })
+}
+
+/// Saves a thumbnail of every pattern file in `dir` into `out_dir`, named
+/// after the pattern with a `.png` extension. Patterns that can't be read
+/// are reported in the results rather than stopping the rest.
+pub fn save_thumbnails<P: AsRef<Path>, Q: AsRef<Path>>(
+    dir: P,
+    out_dir: Q,
+    size: u32,
+    palette: Palette,
+) -> io::Result<Vec<Result<PathBuf, String>>> {
+    let out_dir = out_dir.as_ref();
+    fs::create_dir_all(out_dir)?;
+    let results = pattern_files(dir)?
+        .into_iter()
+        .map(|path| {
+            let pattern = load_pattern(&path)?;
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let thumbnail_path = out_dir.join(format!("{}.png", name));
+            save(&pattern, size, palette, &thumbnail_path)
+                .map_err(|e| format!("could not save {}: {}", thumbnail_path.display(), e))?;
+            Ok(thumbnail_path)
+        })
+        .collect();
+    Ok(results)
+}
+
+fn save(pattern: &Grid, size: u32, palette: Palette, path: &Path) -> ImageResult<()> {
+    thumbnail(pattern, size, palette).save(path)
+}
+
+/// The colour `amount` of the way from dead to alive.
+fn blend(palette: Palette, amount: f32) -> Rgb<u8> {
+    let mix = |dead: u8, alive: u8| (dead as f32 + (alive as f32 - dead as f32) * amount) as u8;
+    Rgb([
+        mix(palette.dead.0, palette.alive.0),
+        mix(palette.dead.1, palette.alive.1),
+        mix(palette.dead.2, palette.alive.2),
+    ])
+}
+
+fn rgb((r, g, b): (u8, u8, u8)) -> Rgb<u8> {
+    Rgb([r, g, b])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blur_test() {
+        let still = thumbnail(
+            &Grid::seed(2, vec![(0, 0), (0, 1), (1, 0), (1, 1)]),
+            40,
+            Palette::default(),
+        );
+        let glider = thumbnail(
+            &Grid::seed_rectangle(3, 3, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]),
+            40,
+            Palette::default(),
+        );
+
+        let alive = rgb(Palette::default().alive);
+        let dead = rgb(Palette::default().dead);
+        let faded = |image: &RgbImage| {
+            image
+                .pixels()
+                .filter(|pixel| **pixel != alive && **pixel != dead)
+                .count()
+        };
+        assert_eq!(still.dimensions(), (40, 40));
+        assert_eq!(still.get_pixel(20, 20), &alive);
+        assert_eq!(still.get_pixel(0, 0), &dead);
+        assert_eq!(faded(&still), 0);
+        assert!(faded(&glider) > 0);
+    }
+
+    #[test]
+    fn save_thumbnails_test() {
+        let dir = std::env::temp_dir().join(format!("thumbnails_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("blinker.rle"), "x = 3, y = 1\n3o!\n").unwrap();
+        fs::write(dir.join("broken.rle"), "x = 3, y = 1\n3q!\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not a pattern").unwrap();
+
+        let results =
+            save_thumbnails(&dir, dir.join("thumbnails"), 16, Palette::default()).unwrap();
+
+        assert_eq!(results.len(), 2);
+        let path = results[0].clone().unwrap();
+        assert_eq!(path, dir.join("thumbnails").join("blinker.png"));
+        assert_eq!(image::open(&path).unwrap().to_rgb8().dimensions(), (16, 16));
+        assert!(results[1].is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,416 @@
+//! How the edges of a bounded grid are joined. Each pair of opposite edges
+//! is either left as a wall of dead cells, joined straight across so that
+//! whatever leaves one side comes back on the other, or joined with a twist
+//! that also turns it upside down (or back to front). Together these give
+//! the plane, torus, cylinder, Möbius strip and Klein bottle. One pair of
+//! joined edges can also be shifted, for the shifted tori used as tracks
+//! for spaceships.
+
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// How one pair of opposite edges is joined.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Join {
+    /// Not joined: the cells beyond the edge are always dead.
+    #[default]
+    Bounded,
+    /// Joined straight across, then moved `shift` cells along the edge, down
+    /// or to the right, when crossing it forwards.
+    Wrap { shift: isize },
+    /// Joined after flipping, so that something leaving one edge near its
+    /// top comes back on the other near its bottom.
+    Twist,
+}
+
+/// Edges joined straight across with no shift.
+const WRAP: Join = Join::Wrap { shift: 0 };
+
+impl Join {
+    fn shift(self) -> isize {
+        match self {
+            Join::Wrap { shift } => shift,
+            _ => 0,
+        }
+    }
+
+    /// Where a cell `pos` along an axis `length` cells long, and `other`
+    /// along the `breadth` cells of the other one, really is once any edge it
+    /// is beyond has been crossed.
+    fn cross(
+        self,
+        length: isize,
+        breadth: isize,
+        pos: isize,
+        other: isize,
+    ) -> Option<(isize, isize)> {
+        if (0..length).contains(&pos) {
+            return Some((pos, other));
+        }
+        let shift = if pos < 0 { -self.shift() } else { self.shift() };
+        match self {
+            Join::Bounded => None,
+            Join::Wrap { .. } => Some((pos.rem_euclid(length), other + shift)),
+            Join::Twist => Some((pos.rem_euclid(length), breadth - 1 - other)),
+        }
+    }
+}
+
+impl FromStr for Join {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Join, String> {
+        match s {
+            "bounded" => Ok(Join::Bounded),
+            "wrap" => Ok(WRAP),
+            "twist" => Ok(Join::Twist),
+            _ => s
+                .strip_prefix("wrap")
+                .filter(|shift| shift.starts_with(['+', '-']))
+                .and_then(|shift| shift.parse().ok())
+                .map(|shift| Join::Wrap { shift })
+                .ok_or_else(|| {
+                    format!(
+                        "unknown edge join '{}', use bounded, wrap, wrap+N, wrap-N or twist",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+impl fmt::Display for Join {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Join::Bounded => write!(f, "bounded"),
+            Join::Wrap { shift: 0 } => write!(f, "wrap"),
+            Join::Wrap { shift } => write!(f, "wrap{:+}", shift),
+            Join::Twist => write!(f, "twist"),
+        }
+    }
+}
+
+/// The surface a grid is drawn on. Replays and other documents hold it as
+/// its name, like `"klein"`, or as `"wrap,bounded"`. At most one pair of
+/// edges can be shifted.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Topology {
+    /// How the left and right edges are joined.
+    pub horizontal: Join,
+    /// How the top and bottom edges are joined.
+    pub vertical: Join,
+}
+
+/// Well-known topologies, by the name they can be given on the command line.
+const NAMED: [(&str, Join, Join); 5] = [
+    ("plane", Join::Bounded, Join::Bounded),
+    ("torus", WRAP, WRAP),
+    ("cylinder", WRAP, Join::Bounded),
+    ("mobius", Join::Twist, Join::Bounded),
+    ("klein", Join::Twist, WRAP),
+];
+
+impl Topology {
+    /// The named topology after this one, in the order `plane`, `torus`,
+    /// `cylinder`, `mobius` and `klein` and back round. Any other topology
+    /// is followed by `plane`.
+    pub fn next_named(self) -> Topology {
+        let index = NAMED
+            .iter()
+            .position(|&(_, horizontal, vertical)| {
+                (horizontal, vertical) == (self.horizontal, self.vertical)
+            })
+            .map_or(0, |index| (index + 1) % NAMED.len());
+        let (_, horizontal, vertical) = NAMED[index];
+        Topology {
+            horizontal,
+            vertical,
+        }
+    }
+
+    /// Reads a topology written the way Golly does, like `T100,50+10`,
+    /// along with the width and height of the grid it gives. The letter is
+    /// `P` for a plane, `T` for a torus, `K` for a Klein bottle and `C` for a
+    /// cross-surface. A `+N` or `-N` after the width of a torus shifts its
+    /// top and bottom edges sideways by N cells, or after the height shifts
+    /// its sides up or down, and a Klein bottle's `*` marks the dimension
+    /// along which its twisted edges run.
+    pub fn from_golly(s: &str) -> Result<(Topology, usize, usize), String> {
+        let invalid = || {
+            format!(
+                "invalid topology '{}', use Golly's notation like T100,50+10",
+                s
+            )
+        };
+        let mut chars = s.chars();
+        let kind = chars.next().ok_or_else(invalid)?;
+        let (width, height) = chars.as_str().split_once(',').ok_or_else(invalid)?;
+        let (width, width_suffix) = golly_dimension(width).ok_or_else(invalid)?;
+        let (height, height_suffix) = golly_dimension(height).ok_or_else(invalid)?;
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "{} is unbounded along one side, which only --infinite can be",
+                s
+            ));
+        }
+
+        let shifted = |shift| Join::Wrap { shift };
+        let (horizontal, vertical) = match (kind, width_suffix, height_suffix) {
+            ('P', Suffix::Plain, Suffix::Plain) => (Join::Bounded, Join::Bounded),
+            ('T', Suffix::Plain, Suffix::Plain) => (WRAP, WRAP),
+            ('T', Suffix::Shift(shift), Suffix::Plain) => (WRAP, shifted(shift)),
+            ('T', Suffix::Plain, Suffix::Shift(shift)) => (shifted(shift), WRAP),
+            ('K', Suffix::Twist, Suffix::Plain) => (WRAP, Join::Twist),
+            ('K', Suffix::Plain, Suffix::Twist) => (Join::Twist, WRAP),
+            ('C', Suffix::Plain, Suffix::Plain) => (Join::Twist, Join::Twist),
+            ('S', _, _) => return Err(format!("{}: spheres aren't supported", s)),
+            _ => return Err(invalid()),
+        };
+        Ok((
+            Topology {
+                horizontal,
+                vertical,
+            },
+            width,
+            height,
+        ))
+    }
+
+    /// Whether no edges are joined, so that patterns run into all four.
+    pub fn is_bounded(self) -> bool {
+        self == Topology::default()
+    }
+
+    /// The cell of a `width` by `height` grid at `row` and `col`, which may
+    /// be up to one cell beyond any of its edges, or `None` if that is off the
+    /// grid.
+    pub fn locate(
+        self,
+        width: usize,
+        height: usize,
+        row: isize,
+        col: isize,
+    ) -> Option<(usize, usize)> {
+        let (width, height) = (width as isize, height as isize);
+        let across = |(row, col)| {
+            let (col, row) = self.horizontal.cross(width, height, col, row)?;
+            Some((row, col))
+        };
+        let down = |(row, col)| self.vertical.cross(height, width, row, col);
+        // Crossing shifted edges can take a cell beyond the others, so those
+        // are crossed first.
+        let (row, col) = if self.vertical.shift() != 0 {
+            down((row, col)).and_then(across)?
+        } else {
+            across((row, col)).and_then(down)?
+        };
+        if (0..height).contains(&row) && (0..width).contains(&col) {
+            Some((row as usize, col as usize))
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    /// Reads a topology from its name, or from how the left and right edges
+    /// are joined then how the top and bottom ones are, like `wrap,twist`.
+    fn from_str(s: &str) -> Result<Topology, String> {
+        if let Some(&(_, horizontal, vertical)) = NAMED.iter().find(|(name, _, _)| *name == s) {
+            return Ok(Topology {
+                horizontal,
+                vertical,
+            });
+        }
+        match s.split_once(',') {
+            Some((horizontal, vertical)) => {
+                let topology = Topology {
+                    horizontal: horizontal.parse()?,
+                    vertical: vertical.parse()?,
+                };
+                if topology.horizontal.shift() != 0 && topology.vertical.shift() != 0 {
+                    return Err(format!("{}: only one pair of edges can be shifted", s));
+                }
+                Ok(topology)
+            }
+            None => Err(format!(
+                "unknown topology '{}', use plane, torus, cylinder, mobius, klein \
+                 or how the sides and the top and bottom are joined, like wrap,twist",
+                s
+            )),
+        }
+    }
+}
+
+/// What follows a dimension in Golly's notation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Suffix {
+    Plain,
+    Twist,
+    Shift(isize),
+}
+
+/// A dimension in Golly's notation and what follows it, like `50+10`.
+fn golly_dimension(text: &str) -> Option<(usize, Suffix)> {
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (size, suffix) = text.split_at(digits);
+    let suffix = match suffix {
+        "" => Suffix::Plain,
+        "*" => Suffix::Twist,
+        _ if suffix.starts_with('+') || suffix.starts_with('-') => {
+            Suffix::Shift(suffix.parse().ok()?)
+        }
+        _ => return None,
+    };
+    Some((size.parse().ok()?, suffix))
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match NAMED.iter().find(|(_, horizontal, vertical)| {
+            (*horizontal, *vertical) == (self.horizontal, self.vertical)
+        }) {
+            Some((name, _, _)) => write!(f, "{}", name),
+            None => write!(f, "{},{}", self.horizontal, self.vertical),
+        }
+    }
+}
+
+impl From<Topology> for String {
+    fn from(topology: Topology) -> String {
+        topology.to_string()
+    }
+}
+
+impl TryFrom<String> for Topology {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Topology, String> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn locate_test() {
+        let torus = Topology::from_str("torus").unwrap();
+        let klein = Topology::from_str("klein").unwrap();
+        let cylinder = Topology::from_str("cylinder").unwrap();
+
+        assert_eq!(torus.locate(5, 4, -1, -1), Some((3, 4)));
+        assert_eq!(klein.locate(5, 4, 1, 5), Some((2, 0)));
+        assert_eq!(klein.locate(5, 4, 4, 1), Some((0, 1)));
+        assert_eq!(cylinder.locate(5, 4, 2, -1), Some((2, 4)));
+        assert_eq!(cylinder.locate(5, 4, -1, 2), None);
+        assert_eq!(Topology::default().locate(5, 4, 0, 5), None);
+        assert_eq!(Topology::default().locate(5, 4, 3, 4), Some((3, 4)));
+
+        let shifted: Topology = "wrap+2,wrap".parse().unwrap();
+        assert_eq!(shifted.locate(5, 4, 0, 5), Some((2, 0)));
+        assert_eq!(shifted.locate(5, 4, 3, 5), Some((1, 0)));
+        assert_eq!(shifted.locate(5, 4, 1, -1), Some((3, 4)));
+        assert_eq!(shifted.locate(5, 4, -1, 2), Some((3, 2)));
+        let down: Topology = "wrap,wrap-1".parse().unwrap();
+        assert_eq!(down.locate(5, 4, 4, 0), Some((0, 4)));
+    }
+
+    #[test]
+    fn shifted_torus_test() {
+        let glider = Grid::seed(6, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let (topology, width, height) = Topology::from_golly("T6,6+1").unwrap();
+
+        let mut grid = glider.clone();
+        for _ in 0..4 * 6 {
+            grid = grid.next_generation_on(topology);
+        }
+
+        // Once round, it came back across the sides a row lower.
+        assert_eq!((width, height), (6, 6));
+        assert_eq!(
+            grid.living_cells(),
+            vec![(1, 1), (2, 2), (3, 0), (3, 1), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn golly_test() {
+        let golly =
+            |s| Topology::from_golly(s).map(|(topology, w, h)| (topology.to_string(), w, h));
+
+        assert_eq!(
+            golly("T100,50+10"),
+            Ok(("wrap+10,wrap".to_string(), 100, 50))
+        );
+        assert_eq!(golly("T100-3,50"), Ok(("wrap,wrap-3".to_string(), 100, 50)));
+        assert_eq!(golly("P30,20"), Ok(("plane".to_string(), 30, 20)));
+        assert_eq!(golly("K10,20*"), Ok(("klein".to_string(), 10, 20)));
+        assert_eq!(golly("K10*,20"), Ok(("wrap,twist".to_string(), 10, 20)));
+        assert_eq!(golly("C10,10"), Ok(("twist,twist".to_string(), 10, 10)));
+        assert!(golly("T10+1,10+1").is_err());
+        assert!(golly("T0,10").is_err());
+        assert!(golly("S10,10").is_err());
+        assert!(golly("T10").is_err());
+        assert!(golly("P10*,10").is_err());
+        assert!("wrap+1,wrap+1".parse::<Topology>().is_err());
+        assert!("wrap+-1,wrap".parse::<Topology>().is_err());
+        assert!("wrap2,wrap".parse::<Topology>().is_err());
+    }
+
+    #[test]
+    fn glider_test() {
+        let glider = Grid::seed(6, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let torus = Topology::from_str("torus").unwrap();
+        let mobius = Topology::from_str("mobius").unwrap();
+
+        let mut on_torus = glider.clone();
+        let mut on_mobius = glider.clone();
+        for _ in 0..4 * 6 {
+            on_torus = on_torus.next_generation_on(torus);
+            on_mobius = on_mobius.next_generation_on(mobius);
+        }
+
+        // Once round the torus brings the glider back where it started.
+        assert_eq!(on_torus, glider);
+        // On the strip it crashes into the top or bottom edge instead.
+        assert_ne!(on_mobius.living_cells(), glider.living_cells());
+    }
+
+    #[test]
+    fn next_named_test() {
+        let next = |s: &str| s.parse::<Topology>().unwrap().next_named().to_string();
+
+        assert_eq!(next("plane"), "torus");
+        assert_eq!(next("klein"), "plane");
+        assert_eq!(next("wrap+3,wrap"), "plane");
+    }
+
+    #[test]
+    fn parse_test() {
+        let topology: Topology = "twist,twist".parse().unwrap();
+
+        assert_eq!(topology.horizontal, Join::Twist);
+        assert_eq!(topology.to_string(), "twist,twist");
+        assert_eq!(
+            "wrap,wrap".parse::<Topology>().unwrap().to_string(),
+            "torus"
+        );
+        assert!(Topology::default().is_bounded());
+        assert!("donut".parse::<Topology>().is_err());
+        assert!("wrap,spiral".parse::<Topology>().is_err());
+        assert_eq!(
+            serde_json::to_string(&Topology::from_str("klein").unwrap()).unwrap(),
+            r#""klein""#
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::animation::FrameStyle;
+use crate::grid::Grid;
+use crate::render;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// A video being encoded by ffmpeg, which is sent each frame as raw RGB as
+/// soon as it is added, so even very long runs never pile up in memory. The
+/// container and codec follow from the file extension, `.mp4` or `.webm`
+/// for instance.
+pub struct Video {
+    ffmpeg: Child,
+    stdin: Option<ChildStdin>,
+    style: FrameStyle,
+    frames: usize,
+}
+
+impl Video {
+    /// Starts ffmpeg writing to `path` for frames of `width` by `height`
+    /// cells.
+    pub fn start<P: AsRef<Path>>(
+        path: P,
+        width: usize,
+        height: usize,
+        style: FrameStyle,
+    ) -> io::Result<Video> {
+        Video::start_with("ffmpeg", path.as_ref(), width, height, style)
+    }
+
+    fn start_with(
+        program: &str,
+        path: &Path,
+        width: usize,
+        height: usize,
+        style: FrameStyle,
+    ) -> io::Result<Video> {
+        let size = (width as u32 * style.scale, height as u32 * style.scale);
+        let mut ffmpeg = Command::new(program)
+            .args(ffmpeg_args(path, size, style.delay))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("could not start {}, is it installed? {}", program, e),
+                )
+            })?;
+        let stdin = ffmpeg.stdin.take();
+        Ok(Video {
+            ffmpeg,
+            stdin,
+            style,
+            frames: 0,
+        })
+    }
+
+    pub fn add_frame(&mut self, grid: &Grid) -> io::Result<()> {
+        let image = render::render(grid, self.style.palette, self.style.scale);
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.write_all(image.as_raw())?,
+            None => return Err(io::Error::other("ffmpeg has already finished")),
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// How many frames have been sent to ffmpeg.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Closes ffmpeg's input and waits for it to finish the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        drop(self.stdin.take());
+        let status = self.ffmpeg.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("ffmpeg failed with {}", status)))
+        }
+    }
+}
+
+/// Arguments telling ffmpeg to read raw RGB frames of `size` pixels from
+/// stdin, one every `delay` milliseconds, and encode them to `path`. The
+/// frames are padded to even dimensions, which most codecs need.
+fn ffmpeg_args(path: &Path, size: (u32, u32), delay: u32) -> Vec<String> {
+    let frame_rate = 1000.0 / delay.max(1) as f64;
+    vec![
+        "-y".to_string(),
+        "-f".to_string(),
+        "rawvideo".to_string(),
+        "-pix_fmt".to_string(),
+        "rgb24".to_string(),
+        "-s".to_string(),
+        format!("{}x{}", size.0, size.1),
+        "-r".to_string(),
+        frame_rate.to_string(),
+        "-i".to_string(),
+        "-".to_string(),
+        "-vf".to_string(),
+        "pad=ceil(iw/2)*2:ceil(ih/2)*2".to_string(),
+        "-pix_fmt".to_string(),
+        "yuv420p".to_string(),
+        path.to_string_lossy().into_owned(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args_test() {
+        let args = ffmpeg_args(Path::new("run.mp4"), (50, 30), 40);
+
+        assert_eq!(args[6], "50x30");
+        assert_eq!(args[8], "25");
+        assert_eq!(args.last().map(String::as_str), Some("run.mp4"));
+    }
+
+    #[test]
+    fn missing_ffmpeg_test() {
+        let result = Video::start_with(
+            "no-such-ffmpeg",
+            Path::new("run.mp4"),
+            5,
+            5,
+            FrameStyle::default(),
+        );
+
+        let message = result.err().unwrap().to_string();
+        assert!(message.starts_with("could not start no-such-ffmpeg, is it installed?"));
+    }
+}
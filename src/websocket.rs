@@ -0,0 +1,350 @@
+//! A small WebSocket server that sends the board to anyone connected, for
+//! web pages and other programs watching a run from elsewhere. Each client
+//! is sent the whole board as a JSON grid when it connects, then a
+//! message per generation with just the cells that changed:
+//!
+//! ```json
+//! {"generation": 12, "born": [[3, 4]], "died": [[2, 4], [4, 4]]}
+//! ```
+//!
+//! Whenever the board changes some other way, such as being cleared or
+//! resized, the whole of it is sent again. Only what is needed to send is
+//! implemented: messages from clients are never read.
+
+use crate::grid::{Grid, State};
+use crate::json;
+use crate::share::{encode_base64, STANDARD_ALPHABET};
+use crate::socket::Socket;
+use serde_json::json;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::time::{Duration, Instant};
+
+/// How long a new client has to send its handshake.
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The longest handshake a client can send.
+const MAX_REQUEST: usize = 8192;
+
+/// What RFC 6455 appends to a client's key before hashing it.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The `host:port` to listen on from an address like `ws://0.0.0.0:9000`.
+pub fn address(spec: &str) -> Result<String, String> {
+    let address = spec.strip_prefix("ws://").unwrap_or(spec);
+    let address = address.strip_suffix('/').unwrap_or(address);
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+            Ok(address.to_string())
+        }
+        _ => Err(format!(
+            "'{}' is not an address like ws://0.0.0.0:9000",
+            spec
+        )),
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+    /// Those who have connected but not yet finished their handshake, with
+    /// when they connected.
+    pending: Vec<(Socket, Instant)>,
+    clients: Vec<Socket>,
+    /// The generation and board last sent.
+    sent: Option<(u64, Grid)>,
+}
+
+impl Server {
+    /// Listens on `address`, a `host:port`.
+    pub fn bind(address: &str) -> io::Result<Server> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Server {
+            listener,
+            pending: Vec::new(),
+            clients: Vec::new(),
+            sent: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Sends `grid` at `generation` to every client: what changed if it
+    /// follows on from the board last sent, or all of it if not. Clients
+    /// whose handshakes have come in since are sent all of it first, and
+    /// any that can't be sent to are dropped. Nothing here waits on a
+    /// client: a handshake that hasn't all arrived is looked at again next
+    /// time, and what a client can't take yet is kept for it.
+    pub fn publish(&mut self, generation: u64, grid: &Grid) {
+        let whole = json::to_json(grid, Some(generation));
+        while let Ok((stream, _)) = self.listener.accept() {
+            match Socket::new(stream) {
+                Ok(socket) => self.pending.push((socket, Instant::now())),
+                Err(e) => eprintln!("a WebSocket client could not connect: {}", e),
+            }
+        }
+        let mut joined = Vec::new();
+        for (mut socket, since) in std::mem::take(&mut self.pending) {
+            let accepted = handshake(&mut socket).and_then(|accepted| {
+                if accepted {
+                    socket.send(&text_frame(&whole))?;
+                }
+                Ok(accepted)
+            });
+            match accepted {
+                Ok(true) => joined.push(socket),
+                Ok(false) if since.elapsed() < TIMEOUT => self.pending.push((socket, since)),
+                Ok(false) => eprintln!("a WebSocket client could not connect: no handshake"),
+                Err(e) => eprintln!("a WebSocket client could not connect: {}", e),
+            }
+        }
+        let message = match &self.sent {
+            Some((sent_generation, sent)) if *sent_generation == generation && sent == grid => None,
+            Some((sent_generation, sent))
+                if *sent_generation + 1 == generation
+                    && (sent.width(), sent.height()) == (grid.width(), grid.height()) =>
+            {
+                Some(delta(generation, sent, grid))
+            }
+            _ => Some(whole),
+        };
+        match message {
+            Some(message) => {
+                let frame = text_frame(&message);
+                self.clients
+                    .retain_mut(|client| client.send(&frame).is_ok());
+                self.sent = Some((generation, grid.clone()));
+            }
+            None => self.clients.retain_mut(|client| client.flush().is_ok()),
+        }
+        // Those who just joined have already been sent all of it.
+        self.clients.append(&mut joined);
+    }
+}
+
+/// Reads what has come of a client's opening handshake from `socket`, and
+/// accepts it if all of it has. Returns whether it has been accepted.
+fn handshake(socket: &mut Socket) -> io::Result<bool> {
+    let request = match socket.head(MAX_REQUEST)? {
+        Some(request) => request,
+        None => return Ok(false),
+    };
+    let key = request
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-key"))
+        .map(|(_, key)| key.trim())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket handshake"))?;
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    );
+    socket.send(response.as_bytes())?;
+    Ok(true)
+}
+
+/// The message for the cells born and died going from `before` to `after`.
+pub fn delta(generation: u64, before: &Grid, after: &Grid) -> String {
+    let (mut born, mut died) = (Vec::new(), Vec::new());
+    for (row, (before_row, after_row)) in before.cells.iter().zip(&after.cells).enumerate() {
+        for (col, (before, after)) in before_row.iter().zip(after_row).enumerate() {
+            match (before, after) {
+                (State::Dead, State::Alive) => born.push((row, col)),
+                (State::Alive, State::Dead) => died.push((row, col)),
+                _ => (),
+            }
+        }
+    }
+    json!({"generation": generation, "born": born, "died": died}).to_string()
+}
+
+/// `text` as an unmasked WebSocket text frame, as servers send them.
+pub fn text_frame(text: &str) -> Vec<u8> {
+    let len = text.len();
+    let mut frame = vec![0x81];
+    if len < 126 {
+        frame.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    frame.extend_from_slice(text.as_bytes());
+    frame
+}
+
+/// The `Sec-WebSocket-Accept` answer to a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut key = encode_base64(
+        &sha1(format!("{}{}", key, GUID).as_bytes()),
+        STANDARD_ALPHABET,
+    );
+    // Unlike share strings, the key keeps its padding.
+    while !key.len().is_multiple_of(4) {
+        key.push('=');
+    }
+    key
+}
+
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+    for chunk in padded.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip(&[a, b, c, d, e]) {
+            *state = state.wrapping_add(*value);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_mut(4).zip(&h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn address_test() {
+        assert_eq!(address("ws://0.0.0.0:9000"), Ok("0.0.0.0:9000".to_string()));
+        assert_eq!(address("localhost:80/"), Ok("localhost:80".to_string()));
+        assert!(address("ws://0.0.0.0").is_err());
+        assert!(address("ws://:9000").is_err());
+        assert!(address("ws://host:99999").is_err());
+    }
+
+    #[test]
+    fn accept_key_test() {
+        // The example from RFC 6455.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        let digest: String = sha1(b"abc").iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(digest, "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+
+    #[test]
+    fn text_frame_test() {
+        assert_eq!(text_frame("hi"), vec![0x81, 2, b'h', b'i']);
+        let medium = "x".repeat(300);
+        assert_eq!(&text_frame(&medium)[..4], &[0x81, 126, 1, 44]);
+        let long = "x".repeat(70_000);
+        let frame = text_frame(&long);
+        assert_eq!(frame[1], 127);
+        assert_eq!(frame.len(), 10 + 70_000);
+    }
+
+    #[test]
+    fn delta_test() {
+        let blinker = Grid::seed(3, vec![(1, 0), (1, 1), (1, 2)]);
+
+        assert_eq!(
+            delta(4, &blinker, &blinker.next_generation()),
+            r#"{"born":[[0,1],[2,1]],"died":[[1,0],[1,2]],"generation":4}"#
+        );
+    }
+
+    #[test]
+    fn publish_test() {
+        let mut server = Server::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                  Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                  Sec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        let blinker = Grid::seed(3, vec![(1, 0), (1, 1), (1, 2)]);
+        // The client's connection may take a moment to be accepted.
+        for _ in 0..100 {
+            server.publish(0, &blinker);
+            if server.clients() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.clients(), 1);
+        server.publish(1, &blinker.next_generation());
+        // Nothing is sent for a board already sent.
+        server.publish(1, &blinker.next_generation());
+        drop(server);
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        let received = String::from_utf8_lossy(&received);
+        assert!(received.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(received.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        assert!(received.contains(&json::to_json(&blinker, Some(0))));
+        assert!(received.ends_with(r#"{"born":[[0,1],[2,1]],"died":[[1,0],[1,2]],"generation":1}"#));
+    }
+
+    #[test]
+    fn silent_client_test() {
+        let mut server = Server::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        let blinker = Grid::seed(3, vec![(1, 0), (1, 1), (1, 2)]);
+        // A client that never sends its handshake doesn't hold up publishing,
+        // and is let go once it has had long enough.
+        let start = Instant::now();
+        for generation in 0..10 {
+            server.publish(generation, &blinker);
+        }
+        assert!(start.elapsed() < TIMEOUT);
+        std::thread::sleep(TIMEOUT);
+        server.publish(10, &blinker);
+        assert!(server.pending.is_empty());
+        assert_eq!(server.clients(), 0);
+    }
+}
@@ -0,0 +1,50 @@
+/*
+ * A C program using every function in the header, built and run by
+ * c_api.rs against the shared library to check the two agree. It exits
+ * with the line of the first check that fails.
+ */
+
+#include "conways_game_of_life.h"
+
+#include <stdio.h>
+
+#define CHECK(condition)                                                   \
+    do {                                                                   \
+        if (!(condition)) {                                                \
+            fprintf(stderr, "check failed on line %d: %s\n", __LINE__,     \
+                    #condition);                                           \
+            return __LINE__;                                               \
+        }                                                                  \
+    } while (0)
+
+int main(void) {
+    uint8_t cells[9];
+    LifeGrid *grid = life_grid_new(3, 3);
+    CHECK(grid != NULL);
+    CHECK(life_grid_width(grid) == 3 && life_grid_height(grid) == 3);
+    CHECK(life_grid_set(grid, 1, 0, 1) == 0);
+    CHECK(life_grid_set(grid, 1, 1, 1) == 0);
+    CHECK(life_grid_set(grid, 1, 2, 1) == 0);
+    CHECK(life_grid_set(grid, 3, 0, 1) == -1);
+    CHECK(life_grid_population(grid) == 3);
+    CHECK(life_grid_set_rule(grid, "B3/S23") == 0);
+    CHECK(life_grid_set_rule(grid, "life") == -1);
+    life_grid_step(grid, 1);
+    CHECK(life_grid_get(grid, 0, 1) == 1);
+    CHECK(life_grid_get(grid, 9, 9) == -1);
+    CHECK(life_grid_read_cells(grid, NULL, 0) == 9);
+    CHECK(life_grid_read_cells(grid, cells, sizeof cells) == 9);
+    CHECK(cells[1] == 1 && cells[3] == 0);
+    CHECK(life_grid_set_topology(grid, "torus") == 0);
+    CHECK(life_grid_set_topology(grid, "doughnut") == -1);
+    life_grid_free(grid);
+
+    grid = life_grid_from_rle("bo$2bo$3o!");
+    CHECK(grid != NULL);
+    CHECK(life_grid_population(grid) == 5);
+    life_grid_free(grid);
+    CHECK(life_grid_from_rle("zz!") == NULL);
+    CHECK(life_grid_new((size_t)-1, 1) == NULL);
+    life_grid_free(NULL);
+    return 0;
+}
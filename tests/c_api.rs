@@ -0,0 +1,39 @@
+//! Builds `c_api.c` against the C header and links it to the shared library,
+//! so a declaration in the header that doesn't match the library fails to
+//! compile or to run.
+
+#![cfg(unix)]
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn c_api_test() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // Cargo builds the shared library next to the tests that use the crate.
+    let exe = env::current_exe().unwrap();
+    let libraries = exe.parent().unwrap();
+    let program =
+        env::temp_dir().join(format!("conways_game_of_life_c_api_{}", std::process::id()));
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let built = Command::new(&compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-pedantic"])
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg(root.join("tests/c_api.c"))
+        .arg("-o")
+        .arg(&program)
+        .arg("-L")
+        .arg(libraries)
+        .arg(format!("-Wl,-rpath,{}", libraries.display()))
+        .arg("-lconways_game_of_life")
+        .status()
+        .unwrap_or_else(|e| panic!("could not run {}: {}", compiler, e));
+    assert!(built.success(), "the C program didn't build");
+
+    let ran = Command::new(&program).status().unwrap();
+    let _ = std::fs::remove_file(&program);
+    assert!(ran.success(), "the C program failed: {}", ran);
+}
@@ -0,0 +1,86 @@
+//! Golden tests of the boards, hashes and results seeds give, so a change
+//! to the generator, the hasher or the order anything is worked out in,
+//! which would make replays and shared seeds come out differently on
+//! another machine or release, fails here rather than going unnoticed.
+
+use conways_game_of_life::determinism::{self, stable_hash};
+use conways_game_of_life::grid::Grid;
+use conways_game_of_life::plane::Plane;
+use conways_game_of_life::rle;
+use conways_game_of_life::rule::Rule;
+use conways_game_of_life::search::Search;
+use conways_game_of_life::soup::Soup;
+use conways_game_of_life::topology::Topology;
+use rand::RngCore;
+
+#[test]
+fn rng_test() {
+    let mut rng = determinism::rng(42);
+    let draws: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+    assert_eq!(
+        draws,
+        vec![
+            9482535800248027256,
+            7566832397956113305,
+            1804347359131428821
+        ]
+    );
+    assert_eq!(
+        Rule::random(&mut determinism::rng_for(7, 3)).to_string(),
+        "B16/S367"
+    );
+}
+
+#[test]
+fn soup_test() {
+    let soup = Soup::default().generate(16, 16, &mut determinism::rng_for(42, 0));
+    assert_eq!(soup.population(), 128);
+    assert_eq!(stable_hash(&soup.living_cells()), 0x325b_8e1a_49ec_226a);
+
+    let noise = Soup {
+        noise: Some(6.0),
+        ..Soup::default()
+    };
+    let noise = noise.generate(32, 24, &mut determinism::rng(9));
+    assert_eq!(noise.population(), 384);
+    assert_eq!(stable_hash(&noise.living_cells()), 0x4377_b7f5_7a23_06a1);
+}
+
+#[test]
+fn step_test() {
+    let mut grid = Grid::random_grid(32, 0.4, &mut determinism::rng(1));
+    let torus: Topology = "torus".parse().unwrap();
+    for _ in 0..200 {
+        grid = grid.next_generation_on(torus);
+    }
+    assert_eq!(grid.population(), 65);
+    assert_eq!(stable_hash(&grid.living_cells()), 0x09fb_3fe8_d1f5_e5e6);
+
+    // The R-pentomino settles after 1103 generations with 116 cells.
+    let r_pentomino = rle::parse("b2o$2o$bo!").unwrap().fitted(3, 3, 0);
+    let mut plane = Plane::from_grid(&r_pentomino, 0, 0);
+    for _ in 0..1103 {
+        plane = plane.next_generation();
+    }
+    assert_eq!(plane.population(), 116);
+    assert_eq!(stable_hash(&plane.living_cells()), 0x6f5d_3546_9147_03d9);
+}
+
+#[test]
+fn search_test() {
+    let search = Search {
+        soups: 40,
+        size: 16,
+        seed: 42,
+        threads: 3,
+        ..Search::default()
+    };
+    let results = search.run(|_| ());
+    assert_eq!(
+        (results.soups, results.unsettled, results.generations),
+        (40, 0, 16432)
+    );
+    assert_eq!(results.counts.get("block"), Some(&185));
+    assert_eq!(results.counts.get("blinker"), Some(&84));
+    assert_eq!(stable_hash(&results.codes), 0xb24d_8da7_7e8b_3ee8);
+}